@@ -0,0 +1,48 @@
+// The per frame image work on a typical crop size, cargo bench
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pixelbot::coord::Coord;
+use pixelbot::image::image_ops::BlendType;
+use pixelbot::image::{self, Bgra8, Color, Image};
+
+const CROP_W: usize = 1152;
+const CROP_H: usize = 592;
+
+// a flat background with a few target colored blocks in it
+fn frame() -> Image<Vec<u8>, Bgra8> {
+    let mut img = image::zeroed::<Bgra8>(CROP_W, CROP_H);
+    img.fill_color(Color::new(60, 90, 40, 255));
+    for (x0, y0) in [(100, 80), (600, 300), (1000, 500)] {
+        for y in y0..y0 + 40 {
+            for x in x0..x0 + 20 {
+                img.set2d(Coord::new(x, y), Color::new(196, 58, 172, 255));
+            }
+        }
+    }
+    img
+}
+
+fn detect(c: &mut Criterion) {
+    let img = frame();
+    let target = [Color::new(196, 58, 172, 255)];
+    c.bench_function("detect_colors", |b| {
+        b.iter(|| img.detect_colors(black_box(&target), 0.83))
+    });
+}
+
+fn blend(c: &mut Criterion) {
+    let mut fg = frame();
+    let bg = frame();
+    c.bench_function("blend_over", |b| {
+        b.iter(|| fg.blend(BlendType::Over, black_box(&bg)))
+    });
+}
+
+fn downscale(c: &mut Criterion) {
+    let img = frame();
+    c.bench_function("downscale_box_2", |b| {
+        b.iter(|| img.downscale_box(black_box(2)))
+    });
+}
+
+criterion_group!(benches, detect, blend, downscale);
+criterion_main!(benches);
@@ -0,0 +1,13 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use pixelbot::config::Config;
+
+// Config files are hand edited, any line has to come back as a value or an error, never a panic
+fuzz_target!(|data: &[u8]| {
+    if let Ok(line) = std::str::from_utf8(data) {
+        // from_file hands parse_line one line at a time
+        if let Some(line) = line.lines().next() {
+            let _ = Config::parse_key_val(line);
+        }
+    }
+});
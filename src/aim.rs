@@ -0,0 +1,1442 @@
+use crate::coord::{center_pixel, frame_center, offset_from_center, Coord, CropPos, DetectPos};
+use crate::image::{
+    image_ops::{color_distance, color_matches},
+    Bgra8, Color, Gray8, Image, Pixel,
+};
+
+use rustc_hash::FxHashSet;
+use std::time::Duration;
+
+// Everything the aim computation needs from the config, built once per reload
+#[derive(Debug, Clone)]
+pub struct AimParams {
+    pub target_colors: Vec<Color<u8>>, // never empty, earlier colors win over later ones
+    pub color_thresh: f32,
+    pub aim_divisor: f32,
+    pub y_multiplier: f32,
+    pub wash_reject_fraction: f32, // frames with more matched pixels than this are ignored
+    pub adaptive_ref_area: u32,
+    pub adaptive_scale_range: (f32, f32), // (1, 1) always uses the plain aim_divisor
+    pub aspect_range: (f32, f32),         // accepted cluster bbox width / height
+    pub outline_samples: u32,
+    pub outline_confirm_fraction: f32, // 0 skips the outline check
+    pub motion_thresh: f32,            // min MotionMap score for a cluster, 0 skips the motion gate
+    pub prefer_largest: bool,          // otherwise the cluster closest to the crosshair wins
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AimStats {
+    pub matched_px: usize,
+    pub clusters_rejected_area: u32,
+    pub clusters_rejected_aspect: u32,
+    pub clusters_rejected_static: u32,
+    pub roi_hit: bool, // the target was found around the last one without searching the whole frame
+}
+
+// Coords are in the frame compute_aim ran on, the full res crop once upscale_aim_result has mapped
+//     a downscaled frame's result back
+#[derive(Debug, Clone)]
+pub struct AimResult {
+    pub relative_coord: Coord<i32>, // Scaled mouse movement, (0, 0) when nothing was found
+    pub cluster: Option<Vec<CropPos>>, // Selected cluster's pixel coords
+    pub rejected: Vec<Vec<CropPos>>, // Clusters thrown out for their shape (eg. health bars) or not moving
+    pub others: Vec<Vec<CropPos>>, // Bbox corners of the acceptable clusters that lost to the selected one
+    pub aim_coord: Option<CropPos>, // Average of the selected cluster's coords
+    pub candidates: u32,           // Number of clusters found
+    pub wash_rejected: bool, // Too much of the frame matched, eg. a flashbang tinting the screen
+    pub effective_divisor: f32, // aim_divisor after scaling for the cluster's size
+    pub cluster_mean: Option<Color<u8>>, // Average color of the selected cluster's pixels
+    pub confirmed: bool, // Enough of the bbox outline matched too, aim_coord is None until it does
+    pub motion_score: Option<f32>, // Selected cluster's MotionMap score, None with the gate off
+    pub matched_color: Option<Color<u8>>, // The target color the selected cluster is closest to
+    pub stats: AimStats,
+}
+
+// Box around the last frame's target, the next frame searches just that first since targets rarely move far.
+//     Every FULL_SEARCH_INTERVAL frames gets a full search anyway, a better target could've shown up elsewhere
+#[derive(Debug, Default)]
+pub struct RoiHint {
+    region: Option<(usize, usize, usize, usize)>, // x, y, w, h in the frame detection runs on
+    dims: (usize, usize),
+    since_full_search: u32,
+}
+
+impl RoiHint {
+    const FULL_SEARCH_INTERVAL: u32 = 10;
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    // None when the frame needs a full search
+    fn region(&mut self, dims: (usize, usize)) -> Option<(usize, usize, usize, usize)> {
+        if dims != self.dims {
+            self.reset();
+            self.dims = dims;
+        }
+        if self.since_full_search >= Self::FULL_SEARCH_INTERVAL {
+            return None;
+        }
+        self.region
+    }
+
+    // the region is the target's bbox grown to twice its size, clamped to the frame
+    fn update(&mut self, result: &AimResult, full_search: bool) {
+        if full_search {
+            self.since_full_search = 0;
+        } else {
+            self.since_full_search += 1;
+        }
+        self.region = match (result.aim_coord, result.cluster.as_deref()) {
+            (Some(_), Some(cluster)) => {
+                let (x, y, w, h) = CropPos::bbox_xywh(cluster);
+                let (grow_x, grow_y) = ((w + 1) / 2 + 1, (h + 1) / 2 + 1);
+                let (x0, y0) = (x.saturating_sub(grow_x), y.saturating_sub(grow_y));
+                let x1 = (x + w + grow_x + 1).min(self.dims.0);
+                let y1 = (y + h + grow_y + 1).min(self.dims.1);
+                Some((x0, y0, x1 - x0, y1 - y0))
+            }
+            _ => None,
+        };
+    }
+}
+
+// `motion` has to have been updated with `frame` already, it's only read when params.motion_thresh > 0.
+//     With a `roi` hint the area around the last target is searched first, falling back to the whole frame
+//     when nothing qualifies there. Several target colors always search everything, the best ranked
+//     color could be anywhere
+pub fn compute_aim(
+    frame: &Image<Vec<u8>, Bgra8>,
+    params: &AimParams,
+    motion: Option<&MotionMap>,
+    roi: Option<&mut RoiHint>,
+) -> AimResult {
+    let roi = match roi {
+        Some(roi) if params.target_colors.len() == 1 => roi,
+        _ => return search(frame, params, motion, None),
+    };
+    let dims = (frame.w, frame.h);
+    if let Some(region) = roi.region(dims) {
+        let mut result = search(frame, params, motion, Some(region));
+        // a cluster running into the region's edge might continue outside of it
+        let contained = result.cluster.as_deref().map_or(false, |cluster| {
+            let (x, y, w, h) = CropPos::bbox_xywh(cluster);
+            let (rx, ry, rw, rh) = region;
+            (x > rx || x == 0)
+                && (y > ry || y == 0)
+                && (x + w + 1 < rx + rw || x + w + 1 == dims.0)
+                && (y + h + 1 < ry + rh || y + h + 1 == dims.1)
+        });
+        if result.aim_coord.is_some() && contained {
+            roi.update(&result, false);
+            result.stats.roi_hit = true;
+            return result;
+        }
+    }
+    let result = search(frame, params, motion, None);
+    roi.update(&result, true);
+    result
+}
+
+// `region` limits which pixels get matched, coords stay in frame space either way
+fn search(
+    frame: &Image<Vec<u8>, Bgra8>,
+    params: &AimParams,
+    motion: Option<&MotionMap>,
+    region: Option<(usize, usize, usize, usize)>,
+) -> AimResult {
+    let mut stats = AimStats::default();
+    let mut candidates = 0;
+    let mut motion_score = None;
+    let motion = motion.filter(|_| params.motion_thresh > 0.);
+
+    // min area for coordinate clusters
+    let min_area = (frame.w / 20) * (frame.h / 20);
+
+    // Search through image and find avg position of the target color
+    let (found_coords, searched_px) = match region {
+        Some((x, y, w, h)) => (
+            frame.detect_colors_in(
+                &params.target_colors,
+                params.color_thresh,
+                Coord::new(x, y),
+                w,
+                h,
+            ),
+            w * h,
+        ),
+        None => (
+            frame.detect_colors(&params.target_colors, params.color_thresh),
+            frame.w * frame.h,
+        ),
+    };
+    stats.matched_px = found_coords.len();
+
+    // full screen color washes match thousands of pixels, aiming at their centroid is just a random flick
+    let matched_fraction = stats.matched_px as f32 / searched_px.max(1) as f32;
+    if matched_fraction > params.wash_reject_fraction {
+        return AimResult {
+            relative_coord: Coord::new(0, 0),
+            cluster: None,
+            rejected: Vec::new(),
+            others: Vec::new(),
+            aim_coord: None,
+            candidates,
+            wash_rejected: true,
+            effective_divisor: params.aim_divisor,
+            cluster_mean: None,
+            confirmed: false,
+            motion_score,
+            matched_color: None,
+            stats,
+        };
+    }
+    // clusters come in priority order, the first acceptable one closest to the earliest target color wins.
+    //     The rest still get checked so the mirror can show every candidate
+    let mut clusters = find_clusters(&found_coords, 2, (frame.w, frame.h));
+    if params.prefer_largest {
+        clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.len())); // stable, ties stay closest first
+    }
+    candidates += clusters.len() as u32;
+    let mut rejected = Vec::new();
+    let mut others = Vec::new();
+    let mut best: Option<(usize, Vec<Coord<usize>>, Option<f32>)> = None; // color rank, cluster, motion
+    for cluster in clusters {
+        let (x, y, w, h) = Coord::bbox_xywh(&cluster[..]);
+        if w * h <= min_area {
+            stats.clusters_rejected_area += 1;
+            continue;
+        }
+        // kill feed banners & health bars are a lot wider than they are tall
+        let aspect = w as f32 / h.max(1) as f32;
+        if aspect < params.aspect_range.0 || aspect > params.aspect_range.1 {
+            stats.clusters_rejected_aspect += 1;
+            rejected.push(crop_space(cluster));
+            continue;
+        }
+        // posters & lights in the target color never move, players (almost) always do
+        let mut score = None;
+        if let Some(motion) = motion {
+            let cluster_score = motion.score((x, y, w, h));
+            if cluster_score < params.motion_thresh {
+                stats.clusters_rejected_static += 1;
+                rejected.push(crop_space(cluster));
+                continue;
+            }
+            score = Some(cluster_score);
+        }
+        let rank = color_rank(frame, &cluster, &params.target_colors);
+        match best {
+            Some((best_rank, _, _)) if rank >= best_rank => {
+                others.push(vec![CropPos::new(x, y), CropPos::new(x + w, y + h)]);
+            }
+            _ => {
+                if let Some((_, beaten, _)) = best.replace((rank, cluster, score)) {
+                    let (x, y, w, h) = Coord::bbox_xywh(&beaten[..]);
+                    others.push(vec![CropPos::new(x, y), CropPos::new(x + w, y + h)]);
+                }
+            }
+        }
+    }
+    let matched_color = best
+        .as_ref()
+        .map(|(rank, _, _)| params.target_colors[*rank]);
+    let cluster = best.map(|(_, cluster, score)| {
+        motion_score = score;
+        cluster
+    });
+
+    let mut cluster_mean = None;
+    let (offset, aim_coord) = match cluster {
+        Some(ref cluster) => {
+            let count = cluster.len();
+
+            // Getting avg position & color of detected points
+            let mut coord_sum = Coord::new(0, 0);
+            let mut color_sum = [0usize; 3];
+            cluster.iter().for_each(|&coord| {
+                coord_sum += coord;
+                let color = frame.get_pixel2d(coord).as_color();
+                color_sum[0] += color.r as usize;
+                color_sum[1] += color.g as usize;
+                color_sum[2] += color.b as usize;
+            });
+            cluster_mean = Some(Color::new(
+                (color_sum[0] / count) as u8,
+                (color_sum[1] / count) as u8,
+                (color_sum[2] / count) as u8,
+                255,
+            ));
+            // centroid stays in f32 until the very end, y_multiplier squashes the vertical distance from
+            //     the crosshair rather than the absolute y so it doesn't depend on where the target is
+            let centroid = Coord::new(
+                coord_sum.x as f32 / count as f32,
+                coord_sum.y as f32 / count as f32,
+            );
+            let center = frame_center((frame.w, frame.h));
+            let aim_y = center.y + (centroid.y - center.y) * params.y_multiplier;
+            let aim_coord = CropPos::new(centroid.x.round() as usize, aim_y.round() as usize);
+
+            (
+                Coord::new(centroid.x - center.x, aim_y - center.y),
+                Some(aim_coord),
+            )
+        }
+        // Found target pixels, but no clusters greater than min_area
+        None => (Coord::new(0., 0.), None),
+    };
+
+    // real outlines are contiguous, noise blobs and bits of ui mostly miss along their bbox
+    let confirmed = match cluster {
+        Some(ref cluster) if params.outline_confirm_fraction > 0. => {
+            let (x, y, w, h) = Coord::bbox_xywh(&cluster[..]);
+            let samples = perimeter_points(Coord::new(x, y), w, h, params.outline_samples as usize);
+            let matched = samples
+                .iter()
+                .filter(|&&c| {
+                    color_matches(
+                        frame.get_pixel2d(c).as_color(),
+                        matched_color.unwrap(),
+                        params.color_thresh,
+                    )
+                })
+                .count();
+            matched as f32 >= samples.len() as f32 * params.outline_confirm_fraction
+        }
+        Some(_) => true,
+        None => false,
+    };
+    let (offset, aim_coord) = match confirmed {
+        true => (offset, aim_coord),
+        false => (Coord::new(0., 0.), None),
+    };
+
+    // scaling for sensitivity, small (far away) clusters get gentler corrections than big ones
+    let effective_divisor = match cluster {
+        Some(ref cluster) => effective_divisor(params, cluster.len()),
+        None => params.aim_divisor,
+    };
+    let relative_coord = scale_offset(offset, effective_divisor);
+
+    AimResult {
+        relative_coord,
+        cluster: cluster.map(crop_space),
+        rejected,
+        others,
+        aim_coord,
+        candidates,
+        wash_rejected: false,
+        effective_divisor,
+        cluster_mean,
+        confirmed,
+        motion_score,
+        matched_color,
+        stats,
+    }
+}
+
+// search works on plain coords, they only become crop positions on the way out
+fn crop_space(cluster: Vec<Coord<usize>>) -> Vec<CropPos> {
+    cluster
+        .into_iter()
+        .map(|c| CropPos::new(c.x, c.y))
+        .collect()
+}
+
+// Index of the target color closest to the cluster's mean color, 0 without any competition
+fn color_rank(
+    frame: &Image<Vec<u8>, Bgra8>,
+    cluster: &[Coord<usize>],
+    colors: &[Color<u8>],
+) -> usize {
+    if colors.len() == 1 {
+        return 0;
+    }
+    let mut sum = [0usize; 3];
+    cluster.iter().for_each(|&coord| {
+        let color = frame.get_pixel2d(coord).as_color();
+        sum[0] += color.r as usize;
+        sum[1] += color.g as usize;
+        sum[2] += color.b as usize;
+    });
+    let count = cluster.len().max(1);
+    let mean = Color::new(
+        (sum[0] / count) as u8,
+        (sum[1] / count) as u8,
+        (sum[2] / count) as u8,
+        255,
+    );
+    colors
+        .iter()
+        .map(|&color| color_distance(mean, color))
+        .enumerate()
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .unwrap()
+        .0
+}
+
+// Frame to frame luma changes per block, averaged over roughly the last `frames` frames so a
+//     player pausing for a moment doesn't drop out right away. Sampled sparsely, it runs every frame
+#[derive(Debug, Clone)]
+pub struct MotionMap {
+    frames: u32,
+    dims: (usize, usize), // frame dims the buffers were made for
+    prev_luma: Vec<u8>,   // one entry per sampled pixel
+    scores: Vec<f32>,     // mean abs luma change per block
+}
+
+impl MotionMap {
+    const BLOCK: usize = 8;
+    const SAMPLE_STEP: usize = 2;
+
+    pub fn new(frames: u32) -> Self {
+        Self {
+            frames: frames.max(1),
+            dims: (0, 0),
+            prev_luma: Vec::new(),
+            scores: Vec::new(),
+        }
+    }
+
+    // Keeps the history, so a config reload doesn't make everything look static for a moment
+    pub fn set_frames(&mut self, frames: u32) {
+        self.frames = frames.max(1);
+    }
+
+    fn blocks_w(&self) -> usize {
+        (self.dims.0 + Self::BLOCK - 1) / Self::BLOCK
+    }
+
+    // `frame` is the luma of the frame detection runs on, see Image::luma_into
+    pub fn update(&mut self, frame: &Image<Vec<u8>, Gray8>) {
+        let (samples_w, samples_h) = (
+            (frame.w + Self::SAMPLE_STEP - 1) / Self::SAMPLE_STEP,
+            (frame.h + Self::SAMPLE_STEP - 1) / Self::SAMPLE_STEP,
+        );
+        let luma_at = |sx: usize, sy: usize| {
+            let c = Coord::new(sx * Self::SAMPLE_STEP, sy * Self::SAMPLE_STEP);
+            frame.get_pixel2d(c).rgba()[0]
+        };
+
+        // nothing to diff against after a resize, everything starts out as static
+        if self.dims != (frame.w, frame.h) {
+            self.dims = (frame.w, frame.h);
+            self.prev_luma = (0..samples_h)
+                .flat_map(|sy| (0..samples_w).map(move |sx| (sx, sy)))
+                .map(|(sx, sy)| luma_at(sx, sy))
+                .collect();
+            let blocks_h = (frame.h + Self::BLOCK - 1) / Self::BLOCK;
+            self.scores = vec![0.; self.blocks_w() * blocks_h];
+            return;
+        }
+
+        let blocks_w = self.blocks_w();
+        let samples_per_block = Self::BLOCK / Self::SAMPLE_STEP;
+        let mut diff_sums = vec![(0u32, 0u32); self.scores.len()]; // (sum, count)
+        for sy in 0..samples_h {
+            for sx in 0..samples_w {
+                let luma = luma_at(sx, sy);
+                let prev = &mut self.prev_luma[sy * samples_w + sx];
+                let block = (sy / samples_per_block) * blocks_w + sx / samples_per_block;
+                diff_sums[block].0 += (luma as i32 - *prev as i32).unsigned_abs();
+                diff_sums[block].1 += 1;
+                *prev = luma;
+            }
+        }
+
+        let alpha = 1. / self.frames as f32;
+        for (score, (sum, count)) in self.scores.iter_mut().zip(diff_sums) {
+            let diff = sum as f32 / count.max(1) as f32;
+            *score += (diff - *score) * alpha;
+        }
+    }
+
+    // Mean score of the blocks a bbox touches
+    pub fn score(&self, (x, y, w, h): (usize, usize, usize, usize)) -> f32 {
+        let blocks_w = self.blocks_w();
+        if blocks_w == 0 {
+            return 0.;
+        }
+        let blocks_h = self.scores.len() / blocks_w;
+        let cols = (x / Self::BLOCK).min(blocks_w - 1)..=((x + w) / Self::BLOCK).min(blocks_w - 1);
+        let rows = (y / Self::BLOCK).min(blocks_h - 1)..=((y + h) / Self::BLOCK).min(blocks_h - 1);
+        let n_blocks = cols.clone().count() * rows.clone().count();
+        let total: f32 = rows
+            .flat_map(|row| cols.clone().map(move |col| row * blocks_w + col))
+            .map(|idx| self.scores[idx])
+            .sum();
+        total / n_blocks.max(1) as f32
+    }
+}
+
+// Ramps the per-frame gain back up to 1 after the target switches, so the first correction toward
+//     the new target is a glide instead of a full jump
+#[derive(Debug, Clone)]
+pub struct SwitchRamp {
+    frames: u32, // 0 disables the ramp
+    start_gain: f32,
+    frame: u32,
+}
+
+impl SwitchRamp {
+    pub fn new(frames: u32, start_gain: f32) -> Self {
+        Self {
+            frames,
+            start_gain,
+            frame: frames,
+        }
+    }
+
+    pub fn trigger(&mut self) {
+        self.frame = 0;
+    }
+
+    // Gain for the current frame, advances the ramp by one frame
+    pub fn next_gain(&mut self) -> f32 {
+        if self.frame >= self.frames {
+            return 1.0;
+        }
+        let t = self.frame as f32 / self.frames as f32;
+        self.frame += 1;
+        self.start_gain + (1.0 - self.start_gain) * t
+    }
+}
+
+// Mean brightness of the square of `radius` px around the crosshair
+pub fn center_luma(luma: &Image<Vec<u8>, Gray8>, radius: usize) -> f32 {
+    let center = center_pixel((luma.w, luma.h));
+    let xs = center.x.saturating_sub(radius)..(center.x + radius + 1).min(luma.w);
+    let ys = center.y.saturating_sub(radius)..(center.y + radius + 1).min(luma.h);
+    let n_px = xs.len() * ys.len();
+    let sum: u32 = ys
+        .flat_map(|y| xs.clone().map(move |x| Coord::new(x, y)))
+        .map(|c| luma.get_pixel2d(c).rgba()[0] as u32)
+        .sum();
+    sum as f32 / n_px.max(1) as f32
+}
+
+// Exponential moving average over the per-frame mouse moves, `alpha` is how much of the previous average
+//     carries over so 0 turns it off. What rounding to whole counts drops carries into the next frame,
+//     small smoothed moves would otherwise round down to nothing forever
+#[derive(Debug, Clone)]
+pub struct AimSmoother {
+    alpha: f32,
+    smoothed: (f32, f32),
+    residual: (f32, f32),
+}
+
+impl AimSmoother {
+    pub fn new(alpha: f32) -> Self {
+        Self {
+            alpha,
+            smoothed: (0., 0.),
+            residual: (0., 0.),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.smoothed = (0., 0.);
+        self.residual = (0., 0.);
+    }
+
+    pub fn next(&mut self, relative: Coord<i32>) -> Coord<i32> {
+        if self.alpha == 0. {
+            return relative;
+        }
+        let alpha = self.alpha;
+        let ema = |prev: f32, new: i32| prev * alpha + new as f32 * (1. - alpha);
+        self.smoothed = (
+            ema(self.smoothed.0, relative.x),
+            ema(self.smoothed.1, relative.y),
+        );
+
+        let exact = (
+            self.smoothed.0 + self.residual.0,
+            self.smoothed.1 + self.residual.1,
+        );
+        let out = Coord::new(exact.0.round() as i32, exact.1.round() as i32);
+        self.residual = (exact.0 - out.x as f32, exact.1 - out.y as f32);
+        out
+    }
+}
+
+// Leads a moving target by `factor` frames of its measured velocity, 0 turns it off. The velocity is
+//     kept in px/s and turned back into a per frame lead with the current frame time, so a frame rate
+//     drop doesn't shrink (or a spike inflate) the lead
+#[derive(Debug, Clone)]
+pub struct LeadPredictor {
+    factor: f32,
+    last: Option<CropPos>,
+    velocity: Option<(f32, f32)>, // px/s
+}
+
+impl LeadPredictor {
+    const VELOCITY_SMOOTHING: f32 = 0.5; // a single noisy centroid shouldn't throw the lead around
+
+    pub fn new(factor: f32) -> Self {
+        Self {
+            factor,
+            last: None,
+            velocity: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.velocity = None;
+    }
+
+    // `switched` is a new target or a jump, the velocity between two different targets means nothing.
+    //     Returns the lead in px, None until two frames of the same target have been seen
+    pub fn update(
+        &mut self,
+        aim_coord: Option<CropPos>,
+        switched: bool,
+        frame_time: Duration,
+    ) -> Option<Coord<f32>> {
+        if self.factor == 0. {
+            return None;
+        }
+        let dt = frame_time.as_secs_f32();
+        let (cur, last) = match (aim_coord, self.last) {
+            (Some(cur), Some(last)) if !switched && dt > 0. => (cur, last),
+            _ => {
+                self.velocity = None;
+                self.last = aim_coord;
+                return None;
+            }
+        };
+        self.last = Some(cur);
+
+        let measured = (
+            (cur.x as f32 - last.x as f32) / dt,
+            (cur.y as f32 - last.y as f32) / dt,
+        );
+        let blend = |prev: f32, new: f32| {
+            prev * Self::VELOCITY_SMOOTHING + new * (1. - Self::VELOCITY_SMOOTHING)
+        };
+        let (vx, vy) = match self.velocity {
+            Some((vx, vy)) => (blend(vx, measured.0), blend(vy, measured.1)),
+            None => measured,
+        };
+        self.velocity = Some((vx, vy));
+        Some(Coord::new(vx * dt * self.factor, vy * dt * self.factor))
+    }
+}
+
+// A jump past 1/8 of the crop's width in a single frame counts as a target switch. Tracking a target
+//     (or the view turning toward it) moves it a lot less than that between frames, a different one
+//     taking over usually lands further away
+pub const SWITCH_JUMP_DIVISOR: usize = 8;
+
+// A target appearing, or the aim point jumping further than `jump_px` in a single frame
+pub fn is_target_switch(prev: Option<CropPos>, cur: Option<CropPos>, jump_px: usize) -> bool {
+    match (prev, cur) {
+        (None, Some(_)) => true,
+        (Some(prev), Some(cur)) => prev.square_dist(*cur) > (jump_px * jump_px) as i32,
+        _ => false,
+    }
+}
+
+// Maps a result computed on a downscaled frame back onto the full frame
+pub fn upscale_aim_result(
+    result: &mut AimResult,
+    small_dims: (usize, usize),
+    full_dims: (usize, usize),
+) {
+    // the result's coords are still the small frame's pixels at this point
+    let upscale = |c: CropPos| DetectPos::new(c.x, c.y).to_crop(small_dims, full_dims);
+    // each sampled pixel stands in for a whole block, so the overlay isn't a grid of dots
+    let (block_w, block_h) = (
+        (full_dims.0 as f32 / small_dims.0 as f32).ceil() as usize,
+        (full_dims.1 as f32 / small_dims.1 as f32).ceil() as usize,
+    );
+    let upscale_cluster = |cluster: &[CropPos]| -> Vec<CropPos> {
+        cluster
+            .iter()
+            .flat_map(|&c| {
+                let origin = upscale(c);
+                (0..block_h).flat_map(move |dy| {
+                    (0..block_w).map(move |dx| CropPos::new(origin.x + dx, origin.y + dy))
+                })
+            })
+            .filter(|c| c.x < full_dims.0 && c.y < full_dims.1)
+            .collect()
+    };
+
+    result.cluster = result.cluster.as_deref().map(&upscale_cluster);
+    result.rejected = result.rejected.iter().map(|c| upscale_cluster(&c[..])).collect();
+    result.others = result.others.iter().map(|c| upscale_cluster(&c[..])).collect();
+    result.aim_coord = result.aim_coord.map(upscale);
+
+    // recomputed from the full res aim coord instead of multiplying the already rounded delta
+    result.relative_coord = match result.aim_coord {
+        Some(aim) => {
+            let offset = offset_from_center(*aim, full_dims);
+            scale_offset(offset, result.effective_divisor)
+        }
+        None => Coord::new(0, 0),
+    };
+}
+
+// Mouse counts for a px offset from the center, rounded once at the very end
+fn scale_offset(offset: Coord<f32>, divisor: f32) -> Coord<i32> {
+    Coord::new(
+        (offset.x / divisor).round() as i32,
+        (offset.y / divisor).round() as i32,
+    )
+}
+
+// `n` points spread evenly around the bbox outline, starting at the top left corner
+fn perimeter_points(origin: Coord<usize>, w: usize, h: usize, n: usize) -> Vec<Coord<usize>> {
+    let perimeter = 2 * (w + h);
+    if perimeter == 0 {
+        return vec![origin];
+    }
+    (0..n)
+        .map(|i| {
+            let d = i * perimeter / n;
+            let (dx, dy) = if d < w {
+                (d, 0)
+            } else if d < w + h {
+                (w, d - w)
+            } else if d < 2 * w + h {
+                (w - (d - w - h), h)
+            } else {
+                (0, h - (d - 2 * w - h))
+            };
+            Coord::new(origin.x + dx, origin.y + dy)
+        })
+        .collect()
+}
+
+// Horizontal distance in px the scene moved between two frames, positive when it moved left (the
+//     view turned right). Slides a strip through the middle of `before` under `after` & takes the
+//     offset that lines up best. None when nothing lines up clearly better than the rest,
+//     eg. flat colors, motion blur, or a shift past `max_shift`
+pub fn measure_shift(
+    before: &Image<Vec<u8>, Bgra8>,
+    after: &Image<Vec<u8>, Bgra8>,
+    max_shift: usize,
+) -> Option<i32> {
+    const STRIP_HALF_H: usize = 8;
+    const MIN_CONTRAST: f32 = 2.; // mean luma difference of a typical misaligned offset
+
+    if (before.w, before.h) != (after.w, after.h) || before.w <= max_shift * 2 + 1 {
+        return None;
+    }
+    let (w, h) = (before.w, before.h);
+    let rows = (h / 2).saturating_sub(STRIP_HALF_H)..(h / 2 + STRIP_HALF_H).min(h);
+    let luma = |img: &Image<Vec<u8>, Bgra8>| -> Vec<Vec<i32>> {
+        rows.clone()
+            .map(|y| {
+                (0..w)
+                    .map(|x| {
+                        let [r, g, b, _] = img.get_pixel2d(Coord::new(x, y)).rgba();
+                        (r as i32 + 2 * g as i32 + b as i32) / 4
+                    })
+                    .collect()
+            })
+            .collect()
+    };
+    let (before_luma, after_luma) = (luma(before), luma(after));
+
+    let max_shift = max_shift as i32;
+    let cols = max_shift as usize..w - max_shift as usize;
+    let errors: Vec<(i32, f32)> = (-max_shift..=max_shift)
+        .map(|shift| {
+            let total: i32 = before_luma
+                .iter()
+                .zip(after_luma.iter())
+                .map(|(before_row, after_row)| {
+                    cols.clone()
+                        .map(|x| (after_row[x] - before_row[(x as i32 + shift) as usize]).abs())
+                        .sum::<i32>()
+                })
+                .sum();
+            (shift, total as f32 / (before_luma.len() * cols.len()).max(1) as f32)
+        })
+        .collect();
+
+    let (best_shift, best_err) = errors
+        .iter()
+        .copied()
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())?;
+    let mut sorted: Vec<f32> = errors.iter().map(|&(_, err)| err).collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let median_err = sorted[sorted.len() / 2];
+
+    // the best offset sitting on the edge of the search usually means the real one is past it
+    if median_err < MIN_CONTRAST || best_err * 2. > median_err || best_shift.abs() == max_shift {
+        return None;
+    }
+    Some(best_shift)
+}
+
+fn effective_divisor(params: &AimParams, cluster_area: usize) -> f32 {
+    let (min, max) = params.adaptive_scale_range;
+    let scale = (params.adaptive_ref_area as f32 / cluster_area.max(1) as f32).sqrt();
+    params.aim_divisor * scale.clamp(min, max)
+}
+
+// Only the in-bounds ones, coords on the image edges have fewer neighbors
+fn coord_neighbors(c: Coord<usize>, range: u32, dims: (usize, usize)) -> Vec<Coord<usize>> {
+    let (w, h) = dims;
+    (1..range as usize + 1)
+        .flat_map(|offset| {
+            let xinc = Some(c.x + offset).filter(|&x| x < w);
+            let yinc = Some(c.y + offset).filter(|&y| y < h);
+            let (xdec, ydec) = (c.x.checked_sub(offset), c.y.checked_sub(offset));
+            let (x, y) = (Some(c.x), Some(c.y));
+            [
+                (xdec, ydec),
+                (x, ydec),
+                (xinc, ydec),
+                (x, yinc),
+                (xinc, y),
+                (xdec, y),
+                (xdec, yinc),
+                (xinc, yinc),
+            ]
+        })
+        .filter_map(|(x, y)| Some(Coord::new(x?, y?)))
+        .collect()
+}
+
+// Every group of coords within `radius` of each other in one pass, sorted by how close their centroid
+//     is to the middle of the frame
+pub fn find_clusters(
+    coords: &FxHashSet<Coord<usize>>,
+    radius: u32,
+    dims: (usize, usize),
+) -> Vec<Vec<Coord<usize>>> {
+    let mut unvisited = coords.clone();
+    let mut clusters = Vec::new();
+    for &seed in coords {
+        if !unvisited.remove(&seed) {
+            continue; // already part of an earlier cluster
+        }
+        let mut cluster = vec![seed];
+        let mut i = 0;
+        while let Some(&coord) = cluster.get(i) {
+            for neighbor in coord_neighbors(coord, radius, dims) {
+                if unvisited.remove(&neighbor) {
+                    cluster.push(neighbor);
+                }
+            }
+            i += 1;
+        }
+        clusters.push(cluster);
+    }
+
+    let center = center_pixel(dims);
+    let centroid_dist = |cluster: &Vec<Coord<usize>>| {
+        let sum = cluster.iter().fold(Coord::new(0, 0), |sum, &c| sum + c);
+        let n = cluster.len();
+        Coord::new(sum.x / n, sum.y / n).square_dist(center)
+    };
+    clusters.sort_by_cached_key(centroid_dist);
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image;
+
+    // odd dims so the frame center lands on a pixel, (100, 50)
+    const FRAME_DIMS: (usize, usize) = (201, 101);
+
+    fn target_color() -> Color<u8> {
+        Color::new(255, 0, 255, 255)
+    }
+
+    fn params() -> AimParams {
+        AimParams {
+            target_colors: vec![target_color()],
+            color_thresh: 0.9,
+            aim_divisor: 1.,
+            y_multiplier: 1.,
+            wash_reject_fraction: 1.,
+            adaptive_ref_area: 1,
+            adaptive_scale_range: (1., 1.),
+            aspect_range: (0., 100.),
+            outline_samples: 16,
+            outline_confirm_fraction: 0.,
+            motion_thresh: 0.,
+            prefer_largest: false,
+        }
+    }
+
+    fn frame_with_blobs(blobs: &[(usize, usize, usize, usize)]) -> Image<Vec<u8>, Bgra8> {
+        frame_with_blobs_in(FRAME_DIMS, blobs)
+    }
+
+    // flat gray frame with a target colored rectangle for each (x, y, w, h)
+    fn frame_with_blobs_in(
+        dims: (usize, usize),
+        blobs: &[(usize, usize, usize, usize)],
+    ) -> Image<Vec<u8>, Bgra8> {
+        let mut frame = image::zeroed::<Bgra8>(dims.0, dims.1);
+        frame.fill_color(Color::new(40, 40, 40, 255));
+        for &(x0, y0, w, h) in blobs {
+            for y in y0..y0 + h {
+                for x in x0..x0 + w {
+                    frame.set2d(Coord::new(x, y), target_color());
+                }
+            }
+        }
+        frame
+    }
+
+    #[test]
+    fn no_target() {
+        let result = compute_aim(&frame_with_blobs(&[]), &params(), None, None);
+        assert_eq!(result.aim_coord, None);
+        assert!(result.cluster.is_none());
+        assert_eq!(result.relative_coord, Coord::new(0, 0));
+        assert_eq!(result.candidates, 0);
+        assert_eq!(result.stats.matched_px, 0);
+    }
+
+    #[test]
+    fn single_target_off_center() {
+        // centroid at (150, 35)
+        let result = compute_aim(
+            &frame_with_blobs(&[(140, 20, 21, 31)]),
+            &params(),
+            None,
+            None,
+        );
+        assert_eq!(result.aim_coord, Some(CropPos::new(150, 35)));
+        assert_eq!(result.relative_coord, Coord::new(50, -15));
+        assert_eq!(result.cluster.map(|c| c.len()), Some(21 * 31));
+        assert_eq!(result.candidates, 1);
+        assert!(result.confirmed);
+    }
+
+    #[test]
+    fn target_below_min_area() {
+        // min area is (201 / 20) * (101 / 20) = 50, a 5x5 blob's bbox is 4x4
+        let result = compute_aim(&frame_with_blobs(&[(60, 60, 5, 5)]), &params(), None, None);
+        assert_eq!(result.aim_coord, None);
+        assert_eq!(result.relative_coord, Coord::new(0, 0));
+        assert_eq!(result.candidates, 1);
+        assert_eq!(result.stats.clusters_rejected_area, 1);
+    }
+
+    // a small blob right next to the crosshair & a big one off to the left
+    fn two_targets() -> Image<Vec<u8>, Bgra8> {
+        frame_with_blobs(&[(90, 40, 11, 11), (10, 20, 41, 61)])
+    }
+
+    #[test]
+    fn two_targets_closest() {
+        let result = compute_aim(&two_targets(), &params(), None, None);
+        assert_eq!(result.aim_coord, Some(CropPos::new(95, 45)));
+        assert_eq!(result.candidates, 2);
+        assert_eq!(
+            result.others,
+            vec![vec![CropPos::new(10, 20), CropPos::new(50, 80)]]
+        );
+    }
+
+    #[test]
+    fn two_targets_largest() {
+        let params = AimParams {
+            prefer_largest: true,
+            ..params()
+        };
+        let result = compute_aim(&two_targets(), &params, None, None);
+        assert_eq!(result.aim_coord, Some(CropPos::new(30, 50)));
+        assert_eq!(result.candidates, 2);
+        assert_eq!(
+            result.others,
+            vec![vec![CropPos::new(90, 40), CropPos::new(100, 50)]]
+        );
+    }
+
+    // even dims put the center between pixels, at (99.5, 49.5)
+    const EVEN_DIMS: (usize, usize) = (200, 100);
+
+    #[test]
+    fn even_frame_centered_target() {
+        // 20x20 blob covering 90..=109 & 40..=59, its centroid is the exact center
+        let frame = frame_with_blobs_in(EVEN_DIMS, &[(90, 40, 20, 20)]);
+        let result = compute_aim(&frame, &params(), None, None);
+        assert!(result.aim_coord.is_some());
+        assert_eq!(result.relative_coord, Coord::new(0, 0));
+    }
+
+    #[test]
+    fn even_frame_offsets_symmetric() {
+        // centroids at x 109.5 & 89.5, 10px either side of the center. Measured from w / 2 they'd
+        //     round to 10 & -11
+        let right = frame_with_blobs_in(EVEN_DIMS, &[(100, 40, 20, 20)]);
+        let left = frame_with_blobs_in(EVEN_DIMS, &[(80, 40, 20, 20)]);
+        let right = compute_aim(&right, &params(), None, None).relative_coord;
+        let left = compute_aim(&left, &params(), None, None).relative_coord;
+        assert_eq!(right, Coord::new(10, 0));
+        assert_eq!(left, Coord::new(-10, 0));
+    }
+
+    #[test]
+    fn odd_frame_centered_target() {
+        // 21x21 blob around the center pixel (100, 50)
+        let result = compute_aim(
+            &frame_with_blobs(&[(90, 40, 21, 21)]),
+            &params(),
+            None,
+            None,
+        );
+        assert_eq!(result.aim_coord, Some(CropPos::new(100, 50)));
+        assert_eq!(result.relative_coord, Coord::new(0, 0));
+    }
+
+    // where the old y_multiplier aimed, it scaled the centroid's absolute y & truncated
+    fn legacy_relative_y(centroid_y: f32, center_y: f32, y_multiplier: f32) -> f32 {
+        (centroid_y * y_multiplier).trunc() - center_y
+    }
+
+    #[test]
+    fn y_multiplier_scales_offset_from_center() {
+        let params = AimParams {
+            y_multiplier: 0.8,
+            ..params()
+        };
+        // 21x21 blobs with their centroid at the top, middle & bottom of the crop, the center's y is 50
+        for (y, new_y, old_y) in [(15, -28, -38), (50, 0, -10), (85, 28, 18)] {
+            let frame = frame_with_blobs(&[(90, y - 10, 21, 21)]);
+            let result = compute_aim(&frame, &params, None, None);
+            assert_eq!(result.relative_coord, Coord::new(0, new_y));
+            assert_eq!(legacy_relative_y(y as f32, 50., 0.8), old_y as f32);
+            // the old one aimed a fixed (1 - 0.8) * 50px higher, wherever the target was
+            assert_eq!(new_y - old_y, 10);
+        }
+    }
+
+    fn coords_sorted(mut coords: Vec<Coord<usize>>) -> Vec<Coord<usize>> {
+        coords.sort_by_key(|c| (c.y, c.x));
+        coords
+    }
+
+    #[test]
+    fn neighbors_at_top_left_corner() {
+        // only the right, down & diagonal down-right arms are left, nothing wraps around
+        let neighbors = coords_sorted(coord_neighbors(Coord::new(0, 0), 2, (10, 10)));
+        let expected = coords_sorted(vec![
+            Coord::new(1, 0),
+            Coord::new(2, 0),
+            Coord::new(0, 1),
+            Coord::new(1, 1),
+            Coord::new(0, 2),
+            Coord::new(2, 2),
+        ]);
+        assert_eq!(neighbors, expected);
+    }
+
+    #[test]
+    fn neighbors_at_bottom_right_corner() {
+        let neighbors = coords_sorted(coord_neighbors(Coord::new(9, 7), 2, (10, 8)));
+        let expected = coords_sorted(vec![
+            Coord::new(7, 5),
+            Coord::new(9, 5),
+            Coord::new(8, 6),
+            Coord::new(9, 6),
+            Coord::new(7, 7),
+            Coord::new(8, 7),
+        ]);
+        assert_eq!(neighbors, expected);
+    }
+
+    #[test]
+    fn neighbors_range_past_the_image() {
+        let dims = (3, 2);
+        for y in 0..dims.1 {
+            for x in 0..dims.0 {
+                for c in coord_neighbors(Coord::new(x, y), 5, dims) {
+                    assert!(c.x < dims.0 && c.y < dims.1, "{:?} out of {:?}", c, dims);
+                    assert_ne!(c, Coord::new(x, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn clusters_touching_corners() {
+        // 3x3 blocks in the top left & bottom right corners, everything else empty
+        let dims = (20, 10);
+        let (w, h) = dims;
+        let mut coords = FxHashSet::default();
+        for y in 0..3 {
+            for x in 0..3 {
+                coords.insert(Coord::new(x, y));
+                coords.insert(Coord::new(w - 1 - x, h - 1 - y));
+            }
+        }
+
+        let clusters = find_clusters(&coords, 2, dims);
+        assert_eq!(clusters.len(), 2);
+        for cluster in &clusters {
+            assert_eq!(cluster.len(), 9);
+            assert!(cluster.iter().all(|c| c.x < w && c.y < h));
+        }
+        let has = |c| {
+            clusters
+                .iter()
+                .filter(|cluster| cluster.contains(&c))
+                .count()
+        };
+        // 9 apiece, so the two corners can't have ended up in the same one
+        assert_eq!(has(Coord::new(0, 0)), 1);
+        assert_eq!(has(Coord::new(w - 1, h - 1)), 1);
+    }
+
+    #[test]
+    fn target_in_frame_corner() {
+        // a blob filling the bottom right corner gets aimed at like any other
+        let (w, h) = FRAME_DIMS;
+        let result = compute_aim(
+            &frame_with_blobs(&[(w - 21, h - 21, 21, 21)]),
+            &params(),
+            None,
+            None,
+        );
+        assert_eq!(result.aim_coord, Some(CropPos::new(w - 11, h - 11)));
+        assert_eq!(result.cluster.map(|c| c.len()), Some(21 * 21));
+        assert_eq!(result.candidates, 1);
+    }
+
+    #[test]
+    fn downscaled_result_matches_full_res() {
+        const FACTOR: usize = 2;
+        let frame = frame_with_blobs(&[(130, 20, 41, 31), (20, 60, 15, 25)]);
+        let full_dims = (frame.w, frame.h);
+        let full = compute_aim(&frame, &params(), None, None);
+
+        let small_dims = (frame.w / FACTOR, frame.h / FACTOR);
+        let small = frame.scale_nearest(small_dims.0, small_dims.1).unwrap();
+        let mut upscaled = compute_aim(&small, &params(), None, None);
+        upscale_aim_result(&mut upscaled, small_dims, full_dims);
+
+        // every small pixel stands for a FACTOR sized block, the two can't agree any closer than that
+        let (a, b) = (full.aim_coord.unwrap(), upscaled.aim_coord.unwrap());
+        assert!(
+            a.x.abs_diff(b.x) <= FACTOR && a.y.abs_diff(b.y) <= FACTOR,
+            "{:?} vs {:?}",
+            a,
+            b
+        );
+        let (a, b) = (full.relative_coord, upscaled.relative_coord);
+        assert!(
+            (a.x - b.x).abs() <= FACTOR as i32 && (a.y - b.y).abs() <= FACTOR as i32,
+            "{:?} vs {:?}",
+            a,
+            b
+        );
+
+        // the upscaled cluster covers the same box, not just every FACTOR-th pixel of it
+        let (fx, fy, fw, fh) = CropPos::bbox_xywh(&full.cluster.unwrap());
+        let (ux, uy, uw, uh) = CropPos::bbox_xywh(&upscaled.cluster.unwrap());
+        for (f, u) in [(fx, ux), (fy, uy), (fx + fw, ux + uw), (fy + fh, uy + uh)] {
+            assert!(
+                f.abs_diff(u) <= FACTOR,
+                "{:?} vs {:?}",
+                (fx, fy, fw, fh),
+                (ux, uy, uw, uh)
+            );
+        }
+        assert_eq!(upscaled.others.len(), 1);
+    }
+
+    #[test]
+    fn color_wash_rejected() {
+        let params = AimParams {
+            wash_reject_fraction: 0.5,
+            ..params()
+        };
+        let mut frame = frame_with_blobs(&[]);
+        frame.fill_color(target_color());
+        let result = compute_aim(&frame, &params, None, None);
+        assert!(result.wash_rejected);
+        assert_eq!(result.aim_coord, None);
+        assert_eq!(result.relative_coord, Coord::new(0, 0));
+
+        // a normal sized target stays well under the fraction
+        let result = compute_aim(&frame_with_blobs(&[(130, 20, 41, 31)]), &params, None, None);
+        assert!(!result.wash_rejected);
+        assert!(result.aim_coord.is_some());
+    }
+
+    #[test]
+    fn aspect_filter_rejects_strip() {
+        // a health bar like strip across the top, the blob below it is the target
+        let params = AimParams {
+            aspect_range: (0.3, 3.0),
+            ..params()
+        };
+        let result = compute_aim(
+            &frame_with_blobs(&[(0, 0, 200, 10), (120, 15, 40, 80)]),
+            &params,
+            None,
+            None,
+        );
+        assert_eq!(result.cluster.map(|c| c.len()), Some(40 * 80));
+        assert_eq!(result.aim_coord, Some(CropPos::new(140, 55)));
+        assert_eq!(result.stats.clusters_rejected_aspect, 1);
+        assert_eq!(result.rejected.len(), 1);
+        assert_eq!(CropPos::bbox_xywh(&result.rejected[0]), (0, 0, 199, 9));
+        assert!(result.others.is_empty());
+    }
+
+    #[test]
+    fn effective_divisor_by_cluster_size() {
+        let params = AimParams {
+            aim_divisor: 2.,
+            adaptive_ref_area: 1000,
+            adaptive_scale_range: (0.5, 2.),
+            ..params()
+        };
+        assert_eq!(effective_divisor(&params, 1000), 2.);
+        // smaller (further away) gets a bigger divisor, so a gentler move
+        assert_eq!(effective_divisor(&params, 250), 4.);
+        assert_eq!(effective_divisor(&params, 4000), 1.);
+        // clamped to the range on both ends
+        assert_eq!(effective_divisor(&params, 10), 4.);
+        assert_eq!(effective_divisor(&params, 100_000), 1.);
+
+        // same offset from the center, only the size differs
+        let small = compute_aim(&frame_with_blobs(&[(145, 45, 10, 10)]), &params, None, None);
+        let large = compute_aim(&frame_with_blobs(&[(130, 30, 40, 40)]), &params, None, None);
+        assert!(small.effective_divisor > large.effective_divisor);
+        assert!(small.relative_coord.x < large.relative_coord.x);
+    }
+
+    #[test]
+    fn unit_scale_range_keeps_aim_divisor() {
+        let params = AimParams {
+            aim_divisor: 2.5,
+            adaptive_ref_area: 1000,
+            ..params()
+        };
+        for area in [0, 1, 10, 999, 1000, 1001, 100_000] {
+            assert_eq!(
+                effective_divisor(&params, area),
+                params.aim_divisor,
+                "area {}",
+                area
+            );
+        }
+    }
+
+    #[test]
+    fn outline_confirm() {
+        let confirming = AimParams {
+            outline_confirm_fraction: 0.5,
+            ..params()
+        };
+        let (x, y, w, h) = (130, 20, 40, 40);
+        // 2px thick hollow box, like a highlighted player outline
+        let outline = [
+            (x, y, w, 2),
+            (x, y + h - 2, w, 2),
+            (x, y, 2, h),
+            (x + w - 2, y, 2, h),
+        ];
+        // only touches its bbox at the middle of each side
+        let cross = [(x + w / 2 - 1, y, 3, h), (x, y + h / 2 - 1, w, 3)];
+
+        for blobs in [&outline[..], &[(x, y, w, h)]] {
+            let result = compute_aim(&frame_with_blobs(blobs), &confirming, None, None);
+            assert!(result.confirmed, "{:?}", blobs);
+            assert_eq!(result.aim_coord, Some(CropPos::new(150, 40)), "{:?}", blobs);
+        }
+
+        let result = compute_aim(&frame_with_blobs(&cross), &confirming, None, None);
+        assert!(result.cluster.is_some());
+        assert!(!result.confirmed);
+        assert_eq!(result.aim_coord, None);
+        assert_eq!(result.relative_coord, Coord::new(0, 0));
+
+        // the check is off at 0
+        let result = compute_aim(&frame_with_blobs(&cross), &params(), None, None);
+        assert!(result.confirmed);
+        assert!(result.aim_coord.is_some());
+    }
+
+    #[test]
+    fn perimeter_points_on_bbox_edges() {
+        let origin = Coord::new(10, 20);
+        let points = perimeter_points(origin, 8, 4, 12);
+        assert_eq!(points.len(), 12);
+        assert_eq!(points[0], origin);
+        for p in points {
+            let on_x_edge = p.x == 10 || p.x == 18;
+            let on_y_edge = p.y == 20 || p.y == 24;
+            assert!(
+                (10..=18).contains(&p.x) && (20..=24).contains(&p.y),
+                "{:?}",
+                p
+            );
+            assert!(on_x_edge || on_y_edge, "{:?} isn't on the bbox edge", p);
+        }
+        assert_eq!(perimeter_points(origin, 0, 0, 5), vec![origin]);
+    }
+
+    // a static blob on the crosshair & one sliding right 4px a frame, fed through a MotionMap
+    fn motion_run(params: &AimParams) -> AimResult {
+        let mut motion = MotionMap::new(4);
+        let mut luma = image::zeroed::<Gray8>(0, 0);
+        let mut result = None;
+        for i in 0..8 {
+            let frame = frame_with_blobs(&[(85, 35, 30, 30), (10 + 4 * i, 30, 25, 30)]);
+            frame.luma_into(&mut luma);
+            motion.update(&luma);
+            result = Some(compute_aim(&frame, params, Some(&motion), None));
+        }
+        result.unwrap()
+    }
+
+    #[test]
+    fn motion_gate_prefers_moving_blob() {
+        let params = AimParams {
+            motion_thresh: 1.,
+            ..params()
+        };
+        let result = motion_run(&params);
+        let (x, _, w, _) = CropPos::bbox_xywh(&result.cluster.unwrap());
+        assert_eq!((x, w), (38, 24));
+        assert!(result.motion_score.unwrap() >= 1.);
+        assert_eq!(result.stats.clusters_rejected_static, 1);
+        assert_eq!(CropPos::bbox_xywh(&result.rejected[0]), (85, 35, 29, 29));
+    }
+
+    #[test]
+    fn zero_motion_thresh_skips_gate() {
+        // closest to the crosshair wins like without a map at all
+        let result = motion_run(&params());
+        assert_eq!(
+            CropPos::bbox_xywh(&result.cluster.unwrap()),
+            (85, 35, 29, 29)
+        );
+        assert_eq!(result.motion_score, None);
+        assert_eq!(result.stats.clusters_rejected_static, 0);
+        assert!(result.rejected.is_empty());
+    }
+
+    #[test]
+    fn motion_map_static_frame_scores_zero() {
+        let mut motion = MotionMap::new(4);
+        let mut luma = image::zeroed::<Gray8>(0, 0);
+        let frame = frame_with_blobs(&[(85, 35, 30, 30)]);
+        frame.luma_into(&mut luma);
+        for _ in 0..4 {
+            motion.update(&luma);
+        }
+        assert_eq!(motion.score((0, 0, FRAME_DIMS.0 - 1, FRAME_DIMS.1 - 1)), 0.);
+        // no frames yet, nothing to score against
+        assert_eq!(MotionMap::new(4).score((0, 0, 10, 10)), 0.);
+    }
+
+    #[test]
+    fn switch_ramp_glides_to_teleported_target() {
+        const RAMP_FRAMES: u32 = 5;
+        const START_GAIN: f32 = 0.3;
+        let jump_px = FRAME_DIMS.0 / SWITCH_JUMP_DIVISOR;
+        let mut ramp = SwitchRamp::new(RAMP_FRAMES, START_GAIN);
+        let mut prev = None;
+        // the blob's top left in the world, the view turns by whatever was sent each frame
+        let (mut world_x, mut view_x) = (130i32, 0i32);
+        // (full correction, sent move, switched) per frame
+        let mut frame = |world_x: i32, view_x: &mut i32| {
+            let x = (world_x - *view_x) as usize;
+            let result = compute_aim(&frame_with_blobs(&[(x, 40, 21, 21)]), &params(), None, None);
+            let switched = is_target_switch(prev, result.aim_coord, jump_px);
+            prev = result.aim_coord;
+            if switched {
+                ramp.trigger();
+            }
+            let full = result.relative_coord.x;
+            let sent = (full as f32 * ramp.next_gain()) as i32;
+            *view_x += sent;
+            (full, sent, switched)
+        };
+
+        // appearing counts as a switch too, settled on it well after the ramp's over
+        for _ in 0..10 {
+            frame(world_x, &mut view_x);
+        }
+        assert_eq!(frame(world_x, &mut view_x), (0, 0, false));
+
+        world_x += 60;
+        let (full, sent, switched) = frame(world_x, &mut view_x);
+        assert!(switched);
+        assert!(full > jump_px as i32);
+        assert!(
+            sent as f32 <= full as f32 * START_GAIN,
+            "{} of {}",
+            sent,
+            full
+        );
+        // the glide itself never looks like another switch, & each frame gets a bigger share
+        let mut last_share = sent as f32 / full as f32;
+        for _ in 1..RAMP_FRAMES {
+            let (full, sent, switched) = frame(world_x, &mut view_x);
+            assert!(!switched);
+            if full != 0 {
+                let share = sent as f32 / full as f32;
+                assert!(share >= last_share, "{} after {}", share, last_share);
+                last_share = share;
+            }
+        }
+        let (full, sent, switched) = frame(world_x, &mut view_x);
+        assert!(!switched);
+        assert_eq!(sent, full);
+    }
+
+    fn hash(mut v: u32) -> u32 {
+        v = (v ^ v >> 16).wrapping_mul(0x045d_9f3b);
+        v = (v ^ v >> 16).wrapping_mul(0x045d_9f3b);
+        v ^ v >> 16
+    }
+
+    // scene with no repeating pattern, `scroll` px further right in the world than the plain one
+    fn textured_frame(scroll: i32) -> Image<Vec<u8>, Bgra8> {
+        let mut frame = image::zeroed::<Bgra8>(FRAME_DIMS.0, FRAME_DIMS.1);
+        for y in 0..frame.h {
+            for x in 0..frame.w {
+                let world_x = (x as i32 + scroll + 100) as u32;
+                let v = hash(world_x ^ (y as u32 / 4) << 16) >> 24;
+                frame.set2d(Coord::new(x, y), Color::new(v as u8, v as u8, v as u8, 255));
+            }
+        }
+        frame
+    }
+
+    #[test]
+    fn measure_shift_finds_scroll() {
+        let before = textured_frame(0);
+        for shift in [1, 3, -3, 7, -7] {
+            // positive when the scene moved left, ie. the view turned right
+            assert_eq!(
+                measure_shift(&before, &textured_frame(shift), 10),
+                Some(shift)
+            );
+        }
+        assert_eq!(measure_shift(&before, &before, 10), Some(0));
+        // further than the search goes
+        assert_eq!(measure_shift(&before, &textured_frame(15), 10), None);
+    }
+
+    #[test]
+    fn measure_shift_flat_frame() {
+        let flat = frame_with_blobs(&[]);
+        assert_eq!(measure_shift(&flat, &flat, 10), None);
+        // too narrow for the search range
+        let before = textured_frame(0);
+        assert_eq!(
+            measure_shift(&before, &textured_frame(3), FRAME_DIMS.0 / 2),
+            None
+        );
+    }
+}
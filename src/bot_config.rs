@@ -0,0 +1,248 @@
+use crate::aim::AimParams;
+use crate::config::{Bounded, CfgKey, Choice, Config, ValType};
+use crate::input::{validated_keycode, KeySource};
+use crate::logging::log_err_once;
+
+use std::time::Duration;
+
+// Everything the aim thread reads from the config, taken in one go on each reload so a frame never sees
+//     half of a change. Keys added for the aim thread get read here and nowhere else
+#[derive(Debug, Clone)]
+pub struct AimConfig {
+    pub aim_params: AimParams,
+    pub aim_key: u16,
+    pub toggle_key: u16,
+    pub calibrate_key: u16,
+    pub screenshot_key: u16,
+    pub toggle_hold: Duration,
+    pub fov_radius: u32, // 0 aims at anything in the crop
+    pub downscale: u32,
+    pub record_frames: bool,
+    pub roi_search: bool,
+    pub motion_gate_frames: u32,
+    pub idle_minutes: u32,       // 0 never idles out
+    pub trigger_luma_delta: u32, // 0 triggers on the color match instead
+    pub switch_ramp: (u32, f32), // (frames, starting gain)
+    pub smoothing_factor: f32,
+    pub prediction_factor: f32,
+    pub mirror_budget: usize,
+}
+
+impl AimConfig {
+    pub fn from_config(cfg: &Config) -> Self {
+        let aim_params = AimParams {
+            target_colors: cfg.get(CfgKey::TargetColor).into(),
+            color_thresh: <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::ColorThresh)).val,
+            // a measured pixels_per_count turns the pixel error into exact counts, aim_divisor is a guess
+            aim_divisor: match <ValType as Into<Bounded<f32>>>::into(
+                cfg.get(CfgKey::PixelsPerCount),
+            )
+            .val
+            {
+                ppc if ppc > 0. => ppc,
+                _ => <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::AimDivisor)).val,
+            },
+            y_multiplier: <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::YOffsetMultiplier))
+                .val,
+            wash_reject_fraction: <ValType as Into<Bounded<_>>>::into(
+                cfg.get(CfgKey::WashRejectFraction),
+            )
+            .val,
+            adaptive_ref_area: <ValType as Into<Bounded<_>>>::into(
+                cfg.get(CfgKey::AdaptiveRefArea),
+            )
+            .val,
+            adaptive_scale_range: (
+                <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::AdaptiveScaleMin)).val,
+                <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::AdaptiveScaleMax)).val,
+            ),
+            aspect_range: (
+                <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::MinClusterAspect)).val,
+                <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::MaxClusterAspect)).val,
+            ),
+            outline_samples: <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::OutlineSamples))
+                .val,
+            outline_confirm_fraction: <ValType as Into<Bounded<_>>>::into(
+                cfg.get(CfgKey::OutlineConfirmFraction),
+            )
+            .val,
+            motion_thresh: <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::MotionGateThresh))
+                .val,
+            prefer_largest: <ValType as Into<Choice>>::into(cfg.get(CfgKey::TargetPriority))
+                .as_str()
+                == "largest",
+        };
+
+        Self {
+            aim_params,
+            aim_key: validated_keycode(cfg, CfgKey::AimKeycode),
+            toggle_key: validated_keycode(cfg, CfgKey::ToggleAimKeycode),
+            calibrate_key: validated_keycode(cfg, CfgKey::CalibrateKeycode),
+            screenshot_key: validated_keycode(cfg, CfgKey::ScreenshotKeycode),
+            toggle_hold: toggle_hold_duration(cfg),
+            fov_radius: <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::FovRadius)).val,
+            downscale: <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::DetectDownscale)).val,
+            record_frames: cfg.get(CfgKey::RecordFrames).into(),
+            roi_search: cfg.get(CfgKey::RoiSearch).into(),
+            motion_gate_frames: <ValType as Into<Bounded<_>>>::into(
+                cfg.get(CfgKey::MotionGateFrames),
+            )
+            .val,
+            idle_minutes: <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::IdleDisableMinutes))
+                .val,
+            trigger_luma_delta: <ValType as Into<Bounded<_>>>::into(
+                cfg.get(CfgKey::TriggerLumaDelta),
+            )
+            .val,
+            switch_ramp: (
+                <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::SwitchRampFrames)).val,
+                <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::SwitchRampStart)).val,
+            ),
+            smoothing_factor: <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::SmoothingFactor))
+                .val,
+            prediction_factor: <ValType as Into<Bounded<_>>>::into(
+                cfg.get(CfgKey::PredictionFactor),
+            )
+            .val,
+            mirror_budget: <ValType as Into<Bounded<u32>>>::into(cfg.get(CfgKey::MirrorPixelBudget))
+                .val as usize,
+        }
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_minutes as u64 * 60)
+    }
+
+    pub fn motion_gated(&self) -> bool {
+        self.aim_params.motion_thresh > 0.
+    }
+
+    // the grayscale copy is skipped entirely when nothing reads it
+    pub fn needs_luma(&self) -> bool {
+        self.motion_gated() || self.trigger_luma_delta > 0
+    }
+
+    // Params for detection on the downscaled frame, cluster areas shrink with the square of the factor
+    pub fn small_aim_params(&self) -> AimParams {
+        AimParams {
+            adaptive_ref_area: (self.aim_params.adaptive_ref_area
+                / (self.downscale * self.downscale))
+                .max(1),
+            ..self.aim_params.clone()
+        }
+    }
+}
+
+// Same as AimConfig, for the click thread
+#[derive(Debug, Clone, Copy)]
+pub struct ClickConfig {
+    pub autoclick_key: u16,
+    pub autoclick_source: KeySource,
+    pub toggle_autoclick_key: u16,
+    pub fake_lmb_key: u16,
+    pub toggle_hold: Duration,
+    pub autoclick_max: Duration,
+    pub stabilize: Duration,
+    pub sleep_range: (u32, u32), // (min, max) ms per click half, never reversed
+    pub normal_timing: bool,
+}
+
+impl ClickConfig {
+    pub fn from_config(cfg: &Config) -> Self {
+        let autoclick_max_secs: u32 =
+            <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::AutoclickMaxSeconds)).val;
+        let stabilize_ms: u32 =
+            <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::ClickStabilizeMs)).val;
+
+        let mut max_sleep: u32 =
+            <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::MaxAutoclickSleepMs)).val;
+        let mut min_sleep: u32 =
+            <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::MinAutoclickSleepMs)).val;
+        // checked again on every reload, only worth saying once per distinct problem
+        if max_sleep < min_sleep {
+            log_err_once!(
+                "{} shouldn't be less than {}\n Using swapped values",
+                CfgKey::MaxAutoclickSleepMs.as_string(),
+                CfgKey::MinAutoclickSleepMs.as_string()
+            );
+            std::mem::swap(&mut max_sleep, &mut min_sleep);
+        }
+
+        Self {
+            autoclick_key: validated_keycode(cfg, CfgKey::AutoclickKeycode),
+            autoclick_source: KeySource::autoclick(cfg),
+            toggle_autoclick_key: validated_keycode(cfg, CfgKey::ToggleAutoclickKeycode),
+            fake_lmb_key: validated_keycode(cfg, CfgKey::FakeLmbKeycode),
+            toggle_hold: toggle_hold_duration(cfg),
+            autoclick_max: Duration::from_secs(autoclick_max_secs as u64),
+            stabilize: Duration::from_millis(stabilize_ms as u64),
+            sleep_range: (min_sleep, max_sleep),
+            normal_timing: <ValType as Into<Choice>>::into(
+                cfg.get(CfgKey::ClickTimingDistribution),
+            )
+            .as_str()
+                == "normal",
+        }
+    }
+}
+
+// Same as AimConfig, for the capture thread
+#[derive(Debug, Clone, Copy)]
+pub struct CaptureConfig {
+    pub fps: u32,
+    pub crop_percent: (f32, f32), // (w, h) fractions cut off each side, resolved against the captured output
+    pub timeout_ms: u32,          // 0 polls instead of blocking
+    pub monitor_idx: u32,
+    pub retry_budget: Duration, // how long to wait on a free duplication slot
+}
+
+impl CaptureConfig {
+    pub fn from_config(cfg: &Config) -> Self {
+        let retry_secs: u32 =
+            <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::DuplicationRetrySecs)).val;
+
+        Self {
+            fps: <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::Fps)).val,
+            crop_percent: (
+                <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::CropWPercent)).val,
+                <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::CropHPercent)).val,
+            ),
+            timeout_ms: <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::CaptureTimeoutMs)).val,
+            monitor_idx: <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::MonitorIndex)).val,
+            retry_budget: Duration::from_secs(retry_secs as u64),
+        }
+    }
+
+    // how long a polling capture sleeps after finding no new frame
+    pub fn poll_sleep(&self) -> Duration {
+        Duration::from_secs_f32(1. / self.fps as f32)
+    }
+}
+
+// Same as AimConfig, for the mover thread
+#[derive(Debug, Clone, Copy)]
+pub struct MoverConfig {
+    pub aim_duration: Duration, // how long one correction gets spread over
+    pub aim_steps: u32,
+}
+
+impl MoverConfig {
+    pub fn from_config(cfg: &Config) -> Self {
+        let aim_dur: u32 =
+            <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::AimDurationMicros)).val;
+
+        Self {
+            aim_duration: Duration::from_micros(aim_dur as u64),
+            aim_steps: <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::AimSteps)).val,
+        }
+    }
+
+    pub fn step_dur(&self) -> Duration {
+        self.aim_duration / self.aim_steps
+    }
+}
+
+fn toggle_hold_duration(cfg: &Config) -> Duration {
+    let hold_ms: u32 = <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::ToggleHoldMs)).val;
+    Duration::from_millis(hold_ms as u64)
+}
@@ -6,13 +6,19 @@ use windows::{
         Graphics::{
             Direct3D::D3D_DRIVER_TYPE_UNKNOWN,
             Direct3D11::{
-                D3D11CreateDevice, ID3D11Device, ID3D11DeviceContext, ID3D11Texture2D,
+                D3D11CreateDevice, ID3D11Device, ID3D11DeviceContext, ID3D11Texture2D, D3D11_BOX,
                 D3D11_CPU_ACCESS_READ, D3D11_SDK_VERSION, D3D11_USAGE_STAGING,
             },
             Dxgi::{
-                CreateDXGIFactory1, IDXGIFactory1, IDXGIOutput, IDXGIOutput1,
+                Common::{
+                    DXGI_FORMAT, DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_B8G8R8A8_UNORM_SRGB,
+                    DXGI_FORMAT_R10G10B10A2_UNORM, DXGI_FORMAT_R16G16B16A16_FLOAT,
+                    DXGI_FORMAT_R8G8B8A8_UNORM,
+                },
+                CreateDXGIFactory1, IDXGIAdapter, IDXGIFactory1, IDXGIOutput, IDXGIOutput1,
                 IDXGIOutputDuplication, IDXGISurface, DXGI_ERROR_ACCESS_LOST,
-                DXGI_ERROR_NOT_CURRENTLY_AVAILABLE, DXGI_ERROR_WAIT_TIMEOUT, DXGI_MAP_READ,
+                DXGI_ERROR_NOT_CURRENTLY_AVAILABLE, DXGI_ERROR_NOT_FOUND, DXGI_ERROR_WAIT_TIMEOUT,
+                DXGI_MAPPED_RECT, DXGI_MAP_READ,
             },
         },
         System::StationsAndDesktops::{CloseDesktop, OpenInputDesktop, SetThreadDesktop},
@@ -21,10 +27,12 @@ use windows::{
 };
 
 use crate::image::{Bgra8, Image};
+use crate::logging::log_err;
 
 #[derive(Debug)]
 pub enum CaptureError {
     AccessLost,
+    NoFreeSlot, // every duplication slot is taken by other programs, one closing frees it up
     WinErr(WinError),
 }
 
@@ -32,21 +40,64 @@ impl From<WinError> for CaptureError {
     fn from(e: WinError) -> Self {
         match e.code() {
             DXGI_ERROR_ACCESS_LOST => CaptureError::AccessLost,
+            DXGI_ERROR_NOT_CURRENTLY_AVAILABLE => CaptureError::NoFreeSlot,
             _ => CaptureError::WinErr(e),
         }
     }
 }
 
+// What the duplication hands us, mostly for the startup log
+#[derive(Debug, Clone, Copy)]
+pub struct OutputInfo {
+    pub format: DXGI_FORMAT,
+    pub w: u32,
+    pub h: u32,
+    pub refresh_hz: f32,
+}
+
+impl OutputInfo {
+    pub fn format_name(&self) -> &'static str {
+        FORMAT_NAMES
+            .iter()
+            .find(|(format, _)| *format == self.format)
+            .map_or("unknown", |(_, name)| name)
+    }
+
+    // Anything that isn't plain BGRA8 costs an extra pass per frame before the pixels are usable
+    pub fn needs_conversion(&self) -> bool {
+        self.format != DXGI_FORMAT_B8G8R8A8_UNORM && self.format != DXGI_FORMAT_B8G8R8A8_UNORM_SRGB
+    }
+}
+
+const FORMAT_NAMES: &[(DXGI_FORMAT, &str)] = &[
+    (DXGI_FORMAT_B8G8R8A8_UNORM, "BGRA8"),
+    (DXGI_FORMAT_B8G8R8A8_UNORM_SRGB, "BGRA8 sRGB"),
+    (DXGI_FORMAT_R8G8B8A8_UNORM, "RGBA8"),
+    (DXGI_FORMAT_R10G10B10A2_UNORM, "RGB10A2"),
+    (DXGI_FORMAT_R16G16B16A16_FLOAT, "RGBA16F (HDR)"),
+];
+
 pub struct DXGICapturer {
     d3d_device: ID3D11Device,
     device_context: ID3D11DeviceContext,
-    primary_output: IDXGIOutput,
+    output: IDXGIOutput,
+    monitor_idx: u32, // the one actually captured, after falling back
     output_dup: Option<IDXGIOutputDuplication>, // Should never be None,
-    surface: Option<IDXGISurface>,
+    staging: Option<StagingTex>,
+    mapped: bool, // the staging surface is mapped & the duplication is holding a frame for us
+}
+
+// CPU readable copy of the desktop texture (or just the captured region of it), kept across frames since creating one every frame adds up at high fps
+struct StagingTex {
+    tex: ID3D11Texture2D,
+    surface: IDXGISurface,
+    dims: (u32, u32),
+    format: DXGI_FORMAT,
 }
 
 impl DXGICapturer {
-    pub fn new() -> Result<Self, CaptureError> {
+    // Falls back to the first output if `monitor_idx` is gone, eg. the monitor got unplugged
+    pub fn new(monitor_idx: u32) -> Result<Self, CaptureError> {
         unsafe {
             let input_desktop_h = OpenInputDesktop(0, false, GENERIC_ALL);
             if input_desktop_h.is_invalid() {
@@ -55,13 +106,32 @@ impl DXGICapturer {
             SetThreadDesktop(input_desktop_h); // don't care if this fails
             CloseDesktop(input_desktop_h);
 
-            let primary_adapter = CreateDXGIFactory1::<IDXGIFactory1>()?.EnumAdapters(0)?;
-            let primary_output = primary_adapter.EnumOutputs(0)?;
+            let mut outputs = enum_outputs()?;
+            if outputs.is_empty() {
+                return Err(
+                    WinError::new(DXGI_ERROR_NOT_FOUND, "No outputs to capture".into()).into(),
+                );
+            }
+            let (adapter, output, monitor_idx) = match monitor_idx as usize {
+                idx if idx < outputs.len() => {
+                    let (adapter, output) = outputs.swap_remove(idx);
+                    (adapter, output, monitor_idx)
+                }
+                _ => {
+                    log_err!(
+                        "Monitor {} doesn't exist ({} found), capturing monitor 0 instead",
+                        monitor_idx,
+                        outputs.len()
+                    );
+                    let (adapter, output) = outputs.swap_remove(0);
+                    (adapter, output, 0)
+                }
+            };
 
             let mut d3d_device = None;
             let mut device_context = None;
             D3D11CreateDevice(
-                primary_adapter,
+                adapter,
                 D3D_DRIVER_TYPE_UNKNOWN,
                 None,
                 0.into(),
@@ -73,15 +143,16 @@ impl DXGICapturer {
                 &mut device_context,
             )?;
 
-            let output_dup =
-                Self::duplicate_output(d3d_device.as_ref().unwrap(), primary_output.clone())?;
+            let output_dup = Self::duplicate_output(d3d_device.as_ref().unwrap(), output.clone())?;
 
             Ok(Self {
                 d3d_device: d3d_device.unwrap(),
                 device_context: device_context.unwrap(),
-                primary_output,
+                output,
+                monitor_idx,
                 output_dup: Some(output_dup),
-                surface: None,
+                staging: None,
+                mapped: false,
             })
         }
     }
@@ -91,9 +162,11 @@ impl DXGICapturer {
         unsafe { self.release_resources()? };
         let invalid_dup = std::mem::take(&mut self.output_dup);
         drop(invalid_dup);
+        // the mode could've changed along with the duplication, the next frame makes a fresh one
+        self.staging = None;
 
         loop {
-            match unsafe { Self::duplicate_output(&self.d3d_device, self.primary_output.clone()) } {
+            match unsafe { Self::duplicate_output(&self.d3d_device, self.output.clone()) } {
                 Ok(out) => {
                     self.output_dup = Some(out);
                     break;
@@ -115,44 +188,135 @@ impl DXGICapturer {
         timeout_ms: u32,
     ) -> Result<Option<Image<&'_ [u8], Bgra8>>, CaptureError> {
         unsafe {
-            self.release_resources()?;
-
-            let mut desktop_resource = None;
-            let mut frame_info = Default::default();
-            if let Err(e) = self.output_dup.as_ref().unwrap().AcquireNextFrame(
-                timeout_ms,
-                &mut frame_info,
-                &mut desktop_resource,
-            ) {
-                return match e.code() {
-                    DXGI_ERROR_WAIT_TIMEOUT => Ok(None),
-                    _ => Err(e.into()),
-                };
+            let (rect, (w, h)) = match self.acquire_frame(timeout_ms, None)? {
+                Some(mapped) => mapped,
+                None => return Ok(None),
+            };
+
+            // always in BGRA8 format
+            let (w, h) = (w as usize, h as usize);
+            let pixels_slice = std::slice::from_raw_parts(rect.pBits, w * h * 4);
+
+            Ok(Some(Image::new(pixels_slice, w, h)))
+        }
+    }
+
+    // Only the w x h region at (x, y) gets copied off the gpu. The rows are copied out of the mapped
+    //     surface one at a time, its pitch can be wider than the region
+    pub fn capture_frame_region(
+        &mut self,
+        timeout_ms: u32,
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+    ) -> Result<Option<Image<Vec<u8>, Bgra8>>, CaptureError> {
+        let region = D3D11_BOX {
+            left: x,
+            top: y,
+            front: 0,
+            right: x + w,
+            bottom: y + h,
+            back: 1,
+        };
+        unsafe {
+            let (rect, (w, h)) = match self.acquire_frame(timeout_ms, Some(region))? {
+                Some(mapped) => mapped,
+                None => return Ok(None),
+            };
+
+            let row_len = w as usize * 4;
+            let mut buf = Vec::with_capacity(row_len * h as usize);
+            for row in 0..h as usize {
+                let row_start = rect.pBits.add(row * rect.Pitch as usize);
+                buf.extend_from_slice(std::slice::from_raw_parts(row_start, row_len));
             }
 
-            let gpu_tex = desktop_resource.unwrap().cast::<ID3D11Texture2D>().unwrap();
+            Ok(Some(Image::new(buf, w as usize, h as usize)))
+        }
+    }
+
+    // Copies the next frame (or `region` of it, clamped to the desktop) into the staging texture & maps it.
+    //     Returns the mapping & the dims of what got copied, None when no new frame came in before the timeout
+    unsafe fn acquire_frame(
+        &mut self,
+        timeout_ms: u32,
+        region: Option<D3D11_BOX>,
+    ) -> Result<Option<(DXGI_MAPPED_RECT, (u32, u32))>, CaptureError> {
+        self.release_resources()?;
+
+        let mut desktop_resource = None;
+        let mut frame_info = Default::default();
+        if let Err(e) = self.output_dup.as_ref().unwrap().AcquireNextFrame(
+            timeout_ms,
+            &mut frame_info,
+            &mut desktop_resource,
+        ) {
+            return match e.code() {
+                DXGI_ERROR_WAIT_TIMEOUT => Ok(None),
+                _ => Err(e.into()),
+            };
+        }
+
+        let gpu_tex = desktop_resource.unwrap().cast::<ID3D11Texture2D>().unwrap();
+
+        let mut desc = Default::default();
+        gpu_tex.GetDesc(&mut desc);
+        let region = region.map(|region| D3D11_BOX {
+            right: region.right.min(desc.Width),
+            bottom: region.bottom.min(desc.Height),
+            ..region
+        });
+        let dims = match region {
+            Some(region) => (
+                region.right.saturating_sub(region.left),
+                region.bottom.saturating_sub(region.top),
+            ),
+            None => (desc.Width, desc.Height),
+        };
 
-            let mut desc = Default::default();
-            gpu_tex.GetDesc(&mut desc);
+        let reusable = self.staging.as_ref().map_or(false, |staging| {
+            staging.dims == dims && staging.format == desc.Format
+        });
+        if !reusable {
+            desc.Width = dims.0;
+            desc.Height = dims.1;
             desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ;
             desc.Usage = D3D11_USAGE_STAGING;
             desc.BindFlags = 0.into();
             desc.MiscFlags = 0.into();
+            let tex = self.d3d_device.CreateTexture2D(&desc, ptr::null())?;
+            self.staging = Some(StagingTex {
+                surface: tex.cast::<IDXGISurface>().unwrap(),
+                tex,
+                dims,
+                format: desc.Format,
+            });
+        }
+        let staging = self.staging.as_ref().unwrap();
+        match region {
+            Some(ref region) => self.device_context.CopySubresourceRegion(
+                &staging.tex,
+                0,
+                0,
+                0,
+                0,
+                &gpu_tex,
+                0,
+                region,
+            ),
+            None => self.device_context.CopyResource(&staging.tex, &gpu_tex),
+        }
 
-            let cpu_tex = self.d3d_device.CreateTexture2D(&desc, ptr::null())?;
-            self.device_context.CopyResource(&cpu_tex, &gpu_tex);
-
-            let mut rect = Default::default();
-            let surface = cpu_tex.cast::<IDXGISurface>().unwrap();
-            surface.Map(&mut rect, DXGI_MAP_READ)?;
-            self.surface = Some(surface);
+        let mut rect = Default::default();
+        staging.surface.Map(&mut rect, DXGI_MAP_READ)?;
+        self.mapped = true;
 
-            // always in BGRA8 format
-            let (w, h) = (desc.Width as usize, desc.Height as usize);
-            let pixels_slice = std::slice::from_raw_parts(rect.pBits, w * h * 4);
+        Ok(Some((rect, dims)))
+    }
 
-            Ok(Some(Image::new(pixels_slice, w, h)))
-        }
+    pub fn monitor_idx(&self) -> u32 {
+        self.monitor_idx
     }
 
     pub fn dims(&self) -> (u32, u32) {
@@ -161,10 +325,23 @@ impl DXGICapturer {
         (desc.ModeDesc.Width, desc.ModeDesc.Height)
     }
 
+    pub fn output_info(&self) -> OutputInfo {
+        let mut desc = Default::default();
+        unsafe { self.output_dup.as_ref().unwrap().GetDesc(&mut desc) };
+        let rate = desc.ModeDesc.RefreshRate;
+        OutputInfo {
+            format: desc.ModeDesc.Format,
+            w: desc.ModeDesc.Width,
+            h: desc.ModeDesc.Height,
+            refresh_hz: rate.Numerator as f32 / rate.Denominator.max(1) as f32,
+        }
+    }
+
+    // Unmaps without dropping the staging texture, the next frame copies into the same one
     unsafe fn release_resources(&mut self) -> Result<(), WinError> {
-        if let Some(ref mut surf) = self.surface {
-            surf.Unmap()?;
-            self.surface = None;
+        if self.mapped {
+            self.staging.as_ref().unwrap().surface.Unmap()?;
+            self.mapped = false;
             self.output_dup.as_ref().unwrap().ReleaseFrame()?
         }
         Ok(())
@@ -186,3 +363,99 @@ impl DXGICapturer {
             })
     }
 }
+
+// Every output on every adapter, in the order monitor_index counts them
+unsafe fn enum_outputs() -> Result<Vec<(IDXGIAdapter, IDXGIOutput)>, WinError> {
+    let factory = CreateDXGIFactory1::<IDXGIFactory1>()?;
+    let mut outputs = Vec::new();
+    // both enumerations end with DXGI_ERROR_NOT_FOUND
+    for adapter_idx in 0.. {
+        let adapter = match factory.EnumAdapters(adapter_idx) {
+            Ok(adapter) => adapter,
+            Err(_) => break,
+        };
+        for output_idx in 0.. {
+            match adapter.EnumOutputs(output_idx) {
+                Ok(output) => outputs.push((adapter.clone(), output)),
+                Err(_) => break,
+            }
+        }
+    }
+    Ok(outputs)
+}
+
+pub fn output_count() -> usize {
+    unsafe { enum_outputs().map_or(0, |outputs| outputs.len()) }
+}
+
+// Desktop size of an output without duplicating it, None if it doesn't exist
+pub fn output_dims(monitor_idx: u32) -> Option<(u32, u32)> {
+    unsafe {
+        let outputs = enum_outputs().ok()?;
+        let (_, output) = outputs.get(monitor_idx as usize)?;
+        let mut desc = Default::default();
+        output.GetDesc(&mut desc).ok()?;
+        let rect = desc.DesktopCoordinates;
+        Some((
+            (rect.right - rect.left) as u32,
+            (rect.bottom - rect.top) as u32,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAMES: usize = 100;
+    const TIMEOUT_MS: u32 = 100;
+
+    // Needs a real output to duplicate, run with `cargo test -- --ignored` on a desktop session.
+    //     Full frames & two region sizes take turns so the staging texture gets both reused & recreated
+    #[test]
+    #[ignore]
+    fn staging_dims_match_every_frame() {
+        let mut capturer = DXGICapturer::new(0).unwrap();
+        let (desktop_w, desktop_h) = capturer.dims();
+        let regions = [(0, 0, desktop_w / 2, desktop_h / 2), (10, 10, 64, 48)];
+
+        let mut captured = 0;
+        for frame in 0..FRAMES {
+            let expected = match frame % 3 {
+                0 => {
+                    let img = match capturer.capture_frame(TIMEOUT_MS).unwrap() {
+                        Some(img) => img,
+                        None => continue, // nothing changed on screen
+                    };
+                    assert_eq!(img.as_slice().len(), img.w * img.h * 4, "frame {}", frame);
+                    assert_eq!((img.w as u32, img.h as u32), (desktop_w, desktop_h));
+                    (desktop_w, desktop_h)
+                }
+                idx => {
+                    let (x, y, w, h) = regions[idx - 1];
+                    let img = match capturer
+                        .capture_frame_region(TIMEOUT_MS, x, y, w, h)
+                        .unwrap()
+                    {
+                        Some(img) => img,
+                        None => continue,
+                    };
+                    assert_eq!(img.as_slice().len(), img.w * img.h * 4, "frame {}", frame);
+                    assert_eq!((img.w as u32, img.h as u32), (w, h), "frame {}", frame);
+                    (w, h)
+                }
+            };
+
+            let staging = capturer.staging.as_ref().unwrap();
+            assert_eq!(staging.dims, expected, "frame {}", frame);
+            assert!(capturer.mapped);
+            captured += 1;
+        }
+        assert!(captured > 0, "no frames came in");
+
+        // the mode could change along with the duplication, reload starts over with a fresh texture
+        capturer.reload().unwrap();
+        assert!(capturer.staging.is_none());
+        assert!(!capturer.mapped);
+    }
+}
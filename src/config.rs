@@ -46,6 +46,26 @@ impl fmt::Display for ParseError {
 
 impl std::error::Error for ParseError {}
 
+// Which step of saving failed, the file on disk is only ever replaced by a complete one
+#[derive(Debug)]
+pub enum WriteError {
+    ReadOld(std::io::Error), // the existing file, read for its comments & line order
+    Write(std::io::Error),   // the temp file next to it
+    Replace(std::io::Error), // moving the temp file over the old one
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ReadOld(e) => write!(f, "Couldn't read the existing config => {}", e),
+            Self::Write(e) => write!(f, "Couldn't write the new config => {}", e),
+            Self::Replace(e) => write!(f, "Couldn't replace the old config => {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WriteError {}
+
 #[derive(Debug, Hash, PartialEq, Eq, FromPrimitive, Clone, Copy)]
 pub enum CfgKey {
     CropW = 0,
@@ -64,21 +84,75 @@ pub enum CfgKey {
     ToggleAutoclickKeycode,
     FakeLmbKeycode,
     TargetColor,
+    GraphSmoothing,
+    PreserveCropPercent,
+    WashRejectFraction,
+    EventCommand,
+    CalibrateKeycode,
+    AdaptiveRefArea,
+    AdaptiveScaleMin,
+    AdaptiveScaleMax,
+    ClickTimingDistribution,
+    MinClusterAspect,
+    MaxClusterAspect,
+    CaptureTimeoutMs,
+    ToggleHoldMs,
+    SwitchRampFrames,
+    SwitchRampStart,
+    AutoclickMaxSeconds,
+    DetectDownscale,
+    OverlayBbox,
+    OverlayCenterCrosshair,
+    OverlayAimCrosshair,
+    OverlayAimLine,
+    OutlineSamples,
+    OutlineConfirmFraction,
+    CropWPercent,
+    CropHPercent,
+    PixelsPerCount,
+    RecordFrames,
+    AutoclickKeySource,
+    AutoclickScanCode,
+    CompactOverlay,
+    CompactOverlayCorner,
+    MotionGateThresh,
+    MotionGateFrames,
+    SoundEnabled,
+    SoundAimToggle,
+    SoundClickmode,
+    SoundTargetAcquired,
+    YOffsetMultiplier,
+    MonitorIndex,
+    IdleDisableMinutes,
+    ScreenshotKeycode,
+    ClickStabilizeMs,
+    FovRadius,
+    DisplayUnits,
+    DuplicationRetrySecs,
+    RoiSearch,
+    SmoothingFactor,
+    TriggerLumaDelta,
+    PredictionFactor,
+    MirrorPixelBudget,
+    TargetPriority,
     _Size, // Last item get assigned the size of the enum
 }
 const N_CFG_KEYS: usize = CfgKey::_Size as usize;
 
+// Crop is stored as the fraction of the screen cut off each side, just under half always leaves a region
+pub const MAX_CROP_PERCENT: f32 = 0.49;
+
 impl CfgKey {
     pub fn default_val(&self) -> ValType {
         use CfgKey::*;
         use ValType::*;
 
         match *self {
-            CropW => Unsigned(Bounded::new(1152, 0..=2560 - 1)), // Bounds set at runtime for crop_w & crop_h
-            CropH => Unsigned(Bounded::new(592, 0..=1440 - 1)),
+            CropW => Unsigned(Bounded::new(1152, 0..=u32::MAX)), // legacy pixel crop, only read to migrate old configs
+            CropH => Unsigned(Bounded::new(592, 0..=u32::MAX)),
             ColorThresh => Float(Bounded::new(0.83, 0.001..=0.999)),
             AimDivisor => Float(Bounded::new(3., 1.0..=10.0)),
-            YMultiplier => Float(Bounded::new(0.9, 0.0..=1.0)),
+            YMultiplier => Float(Bounded::new(0.9, 0.0..=1.0)), // legacy, scaled the absolute y. see y_offset_multiplier
             Fps => Unsigned(Bounded::new(144, 1..=240)),
             MaxAutoclickSleepMs => Unsigned(Bounded::new(90, 0..=100)),
             MinAutoclickSleepMs => Unsigned(Bounded::new(50, 0..=100)),
@@ -89,7 +163,61 @@ impl CfgKey {
             ToggleAimKeycode => Keycode(190),
             ToggleAutoclickKeycode => Keycode(188),
             FakeLmbKeycode => Keycode(4),
-            TargetColor => ColorRgb8(Color::<u8>::new(196, 58, 172, 255)),
+            TargetColor => ColorList(vec![Color::<u8>::new(196, 58, 172, 255)]),
+            GraphSmoothing => Unsigned(Bounded::new(5, 1..=60)),
+            PreserveCropPercent => Bool(false),
+            WashRejectFraction => Float(Bounded::new(1.0, 0.01..=1.0)), // 1.0 never rejects
+            EventCommand => Text(String::new()),
+            CalibrateKeycode => Keycode(119), // F8
+            AdaptiveRefArea => Unsigned(Bounded::new(2500, 1..=100_000)), // cluster area in px that gets the plain aim_divisor
+            AdaptiveScaleMin => Float(Bounded::new(1.0, 0.1..=1.0)), // min & max of 1 disable the scaling
+            AdaptiveScaleMax => Float(Bounded::new(1.0, 1.0..=10.0)),
+            ClickTimingDistribution => Choice(self::Choice::new(0, &["uniform", "normal"])),
+            MinClusterAspect => Float(Bounded::new(0.0, 0.0..=10.0)), // bbox width / height
+            MaxClusterAspect => Float(Bounded::new(100.0, 0.1..=100.0)),
+            CaptureTimeoutMs => Unsigned(Bounded::new(0, 0..=30)),
+            ToggleHoldMs => Unsigned(Bounded::new(0, 0..=2000)),
+            SwitchRampFrames => Unsigned(Bounded::new(0, 0..=60)), // 0 disables the ramp
+            SwitchRampStart => Float(Bounded::new(0.3, 0.05..=1.0)),
+            AutoclickMaxSeconds => Unsigned(Bounded::new(0, 0..=600)),
+            DetectDownscale => Unsigned(Bounded::new(1, 1..=4)),
+            OverlayBbox => Bool(true),
+            OverlayCenterCrosshair => Bool(true),
+            OverlayAimCrosshair => Bool(true),
+            OverlayAimLine => Bool(true),
+            OutlineSamples => Unsigned(Bounded::new(16, 4..=64)),
+            OutlineConfirmFraction => Float(Bounded::new(0.0, 0.0..=1.0)),
+            CropWPercent => Float(Bounded::new(0.45, 0.0..=MAX_CROP_PERCENT)),
+            CropHPercent => Float(Bounded::new(0.41, 0.0..=MAX_CROP_PERCENT)),
+            PixelsPerCount => Float(Bounded::new(0.0, 0.0..=20.0)),
+            RecordFrames => Bool(false),
+            AutoclickKeySource => Choice(self::Choice::new(0, &["virtual_key", "scan_code"])),
+            AutoclickScanCode => Unsigned(Bounded::new(0, 0..=0xFFFF)), // recorded by the gui, 0 when unknown
+            CompactOverlay => Bool(false),
+            CompactOverlayCorner => Choice(self::Choice::new(
+                0,
+                &["top_right", "top_left", "bottom_right", "bottom_left"],
+            )),
+            MotionGateThresh => Float(Bounded::new(0.0, 0.0..=30.0)), // 0 disables the gate
+            MotionGateFrames => Unsigned(Bounded::new(8, 1..=60)),
+            SoundEnabled => Bool(false), // master switch, the per event ones only count while it's on
+            SoundAimToggle => Bool(false),
+            SoundClickmode => Bool(false),
+            SoundTargetAcquired => Bool(false),
+            YOffsetMultiplier => Float(Bounded::new(0.9, 0.0..=1.0)),
+            MonitorIndex => Unsigned(Bounded::new(0, 0..=15)), // narrowed to the connected outputs on startup
+            IdleDisableMinutes => Unsigned(Bounded::new(0, 0..=120)),
+            ScreenshotKeycode => Keycode(120), // F9
+            ClickStabilizeMs => Unsigned(Bounded::new(0, 0..=50)),
+            FovRadius => Unsigned(Bounded::new(0, 0..=4000)), // narrowed to half the screen diagonal on startup
+            DuplicationRetrySecs => Unsigned(Bounded::new(30, 0..=600)),
+            RoiSearch => Bool(false),
+            SmoothingFactor => Float(Bounded::new(0., 0.0..=0.95)), // 0 disables the smoothing
+            TriggerLumaDelta => Unsigned(Bounded::new(0, 0..=255)), // 0 triggers on detected targets
+            PredictionFactor => Float(Bounded::new(0., 0.0..=3.0)), // 0 disables the lead
+            MirrorPixelBudget => Unsigned(Bounded::new(2_000_000, 100_000..=40_000_000)),
+            TargetPriority => Choice(self::Choice::new(0, &["closest", "largest"])),
+            DisplayUnits => Choice(self::Choice::new(0, &["auto", "ms", "us", "fps_priority"])),
             _Size => panic!(),
         }
     }
@@ -103,9 +231,162 @@ impl CfgKey {
         matches!(self.default_val(), ValType::Keycode(_))
     }
 
+    // Still parsed so old config files load, but never written back or shown in the gui
+    pub fn is_legacy(&self) -> bool {
+        matches!(self, CfgKey::CropW | CfgKey::CropH | CfgKey::YMultiplier)
+    }
+
+    // Keys that stay the same whichever game is being played (the kill switch, the gui & capture setup).
+    //     Everything else is gameplay tuning & binds, which a profile gets its own copy of
+    pub fn is_global(&self) -> bool {
+        use CfgKey::*;
+
+        matches!(
+            self,
+            ToggleAimKeycode
+                | ToggleAutoclickKeycode
+                | CalibrateKeycode
+                | ScreenshotKeycode
+                | ToggleHoldMs
+                | GraphSmoothing
+                | DisplayUnits
+                | PreserveCropPercent
+                | OverlayBbox
+                | OverlayCenterCrosshair
+                | OverlayAimCrosshair
+                | OverlayAimLine
+                | CompactOverlay
+                | CompactOverlayCorner
+                | SoundEnabled
+                | SoundAimToggle
+                | SoundClickmode
+                | SoundTargetAcquired
+                | EventCommand
+                | MonitorIndex
+                | CaptureTimeoutMs
+                | DuplicationRetrySecs
+                | MirrorPixelBudget
+                | RecordFrames
+                | IdleDisableMinutes
+        )
+    }
+
     pub fn as_string(&self) -> String {
         camel_to_snake(&format!("{:?}", self))
     }
+
+    // Written as an inline comment when a key gets added to the config file for the first time
+    pub fn comment(&self) -> Option<&'static str> {
+        use CfgKey::*;
+
+        match *self {
+            EventCommand => Some("program run with one of: aim_on, aim_off, target_acquired, target_lost, clickmode_changed"),
+            ClickTimingDistribution => Some("uniform or normal"),
+            ToggleHoldMs => Some("toggle keys only fire after being held this long, 0 fires on press"),
+            TargetColor => Some("one or more r, g, b colors separated by ;, when several show up the earliest one in the list wins"),
+            IdleDisableMinutes => Some("stops moving the mouse after this many minutes without pressing any bound key, the next press resumes. 0 is off"),
+            TargetPriority => Some("closest or largest, which target wins when several are on screen"),
+            MirrorPixelBudget => Some("largest frame in pixels sent to the mirror, bigger crops get shrunk before they leave the aim thread. Detection always sees the full frame"),
+            PredictionFactor => Some("how many frames ahead to lead a moving target by, based on how fast it moved across the last frames, 0 to aim right at it"),
+            TriggerLumaDelta => Some("trigger mode fires when the brightness right at the crosshair jumps by more than this between frames instead of on detected targets, 0 to use detection"),
+            SmoothingFactor => Some("how much of the previous frames' movement carries over into the next one, higher is smoother but lags behind the target more"),
+            RoiSearch => Some("searches around the last target first & only scans the whole crop when it's not there, ignored with several target colors"),
+            DuplicationRetrySecs => Some("how long to keep retrying when other programs hold every screen duplication slot, before giving up on capturing"),
+            DisplayUnits => Some("auto, ms, us or fps_priority. how the gui shows frame & click times, auto keeps the number between 1 & 999"),
+            FovRadius => Some("targets aimed at further than this many px from the crosshair are ignored, the mirror draws it as a circle. 0 is off"),
+            ClickStabilizeMs => Some("aim movement holds off from this many ms before an autoclick until this many ms after its release (after the press for redirected clicks), so the shot doesn't land mid-correction. 0 is off"),
+            ScreenshotKeycode => Some("saves what the bot currently sees (the crop) as a png next to the config"),
+            MonitorIndex => Some("which monitor to capture, counted across every gpu's outputs. falls back to 0 if it's unplugged"),
+            YOffsetMultiplier => Some("scales the vertical distance from the crosshair to the target, under 1 aims gentler up & down"),
+            SoundEnabled => Some("beeps on the events below that are turned on, for when the gui isn't visible"),
+            SoundTargetAcquired => Some("short tick when a target first shows up, at most 4 a second"),
+            MotionGateThresh => Some("clusters whose area changed less than this (mean luma per frame) are ignored, for static decoys in the target color. 0 is off"),
+            MotionGateFrames => Some("roughly how many frames the motion gate averages over"),
+            CompactOverlay => Some("starts in the small always on top window instead of the full gui, double click it to get the gui back"),
+            CompactOverlayCorner => Some("top_right, top_left, bottom_right or bottom_left"),
+            AutoclickKeySource => Some("virtual_key or scan_code. scan_code follows the physical key when the keyboard layout remaps it, it doesn't get past games that block virtual key queries. rebind autoclick after switching"),
+            AutoclickScanCode => Some("set when rebinding autoclick with autoclick_key_source = scan_code"),
+            RecordFrames => Some("keeps the last ~2s of frames in memory at quarter res, ctrl+d dumps them next to the config"),
+            PixelsPerCount => Some("px the view turns per mouse count, set by the calibrate counts button. replaces aim_divisor when not 0"),
+            CropWPercent | CropHPercent => Some("fraction of the screen cropped off each side, works the same on any resolution"),
+            OutlineConfirmFraction => Some("fraction of outline_samples points along the target's bbox that also have to match before aiming at it. 0 is off"),
+            DetectDownscale => Some("detection runs on the crop shrunk by this factor, the mirror still shows it at full res. 1 is off"),
+            AutoclickMaxSeconds => Some("autoclicking stops after this many seconds of holding, until the key is released. 0 is unlimited"),
+            CaptureTimeoutMs => Some("0 polls & sleeps 1/fps between frames, otherwise blocks up to this long for a new frame. over 1000/fps it can undercut the fps cap"),
+            _ => None,
+        }
+    }
+}
+
+// Everything an external tool needs to know about a key, built from default_val so it can't drift
+#[derive(Debug, Clone)]
+pub struct KeySchema {
+    pub name: String,
+    pub kind: &'static str,
+    pub bounds: Option<(String, String)>,
+    pub options: &'static [&'static str], // only for choice keys
+    pub default: String,
+    pub legacy: bool,
+    pub global: bool, // false for the keys a profile gets its own copy of
+    pub comment: Option<&'static str>,
+}
+
+impl KeySchema {
+    fn new(key: CfgKey) -> Self {
+        let default = key.default_val();
+        let (kind, bounds, options) = match default {
+            ValType::Keycode(_) => ("keycode", None, &[][..]),
+            ValType::Unsigned(ref v) => (
+                "unsigned",
+                Some((v.bounds.start().to_string(), v.bounds.end().to_string())),
+                &[][..],
+            ),
+            ValType::Float(ref v) => (
+                "float",
+                Some((v.bounds.start().to_string(), v.bounds.end().to_string())),
+                &[][..],
+            ),
+            ValType::ColorRgb8(_) => ("color_rgb8", None, &[][..]),
+            ValType::ColorList(_) => ("color_list", None, &[][..]),
+            ValType::Bool(_) => ("bool", None, &[][..]),
+            ValType::Text(_) => ("text", None, &[][..]),
+            ValType::Choice(ref c) => ("choice", None, c.options),
+        };
+        Self {
+            name: key.as_string(),
+            kind,
+            bounds,
+            options,
+            default: default.to_string(),
+            legacy: key.is_legacy(),
+            global: key.is_global(),
+            comment: key.comment(),
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let bounds = match self.bounds {
+            Some((ref start, ref end)) => format!("[{}, {}]", start, end),
+            None => "null".to_string(),
+        };
+        let options = self
+            .options
+            .iter()
+            .map(|opt| json_str(opt))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "{{\"name\": {}, \"kind\": {}, \"bounds\": {}, \"options\": [{}], \"default\": {}, \"legacy\": {}, \"scope\": {}, \"comment\": {}}}",
+            json_str(&self.name),
+            json_str(self.kind),
+            bounds,
+            options,
+            json_str(&self.default),
+            self.legacy,
+            json_str(if self.global { "global" } else { "profile" }),
+            self.comment.map_or("null".to_string(), json_str)
+        )
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -120,6 +401,23 @@ impl<T> Bounded<T> {
     }
 }
 
+// One of a fixed set of named options, stored by name in the config file
+#[derive(Debug, PartialEq, Clone)]
+pub struct Choice {
+    pub idx: usize,
+    pub options: &'static [&'static str],
+}
+
+impl Choice {
+    pub fn new(idx: usize, options: &'static [&'static str]) -> Self {
+        Self { idx, options }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.options[self.idx]
+    }
+}
+
 macro_rules! enum_valtype {
     ($(($name: ident, $val_typ: ty)),*) => {
         #[derive(Debug, PartialEq, Clone)]
@@ -145,7 +443,11 @@ enum_valtype!(
     (Keycode, u16),
     (Unsigned, Bounded<u32>),
     (Float, Bounded<f32>),
-    (ColorRgb8, Color<u8>)
+    (ColorRgb8, Color<u8>),
+    (ColorList, Vec<Color<u8>>),
+    (Bool, bool),
+    (Text, String),
+    (Choice, Choice)
 );
 
 impl Display for ValType {
@@ -155,6 +457,16 @@ impl Display for ValType {
             Self::Unsigned(v) => write!(f, "{}", v.val),
             Self::Float(v) => write!(f, "{}", v.val),
             Self::ColorRgb8(c) => write!(f, "{}, {}, {}", c.r, c.g, c.b),
+            Self::ColorList(colors) => {
+                let colors: Vec<String> = colors
+                    .iter()
+                    .map(|c| format!("{}, {}, {}", c.r, c.g, c.b))
+                    .collect();
+                write!(f, "{}", colors.join("; "))
+            }
+            Self::Bool(b) => write!(f, "{}", b),
+            Self::Text(t) => write!(f, "{}", t),
+            Self::Choice(c) => write!(f, "{}", c.as_str()),
         }
     }
 }
@@ -164,9 +476,12 @@ struct LineData {
     comment: Option<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Config {
     map: FxHashMap<CfgKey, ValType>,
+    dirty_keys: FxHashSet<CfgKey>,
+    legacy_crop: Vec<(CfgKey, u32)>, // percent key & the pixel value it replaces, see migrate_legacy_crop
+    legacy_y_multiplier: Option<f32>,
     pub is_dirty: bool,
 }
 
@@ -175,19 +490,104 @@ impl Config {
         CfgKey::iter().for_each(|key| {
             map.entry(key).or_insert_with(|| key.default_val());
         });
-        Self { map, is_dirty: false }
+        Self {
+            map,
+            dirty_keys: FxHashSet::default(),
+            legacy_crop: Vec::new(),
+            legacy_y_multiplier: None,
+            is_dirty: false,
+        }
     }
 
     pub fn default() -> Self {
         Self::new(CfgKey::iter().map(|key| (key, key.default_val())).collect())
     }
 
+    pub fn schema() -> Vec<KeySchema> {
+        CfgKey::iter().map(KeySchema::new).collect()
+    }
+
+    // Defaults are written the same way as in the config file, eg. colors are "r, g, b"
+    pub fn schema_json() -> String {
+        let keys = Self::schema()
+            .iter()
+            .map(|key| format!("  {}", key.to_json()))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        format!("[\n{}\n]", keys)
+    }
+
     pub fn get(&self, key: CfgKey) -> ValType {
         self.map.get(&key).unwrap().clone()
     }
 
+    // Whether `key` holds a different value than in `baseline`, eg. the config as it was last loaded or saved.
+    //     Compares what would get written to the file, so runtime bounds changes & float noise past what
+    //     survives a save don't count
+    pub fn differs_from(&self, baseline: &Config, key: CfgKey) -> bool {
+        self.map.get(&key).map(ToString::to_string)
+            != baseline.map.get(&key).map(ToString::to_string)
+    }
+
+    pub fn changed_keys<'a>(&'a self, baseline: &'a Config) -> impl Iterator<Item = CfgKey> + 'a {
+        CfgKey::iter().filter(move |&key| self.differs_from(baseline, key))
+    }
+
+    // Crop in pixels for a screen of `screen_dims`, re-resolved whenever the captured resolution changes
+    pub fn crop_px(&self, screen_dims: (u32, u32)) -> (u32, u32) {
+        let w_percent: f32 =
+            <ValType as Into<Bounded<_>>>::into(self.get(CfgKey::CropWPercent)).val;
+        let h_percent: f32 =
+            <ValType as Into<Bounded<_>>>::into(self.get(CfgKey::CropHPercent)).val;
+        (
+            crop_px(w_percent, screen_dims.0),
+            crop_px(h_percent, screen_dims.1),
+        )
+    }
+
+    // Old configs stored crop_w & crop_h in pixels of whatever screen they were saved on,
+    //     they're converted against the current screen once it's known. Returns the keys that got set
+    pub fn migrate_legacy_crop(&mut self, screen_dims: (u32, u32)) -> Vec<CfgKey> {
+        std::mem::take(&mut self.legacy_crop)
+            .into_iter()
+            .map(|(key, px)| {
+                let screen_len = match key {
+                    CfgKey::CropWPercent => screen_dims.0,
+                    _ => screen_dims.1,
+                };
+                let percent = crop_percent(px, screen_len);
+                self.set_val(key, ValType::Float(Bounded::new(percent, 0.0..=0.0)))
+                    .unwrap();
+                key
+            })
+            .collect()
+    }
+
+    // y_multiplier used to scale the target's absolute y. That's the same as scaling the offset from the
+    //     center plus aiming a fixed (1 - y_multiplier) * center_y px higher, which no offset multiplier can
+    //     keep. Carrying the value over keeps the proportional part, the caller says the bias is gone
+    pub fn migrate_legacy_y_multiplier(&mut self) -> Option<f32> {
+        let val = self.legacy_y_multiplier.take()?;
+        self.set_val(
+            CfgKey::YOffsetMultiplier,
+            ValType::Float(Bounded::new(val, 0.0..=0.0)),
+        )
+        .unwrap();
+        Some(val)
+    }
+
+    // The pickers only ever produce one color, it replaces the first (highest priority) one of a list
+    pub fn set_first_color(&mut self, key: CfgKey, color: Color<u8>) -> Result<(), &'static str> {
+        let mut colors: Vec<Color<u8>> = self.get(key).into();
+        colors[0] = color;
+        self.set_val(key, ValType::ColorList(colors))
+    }
+
     pub fn set_val(&mut self, key: CfgKey, new_val: ValType) -> Result<(), &'static str> {
         const ERR_MSG: &str = "Value not in bounds";
+        if let ValType::Keycode(key_code) = new_val {
+            self.check_keycode_conflict(key, key_code)?;
+        }
         match self.map.get_mut(&key).unwrap() {
             ValType::Unsigned(bv) => {
                 let new_val: Bounded<_> = new_val.into();
@@ -207,22 +607,58 @@ impl Config {
             }
             ValType::Keycode(kc) => *kc = new_val.into(),
             ValType::ColorRgb8(c) => *c = new_val.into(),
+            ValType::ColorList(colors) => {
+                let new_colors: Vec<Color<u8>> = new_val.into();
+                if new_colors.is_empty() {
+                    return Err("Need at least one color");
+                }
+                *colors = new_colors;
+            }
+            ValType::Bool(b) => *b = new_val.into(),
+            ValType::Text(t) => *t = new_val.into(),
+            ValType::Choice(c) => *c = new_val.into(),
         }
+        self.dirty_keys.insert(key);
         self.is_dirty = true;
         Ok(())
     }
 
+    // Our own synthetic clicks would retrigger the autoclicker forever
+    fn check_keycode_conflict(&self, key: CfgKey, key_code: u16) -> Result<(), &'static str> {
+        let other = match key {
+            CfgKey::AutoclickKeycode => CfgKey::FakeLmbKeycode,
+            CfgKey::FakeLmbKeycode => CfgKey::AutoclickKeycode,
+            _ => return Ok(()),
+        };
+        if <ValType as Into<u16>>::into(self.get(other)) == key_code {
+            return Err("autoclick_keycode and fake_lmb_keycode can't be the same button, the fake clicks would keep retriggering the autoclicker");
+        }
+        Ok(())
+    }
+
+    // Per-key dirty flag for consumers that only care about a single key (eg. the gui graph),
+    //     cleared once taken so each change is only handled once
+    pub fn take_dirty_key(&mut self, key: CfgKey) -> bool {
+        self.dirty_keys.remove(&key)
+    }
+
+    pub fn is_key_dirty(&self, key: CfgKey) -> bool {
+        self.dirty_keys.contains(&key)
+    }
+
     pub fn set_bounds(&mut self, key: CfgKey, new_val: ValType) -> Result<(), &'static str> {
         match self.map.get_mut(&key).unwrap() {
             ValType::Unsigned(ref mut val_ref) => {
                 let new_val_cast: Bounded<_> = new_val.into();
                 val_ref.bounds = new_val_cast.bounds;
+                self.dirty_keys.insert(key);
                 self.is_dirty = true;
                 Ok(())
             }
             ValType::Float(ref mut val_ref) => {
                 let new_val_cast: Bounded<_> = new_val.into();
                 val_ref.bounds = new_val_cast.bounds;
+                self.dirty_keys.insert(key);
                 self.is_dirty = true;
                 Ok(())
             }
@@ -230,16 +666,37 @@ impl Config {
         }
     }
 
-    pub fn write_to_file(&self, path: &str) -> std::io::Result<()> {
+    // Written to `<path>.tmp` & renamed over the old file, a crash or full disk mid write can't truncate it
+    pub fn write_to_file(&self, path: &str) -> Result<(), WriteError> {
         let file_path = Path::new(path);
         let mut out_content = "".to_string();
         let mut written_keys = FxHashSet::<CfgKey>::default();
 
+        let mut sections = String::new();
+
         // overwriting keys already written to file to preserve comments & line ordering
-        if let Ok(read_handle) = File::open(file_path) {
+        let read_handle = match File::open(file_path) {
+            Ok(handle) => Some(handle),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(WriteError::ReadOld(e)),
+        };
+        if let Some(read_handle) = read_handle {
             for (line_num, line) in BufReader::new(read_handle).lines().enumerate() {
                 let line_num = (line_num as u32) + 1;
-                match Self::parse_line(line?, line_num) {
+                let line = line.map_err(WriteError::ReadOld)?;
+
+                // sections (eg. [theme]) are owned by other parsers, they're copied over as is
+                if !sections.is_empty() || is_section_header(&line) {
+                    sections.push_str(&line);
+                    sections.push('\n');
+                    continue;
+                }
+                match Self::parse_line(line, line_num) {
+                    // replaced by newer keys, which get written with the rest below
+                    Ok(LineData {
+                        key_val_pair: Some((k, _)),
+                        ..
+                    }) if k.is_legacy() => continue,
                     Ok(line_data) => {
                         if let Some((k, _)) = line_data.key_val_pair {
                             let val = self.map.get(&k).unwrap();
@@ -263,13 +720,36 @@ impl Config {
         // writing the rest of the unwritten values
         out_content.push_str(
             &CfgKey::iter()
-                .filter(|k| !written_keys.contains(k))
+                .filter(|k| !written_keys.contains(k) && !k.is_legacy())
                 .map(|k| self.map.get_key_value(&k).unwrap())
-                .map(|(k, v)| format!("{} = {}\n", k.as_string(), v))
+                .map(|(k, v)| match k.comment() {
+                    Some(comment) => format!("{} = {} # {}\n", k.as_string(), v, comment),
+                    None => format!("{} = {}\n", k.as_string(), v),
+                })
                 .collect::<String>(),
         );
+        out_content.push_str(&sections);
 
-        File::create(file_path)?.write_all(out_content.as_bytes())
+        // the %APPDATA% directory doesn't exist until the first save
+        if let Some(dir) = file_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(WriteError::Write)?;
+        }
+        let tmp_path = format!("{}.tmp", path);
+        let mut tmp_file = File::create(&tmp_path).map_err(WriteError::Write)?;
+        tmp_file
+            .write_all(out_content.as_bytes())
+            .and_then(|_| tmp_file.sync_all())
+            .map_err(WriteError::Write)?;
+        drop(tmp_file);
+
+        // rename replaces the destination, unless something else has it open. Removing it first
+        //     usually gets past that, the temp file still has everything if the second try fails too
+        std::fs::rename(&tmp_path, file_path)
+            .or_else(|_| {
+                std::fs::remove_file(file_path)?;
+                std::fs::rename(&tmp_path, file_path)
+            })
+            .map_err(WriteError::Replace)
     }
 
     pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
@@ -277,46 +757,86 @@ impl Config {
         let infile = File::open(Path::new(path))?;
         for (line_num, line) in BufReader::new(infile).lines().enumerate() {
             let line_num = (line_num as u32) + 1;
-            let LineData { key_val_pair, comment: _ } = Self::parse_line(line?, line_num)?;
+            let line = line?;
+            if is_section_header(&line) {
+                break; // everything past the first section isn't a config key
+            }
+            let LineData { key_val_pair, comment: _ } = Self::parse_line(line, line_num)?;
             if let Some((k, v)) = key_val_pair {
                 out_map.insert(k, v);
             }
         }
 
+        // pixel crop values from before the percent keys existed, converted later by migrate_legacy_crop
+        let legacy_crop: Vec<(CfgKey, u32)> = [
+            (CfgKey::CropW, CfgKey::CropWPercent),
+            (CfgKey::CropH, CfgKey::CropHPercent),
+        ]
+        .into_iter()
+        .filter(|(_, percent_key)| !out_map.contains_key(percent_key))
+        .filter_map(|(legacy_key, percent_key)| {
+            let px: u32 =
+                <ValType as Into<Bounded<_>>>::into(out_map.get(&legacy_key)?.clone()).val;
+            Some((percent_key, px))
+        })
+        .collect();
+
+        let legacy_y_multiplier: Option<f32> =
+            match out_map.contains_key(&CfgKey::YOffsetMultiplier) {
+                true => None,
+                false => out_map
+                    .get(&CfgKey::YMultiplier)
+                    .map(|v| <ValType as Into<Bounded<_>>>::into(v.clone()).val),
+            };
+
         let unused_keys: Vec<CfgKey> = CfgKey::iter()
-            .filter(|k| !out_map.contains_key(k))
+            .filter(|k| !k.is_legacy() && !out_map.contains_key(k))
+            .filter(|k| !legacy_crop.iter().any(|(percent_key, _)| percent_key == k))
+            .filter(|k| !(*k == CfgKey::YOffsetMultiplier && legacy_y_multiplier.is_some()))
             .collect();
+
+        // Config::new() auto fills in unused keys with defaults
+        let mut cfg = Config::new(out_map);
+        cfg.legacy_crop = legacy_crop;
+        cfg.legacy_y_multiplier = legacy_y_multiplier;
         if unused_keys.is_empty() {
-            Ok(Config::new(out_map))
+            Ok(cfg)
         } else {
-            // Config::new() auto fills in unused keys with defaults
-            Err(ParseError::NotExhaustive(Config::new(out_map), unused_keys).into())
+            Err(ParseError::NotExhaustive(cfg, unused_keys).into())
         }
     }
 
+    // One line of a config file with its comment dropped, None for blank & comment only lines.
+    //     Lets the fuzz target in fuzz/ get at parse_line
+    pub fn parse_key_val(line: &str) -> Result<Option<(CfgKey, ValType)>, ParseError> {
+        Self::parse_line(line.to_string(), 1).map(|line_data| line_data.key_val_pair)
+    }
+
     fn parse_line(line: String, line_num: u32) -> Result<LineData, ParseError> {
         static KEY_LOOKUP: SyncLazy<FxHashMap<String, CfgKey>> = SyncLazy::new(|| {
             FxHashMap::from_iter(CfgKey::iter().map(|k| k.as_string()).zip(CfgKey::iter()))
         });
 
-        let (mut key_val, comment) = match line.split_once('#') {
+        let (key_val, comment) = match line.split_once('#') {
             Some((key_val, comment)) => (key_val.to_string(), Some(comment.to_string())),
             None => (line, None),
         };
-        key_val.retain(|c| c != ' ');
-        let (key_str, val_str) = match key_val.split_once('=') {
-            Some((key_str, val_str)) => (key_str, val_str),
+        let (key_str, raw_val_str) = match key_val.split_once('=') {
+            Some((key_str, val_str)) => (key_str.replace(' ', ""), val_str),
             None => {
-                if key_val.is_empty() {
+                if key_val.replace(' ', "").is_empty() {
                     return Ok(LineData { key_val_pair: None, comment }); // empty line is valid
                 } else {
                     return Err(ParseError::Parse(line_num, "No delimiter".into()));
                 }
             }
         };
+        // text values keep their inner spaces (eg. paths), everything else ignores them
+        let val_str = raw_val_str.replace(' ', "");
+        let val_str = val_str.as_str();
 
         let key = KEY_LOOKUP
-            .get(key_str)
+            .get(key_str.as_str())
             .ok_or(ParseError::InvalidKey(line_num))?;
 
         // matching the default value for type info
@@ -344,18 +864,37 @@ impl Config {
                 }
                 ValType::Float(Bounded::new(val, v.bounds))
             }
-            ValType::ColorRgb8(_) => {
-                let mut rgb = [0u8; 3];
-                let mut elems = 0;
-                for res in val_str.split(',').map(|num| num.parse::<u8>()) {
-                    rgb[elems] = res.map_err(|e| ParseError::Parse(line_num, format!("{}", e)))?;
-                    elems += 1;
-                }
-                if elems != 3 {
-                    return Err(ParseError::Parse(line_num, "Invalid color".into()));
+            ValType::ColorRgb8(_) => ValType::ColorRgb8(parse_rgb(val_str, line_num)?),
+            // a single color is still a valid list, so configs from before lists keep loading
+            ValType::ColorList(_) => {
+                let colors = val_str
+                    .split(';')
+                    .filter(|color| !color.is_empty())
+                    .map(|color| parse_rgb(color, line_num))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if colors.is_empty() {
+                    return Err(ParseError::Parse(
+                        line_num,
+                        "Expected at least one color".into(),
+                    ));
                 }
-                ValType::ColorRgb8(Color::new(rgb[0], rgb[1], rgb[2], 255))
+                ValType::ColorList(colors)
             }
+            ValType::Bool(_) => ValType::Bool(
+                val_str
+                    .parse::<bool>()
+                    .map_err(|e| ParseError::Parse(line_num, format!("{}", e)))?,
+            ),
+            ValType::Text(_) => ValType::Text(raw_val_str.trim().to_string()),
+            ValType::Choice(c) => match c.options.iter().position(|&opt| opt == val_str) {
+                Some(idx) => ValType::Choice(Choice::new(idx, c.options)),
+                None => {
+                    return Err(ParseError::Parse(
+                        line_num,
+                        format!("Expected one of: {}", c.options.join(", ")),
+                    ))
+                }
+            },
         };
         Ok(LineData {
             key_val_pair: Some((*key, val)),
@@ -364,6 +903,59 @@ impl Config {
     }
 }
 
+fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// Pixels cut off each side of a `screen_len` long axis
+pub fn crop_px(percent: f32, screen_len: u32) -> u32 {
+    let px = (percent * screen_len as f32).round() as u32;
+    px.min((screen_len / 2).saturating_sub(1))
+}
+
+pub fn crop_percent(px: u32, screen_len: u32) -> f32 {
+    (px as f32 / screen_len.max(1) as f32).min(MAX_CROP_PERCENT)
+}
+
+// The percent that keeps a crop the same size in pixels after its axis changed from `old_len` to `new_len`
+pub fn rescale_crop_percent(percent: f32, old_len: u32, new_len: u32) -> f32 {
+    crop_percent(crop_px(percent, old_len), new_len)
+}
+
+// "r,g,b" with the spaces already stripped
+fn parse_rgb(val_str: &str, line_num: u32) -> Result<Color<u8>, ParseError> {
+    let mut rgb = [0u8; 3];
+    let mut elems = 0;
+    for res in val_str.split(',').map(|num| num.parse::<u8>()) {
+        if elems == 3 {
+            return Err(ParseError::Parse(line_num, "Invalid color".into()));
+        }
+        rgb[elems] = res.map_err(|e| ParseError::Parse(line_num, format!("{}", e)))?;
+        elems += 1;
+    }
+    if elems != 3 {
+        return Err(ParseError::Parse(line_num, "Invalid color".into()));
+    }
+    Ok(Color::new(rgb[0], rgb[1], rgb[2], 255))
+}
+
+// Sections go at the end of the file, after all the regular keys
+fn is_section_header(line: &str) -> bool {
+    line.trim_start().starts_with('[')
+}
+
 fn camel_to_snake(camel_str: &str) -> String {
     let mut snake_str = camel_str.to_string().to_lowercase();
     let mut insert_offset = 0;
@@ -378,3 +970,356 @@ fn camel_to_snake(camel_str: &str) -> String {
         });
     snake_str
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_val(key: CfgKey, val: &str) -> ValType {
+        let line = format!("{} = {}", key.as_string(), val);
+        match Config::parse_line(line.clone(), 1) {
+            Ok(LineData {
+                key_val_pair: Some((parsed_key, val)),
+                ..
+            }) if parsed_key == key => val,
+            res => panic!("{:?} parsed to {:?}", line, res.map(|d| d.key_val_pair)),
+        }
+    }
+
+    #[test]
+    fn schema_json_lists_every_key_once() {
+        let json = Config::schema_json();
+        assert!(json.starts_with("[\n") && json.ends_with("\n]"));
+        for key in CfgKey::iter() {
+            let name_field = format!("{{\"name\": {},", json_str(&key.as_string()));
+            assert_eq!(
+                json.matches(&name_field).count(),
+                1,
+                "{} isn't in the schema exactly once",
+                key.as_string()
+            );
+        }
+        assert_eq!(json.matches("{\"name\": ").count(), N_CFG_KEYS);
+    }
+
+    #[test]
+    fn schema_defaults_parse_back() {
+        for (key, schema) in CfgKey::iter().zip(Config::schema()) {
+            assert_eq!(schema.name, key.as_string());
+            assert_eq!(
+                parse_val(key, &schema.default),
+                key.default_val(),
+                "default of {} doesn't parse back",
+                schema.name
+            );
+        }
+    }
+
+    #[test]
+    fn migrate_legacy_y_multiplier() {
+        let path = std::env::temp_dir().join(format!("pixelbot_y_mult_{}.cfg", std::process::id()));
+        let path_str = path.to_str().unwrap();
+        let _ = std::fs::remove_file(&path);
+        Config::default().write_to_file(path_str).unwrap();
+        let old_cfg: String = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| match line.starts_with("y_offset_multiplier") {
+                true => "y_multiplier = 0.7\n".to_string(),
+                false => format!("{}\n", line),
+            })
+            .collect();
+        std::fs::write(&path, old_cfg).unwrap();
+
+        let cfg = Config::from_file(path_str);
+        std::fs::remove_file(&path).unwrap();
+        let mut cfg = cfg.unwrap();
+        assert_eq!(cfg.migrate_legacy_y_multiplier(), Some(0.7));
+        let migrated: Bounded<f32> = cfg.get(CfgKey::YOffsetMultiplier).into();
+        assert_eq!(migrated.val, 0.7);
+        assert!(cfg.is_key_dirty(CfgKey::YOffsetMultiplier));
+        assert_eq!(cfg.migrate_legacy_y_multiplier(), None);
+    }
+
+    #[test]
+    fn float_display_round_trips() {
+        // pixels_per_count takes 0..=20, plenty for values with long or inexact decimal forms
+        let key = CfgKey::PixelsPerCount;
+        let values = [
+            0.,
+            0.1,
+            0.83,
+            1. / 3.,
+            2. / 3.,
+            0.1 + 0.2,
+            f32::from_bits(0.3f32.to_bits() + 1), // the next f32 after 0.3
+            1e-7,
+            f32::MIN_POSITIVE,
+            std::f32::consts::E,
+            7.0000005,
+            19.999998,
+            20.,
+        ];
+        for val in values {
+            let bounded = ValType::Float(Bounded::new(val, 0.0..=20.0));
+            let parsed: Bounded<f32> = parse_val(key, &bounded.to_string()).into();
+            assert_eq!(
+                parsed.val.to_bits(),
+                val.to_bits(),
+                "{} was written as {}",
+                val,
+                bounded
+            );
+        }
+    }
+
+    #[test]
+    fn differs_from_ignores_bounds_changes() {
+        let baseline = Config::default();
+        let mut cfg = baseline.clone();
+        cfg.set_bounds(
+            CfgKey::MonitorIndex,
+            ValType::Unsigned(Bounded::new(0, 0..=1)),
+        )
+        .unwrap();
+        assert!(!cfg.differs_from(&baseline, CfgKey::MonitorIndex));
+
+        cfg.set_val(
+            CfgKey::ColorThresh,
+            ValType::Float(Bounded::new(0.83 + f32::EPSILON, 0.0..=0.0)),
+        )
+        .unwrap();
+        assert!(cfg.differs_from(&baseline, CfgKey::ColorThresh));
+        assert_eq!(
+            cfg.changed_keys(&baseline).collect::<Vec<_>>(),
+            vec![CfgKey::ColorThresh]
+        );
+    }
+
+    // the next float past `val`, towards +inf when `up`
+    fn next_float(val: f32, up: bool) -> f32 {
+        let smallest = f32::from_bits(1);
+        if val == 0. {
+            return if up { smallest } else { -smallest };
+        }
+        // a float's bits count up with its distance from zero
+        match (val > 0.) == up {
+            true => f32::from_bits(val.to_bits() + 1),
+            false => f32::from_bits(val.to_bits() - 1),
+        }
+    }
+
+    fn out_of_bounds(key: CfgKey, val: &str) -> bool {
+        let line = format!("{} = {}", key.as_string(), val);
+        matches!(Config::parse_line(line, 1), Err(ParseError::OutOfBounds(1)))
+    }
+
+    #[test]
+    fn bounds_round_trip_every_key() {
+        for key in CfgKey::iter() {
+            let name = key.as_string();
+            match key.default_val() {
+                ValType::Unsigned(v) => {
+                    let (start, end) = (*v.bounds.start(), *v.bounds.end());
+                    for val in [start, end] {
+                        let parsed: Bounded<u32> = parse_val(key, &val.to_string()).into();
+                        assert_eq!(parsed, Bounded::new(val, start..=end), "{} = {}", name, val);
+                    }
+                    if start > 0 {
+                        assert!(out_of_bounds(key, &(start - 1).to_string()), "{}", name);
+                    }
+                    if end < u32::MAX {
+                        assert!(out_of_bounds(key, &(end + 1).to_string()), "{}", name);
+                    }
+                }
+                ValType::Float(v) => {
+                    let (start, end) = (*v.bounds.start(), *v.bounds.end());
+                    for val in [start, end] {
+                        let written = ValType::Float(Bounded::new(val, start..=end)).to_string();
+                        let parsed: Bounded<f32> = parse_val(key, &written).into();
+                        assert_eq!(
+                            parsed.val.to_bits(),
+                            val.to_bits(),
+                            "{} = {}",
+                            name,
+                            written
+                        );
+                        assert_eq!(parsed.bounds, start..=end, "{}", name);
+                    }
+                    assert!(
+                        out_of_bounds(key, &next_float(start, false).to_string()),
+                        "{}",
+                        name
+                    );
+                    assert!(
+                        out_of_bounds(key, &next_float(end, true).to_string()),
+                        "{}",
+                        name
+                    );
+                }
+                // nothing to go past, the default still has to come back the same
+                default => assert_eq!(parse_val(key, &default.to_string()), default, "{}", name),
+            }
+        }
+    }
+
+    #[test]
+    fn global_and_profile_keys() {
+        // the kill switch stays put across games, the binds used while playing don't
+        assert!(CfgKey::ToggleAimKeycode.is_global());
+        assert!(CfgKey::ToggleAutoclickKeycode.is_global());
+        assert!(!CfgKey::AimKeycode.is_global());
+        assert!(!CfgKey::AutoclickKeycode.is_global());
+        assert!(!CfgKey::ColorThresh.is_global());
+
+        let json = Config::schema_json();
+        let n_global = CfgKey::iter().filter(|key| key.is_global()).count();
+        assert_eq!(json.matches("\"scope\": \"global\"").count(), n_global);
+        assert_eq!(
+            json.matches("\"scope\": \"profile\"").count(),
+            N_CFG_KEYS - n_global
+        );
+        for (key, schema) in CfgKey::iter().zip(Config::schema()) {
+            assert_eq!(schema.global, key.is_global(), "{}", schema.name);
+        }
+    }
+
+    #[test]
+    fn rescale_crop_percent_keeps_px() {
+        // 1440p -> 1080p, 768 x 432 px off each side both times
+        assert_eq!(rescale_crop_percent(0.3, 2560, 1920), 0.4);
+        assert_eq!(rescale_crop_percent(0.3, 1440, 1080), 0.4);
+        // 1080p -> 1440p
+        assert_eq!(rescale_crop_percent(0.4, 1920, 2560), 0.3);
+        assert_eq!(rescale_crop_percent(0.4, 1080, 1440), 0.3);
+        // percents that don't divide evenly still land on the same px
+        assert_eq!(crop_px(rescale_crop_percent(0.25, 1440, 1080), 1080), 360);
+    }
+
+    #[test]
+    fn rescale_crop_percent_clamped() {
+        // 1152 px doesn't fit on a 1920 wide screen, it gets as close as the bounds allow
+        assert_eq!(rescale_crop_percent(0.45, 2560, 1920), MAX_CROP_PERCENT);
+        assert_eq!(
+            rescale_crop_percent(MAX_CROP_PERCENT, 3840, 800),
+            MAX_CROP_PERCENT
+        );
+
+        let mut cfg = Config::default();
+        for (old_len, new_len) in [
+            (2560, 1920),
+            (1920, 2560),
+            (3840, 800),
+            (1440, 1080),
+            (1080, 1440),
+        ] {
+            for percent in [0., 0.1, 0.3, 0.45, MAX_CROP_PERCENT] {
+                let new_percent = rescale_crop_percent(percent, old_len, new_len);
+                // has to be a value the percent keys accept, apply_screen_resize unwraps setting it
+                cfg.set_val(
+                    CfgKey::CropWPercent,
+                    ValType::Float(Bounded::new(new_percent, 0.0..=0.0)),
+                )
+                .unwrap();
+                assert!(crop_px(new_percent, new_len) < new_len / 2);
+            }
+        }
+    }
+
+    // a config saved before the percent keys, with `crop` in place of them
+    fn load_legacy_crop(name: &str, crop: &[&str]) -> Config {
+        let path =
+            std::env::temp_dir().join(format!("pixelbot_{}_{}.cfg", name, std::process::id()));
+        let path_str = path.to_str().unwrap();
+        let _ = std::fs::remove_file(&path);
+        Config::default().write_to_file(path_str).unwrap();
+        let mut old_cfg: String = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .filter(|line| {
+                !line.starts_with("crop_w_percent") && !line.starts_with("crop_h_percent")
+            })
+            .map(|line| format!("{}\n", line))
+            .collect();
+        old_cfg.insert_str(
+            0,
+            &crop
+                .iter()
+                .map(|line| format!("{}\n", line))
+                .collect::<String>(),
+        );
+        std::fs::write(&path, old_cfg).unwrap();
+
+        let cfg = Config::from_file(path_str);
+        std::fs::remove_file(&path).unwrap();
+        cfg.unwrap()
+    }
+
+    fn crop_percents(cfg: &Config) -> (f32, f32) {
+        (
+            <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::CropWPercent)).val,
+            <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::CropHPercent)).val,
+        )
+    }
+
+    #[test]
+    fn migrate_legacy_crop() {
+        let legacy = ["crop_w = 768", "crop_h = 432"];
+        for (screen, percents) in [((1920, 1080), (0.4, 0.4)), ((2560, 1440), (0.3, 0.3))] {
+            let mut cfg = load_legacy_crop("legacy_crop", &legacy);
+            assert_eq!(
+                cfg.migrate_legacy_crop(screen),
+                vec![CfgKey::CropWPercent, CfgKey::CropHPercent]
+            );
+            assert_eq!(crop_percents(&cfg), percents);
+            assert_eq!(cfg.crop_px(screen), (768, 432));
+            assert!(
+                cfg.is_key_dirty(CfgKey::CropWPercent) && cfg.is_key_dirty(CfgKey::CropHPercent)
+            );
+            assert_eq!(cfg.migrate_legacy_crop(screen), vec![]);
+        }
+    }
+
+    #[test]
+    fn migrate_legacy_crop_clamped() {
+        // saved on a bigger screen, more than half of a 1080p one
+        for screen in [(1920, 1080), (2560, 1440), (1280, 720)] {
+            let mut cfg =
+                load_legacy_crop("legacy_crop_clamped", &["crop_w = 1152", "crop_h = 592"]);
+            cfg.migrate_legacy_crop(screen);
+            let (w_percent, h_percent) = crop_percents(&cfg);
+            assert!(w_percent <= MAX_CROP_PERCENT && h_percent <= MAX_CROP_PERCENT);
+            let (w, h) = cfg.crop_px(screen);
+            assert!(
+                w < screen.0 / 2 && h < screen.1 / 2,
+                "{:?} on {:?}",
+                (w, h),
+                screen
+            );
+        }
+    }
+
+    #[test]
+    fn legacy_crop_ignored_next_to_percent() {
+        let mut cfg = load_legacy_crop(
+            "legacy_crop_both",
+            &[
+                "crop_w = 768",
+                "crop_w_percent = 0.2",
+                "crop_h_percent = 0.1",
+            ],
+        );
+        assert_eq!(cfg.migrate_legacy_crop((1920, 1080)), vec![]);
+        assert_eq!(crop_percents(&cfg), (0.2, 0.1));
+    }
+
+    #[test]
+    fn crop_px_never_past_the_middle() {
+        for len in [0, 1, 2, 3, 720, 1080, 1440, 1920, 2560] {
+            for percent in [0., 0.25, MAX_CROP_PERCENT, 0.5, 1.] {
+                let px = crop_px(percent, len);
+                assert!(px <= (len / 2).saturating_sub(1), "{} of {}", percent, len);
+            }
+        }
+    }
+}
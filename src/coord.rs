@@ -1,4 +1,4 @@
-use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::ops::{Add, AddAssign, Deref, Sub, SubAssign};
 
 use num_traits::{AsPrimitive, Bounded};
 
@@ -75,6 +75,20 @@ impl<T: AsPrimitive<i32>> Coord<T> {
 
 impl<T> Coord<T> {
     pub fn bbox(coord_cluster: &[Coord<T>]) -> (Coord<T>, Coord<T>)
+    where
+        T: Copy + Ord + Bounded,
+    {
+        Self::bbox_iter(coord_cluster.iter().copied())
+    }
+
+    pub fn bbox_xywh(coord_cluster: &[Coord<T>]) -> (T, T, T, T)
+    where
+        T: Copy + Ord + Bounded + Sub<Output = T>,
+    {
+        Self::xywh(Self::bbox(coord_cluster))
+    }
+
+    fn bbox_iter(coords: impl Iterator<Item = Coord<T>>) -> (Coord<T>, Coord<T>)
     where
         T: Copy + Ord + Bounded,
     {
@@ -82,7 +96,7 @@ impl<T> Coord<T> {
         let mut y_max = T::min_value();
         let mut x_min = T::max_value();
         let mut y_min = T::max_value();
-        for coord in coord_cluster {
+        for coord in coords {
             if coord.x > x_max {
                 x_max = coord.x;
             }
@@ -99,11 +113,10 @@ impl<T> Coord<T> {
         (Coord::new(x_min, y_min), Coord::new(x_max, y_max))
     }
 
-    pub fn bbox_xywh(coord_cluster: &[Coord<T>]) -> (T, T, T, T)
+    fn xywh((min_coord, max_coord): (Coord<T>, Coord<T>)) -> (T, T, T, T)
     where
-        T: Copy + Ord + Bounded + Sub<Output = T>,
+        T: Copy + Sub<Output = T>,
     {
-        let (min_coord, max_coord) = Self::bbox(coord_cluster);
         (
             min_coord.x,
             min_coord.y,
@@ -112,3 +125,300 @@ impl<T> Coord<T> {
         )
     }
 }
+
+// The true center of a w x h pixel grid, it lands between the two middle pixels on even sizes.
+//     Everything measuring distance from the crosshair goes through this, so the math can't drift apart
+pub fn frame_center(dims: (usize, usize)) -> Coord<f32> {
+    Coord::new(
+        (dims.0.max(1) - 1) as f32 / 2.,
+        (dims.1.max(1) - 1) as f32 / 2.,
+    )
+}
+
+// Negative when left of / above the center, a pixel on the center is exactly 0
+pub fn offset_from_center(pos: Coord<usize>, dims: (usize, usize)) -> Coord<f32> {
+    let center = frame_center(dims);
+    Coord::new(pos.x as f32 - center.x, pos.y as f32 - center.y)
+}
+
+// The pixel at (or just up & left of) the true center, for sampling & drawing
+pub fn center_pixel(dims: (usize, usize)) -> Coord<usize> {
+    Coord::new(dims.0.saturating_sub(1) / 2, dims.1.saturating_sub(1) / 2)
+}
+
+// Typed coordinate spaces, so a position in one can't silently get used as a position in another.
+//     Zero cost wrappers, deref to the plain coord for the math. The field stays private, the only way
+//     between spaces is through the conversions below
+macro_rules! coord_space {
+    ($name: ident, $inner: ty) => {
+        #[repr(transparent)]
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+        pub struct $name(Coord<$inner>);
+
+        impl $name {
+            pub fn new(x: $inner, y: $inner) -> Self {
+                Self(Coord::new(x, y))
+            }
+
+            // Coord::bbox_xywh without unwrapping every coord into a new vec first
+            pub fn bbox_xywh(cluster: &[Self]) -> ($inner, $inner, $inner, $inner) {
+                Coord::xywh(Coord::bbox_iter(cluster.iter().map(|c| c.0)))
+            }
+        }
+
+        impl Deref for $name {
+            type Target = Coord<$inner>;
+            fn deref(&self) -> &Coord<$inner> {
+                &self.0
+            }
+        }
+    };
+}
+
+coord_space!(ScreenPos, usize); // the full captured screen (or a screenshot of it)
+coord_space!(CropPos, usize); // the center crop at full res
+coord_space!(DetectPos, usize); // the crop after detect_downscale
+coord_space!(MirrorPos, usize); // the crop scaled to fit the mirror widget
+coord_space!(ViewPos, i32); // widget relative, eg. mouse clicks, can land outside the image
+
+impl DetectPos {
+    // Same rounding as `scale_nearest`, so it lands on the pixel that was actually sampled
+    pub fn to_crop(self, detect_dims: (usize, usize), crop_dims: (usize, usize)) -> CropPos {
+        let (sx, sy) = (
+            crop_dims.0 as f32 / detect_dims.0 as f32,
+            crop_dims.1 as f32 / detect_dims.1 as f32,
+        );
+        CropPos::new(
+            (crop_dims.0 - 1).min((self.x as f32 * sx).round() as usize),
+            (crop_dims.1 - 1).min((self.y as f32 * sy).round() as usize),
+        )
+    }
+}
+
+impl CropPos {
+    // `crop_origin` is the screen position of the crop's top left corner
+    pub fn to_screen(self, crop_origin: ScreenPos) -> ScreenPos {
+        ScreenPos(self.0 + crop_origin.0)
+    }
+
+    pub fn to_mirror(self, crop_dims: (usize, usize), mirror_dims: (usize, usize)) -> MirrorPos {
+        MirrorPos::new(
+            (self.x as f32 * (mirror_dims.0 as f32 / crop_dims.0 as f32)) as usize,
+            (self.y as f32 * (mirror_dims.1 as f32 / crop_dims.1 as f32)) as usize,
+        )
+    }
+}
+
+impl ViewPos {
+    // Maps a position in a view showing a letterboxed screenshot back to the screenshot's pixel,
+    //     None when it landed on the bars
+    pub fn unletterbox(self, view_dims: (usize, usize), img_dims: (usize, usize)) -> Option<ScreenPos> {
+        self.unletterbox_coord(view_dims, img_dims).map(ScreenPos)
+    }
+
+    // Same as unletterbox, for the mirror showing the crop
+    pub fn unletterbox_crop(
+        self,
+        view_dims: (usize, usize),
+        crop_dims: (usize, usize),
+    ) -> Option<CropPos> {
+        self.unletterbox_coord(view_dims, crop_dims).map(CropPos)
+    }
+
+    fn unletterbox_coord(
+        self,
+        view_dims: (usize, usize),
+        img_dims: (usize, usize),
+    ) -> Option<Coord<usize>> {
+        let ratio =
+            (view_dims.0 as f32 / img_dims.0 as f32).min(view_dims.1 as f32 / img_dims.1 as f32);
+        let scaled = (
+            ((img_dims.0 as f32 * ratio) as usize).max(1),
+            ((img_dims.1 as f32 * ratio) as usize).max(1),
+        );
+        let offset = ((view_dims.0 - scaled.0) / 2, (view_dims.1 - scaled.1) / 2);
+
+        let (x, y) = (self.x - offset.0 as i32, self.y - offset.1 as i32);
+        if x < 0 || y < 0 || x as usize >= scaled.0 || y as usize >= scaled.1 {
+            return None;
+        }
+        Some(Coord::new(
+            (img_dims.0 - 1).min(x as usize * img_dims.0 / scaled.0),
+            (img_dims.1 - 1).min(y as usize * img_dims.1 / scaled.1),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_center_odd_dims() {
+        assert_eq!(frame_center((5, 3)), Coord::new(2., 1.));
+        assert_eq!(center_pixel((5, 3)), Coord::new(2, 1));
+        assert_eq!(
+            offset_from_center(Coord::new(2, 1), (5, 3)),
+            Coord::new(0., 0.)
+        );
+    }
+
+    #[test]
+    fn frame_center_even_dims() {
+        // between the two middle pixels, center_pixel takes the one up & left
+        assert_eq!(frame_center((4, 2)), Coord::new(1.5, 0.5));
+        assert_eq!(center_pixel((4, 2)), Coord::new(1, 0));
+        assert_eq!(
+            offset_from_center(Coord::new(1, 0), (4, 2)),
+            Coord::new(-0.5, -0.5)
+        );
+        assert_eq!(
+            offset_from_center(Coord::new(2, 1), (4, 2)),
+            Coord::new(0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn frame_center_mixed_dims() {
+        assert_eq!(frame_center((4, 3)), Coord::new(1.5, 1.));
+        assert_eq!(center_pixel((4, 3)), Coord::new(1, 1));
+    }
+
+    #[test]
+    fn offsets_symmetric_around_center() {
+        for dims in [(4, 4), (5, 5), (6, 3), (7, 2)] {
+            for x in 0..dims.0 {
+                let mirrored = dims.0 - 1 - x;
+                let a = offset_from_center(Coord::new(x, 0), dims);
+                let b = offset_from_center(Coord::new(mirrored, 0), dims);
+                assert_eq!(a.x, -b.x, "{:?} at x {}", dims, x);
+            }
+        }
+    }
+
+    #[test]
+    fn frame_center_degenerate_dims() {
+        assert_eq!(frame_center((1, 1)), Coord::new(0., 0.));
+        assert_eq!(frame_center((0, 0)), Coord::new(0., 0.));
+        assert_eq!(center_pixel((0, 0)), Coord::new(0, 0));
+    }
+
+    #[test]
+    fn detect_to_crop_known_scale() {
+        assert_eq!(
+            DetectPos::new(3, 5).to_crop((50, 25), (200, 100)),
+            CropPos::new(12, 20)
+        );
+        assert_eq!(
+            DetectPos::new(49, 24).to_crop((50, 25), (200, 100)),
+            CropPos::new(196, 96)
+        );
+    }
+
+    #[test]
+    fn detect_to_crop_hits_sampled_pixel() {
+        use crate::image::{self, Bgra8, Color, Pixel};
+
+        // every pixel is colored by its own position, so the sampled one can be read back
+        for (crop_dims, detect_dims) in [
+            ((200, 100), (50, 25)),
+            ((190, 107), (63, 35)),
+            ((10, 10), (3, 3)),
+        ] {
+            let mut crop = image::zeroed::<Bgra8>(crop_dims.0, crop_dims.1);
+            for y in 0..crop.h {
+                for x in 0..crop.w {
+                    crop.set2d(Coord::new(x, y), Color::new(x as u8, y as u8, 0, 255));
+                }
+            }
+            let small = crop.scale_nearest(detect_dims.0, detect_dims.1).unwrap();
+            for y in 0..small.h {
+                for x in 0..small.w {
+                    let pos = DetectPos::new(x, y).to_crop(detect_dims, crop_dims);
+                    assert_eq!(
+                        small.get_pixel2d(Coord::new(x, y)).as_color(),
+                        crop.get_pixel2d(*pos).as_color(),
+                        "{:?} -> {:?} at {}, {}",
+                        detect_dims,
+                        crop_dims,
+                        x,
+                        y
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn crop_to_mirror_and_screen() {
+        assert_eq!(
+            CropPos::new(100, 50).to_mirror((200, 100), (100, 50)),
+            MirrorPos::new(50, 25)
+        );
+        assert_eq!(
+            CropPos::new(199, 99).to_mirror((200, 100), (100, 50)),
+            MirrorPos::new(99, 49)
+        );
+        assert_eq!(
+            CropPos::new(3, 3).to_mirror((200, 100), (400, 200)),
+            MirrorPos::new(6, 6)
+        );
+        assert_eq!(
+            CropPos::new(5, 6).to_screen(ScreenPos::new(100, 200)),
+            ScreenPos::new(105, 206)
+        );
+    }
+
+    #[test]
+    fn unletterbox_bars() {
+        // 200x100 in a 200x200 view, 50px bars above & below
+        let (view, img) = ((200, 200), (200, 100));
+        assert_eq!(
+            ViewPos::new(0, 50).unletterbox(view, img),
+            Some(ScreenPos::new(0, 0))
+        );
+        assert_eq!(
+            ViewPos::new(199, 149).unletterbox(view, img),
+            Some(ScreenPos::new(199, 99))
+        );
+        assert_eq!(ViewPos::new(100, 49).unletterbox(view, img), None);
+        assert_eq!(ViewPos::new(100, 150).unletterbox(view, img), None);
+        assert_eq!(ViewPos::new(-1, 60).unletterbox(view, img), None);
+
+        // 100x100 in a 300x100 view, bars on the sides
+        let (view, img) = ((300, 100), (100, 100));
+        assert_eq!(ViewPos::new(99, 50).unletterbox(view, img), None);
+        assert_eq!(
+            ViewPos::new(100, 0).unletterbox(view, img),
+            Some(ScreenPos::new(0, 0))
+        );
+        assert_eq!(ViewPos::new(200, 50).unletterbox(view, img), None);
+    }
+
+    #[test]
+    fn unletterbox_scaled_down() {
+        // 400x200 shown at 100x50 in a 100x100 view
+        let (view, img) = ((100, 100), (400, 200));
+        assert_eq!(
+            ViewPos::new(50, 50).unletterbox(view, img),
+            Some(ScreenPos::new(200, 100))
+        );
+        assert_eq!(
+            ViewPos::new(99, 74).unletterbox_crop(view, img),
+            Some(CropPos::new(396, 196))
+        );
+        assert_eq!(ViewPos::new(50, 75).unletterbox_crop(view, img), None);
+    }
+
+    #[test]
+    fn typed_bbox_matches_plain() {
+        let cluster = [
+            MirrorPos::new(4, 9),
+            MirrorPos::new(1, 3),
+            MirrorPos::new(7, 5),
+        ];
+        let plain: Vec<Coord<usize>> = cluster.iter().map(|c| **c).collect();
+        assert_eq!(MirrorPos::bbox_xywh(&cluster), Coord::bbox_xywh(&plain));
+        assert_eq!(MirrorPos::bbox_xywh(&cluster), (1, 3, 6, 6));
+    }
+}
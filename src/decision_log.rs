@@ -0,0 +1,117 @@
+use crate::aim::AimStats;
+use crate::coord::{Coord, CropPos};
+use crate::logging::{log, log_err};
+
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::Instant;
+
+const LOG_LEN: usize = 200; // ~90 bytes a record, under 20kb total
+
+// What the aim thread decided on a single frame, kept small & Copy so the ring is one flat allocation
+#[derive(Debug, Clone, Copy)]
+pub struct Decision {
+    pub at: Instant,
+    pub area: u32, // selected cluster's pixel count, 0 without one
+    pub aim_coord: Option<CropPos>,
+    pub candidates: u32,
+    pub stats: AimStats,
+    pub relative_coord: Coord<i32>, // after the switch ramp
+    pub gain: f32,                  // switch ramp gain, 1 when it isn't ramping
+    pub wash_rejected: bool,
+    pub wash_grace: bool, // aimed at the last target because this frame was washed out
+    pub unconfirmed: bool, // found a cluster but its outline didn't check out
+    pub outside_fov: bool, // found one but it was too far from the crosshair
+    pub moved: bool,      // the correction was actually sent to the mouse
+}
+
+// Ring of the last few hundred decisions, only ever touched by the aim thread so dumping is a command
+//     instead of a lock on the hot path
+pub struct DecisionLog {
+    records: Vec<Decision>,
+    next: usize,
+}
+
+impl DecisionLog {
+    pub fn new() -> Self {
+        Self {
+            records: Vec::with_capacity(LOG_LEN),
+            next: 0,
+        }
+    }
+
+    pub fn push(&mut self, decision: Decision) {
+        if self.records.len() < LOG_LEN {
+            self.records.push(decision);
+        } else {
+            self.records[self.next] = decision;
+        }
+        self.next = (self.next + 1) % LOG_LEN;
+    }
+
+    // One line a frame, oldest first
+    pub fn format(&self) -> String {
+        let now = Instant::now();
+        let mut out = String::from(
+            "# ms_ago, area, aim_x, aim_y, candidates, rejected area/aspect/static, move_x, move_y, gain, gates\n",
+        );
+        let oldest_first = (0..self.records.len()).map(|i| match self.records.len() {
+            LOG_LEN => &self.records[(self.next + i) % LOG_LEN],
+            _ => &self.records[i], // not wrapped around yet
+        });
+        for d in oldest_first {
+            let (aim_x, aim_y) = match d.aim_coord {
+                Some(c) => (c.x.to_string(), c.y.to_string()),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            let gates: Vec<&str> = [
+                (d.wash_rejected, "wash"),
+                (d.wash_grace, "grace"),
+                (d.unconfirmed, "unconfirmed"),
+                (d.outside_fov, "fov"),
+                (d.gain < 1., "ramp"),
+                (!d.moved, "no_move"),
+            ]
+            .into_iter()
+            .filter_map(|(fired, name)| fired.then(|| name))
+            .collect();
+            let _ = writeln!(
+                out,
+                "{}, {}, {}, {}, {}, {}/{}/{}, {}, {}, {:.2}, {}",
+                now.duration_since(d.at).as_millis(),
+                d.area,
+                aim_x,
+                aim_y,
+                d.candidates,
+                d.stats.clusters_rejected_area,
+                d.stats.clusters_rejected_aspect,
+                d.stats.clusters_rejected_static,
+                d.relative_coord.x,
+                d.relative_coord.y,
+                d.gain,
+                gates.join(" ")
+            );
+        }
+        out
+    }
+
+    pub fn dump(&self) {
+        if self.records.is_empty() {
+            log!("Decision log is empty, nothing to dump");
+            return;
+        }
+        log!(
+            "Last {} aim decisions:\n{}",
+            self.records.len(),
+            self.format()
+        );
+    }
+
+    // Next to the frame ring's bmps, so the pictures & the reasoning line up
+    pub fn write_to(&self, dir: &Path) {
+        let path = dir.join("decisions.txt");
+        if let Err(e) = std::fs::write(&path, self.format()) {
+            log_err!("Error writing decisions to {}:\n\t{}", path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::config::{CfgKey, Config};
+use crate::logging::log_err;
+use crate::pixel_bot::{spawn_bot_thread, BotThread};
+use crate::sound::spawn_sound_thread;
+
+use crossbeam::channel::{self, Sender};
+use rustc_hash::FxHashMap;
+use std::process::Command;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BotEvent {
+    AimOn,
+    AimOff,
+    TargetAcquired,
+    TargetLost,
+    ClickmodeChanged,
+}
+
+impl BotEvent {
+    pub fn as_arg(&self) -> &'static str {
+        match *self {
+            Self::AimOn => "aim_on",
+            Self::AimOff => "aim_off",
+            Self::TargetAcquired => "target_acquired",
+            Self::TargetLost => "target_lost",
+            Self::ClickmodeChanged => "clickmode_changed",
+        }
+    }
+}
+
+// Cheap to clone, never blocks the sending thread. Events are dropped when a queue is full
+#[derive(Clone)]
+pub struct EventSender {
+    command_tx: Sender<BotEvent>,
+    sound_tx: Sender<BotEvent>,
+}
+
+impl EventSender {
+    pub fn send(&self, event: BotEvent) {
+        let _ = self.command_tx.try_send(event);
+        let _ = self.sound_tx.try_send(event);
+    }
+}
+
+// Every event goes to both the external command & the sound thread, separately so a slow command
+//     never delays a beep. Both threads exit once every EventSender has been dropped
+pub fn spawn_event_thread(config: Arc<RwLock<Config>>) -> (EventSender, Vec<BotThread>) {
+    const QUEUE_LEN: usize = 16;
+    const MIN_REPEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+    let (sound_tx, sound_rx) = channel::bounded::<BotEvent>(QUEUE_LEN);
+    let sound_handle = spawn_sound_thread(config.clone(), sound_rx);
+
+    let (command_tx, receiver) = channel::bounded::<BotEvent>(QUEUE_LEN);
+    let handle = spawn_bot_thread("event", move || {
+        let mut last_sent: FxHashMap<BotEvent, Instant> = FxHashMap::default();
+
+        for event in receiver.iter() {
+            let command: String = config.read().unwrap().get(CfgKey::EventCommand).into();
+            if command.is_empty() {
+                continue;
+            }
+
+            // rate limiting identical events
+            if let Some(last) = last_sent.get(&event) {
+                if last.elapsed() < MIN_REPEAT_INTERVAL {
+                    continue;
+                }
+            }
+            last_sent.insert(event, Instant::now());
+
+            // no shell, the command is run directly with the event name as its only argument
+            match Command::new(&command).arg(event.as_arg()).spawn() {
+                Ok(mut child) => {
+                    let _ = child.wait();
+                }
+                Err(e) => log_err!(
+                    "Error running {} `{}`:\n\t{}",
+                    CfgKey::EventCommand.as_string(),
+                    command,
+                    e
+                ),
+            }
+        }
+    });
+
+    (EventSender { command_tx, sound_tx }, vec![handle, sound_handle])
+}
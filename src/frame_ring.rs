@@ -0,0 +1,138 @@
+use crate::coord::{Coord, CropPos};
+use crate::image::{self, Bgra8, Image};
+use crate::logging::{log, log_err};
+use crate::paths::resolve_data_path;
+
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+const RING_LEN: usize = 64; // ~2 seconds at the usual frame rates
+const RING_DOWNSCALE: usize = 4;
+
+// What the aim thread made of a frame, written to the dump's manifest
+#[derive(Debug, Clone, Copy)]
+pub struct FrameRecord {
+    pub aim_coord: Option<CropPos>,
+    pub relative_coord: Coord<i32>,
+    pub confirmed: bool,
+    pub wash_rejected: bool,
+    pub candidates: u32,
+}
+
+struct RingSlot {
+    img: Image<Vec<u8>, Bgra8>,
+    record: FrameRecord,
+    seen_at: Instant,
+}
+
+// The last few seconds of what the bot saw at quarter res, for figuring out what went wrong after the fact.
+//     Slots are allocated once and overwritten, only a crop size change reallocates them
+pub struct FrameRing {
+    slots: Vec<RingSlot>,
+    next: usize,
+}
+
+impl FrameRing {
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::with_capacity(RING_LEN),
+            next: 0,
+        }
+    }
+
+    pub fn push(&mut self, frame: &Image<Vec<u8>, Bgra8>, record: FrameRecord) {
+        let small_w = (frame.w / RING_DOWNSCALE).max(1);
+        let small_h = (frame.h / RING_DOWNSCALE).max(1);
+
+        if self.slots.len() < RING_LEN {
+            self.slots.push(RingSlot {
+                img: image::zeroed::<Bgra8>(small_w, small_h),
+                record,
+                seen_at: Instant::now(),
+            });
+        }
+        let slot = &mut self.slots[self.next];
+        if (slot.img.w, slot.img.h) != (small_w, small_h) {
+            slot.img = image::zeroed::<Bgra8>(small_w, small_h);
+        }
+        frame.scale_nearest_into(&mut slot.img);
+        slot.record = record;
+        slot.seen_at = Instant::now();
+        self.next = (self.next + 1) % RING_LEN;
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.next = 0;
+    }
+
+    // Writes every frame as a bmp (oldest first) plus a manifest of the detection results into a new directory,
+    //     returns the directory if it all got written
+    pub fn dump(&self) -> Option<PathBuf> {
+        if self.slots.is_empty() {
+            log!("Frame ring is empty, nothing to dump");
+            return None;
+        }
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let dir = resolve_data_path(&format!("frames-{}", secs));
+        match self.write_dump(&dir) {
+            Ok(_) => {
+                log!(
+                    "Dumped the last {} frames to {}",
+                    self.slots.len(),
+                    dir.display()
+                );
+                Some(dir)
+            }
+            Err(e) => {
+                log_err!("Error dumping frames to {}:\n\t{}", dir.display(), e);
+                None
+            }
+        }
+    }
+
+    fn write_dump(&self, dir: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        let now = Instant::now();
+
+        let mut manifest = format!(
+            "# {}x downscaled, coords are in the full res crop\n\
+            # frame, ms_ago, aim_x, aim_y, move_x, move_y, confirmed, wash_rejected, candidates\n",
+            RING_DOWNSCALE
+        );
+        let oldest_first = (0..self.slots.len()).map(|i| {
+            let idx = match self.slots.len() {
+                RING_LEN => (self.next + i) % RING_LEN,
+                _ => i, // not wrapped around yet
+            };
+            &self.slots[idx]
+        });
+        for (i, slot) in oldest_first.enumerate() {
+            slot.img.save_bmp(dir.join(format!("frame-{:03}.bmp", i)))?;
+
+            let r = &slot.record;
+            let (aim_x, aim_y) = match r.aim_coord {
+                Some(c) => (c.x.to_string(), c.y.to_string()),
+                None => (String::new(), String::new()),
+            };
+            let _ = writeln!(
+                manifest,
+                "{}, {}, {}, {}, {}, {}, {}, {}, {}",
+                i,
+                now.duration_since(slot.seen_at).as_millis(),
+                aim_x,
+                aim_y,
+                r.relative_coord.x,
+                r.relative_coord.y,
+                r.confirmed,
+                r.wash_rejected,
+                r.candidates
+            );
+        }
+        std::fs::write(dir.join("manifest.txt"), manifest)
+    }
+}
@@ -1,18 +1,29 @@
-use crate::config::{Bounded, CfgKey, Config, ValType};
-use crate::coord::Coord;
-use crate::image::{self, image_ops::BlendType, Bgra8, Rgba8};
-use crate::input::{get_any_pressed_key, keycode_to_string, wait_for_release};
+use crate::config::{self, Bounded, CfgKey, Choice, Config, ValType};
+use crate::coord::{self, Coord, CropPos, MirrorPos, ViewPos};
+use crate::image::{
+    self,
+    image_ops::{suggest_thresh, BlendType},
+    Bgra8, Pixel, Rgba8,
+};
+use crate::input::{
+    get_any_pressed_key, keycode_to_scancode, keycode_to_string, wait_for_release, DriverStatus,
+};
 use crate::logging::{self, drain_log, log, log_err};
+use crate::paths::resolve_data_path;
 use crate::pixel_bot;
+use crate::self_test;
+use crate::theme::Theme;
 
 use crossbeam::channel;
 use fltk::{
     app::{self, App},
-    button::Button,
+    button::{Button, CheckButton},
+    dialog::{self, NativeFileChooser, NativeFileChooserType},
     draw,
-    enums::{Align, Color, Cursor, Event, Font, FrameType, Key},
+    enums::{Align, CallbackTrigger, Color, Cursor, Event, Font, FrameType, Key, Shortcut},
     frame::Frame,
-    group::Group,
+    group::{Flex, Group},
+    input::{FloatInput, Input, IntInput},
     prelude::*,
     text::{SimpleTerminal, StyleTableEntry, TextBuffer},
     valuator::HorFillSlider,
@@ -27,35 +38,6 @@ use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
-struct Palette;
-impl Palette {
-    const BG0_H: Color = Color::from_hex(0x1d2021);
-    const BG0: Color = Color::from_hex(0x282828);
-    const BG1: Color = Color::from_hex(0x3c3836);
-    const GRAY: Color = Color::from_hex(0x928374);
-    const FG0: Color = Color::from_hex(0xfbf1c7);
-    const FG1: Color = Color::from_hex(0xebdbb2);
-    const FG2: Color = Color::from_hex(0xd5c4a1);
-
-    const RED: Color = Color::from_hex(0xfb4934);
-    const GREEN: Color = Color::from_hex(0xb8bb26);
-    const YELLOW: Color = Color::from_hex(0xfabd2f);
-    const BLUE: Color = Color::from_hex(0x83a598);
-    const PURPLE: Color = Color::from_hex(0xd3869b);
-    const AQUA: Color = Color::from_hex(0x8ec07c);
-    const ORANGE: Color = Color::from_hex(0xfe8019);
-
-    const COLORS: [Color; 7] = [
-        Self::RED,
-        Self::GREEN,
-        Self::YELLOW,
-        Self::BLUE,
-        Self::PURPLE,
-        Self::AQUA,
-        Self::ORANGE,
-    ];
-}
-
 #[derive(Clone, Copy, Debug)]
 pub struct Bounds {
     x: i32,
@@ -100,7 +82,9 @@ where
 
         // getting px width of single character
         self.set_label("_");
-        let line_w = ((max_w / self.measure_label().0) - MARGIN) as usize;
+        // a font without the glyph measures 0 wide
+        let char_w = self.measure_label().0.max(1);
+        let line_w = ((max_w / char_w) - MARGIN).max(1) as usize;
 
         // wrapping label string
         let mut label_bytes = label.into_bytes();
@@ -110,6 +94,38 @@ where
     }
 }
 
+// Embedded copies of the fonts in assets/, so labels render the same when the files aren't shipped with the exe
+const FONT_MEDIUM_BYTES: &[u8] = include_bytes!("../assets/JetBrainsMono-Medium.ttf");
+const FONT_BOLD_BYTES: &[u8] = include_bytes!("../assets/JetBrainsMono-Bold.ttf");
+
+// fltk only loads fonts from a path, so the embedded fallback goes through a temp file
+fn load_ui_font(slot: Font, file_name: &str, embedded: &'static [u8]) {
+    let font_path = resolve_data_path(&format!("assets/{}", file_name));
+    if let Ok(font) = Font::load_font(&font_path.to_string_lossy()) {
+        Font::set_font(slot, &font);
+        return;
+    }
+
+    let tmp_path = std::env::temp_dir().join(format!("pixelbot-{}", file_name));
+    let loaded = std::fs::write(&tmp_path, embedded)
+        .ok()
+        .and_then(|_| Font::load_font(&tmp_path.to_string_lossy()).ok());
+    match loaded {
+        Some(font) => {
+            log_err!(
+                "Font {} not found, using the embedded copy",
+                font_path.display()
+            );
+            Font::set_font(slot, &font);
+        }
+        None => log_err!(
+            "Font {} not found and the embedded copy couldn't be loaded, falling back to the system {:?}",
+            font_path.display(),
+            slot
+        ),
+    }
+}
+
 trait InternalColorConvert {
     fn from_internal(color: image::Color<u8>) -> Self;
     fn to_internal(&self) -> image::Color<u8>;
@@ -124,7 +140,46 @@ impl InternalColorConvert for Color {
     }
 }
 
-struct Graph<const CIRC_BUF_SIZE: usize> {
+// Averages over however many samples have been pushed so far, so a partially filled window
+//     (startup, or right after a resize) doesn't get dragged down by empty slots
+struct RollingAvg {
+    buf: VecDeque<Duration>,
+    size: usize,
+}
+
+impl RollingAvg {
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1);
+        Self {
+            buf: VecDeque::with_capacity(size),
+            size,
+        }
+    }
+
+    pub fn push(&mut self, sample: Duration) {
+        self.buf.push_back(sample);
+        if self.buf.len() > self.size {
+            self.buf.pop_front();
+        }
+    }
+
+    pub fn set_size(&mut self, size: usize) {
+        self.size = size.max(1);
+        while self.buf.len() > self.size {
+            self.buf.pop_front();
+        }
+    }
+
+    pub fn avg(&self) -> Option<Duration> {
+        if self.buf.is_empty() {
+            None
+        } else {
+            Some(self.buf.iter().sum::<Duration>() / self.buf.len() as u32)
+        }
+    }
+}
+
+struct Graph {
     b: Bounds,
     data_range: Range<i32>,
     points: VecDeque<Coord<i32>>,
@@ -133,12 +188,18 @@ struct Graph<const CIRC_BUF_SIZE: usize> {
     frame: Frame,
     label_frame: Frame,
     redraw: bool,
-    rolling_avg_buf: [Duration; CIRC_BUF_SIZE],
-    rolling_avg_idx: usize,
+    rolling_avg: RollingAvg,
+    budget: Option<Duration>, // frame time the configured fps allows, drawn as a flat line
+    theme: Rc<Theme>,
 }
 
-impl<const CIRC_BUF_SIZE: usize> Graph<CIRC_BUF_SIZE> {
-    pub fn new(b: Bounds, data_range: Range<i32>) -> Self {
+impl Graph {
+    pub fn new(
+        b: Bounds,
+        data_range: Range<i32>,
+        rolling_avg_size: usize,
+        theme: Rc<Theme>,
+    ) -> Self {
         let label_h = (b.h as f32 * 0.05) as i32;
         let (frame_w, frame_h) = (b.w, b.h - label_h);
         let frame = Frame::new(b.x, b.y, frame_w, frame_h, "");
@@ -147,13 +208,13 @@ impl<const CIRC_BUF_SIZE: usize> Graph<CIRC_BUF_SIZE> {
 
         let graph_img = image::zeroed::<Rgba8>(frame_w as usize, frame_h as usize);
         let mut bg_img = image::zeroed::<Rgba8>(frame_w as usize, frame_h as usize);
-        bg_img.fill_color(Palette::BG0.to_internal());
-        bg_img.draw_grid(30, Palette::AQUA.to_internal());
+        bg_img.fill_color(theme.bg0.to_internal());
+        bg_img.draw_grid(30, theme.aqua.to_internal());
 
         label_frame.set_label_font(Font::Courier);
         label_frame.set_label_size(label_h - 2 /*small margin*/);
         label_frame.set_frame(FrameType::FlatBox);
-        label_frame.set_color(Palette::BG0_H);
+        label_frame.set_color(theme.bg0_h);
 
         Self {
             b,
@@ -164,12 +225,37 @@ impl<const CIRC_BUF_SIZE: usize> Graph<CIRC_BUF_SIZE> {
             frame,
             label_frame,
             redraw: false,
-            rolling_avg_buf: [Duration::default(); CIRC_BUF_SIZE],
-            rolling_avg_idx: 0,
+            rolling_avg: RollingAvg::new(rolling_avg_size),
+            budget: None,
+            theme,
+        }
+    }
+
+    pub fn set_budget(&mut self, budget: Duration) {
+        if self.budget != Some(budget) {
+            self.budget = Some(budget);
+            self.redraw = true;
         }
     }
 
+    // times outside of the data range get pinned to the top/bottom edge
+    fn time_to_y(&self, time: Duration) -> i32 {
+        let time_norm = clamp(
+            1. - ((time.as_secs_f32() * 1e3 - self.data_range.start as f32)
+                / self.data_range.end as f32),
+            0.,
+            1.,
+        );
+        ((self.frame.h() - 1) as f32 * time_norm) as i32
+    }
+
+    // Only trims the averaging window, the plotted points are left alone
+    pub fn set_rolling_avg_size(&mut self, size: usize) {
+        self.rolling_avg.set_size(size);
+    }
+
     fn draw_lines(&mut self) {
+        let line_color = self.theme.red.to_internal();
         self.img.fill_zeroes();
         self.points.make_contiguous().windows(2).for_each(|coords| {
             let p1 = coords[0];
@@ -177,10 +263,19 @@ impl<const CIRC_BUF_SIZE: usize> Graph<CIRC_BUF_SIZE> {
             self.img.draw_line(
                 Coord::new(p1.x as usize, p1.y as usize),
                 Coord::new(p2.x as usize, p2.y as usize),
-                Palette::RED.to_internal(),
+                line_color,
             );
         });
 
+        if let Some(budget) = self.budget {
+            let y = self.time_to_y(budget) as usize;
+            self.img.draw_line(
+                Coord::new(0, y),
+                Coord::new(self.img.w - 1, y),
+                self.theme.yellow.to_internal(),
+            );
+        }
+
         self.img.blend(BlendType::Over, &self.bg_img);
     }
 
@@ -193,8 +288,8 @@ impl<const CIRC_BUF_SIZE: usize> Graph<CIRC_BUF_SIZE> {
                 self.points.clear();
                 self.img = scaled_img;
                 self.bg_img = image::zeroed::<Rgba8>(frame_w as usize, frame_h as usize);
-                self.bg_img.fill_color(Palette::BG0.to_internal());
-                self.bg_img.draw_grid(30, Palette::AQUA.to_internal());
+                self.bg_img.fill_color(self.theme.bg0.to_internal());
+                self.bg_img.draw_grid(30, self.theme.aqua.to_internal());
             }
 
             self.draw_lines();
@@ -204,58 +299,153 @@ impl<const CIRC_BUF_SIZE: usize> Graph<CIRC_BUF_SIZE> {
         }
     }
 
-    pub fn tick(&mut self, single_time: Duration) {
+    pub fn tick(&mut self, single_time: Duration, units: DisplayUnits) {
         const INC: i32 = 3;
 
-        self.rolling_avg_idx = (self.rolling_avg_idx + 1) % CIRC_BUF_SIZE;
-        self.rolling_avg_buf[self.rolling_avg_idx] = single_time;
-        let avg_time = self.rolling_avg_buf.iter().sum::<Duration>() / CIRC_BUF_SIZE as u32;
-
-        let time_norm = clamp(
-            1. - ((avg_time.as_millis() as i32 - self.data_range.start) as f32
-                / self.data_range.end as f32),
-            0.,
-            1.,
-        );
-        let y_scaled = (self.frame.h() - 1) as f32 * time_norm;
+        self.rolling_avg.push(single_time);
+        let avg_time = self.rolling_avg.avg().unwrap_or_default();
+        let y_scaled = self.time_to_y(avg_time);
 
         self.points
             .iter_mut()
             .for_each(|c| (*c).x = clamp(c.x + INC, 0, self.frame.w() - 1));
-        self.points.push_back(Coord::new(0, y_scaled as i32));
+        self.points.push_back(Coord::new(0, y_scaled));
         if self.points.len() > ((self.frame.w() - 1) / INC) as usize {
             self.points.pop_front();
         };
-        self.label_frame.set_label(&format!(
-            "Frame time: {:.2}ms | FPS: {:.0}",
-            avg_time.as_secs_f32() * 1000.,
-            1. / avg_time.as_secs_f32()
-        ));
+        let label = match self.budget {
+            Some(budget) => format!(
+                "{} | budget {}",
+                units.frame_time(avg_time),
+                units.duration(budget)
+            ),
+            None => units.frame_time(avg_time),
+        };
+        self.label_frame.set_label(&label);
 
         self.redraw = true;
     }
 }
 
+const BUDGET_WATCH_SAMPLES: usize = 120;
+const BUDGET_GRACE: Duration = Duration::from_secs(3); // short spikes over budget are normal, only warn on a steady overrun
+
+// Checks the rolling mean of the stage timings against the frame budget, and says what to do about it once
+struct BudgetWatch {
+    capture: RollingAvg,
+    convert: RollingAvg,
+    process: RollingAvg,
+    over_since: Option<Instant>,
+    warned_for: Option<Duration>, // budget we already warned about, a new fps gets its own warning
+}
+
+impl BudgetWatch {
+    pub fn new() -> Self {
+        Self {
+            capture: RollingAvg::new(BUDGET_WATCH_SAMPLES),
+            convert: RollingAvg::new(BUDGET_WATCH_SAMPLES),
+            process: RollingAvg::new(BUDGET_WATCH_SAMPLES),
+            over_since: None,
+            warned_for: None,
+        }
+    }
+
+    // stale samples from before a stop would trigger a warning right away
+    pub fn reset(&mut self) {
+        *self = Self {
+            warned_for: self.warned_for,
+            ..Self::new()
+        };
+    }
+
+    pub fn update(
+        &mut self,
+        timings: &pixel_bot::Timings,
+        budget: Duration,
+        now: Instant,
+    ) -> Option<String> {
+        self.capture.push(timings.capture);
+        self.convert.push(timings.convert);
+        self.process.push(timings.process);
+
+        let mean = pixel_bot::Timings {
+            capture: self.capture.avg().unwrap_or_default(),
+            convert: self.convert.avg().unwrap_or_default(),
+            process: self.process.avg().unwrap_or_default(),
+        };
+        if mean.capture + mean.process <= budget {
+            self.over_since = None;
+            return None;
+        }
+
+        let over_since = *self.over_since.get_or_insert(now);
+        if now - over_since < BUDGET_GRACE || self.warned_for == Some(budget) {
+            return None;
+        }
+        self.warned_for = Some(budget);
+        Some(budget_suggestion(&mean, budget))
+    }
+}
+
+// Names the stage that eats the most of the frame budget, convert gets split out of capture since it has its own fix
+fn budget_suggestion(mean: &pixel_bot::Timings, budget: Duration) -> String {
+    let units = DisplayUnits::Ms;
+    let fps = (1. / budget.as_secs_f32()).round();
+    let capture_only = mean.capture.saturating_sub(mean.convert);
+    let header = format!(
+        "A frame takes {} on average, but {} fps only leaves {}.",
+        units.duration(mean.capture + mean.process),
+        fps,
+        units.duration(budget)
+    );
+
+    let advice = if mean.process >= capture_only && mean.process >= mean.convert {
+        format!(
+            "Detection is the slowest stage ({}), raise {} or crop the search region down",
+            units.duration(mean.process),
+            CfgKey::DetectDownscale.as_string()
+        )
+    } else if mean.convert >= capture_only {
+        format!(
+            "Converting captured frames to BGRA8 is the slowest stage ({}), \
+             turning off HDR / 10 bit color on this display skips it",
+            units.duration(mean.convert)
+        )
+    } else {
+        format!(
+            "Capture is the slowest stage ({}), the screen can't be grabbed any faster on this machine, \
+             lower {} to match",
+            units.duration(capture_only),
+            CfgKey::Fps.as_string()
+        )
+    };
+    format!("{}\n\t{}", header, advice)
+}
+
 #[derive(Debug)]
 struct CropBox {
     bg_bx: Group,
     bx: Rc<RefCell<Group>>,
     ratio_cache: Rc<Cell<(f32, f32)>>, // last valid w/h ratios
+    region_dims: (i32, i32),           // detection region size in screen pixels
 }
 
 impl CropBox {
-    pub fn new(b: Bounds) -> Self {
+    pub fn new(b: Bounds, theme: &Theme) -> Self {
         let mut draw_frame = Frame::new(b.x, b.y, b.w, b.h, "");
         draw_frame.set_frame(FrameType::FlatBox);
 
         let mut bg_box = Group::new(b.x, b.y, b.w, b.h, "");
         bg_box.set_frame(app::frame_type());
-        bg_box.set_color(Palette::BG0);
+        bg_box.set_color(theme.bg0);
         bg_box.end();
 
         let mut fg_box = Group::new(b.x, b.y, b.w, b.h, "");
         fg_box.set_frame(app::frame_type());
-        fg_box.set_color(Palette::GREEN);
+        fg_box.set_color(theme.green);
+        fg_box.set_align(Align::Center | Align::Inside);
+        fg_box.set_label_font(Font::CourierBold);
+        fg_box.set_label_color(theme.bg0);
         fg_box.end();
 
         bg_box.draw(move |_| {
@@ -289,7 +479,21 @@ impl CropBox {
             bx: fg_box_rc,
             bg_bx: bg_box,
             ratio_cache,
+            region_dims: (0, 0),
+        }
+    }
+
+    // Labels the green box with the size of the region that actually gets searched (screen - 2 * crop)
+    pub fn set_region_dims(&mut self, w: Option<i32>, h: Option<i32>) {
+        if let Some(w) = w {
+            self.region_dims.0 = w;
         }
+        if let Some(h) = h {
+            self.region_dims.1 = h;
+        }
+        let mut bx = self.bx.borrow_mut();
+        bx.set_label(&format!("{}x{}", self.region_dims.0, self.region_dims.1));
+        bx.redraw();
     }
 
     pub fn change_bounds(&mut self, x_percent: f64, y_percent: f64) {
@@ -335,7 +539,7 @@ struct ResponsiveButton {
 }
 
 impl ResponsiveButton {
-    fn new(bnds: Bounds, label: String, font: Font, init_color: Color) -> Self {
+    fn new(bnds: Bounds, label: String, font: Font, init_color: Color, theme: &Theme) -> Self {
         let button_released = unique_event_id();
         let button_pushed = unique_event_id();
         let fade = unique_event_id();
@@ -345,9 +549,9 @@ impl ResponsiveButton {
         let mut grp = Group::new(bnds.x, bnds.y, bnds.w, bnds.h, "");
         let mut rand_frame = Frame::new(bnds.x, bnds.y, bnds.w, bnds.h, "");
         grp.set_frame(app::frame_type());
-        grp.set_color(Palette::BG0_H);
+        grp.set_color(theme.bg0_h);
         draw_frame.set_frame(FrameType::FlatBox);
-        draw_frame.set_color(Palette::BG0);
+        draw_frame.set_color(theme.bg0);
 
         let mut rng = rand::thread_rng();
         rand_frame.set_frame(FrameType::RoundedFrame);
@@ -362,15 +566,17 @@ impl ResponsiveButton {
 
         const LERP_INC: f32 = 1. / 10.;
         const ITER_TIME: f64 = 1. / 144.;
-        let fade_color = Palette::BG0_H;
+        let fade_color = theme.bg0_h;
+        let accents = theme.accents.clone();
         let mut rand_color = Color::Black;
         let mut fade_lerp = 0.;
         let mut continue_fading = false;
         grp.handle(move |g, ev| match ev {
             Event::Enter => {
                 loop {
-                    let new_rand_color = Palette::COLORS.into_iter().choose(&mut rng).unwrap();
-                    if rand_color != new_rand_color {
+                    let new_rand_color = *accents.iter().choose(&mut rng).unwrap();
+                    // a single accent theme can't avoid repeating itself
+                    if rand_color != new_rand_color || accents.len() == 1 {
                         rand_color = new_rand_color;
                         break;
                     }
@@ -441,192 +647,1613 @@ impl ResponsiveButton {
     }
 }
 
-pub struct Gui {
-    app: App,
-    window: Window,
-    config: Arc<RwLock<Config>>,
-
-    // we don't want multiple keycode buttons searching for input concurrently
-    capture_input_lock: Rc<Cell<bool>>,
+// Which overlay elements get drawn on the mirror, all on by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct OverlayOptions {
+    bbox: bool,
+    center_crosshair: bool,
+    aim_crosshair: bool,
+    aim_line: bool,
 }
 
-impl Gui {
-    pub fn new(w: i32, h: i32, config: Arc<RwLock<Config>>) -> Self {
-        let app = App::default();
+impl Default for OverlayOptions {
+    fn default() -> Self {
+        Self {
+            bbox: true,
+            center_crosshair: true,
+            aim_crosshair: true,
+            aim_line: true,
+        }
+    }
+}
 
-        app::set_visible_focus(false);
-        app::set_frame_type(FrameType::RFlatBox);
-        app::set_frame_border_radius_max(10);
-        app::add_handler(|ev| matches!(ev, Event::Shortcut) && (app::event_key() == (Key::Escape)));
+impl OverlayOptions {
+    const KEYS: [(CfgKey, &'static str); 4] = [
+        (CfgKey::OverlayBbox, "bbox"),
+        (CfgKey::OverlayCenterCrosshair, "center"),
+        (CfgKey::OverlayAimCrosshair, "aim"),
+        (CfgKey::OverlayAimLine, "line"),
+    ];
 
-        if let Ok(font) = Font::load_font("assets/JetBrainsMono-Medium.ttf") {
-            Font::set_font(Font::Courier, &font);
+    pub fn from_config(cfg: &Config) -> Self {
+        Self {
+            bbox: cfg.get(CfgKey::OverlayBbox).into(),
+            center_crosshair: cfg.get(CfgKey::OverlayCenterCrosshair).into(),
+            aim_crosshair: cfg.get(CfgKey::OverlayAimCrosshair).into(),
+            aim_line: cfg.get(CfgKey::OverlayAimLine).into(),
         }
-        if let Ok(font) = Font::load_font("assets/JetBrainsMono-Bold.ttf") {
-            Font::set_font(Font::CourierBold, &font);
+    }
+}
+
+struct MirrorPanel {
+    img_frame: Frame,
+    divisor_label: Frame,
+    color_label: Frame,
+    img_frame_img: image::Image<Vec<u8>, Rgba8>,
+    raw_img: Option<image::Image<Vec<u8>, Rgba8>>, // latest frame scaled to the mirror, before any overlays
+    raw_held: Rc<Cell<bool>>,
+    last_crop: Rc<RefCell<Option<image::Image<Vec<u8>, Bgra8>>>>, // latest frame, full res unless it was over the pixel budget, for picking colors
+    picking: Rc<Cell<bool>>, // the next click on the mirror picks the target color
+    shown_raw: bool,
+    stopped: bool, // the last frame stays up dimmed
+    last_wash_rejected: Option<Instant>,
+    config: Arc<RwLock<Config>>,
+    theme: Rc<Theme>,
+}
+
+impl MirrorPanel {
+    pub fn new(
+        b: Bounds,
+        config: Arc<RwLock<Config>>,
+        refresher: &ConfigRefresher,
+        theme: Rc<Theme>,
+    ) -> Self {
+        const TOGGLE_ROW_H: i32 = 20;
+
+        let mut grp = Group::new(b.x, b.y, b.w, b.h, "");
+        let img_h = b.h - TOGGLE_ROW_H;
+        let mut img_frame = Frame::new(b.x, b.y, b.w, img_h, "")
+            .with_align(Align::Top | Align::Left | Align::Inside);
+        img_frame.set_label_font(Font::CourierBold);
+        img_frame.set_label_color(theme.red);
+        let mut divisor_label = Frame::new(b.x, b.y, b.w, img_h, "")
+            .with_align(Align::Bottom | Align::Left | Align::Inside);
+        divisor_label.set_label_font(Font::Courier);
+        divisor_label.set_label_color(theme.fg2);
+        let mut color_label = Frame::new(b.x, b.y, b.w, img_h, "")
+            .with_align(Align::Bottom | Align::Right | Align::Inside);
+        color_label.set_label_font(Font::Courier);
+        color_label.set_label_color(theme.fg2);
+        let img_frame_img = image::zeroed::<Rgba8>(b.w as usize, img_h as usize);
+
+        // overlay element toggles under the mirror
+        let row = Flex::new(b.x, b.y + img_h, b.w, TOGGLE_ROW_H, None).row();
+        for (key, label) in OverlayOptions::KEYS {
+            let mut check = CheckButton::default().with_label(label);
+            check.set_label_color(theme.fg2);
+            check.set_selection_color(theme.green);
+            check.set_checked(config.read().unwrap().get(key).into());
+
+            let cb_config = config.clone();
+            let cb_refresher = refresher.clone();
+            check.set_callback(move |check| {
+                if cb_refresher.is_refreshing() {
+                    return;
+                }
+                cb_config
+                    .write()
+                    .unwrap()
+                    .set_val(key, ValType::Bool(check.is_checked()))
+                    .unwrap();
+            });
+
+            let mut refresh_check = check.clone();
+            refresher.register(key, move |cfg| refresh_check.set_checked(cfg.get(key).into()));
         }
-        let window = Window::new(w / 2, h / 2, w, h, "pb");
 
-        let capture_input_lock = Rc::new(Cell::new(false));
+        // holding this (or space) shows the plain frame, for when the overlays cover the interesting pixels
+        let raw_held = Rc::new(Cell::new(false));
+        let mut raw_but = Button::default().with_label("hold: raw");
+        raw_but.set_frame(app::frame_type());
+        raw_but.set_color(theme.bg0_h);
+        raw_but.set_label_color(theme.fg2);
+        raw_but.set_label_font(Font::Courier);
+        let but_held = raw_held.clone();
+        raw_but.handle(move |_, ev| match ev {
+            Event::Push => {
+                but_held.set(true);
+                true
+            }
+            Event::Released => {
+                but_held.set(false);
+                true
+            }
+            _ => false,
+        });
+        let key_held = raw_held.clone();
+        app::add_handler(move |ev| {
+            if app::event_key() != Key::from_char(' ') {
+                return false;
+            }
+            match ev {
+                Event::Shortcut => key_held.set(true), // unhandled keydowns come through as shortcuts
+                Event::KeyUp => key_held.set(false),
+                _ => return false,
+            }
+            true
+        });
+
+        // eyedropper, the next click on the mirror samples the full res frame under it
+        let picking = Rc::new(Cell::new(false));
+        let mut pick_but = Button::default().with_label("pick color");
+        pick_but.set_frame(app::frame_type());
+        pick_but.set_color(theme.bg0_h);
+        pick_but.set_label_color(theme.fg2);
+        pick_but.set_label_font(Font::Courier);
+        let but_picking = picking.clone();
+        pick_but.set_callback(move |_| {
+            but_picking.set(true);
+            draw::set_cursor(Cursor::Cross);
+            log!("Click the mirror to pick the target color, Esc to cancel");
+        });
+
+        let mut swatch = Frame::default();
+        swatch.set_frame(FrameType::FlatBox);
+        let first_color = |cfg: &Config| {
+            let colors: Vec<image::Color<u8>> = cfg.get(CfgKey::TargetColor).into();
+            Color::from_internal(colors[0])
+        };
+        swatch.set_color(first_color(&config.read().unwrap()));
+        row.set_size(&swatch, TOGGLE_ROW_H);
+        let mut refresh_swatch = swatch.clone();
+        refresher.register(CfgKey::TargetColor, move |cfg| {
+            refresh_swatch.set_color(first_color(cfg));
+            refresh_swatch.redraw();
+        });
+        row.end();
+
+        let last_crop: Rc<RefCell<Option<image::Image<Vec<u8>, Bgra8>>>> =
+            Rc::new(RefCell::new(None));
+        let frame_picking = picking.clone();
+        let frame_crop = last_crop.clone();
+        let pick_config = config.clone();
+        let pick_refresher = refresher.clone();
+        img_frame.handle(move |f, ev| match ev {
+            Event::Push if frame_picking.get() => {
+                frame_picking.set(false);
+                draw::set_cursor(Cursor::Default);
+                let click = ViewPos::new(app::event_x() - f.x(), app::event_y() - f.y());
+                let picked = frame_crop.borrow().as_ref().and_then(|crop| {
+                    let pos = click
+                        .unletterbox_crop((f.w() as usize, f.h() as usize), (crop.w, crop.h))?;
+                    Some(crop.get_pixel2d(*pos).as_color())
+                });
+                match picked {
+                    Some(color) => {
+                        pick_config
+                            .write()
+                            .unwrap()
+                            .set_first_color(CfgKey::TargetColor, color)
+                            .unwrap();
+                        pick_refresher.refresh(&[CfgKey::TargetColor]);
+                        log!("Picked target color {}, {}, {}", color.r, color.g, color.b);
+                    }
+                    None => log!("Color pick cancelled"), // landed on the letterbox bars
+                }
+                true
+            }
+            _ => false,
+        });
+        grp.end();
+        grp.resizable(&img_frame);
 
         Self {
-            window,
-            app,
+            img_frame,
+            divisor_label,
+            color_label,
+            img_frame_img,
+            raw_img: None,
+            raw_held,
+            last_crop,
+            picking,
+            shown_raw: false,
+            stopped: false,
+            last_wash_rejected: None,
             config,
-            capture_input_lock,
+            theme,
         }
     }
 
-    pub fn wait(&mut self, dur_secs: f64) -> bool {
-        app::sleep(dur_secs);
-        self.app.wait()
+    // flashes a warning for a bit after a frame got rejected as a color wash
+    fn update_wash_label(&mut self, wash_rejected: bool) {
+        const FLASH_DUR: Duration = Duration::from_millis(500);
+
+        if wash_rejected {
+            self.last_wash_rejected = Some(Instant::now());
+            self.img_frame.set_label("wash rejected");
+        } else if let Some(last) = self.last_wash_rejected {
+            if last.elapsed() > FLASH_DUR {
+                self.last_wash_rejected = None;
+                self.img_frame.set_label("");
+            }
+        }
     }
 
-    pub fn init(
-        &mut self,
-        screen_aspect_ratio: f32,
-        receiver: channel::Receiver<pixel_bot::Message>,
-        cfg_path: &'static str,
-    ) {
-        let (r, g, b) = Palette::FG2.to_rgb();
-        app::set_foreground_color(r, g, b);
+    pub fn update(&mut self, data: pixel_bot::CapData) {
+        self.update_wash_label(data.wash_rejected);
+        // readout for tuning the adaptive aim scaling
+        match (data.aim_coord, data.motion_score) {
+            (Some(_), Some(motion)) => self.divisor_label.set_label(&format!(
+                "divisor: {:.2} | motion: {:.1}",
+                data.effective_divisor, motion
+            )),
+            (Some(_), None) => self
+                .divisor_label
+                .set_label(&format!("divisor: {:.2}", data.effective_divisor)),
+            (None, _) => self.divisor_label.set_label(""),
+        }
 
-        let (r, g, b) = Palette::BG1.to_rgb();
-        app::set_background_color(r, g, b);
+        // yellow once the measured color gets close to falling out of the threshold
+        match data.color_readout {
+            Some(readout) => {
+                let marginal = readout.distance > readout.max_distance * 0.8;
+                self.color_label.set_label(&format!(
+                    "color dist: {:.3} / {:.3}",
+                    readout.distance, readout.max_distance
+                ));
+                self.color_label.set_label_color(if marginal {
+                    self.theme.yellow
+                } else {
+                    self.theme.fg2
+                });
+            }
+            None => self.color_label.set_label(""),
+        }
 
-        self.window.set_color(Color::BackGround);
+        let (frame_w, frame_h) = (self.img_frame.w() as usize, self.img_frame.h() as usize);
+        // the coords are in the full crop, the image might've been shrunk to fit the pixel budget
+        let (old_w, old_h) = data.crop_dims;
+        let mut resized_data_img = match data.img.scale_keep_aspect(frame_w, frame_h) {
+            Some(resized) => resized,
+            None => data.img._clone(),
+        };
+        *self.last_crop.borrow_mut() = Some(data.img);
+        // overlays go on a copy, so holding the raw view doesn't have to wait for the next frame
+        self.raw_img = Some(resized_data_img.clone());
+
+        // crop space -> mirror space
+        let mirror_dims = (resized_data_img.w, resized_data_img.h);
+        let to_mirror = |coord: CropPos| coord.to_mirror((old_w, old_h), mirror_dims);
+
+        let fov_radius: u32 =
+            <ValType as Into<Bounded<_>>>::into(self.config.read().unwrap().get(CfgKey::FovRadius))
+                .val;
+        if fov_radius != 0 {
+            let radius = fov_radius as usize * mirror_dims.0 / old_w.max(1);
+            let center = coord::center_pixel(mirror_dims);
+            resized_data_img.draw_circle(center, radius, self.theme.purple.to_internal());
+        }
 
-        let (win_w, win_h) = (self.window.w(), self.window.h());
+        for cluster in data.rejected_coords {
+            let cluster = cluster.into_iter().map(to_mirror).collect();
+            draw_rejected_overlay(&mut resized_data_img, cluster, &self.theme);
+        }
+        // would've been fine targets, something else just ranked higher
+        for corners in data.other_targets {
+            let corners: Vec<MirrorPos> = corners.into_iter().map(to_mirror).collect();
+            let (x, y, w, h) = MirrorPos::bbox_xywh(&corners);
+            resized_data_img.draw_bbox(Coord::new(x, y), w, h, self.theme.fg2.to_internal());
+        }
 
-        self.window.make_resizable(true);
-        self.window.size_range(800, 700, 3480, 2160);
+        // selected but the outline check hasn't confirmed it yet
+        if let (false, Some(ref target_coords)) = (data.confirmed, &data.target_coords) {
+            let cluster = target_coords.iter().copied().map(to_mirror).collect();
+            draw_pending_overlay(&mut resized_data_img, cluster, &self.theme);
+        }
 
-        const GAP: i32 = 5;
-        const MIDDLE_OFFSET: i32 = 50;
+        if let (Some(aim_coord), Some(target_coords)) = (data.aim_coord, data.target_coords) {
+            let aim_point = to_mirror(data.aim_point.unwrap_or(aim_coord));
+            let predicted_point = data.predicted_point.map(to_mirror);
+            let aim_coord = to_mirror(aim_coord);
+            let target_coords = target_coords.into_iter().map(to_mirror).collect();
+
+            let overlay = OverlayOptions::from_config(&self.config.read().unwrap());
+            draw_image_overlay(
+                &mut resized_data_img,
+                aim_coord,
+                aim_point,
+                predicted_point,
+                target_coords,
+                &overlay,
+                &self.theme,
+            );
+        }
 
-        // Sliders & crop widget (right side)
-        let right_x = (win_w / 2) + MIDDLE_OFFSET;
-        let right_y = GAP;
-        let right_w = ((win_w - (GAP * 2)) / 2) - MIDDLE_OFFSET;
-        let slider_h = (win_w as f32 * 0.05) as i32;
+        if let Some(resized_bg) = self.img_frame_img.scale_nearest(frame_w, frame_h) {
+            self.img_frame_img = resized_bg;
+        }
 
-        // crop widget
-        let crop_box_b = self.create_crop_widget(
-            right_x,
-            right_y,
-            screen_aspect_ratio,
-            slider_h,
-            right_w,
-            GAP,
-        );
+        self.img_frame_img.fill_color(self.theme.bg0.to_internal());
+        self.img_frame_img.layer_image_over(&resized_data_img);
+
+        // configured target color next to the measured cluster mean, top right corner
+        if let Some(readout) = data.color_readout {
+            const SWATCH: usize = 14;
+            const MARGIN: usize = 4;
+            if frame_w > (SWATCH + MARGIN) * 2 && frame_h > SWATCH + MARGIN {
+                let right = frame_w - MARGIN - SWATCH;
+                draw_swatch(
+                    &mut self.img_frame_img,
+                    Coord::new(right - SWATCH - MARGIN, MARGIN),
+                    SWATCH,
+                    readout.target,
+                );
+                draw_swatch(
+                    &mut self.img_frame_img,
+                    Coord::new(right, MARGIN),
+                    SWATCH,
+                    readout.measured,
+                );
+            }
+        }
 
-        // slider group
-        let mut cur_slider_b = Bounds::new(
-            right_x,
-            crop_box_b.y + crop_box_b.h + GAP,
-            right_w,
-            slider_h,
-        );
-        let mut slider_grp_b = cur_slider_b;
-        let mut colors_cycle = Palette::COLORS.into_iter().cycle().skip(2); // crop sliders took the first two colors
+        self.present();
+    }
 
-        CfgKey::iter()
-            .filter(|key| !matches!(key, CfgKey::CropW | CfgKey::CropH))
-            .filter(|key| matches!(key.default_val(), ValType::Unsigned(_) | ValType::Float(_)))
-            .for_each(|key| {
-                self.create_config_slider(
-                    cur_slider_b,
-                    key,
-                    key.as_string(),
-                    colors_cycle.next().unwrap(),
-                );
-                cur_slider_b.y += cur_slider_b.h + GAP;
-            });
-        slider_grp_b.h = cur_slider_b.y - slider_grp_b.y;
+    // Called every idle, swaps between the overlaid & raw frame as soon as the hold changes
+    pub fn tick(&mut self) {
+        if self.raw_held.get() != self.shown_raw {
+            self.present();
+        }
+    }
 
-        // keycode button group
-        let buttons_y = slider_grp_b.y + slider_grp_b.h;
-        self.create_cfg_button_group(
-            Bounds::new(right_x, buttons_y, right_w, (win_w - buttons_y) - GAP),
-            3,
-            cfg_path,
-            GAP,
-        );
+    pub fn set_stopped(&mut self, stopped: bool) {
+        self.stopped = stopped;
+        let label = if stopped { "STOPPED" } else { "" };
+        self.img_frame.set_label(label);
+        self.present();
+    }
 
-        // Screen mirror widget, graph, and terminal (left side)
-        let left_w = (win_w / 2) + MIDDLE_OFFSET;
-        let left_h = win_h / 3;
+    fn present(&mut self) {
+        if self.stopped {
+            let shade = self.theme.bg0.to_internal();
+            let mut dimmed = image::zeroed::<Rgba8>(self.img_frame_img.w, self.img_frame_img.h);
+            dimmed.fill_color(image::Color::new(shade.r, shade.g, shade.b, 190));
+            dimmed.blend(BlendType::Over, &self.img_frame_img);
+            draw::draw_rgba(&mut self.img_frame, dimmed.as_slice()).unwrap();
+            self.img_frame.redraw();
+            return;
+        }
+        self.shown_raw = self.raw_held.get();
+        match (self.shown_raw, &self.raw_img) {
+            (true, Some(raw_img)) => {
+                let mut raw_frame_img =
+                    image::zeroed::<Rgba8>(self.img_frame_img.w, self.img_frame_img.h);
+                raw_frame_img.fill_color(self.theme.bg0.to_internal());
+                raw_frame_img.layer_image_over(raw_img);
+                draw::draw_rgba(&mut self.img_frame, raw_frame_img.as_slice()).unwrap();
+            }
+            _ => draw::draw_rgba(&mut self.img_frame, self.img_frame_img.as_slice()).unwrap(),
+        }
+        self.img_frame.redraw();
+    }
+}
 
-        let frm_b = Bounds::new(0, 0, left_w, left_h + (GAP * 2)).gapify(GAP);
-        let mut img_frame = Frame::new(frm_b.x, frm_b.y, frm_b.w, frm_b.h, "");
-        let mut img_frame_img = image::zeroed::<Rgba8>(frm_b.w as usize, frm_b.h as usize);
+fn draw_swatch(
+    img: &mut image::Image<Vec<u8>, Rgba8>,
+    origin: Coord<usize>,
+    size: usize,
+    color: image::Color<u8>,
+) {
+    for y in origin.y..(origin.y + size).min(img.h) {
+        for x in origin.x..(origin.x + size).min(img.w) {
+            img.set2d(Coord::new(x, y), color);
+        }
+    }
+}
 
-        let mut graph = Graph::<5>::new(
-            Bounds::new(0, frm_b.y + frm_b.h, left_w, left_h).gapify(GAP),
-            5..50,
-        );
-        let mut term =
-            Self::create_term(Bounds::new(0, graph.b.y + graph.b.h, left_w, left_h).gapify(GAP));
-        let mut style_buffer = TextBuffer::default();
-        let entries: Vec<StyleTableEntry> = vec![
-            StyleTableEntry {
-                // A
-                color: Color::ForeGround,
-                font: Font::Courier,
-                size: 12,
-            },
-            StyleTableEntry {
-                // B
-                color: Palette::RED,
-                font: Font::CourierBold,
-                size: 12,
-            },
-        ];
+struct GraphPanel {
+    grp: Group,
+    graph: Graph,
+    stopped: bool, // frame times that trickle in after a stop would only skew the average
+    budget_watch: BudgetWatch,
+    driver_light: Frame,
+    click_hist: Frame,
+    config: Arc<RwLock<Config>>,
+    theme: Rc<Theme>,
+}
 
-        let mut now = Instant::now();
-        app::add_idle3(move |_| {
-            // blinking terminal cursor
-            if now.elapsed() > Duration::from_secs_f32(0.5) {
-                if term.cursor_color() == term.color() {
-                    term.set_cursor_color(Color::ForeGround);
-                    term.redraw();
-                } else {
-                    term.set_cursor_color(term.color());
-                    term.redraw();
-                }
-                now = Instant::now();
-            }
-
-            // real ansi codes dont work when I want a font that isnt courier,
-            //    so error messages get wrapped in '\x1b' to achieve the same effect using the style buffer
-            let log = drain_log();
-            if !log.is_empty() {
-                let mut flag = true;
-                for c in log.chars() {
-                    if c == logging::FAKE_ANSI {
-                        flag = !flag;
-                        continue;
-                    }
-                    if flag {
-                        style_buffer.append("A");
-                    } else {
-                        style_buffer.append("B");
-                    }
-                }
-                term.append(log.as_str());
-                term.set_highlight_data(style_buffer.clone(), entries.clone());
-            }
+impl GraphPanel {
+    pub fn new(b: Bounds, config: Arc<RwLock<Config>>, theme: Rc<Theme>) -> Self {
+        let graph_smoothing: u32 =
+            <ValType as Into<Bounded<_>>>::into(config.read().unwrap().get(CfgKey::GraphSmoothing))
+                .val;
+
+        let grp = Group::new(b.x, b.y, b.w, b.h, "");
+        let graph = Graph::new(b, 5..50, graph_smoothing as usize, theme.clone());
+
+        // driver status shares the row with the graph label
+        let label_b = &graph.label_frame;
+        let mut driver_light = Frame::new(label_b.x(), label_b.y(), label_b.w(), label_b.h(), "")
+            .with_align(Align::Right | Align::Inside);
+        driver_light.set_label_font(Font::CourierBold);
+        driver_light.set_label_size(label_b.label_size());
+        driver_light.set_label_color(theme.gray);
+        driver_light.set_label("driver: ...");
+
+        // autoclick interval histogram, drawn as a text sparkline in the middle of the label row
+        let mut click_hist = Frame::new(label_b.x(), label_b.y(), label_b.w(), label_b.h(), "")
+            .with_align(Align::Center | Align::Inside);
+        click_hist.set_label_font(Font::Courier);
+        click_hist.set_label_size(label_b.label_size());
+        click_hist.set_label_color(theme.fg2);
+        grp.end();
 
-            let msgs: Vec<_> = receiver.try_iter().collect();
+        Self {
+            grp,
+            graph,
+            stopped: false,
+            budget_watch: BudgetWatch::new(),
+            driver_light,
+            click_hist,
+            config,
+            theme,
+        }
+    }
+
+    // the plot holds still until frames come in again
+    pub fn set_stopped(&mut self, stopped: bool) {
+        self.stopped = stopped;
+        if stopped {
+            self.budget_watch.reset();
+            self.graph.label_frame.set_label("stopped");
+            self.graph.label_frame.redraw_label();
+        }
+    }
+
+    fn set_driver_status(&mut self, status: DriverStatus) {
+        let (label, color) = match status {
+            DriverStatus::Ok => ("driver: ok", self.theme.green),
+            DriverStatus::Degraded => ("driver: degraded", self.theme.yellow),
+            DriverStatus::Missing => ("driver: missing", self.theme.red),
+            DriverStatus::TimedOut => ("mouse capture timed out", self.theme.red),
+        };
+        self.driver_light.set_label(label);
+        self.driver_light.set_label_color(color);
+        self.driver_light.redraw_label();
+    }
+
+    pub fn update(&mut self, msgs: &[pixel_bot::Message], units: DisplayUnits) {
+        // resizing the graph's averaging window without touching the plotted history
+        if self
+            .config
+            .read()
+            .unwrap()
+            .is_key_dirty(CfgKey::GraphSmoothing)
+        {
+            let mut cfg = self.config.write().unwrap();
+            cfg.take_dirty_key(CfgKey::GraphSmoothing);
+            let graph_smoothing: u32 =
+                <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::GraphSmoothing)).val;
+            drop(cfg);
+            self.graph.set_rolling_avg_size(graph_smoothing as usize);
+        }
 
-            // graph messages
+        let fps: u32 =
+            <ValType as Into<Bounded<_>>>::into(self.config.read().unwrap().get(CfgKey::Fps)).val;
+        let budget = Duration::from_secs(1) / fps.max(1);
+        self.graph.set_budget(budget);
+
+        msgs.iter()
+            .filter_map(|msg| match msg {
+                pixel_bot::Message::IterTime(time) if !self.stopped => Some(time),
+                _ => None,
+            })
+            .for_each(|&dur| self.graph.tick(dur, units));
+        self.graph.draw();
+
+        if !self.stopped {
+            let now = Instant::now();
             msgs.iter()
                 .filter_map(|msg| match msg {
-                    pixel_bot::Message::IterTime(time) => Some(time),
+                    pixel_bot::Message::Timings(timings) => {
+                        self.budget_watch.update(timings, budget, now)
+                    }
                     _ => None,
                 })
-                .for_each(|&dur| graph.tick(dur));
-            graph.draw();
+                .for_each(|suggestion| log_err!("{}", suggestion));
+        }
+
+        if let Some(&status) = msgs.iter().rev().find_map(|msg| match msg {
+            pixel_bot::Message::DriverStatus(status) => Some(status),
+            _ => None,
+        }) {
+            self.set_driver_status(status);
+        }
+
+        if let Some(intervals) = msgs.iter().rev().find_map(|msg| match msg {
+            pixel_bot::Message::ClickStats(intervals) => Some(intervals),
+            _ => None,
+        }) {
+            let range = match (intervals.iter().min(), intervals.iter().max()) {
+                (Some(&min), Some(&max)) => {
+                    format!(" {}-{}", units.duration(min), units.duration(max))
+                }
+                _ => String::new(),
+            };
+            self.click_hist.set_label(&format!(
+                "clicks: {}{}",
+                interval_histogram(intervals, 12),
+                range
+            ));
+            self.click_hist.redraw_label();
+        }
+    }
+}
+
+const SESSION_LOG_PATH: &str = "session.log";
+const SESSION_LOG_LINES: usize = 200;
+const SESSION_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+const TERM_MAX_RECORDS: usize = 4000; // older text gets dropped from the terminal along with its record
+const SEARCH_H: i32 = 20;
+const MATCH_LABEL_W: i32 = 100;
+
+// A run of terminal text that shares a style, the styles are re-derived from these whenever the search changes
+struct LogRecord {
+    text: String,
+    style: u8, // b'A' normal, b'B' error, b'C' previous session
+}
+
+// Shared with the search box's handlers, applied on the next update
+#[derive(Default)]
+struct SearchState {
+    query: String,
+    changed: bool,
+    jump: bool, // enter was pressed, scroll to the next match
+}
+
+struct TerminalPanel {
+    term: SimpleTerminal,
+    style_buffer: TextBuffer,
+    entries: Vec<StyleTableEntry>,
+    last_blink: Instant,
+    history: VecDeque<String>, // recent lines without style markers, dumped to the session log
+    last_session_save: Instant,
+    records: VecDeque<LogRecord>,
+    search: Rc<RefCell<SearchState>>,
+    match_label: Frame,
+    matches: Vec<usize>, // byte offsets of the current query's matches
+    next_match: usize,
+}
+
+impl TerminalPanel {
+    pub fn new(b: Bounds, theme: &Theme) -> Self {
+        let mut grp = Group::new(b.x, b.y, b.w, b.h, "");
+        let mut search_input = Input::new(b.x, b.y, b.w - MATCH_LABEL_W, SEARCH_H, "");
+        let mut match_label =
+            Frame::new(b.x + b.w - MATCH_LABEL_W, b.y, MATCH_LABEL_W, SEARCH_H, "");
+        let mut term = SimpleTerminal::new(b.x, b.y + SEARCH_H, b.w, b.h - SEARCH_H, "");
+        grp.end();
+        grp.resizable(&term);
+
+        term.set_selection_color(Color::ForeGround);
+        term.set_color(theme.bg0_h);
+        term.set_cursor_color(Color::ForeGround);
+        term.set_cursor_style(fltk::text::Cursor::Simple);
+        term.set_scrollbar_size(-1); // no scrollbar
+        term.set_ansi(true);
+        term.set_frame(app::frame_type());
+
+        search_input.set_frame(app::frame_type());
+        search_input.set_color(theme.bg0_h);
+        search_input.set_text_color(Color::ForeGround);
+        search_input.set_text_font(Font::Courier);
+        search_input.set_tooltip("Search the log, enter jumps to the next match, esc clears");
+        match_label.set_label_font(Font::Courier);
+        match_label.set_label_color(theme.fg2);
+        match_label.set_label("search");
+
+        let search = Rc::new(RefCell::new(SearchState::default()));
+        let changed_search = search.clone();
+        search_input.set_trigger(CallbackTrigger::Changed);
+        search_input.set_callback(move |input| {
+            let mut search = changed_search.borrow_mut();
+            search.query = input.value();
+            search.changed = true;
+        });
+        // handled before the window sees them, so esc here doesn't reach the global escape handling
+        let key_search = search.clone();
+        search_input.handle(move |input, ev| match ev {
+            Event::KeyDown if app::event_key() == Key::Enter => {
+                key_search.borrow_mut().jump = true;
+                true
+            }
+            Event::KeyDown if app::event_key() == Key::Escape => {
+                input.set_value("");
+                let mut search = key_search.borrow_mut();
+                search.query.clear();
+                search.changed = true;
+                true
+            }
+            _ => false,
+        });
+
+        let entries: Vec<StyleTableEntry> = vec![
+            StyleTableEntry {
+                // A
+                color: Color::ForeGround,
+                font: Font::Courier,
+                size: 12,
+            },
+            StyleTableEntry {
+                // B
+                color: theme.red,
+                font: Font::CourierBold,
+                size: 12,
+            },
+            StyleTableEntry {
+                // C
+                color: theme.gray,
+                font: Font::Courier,
+                size: 12,
+            },
+            StyleTableEntry {
+                // D, search matches
+                color: theme.yellow,
+                font: Font::CourierBold,
+                size: 12,
+            },
+        ];
+
+        let mut ret = Self {
+            term,
+            style_buffer: TextBuffer::default(),
+            entries,
+            last_blink: Instant::now(),
+            history: VecDeque::new(),
+            last_session_save: Instant::now(),
+            records: VecDeque::new(),
+            search,
+            match_label,
+            matches: Vec::new(),
+            next_match: 0,
+        };
+        ret.replay_previous_session();
+        ret
+    }
+
+    // Appends to the terminal & its style buffer, dropping the oldest text past TERM_MAX_RECORDS
+    fn push_record(&mut self, text: String, style: u8) {
+        if text.is_empty() {
+            return;
+        }
+        self.style_buffer
+            .append(&String::from_utf8(vec![style; text.len()]).unwrap());
+        self.term.append(&text);
+        self.records.push_back(LogRecord { text, style });
+
+        while self.records.len() > TERM_MAX_RECORDS {
+            let old_len = self.records.pop_front().unwrap().text.len() as i32;
+            if let Some(mut buf) = self.term.buffer() {
+                buf.remove(0, old_len);
+            }
+            self.style_buffer.remove(0, old_len);
+        }
+    }
+
+    // Level styling from the records, with the search matches tagged over it
+    fn restyle(&mut self) {
+        let query = self.search.borrow().query.to_ascii_lowercase();
+        let text: String = self.records.iter().map(|r| r.text.as_str()).collect();
+        let mut styles: Vec<u8> = self
+            .records
+            .iter()
+            .flat_map(|r| std::iter::repeat(r.style).take(r.text.len()))
+            .collect();
+
+        self.matches.clear();
+        if !query.is_empty() {
+            // ascii lowercasing keeps the byte offsets lined up with the original text
+            for (start, _) in text.to_ascii_lowercase().match_indices(&query) {
+                styles[start..start + query.len()].fill(b'D');
+                self.matches.push(start);
+            }
+        }
+        self.next_match = self.next_match.min(self.matches.len().saturating_sub(1));
+
+        self.style_buffer
+            .set_text(&String::from_utf8(styles).unwrap());
+        self.term
+            .set_highlight_data(self.style_buffer.clone(), self.entries.clone());
+        self.match_label
+            .set_label(&match (query.is_empty(), self.matches.len()) {
+                (true, _) => "search".to_string(),
+                (false, 1) => "1 match".to_string(),
+                (false, n) => format!("{} matches", n),
+            });
+        self.term.redraw();
+    }
+
+    // Cycles forward through the matches, wrapping back to the first
+    fn jump_to_next_match(&mut self) {
+        if self.matches.is_empty() {
+            return;
+        }
+        self.next_match %= self.matches.len();
+        let pos = self.matches[self.next_match] as i32;
+        self.term.set_insert_position(pos);
+        self.term.show_insert_position();
+        self.match_label
+            .set_label(&format!("{}/{}", self.next_match + 1, self.matches.len()));
+        self.next_match += 1;
+    }
+
+    // Shows the tail of the last session's log (dimmed) if it crashed or closed recently
+    fn replay_previous_session(&mut self) {
+        const MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+        let path = resolve_data_path(SESSION_LOG_PATH);
+        let is_recent = std::fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .map_or(false, |age| age < MAX_AGE);
+        if !is_recent {
+            return;
+        }
+
+        if let Ok(contents) = std::fs::read_to_string(&path) {
+            let lines: Vec<&str> = contents.lines().collect();
+            let tail = &lines[lines.len().saturating_sub(SESSION_LOG_LINES)..];
+            if tail.is_empty() {
+                return;
+            }
+
+            let mut replay = "--- previous session ---\n".to_string();
+            tail.iter().for_each(|line| {
+                replay.push_str(line);
+                replay.push('\n');
+            });
+            replay.push_str("--- end of previous session ---\n");
+
+            self.push_record(replay, b'C');
+            self.term
+                .set_highlight_data(self.style_buffer.clone(), self.entries.clone());
+        }
+    }
+
+    pub fn save_session(&mut self) {
+        let mut out = String::new();
+        self.history.iter().for_each(|line| {
+            out.push_str(line);
+            out.push('\n');
+        });
+        let path = resolve_data_path(SESSION_LOG_PATH);
+        if let Err(e) = std::fs::write(&path, out) {
+            log_err!("Error saving session log to {}:\n\t{}", path.display(), e);
+        }
+        self.last_session_save = Instant::now();
+    }
+
+    pub fn update(&mut self) {
+        // blinking terminal cursor
+        if self.last_blink.elapsed() > Duration::from_secs_f32(0.5) {
+            if self.term.cursor_color() == self.term.color() {
+                self.term.set_cursor_color(Color::ForeGround);
+            } else {
+                let bg = self.term.color();
+                self.term.set_cursor_color(bg);
+            }
+            self.term.redraw();
+            self.last_blink = Instant::now();
+        }
+
+        // real ansi codes dont work when I want a font that isnt courier,
+        //    so error messages get wrapped in '\x1b' to achieve the same effect using the style buffer
+        let log = drain_log();
+        let searching = !self.search.borrow().query.is_empty();
+        if !log.is_empty() {
+            for (i, segment) in log.split(logging::FAKE_ANSI).enumerate() {
+                let style = if i % 2 == 0 { b'A' } else { b'B' };
+                self.push_record(segment.to_string(), style);
+            }
+            // new text can match too
+            if searching {
+                self.search.borrow_mut().changed = true;
+            } else {
+                self.term
+                    .set_highlight_data(self.style_buffer.clone(), self.entries.clone());
+            }
+
+            log.lines()
+                .map(|line| line.replace(logging::FAKE_ANSI, ""))
+                .for_each(|line| {
+                    self.history.push_back(line);
+                    if self.history.len() > SESSION_LOG_LINES {
+                        self.history.pop_front();
+                    }
+                });
+        }
+
+        let (changed, jump) = {
+            let mut search = self.search.borrow_mut();
+            (
+                std::mem::take(&mut search.changed),
+                std::mem::take(&mut search.jump),
+            )
+        };
+        if changed {
+            self.restyle();
+        }
+        if jump {
+            self.jump_to_next_match();
+        }
+
+        if self.last_session_save.elapsed() > SESSION_SAVE_INTERVAL {
+            self.save_session();
+        }
+    }
+}
+
+// Folds the per second summaries into a terminal line once a minute, for checking on long sessions
+#[derive(Default)]
+struct SummaryLogger {
+    total: pixel_bot::Summary,
+    received: u32,
+}
+
+impl SummaryLogger {
+    const SUMMARIES_PER_LINE: u32 = 60;
+
+    pub fn update(&mut self, msgs: &[pixel_bot::Message]) {
+        for summary in msgs.iter().filter_map(|msg| match msg {
+            pixel_bot::Message::Summary(summary) => Some(summary),
+            _ => None,
+        }) {
+            self.total.merge(summary);
+            self.received += 1;
+            if self.received == Self::SUMMARIES_PER_LINE {
+                let total = std::mem::take(&mut self.total);
+                log!(
+                    "Last minute: {} frames, {} with a target ({} found near the last one), rejected {} small, {} misshapen & {} static clusters, {} outside the fov, {} capture reloads",
+                    total.frames,
+                    total.frames_with_target,
+                    total.roi_hits,
+                    total.clusters_rejected_area,
+                    total.clusters_rejected_aspect,
+                    total.clusters_rejected_static,
+                    total.clusters_rejected_fov,
+                    total.capture_reloads
+                );
+                self.received = 0;
+            }
+        }
+    }
+}
+
+// Runtime facts along the bottom of the window, everything shown comes from bot messages
+struct StatusBar {
+    state_seg: Frame,
+    fps_seg: Frame,
+    click_seg: Frame,
+    target_seg: Frame,
+    capture_seg: Frame,
+    state: Option<pixel_bot::BotState>,
+    click_mode: Option<pixel_bot::ClickMode>,
+    has_target: bool,
+    capture_format: Option<pixel_bot::CaptureFormat>,
+    iter_avg: RollingAvg,
+    last_update: Instant,
+    theme: Rc<Theme>,
+}
+
+impl StatusBar {
+    pub fn new(b: Bounds, theme: Rc<Theme>) -> Self {
+        let mut row = Flex::new(b.x, b.y, b.w, b.h, None).row();
+        row.set_frame(FrameType::FlatBox);
+        row.set_color(theme.bg0_h);
+        let segment = || {
+            let mut seg = Frame::default().with_align(Align::Left | Align::Inside);
+            seg.set_label_font(Font::Courier);
+            seg.set_label_size(clamp(b.h - 6, 1, 14));
+            seg.set_label_color(theme.gray);
+            seg
+        };
+        let (state_seg, fps_seg, click_seg, target_seg, capture_seg) =
+            (segment(), segment(), segment(), segment(), segment());
+        row.end();
+
+        Self {
+            state_seg,
+            fps_seg,
+            click_seg,
+            target_seg,
+            capture_seg,
+            state: None,
+            click_mode: None,
+            has_target: false,
+            capture_format: None,
+            iter_avg: RollingAvg::new(60),
+            last_update: Instant::now(),
+            theme,
+        }
+    }
+
+    pub fn update(&mut self, msgs: &[pixel_bot::Message], units: DisplayUnits) {
+        const UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+        for msg in msgs {
+            match msg {
+                pixel_bot::Message::BotState(state) => self.state = Some(*state),
+                pixel_bot::Message::ClickMode(mode) => self.click_mode = Some(*mode),
+                pixel_bot::Message::IterTime(time) => self.iter_avg.push(*time),
+                pixel_bot::Message::CaptureData(data) => self.has_target = data.aim_coord.is_some(),
+                pixel_bot::Message::CaptureFormat(format) => self.capture_format = Some(*format),
+                _ => {}
+            }
+        }
+
+        if self.last_update.elapsed() < UPDATE_INTERVAL {
+            return;
+        }
+        self.last_update = Instant::now();
+        let theme = &self.theme;
+
+        let waiting_label;
+        let (state_label, state_color) = match self.state {
+            Some(pixel_bot::BotState::Running) => ("running", theme.green),
+            Some(pixel_bot::BotState::Paused) => ("paused", theme.yellow),
+            Some(pixel_bot::BotState::Stalled) => ("stalled", theme.red),
+            Some(pixel_bot::BotState::Idle) => ("idle", theme.blue),
+            Some(pixel_bot::BotState::WaitingForSlot(secs)) => {
+                waiting_label =
+                    format!("waiting for a free duplication slot (retrying… {}s)", secs);
+                (waiting_label.as_str(), theme.orange)
+            }
+            None => ("...", theme.gray),
+        };
+        set_segment(&mut self.state_seg, &format!("bot: {}", state_label), state_color);
+
+        let (fps_label, fps_color) = fps_label(&self.iter_avg, units, theme);
+        set_segment(&mut self.fps_seg, &fps_label, fps_color);
+
+        let (click_label, click_color) = match self.click_mode {
+            Some(pixel_bot::ClickMode::Regular) => ("regular", theme.green),
+            Some(pixel_bot::ClickMode::Auto) => ("auto", theme.yellow),
+            Some(pixel_bot::ClickMode::Redirected(_)) => ("redirected", theme.red),
+            Some(pixel_bot::ClickMode::Trigger) => ("trigger", theme.orange),
+            None => ("...", theme.gray),
+        };
+        set_segment(&mut self.click_seg, &format!("click: {}", click_label), click_color);
+
+        let (target_label, target_color) = match self.has_target {
+            true => ("target: yes", theme.green),
+            false => ("target: no", theme.gray),
+        };
+        set_segment(&mut self.target_seg, target_label, target_color);
+
+        // yellow when every frame pays for a format conversion, the log has the details
+        let (capture_label, capture_color) = match self.capture_format {
+            Some(format) if format.needs_conversion => (format.name, theme.yellow),
+            Some(format) => (format.name, theme.green),
+            None => ("...", theme.gray),
+        };
+        set_segment(&mut self.capture_seg, &format!("capture: {}", capture_label), capture_color);
+    }
+}
+
+fn set_segment(seg: &mut Frame, label: &str, color: Color) {
+    seg.set_label(label);
+    seg.set_label_color(color);
+    seg.redraw_label();
+}
+
+// Colored by fps whichever unit it's shown in
+fn fps_label(iter_avg: &RollingAvg, units: DisplayUnits, theme: &Theme) -> (String, Color) {
+    match iter_avg.avg() {
+        Some(avg) if !avg.is_zero() => {
+            let fps = 1. / avg.as_secs_f32();
+            let color = if fps >= 60. {
+                theme.green
+            } else if fps >= 30. {
+                theme.yellow
+            } else {
+                theme.red
+            };
+            let label = match units {
+                DisplayUnits::FpsPriority => format!("fps: {:.0}", fps),
+                _ => format!("frame: {}", units.duration(avg)),
+            };
+            (label, color)
+        }
+        _ if units == DisplayUnits::FpsPriority => ("fps: --".to_string(), theme.gray),
+        _ => ("frame: --".to_string(), theme.gray),
+    }
+}
+
+// The display_units choice, every frame & click time in the gui goes through here
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DisplayUnits {
+    Auto,
+    Ms,
+    Us,
+    FpsPriority,
+}
+
+impl DisplayUnits {
+    fn from_config(cfg: &Config) -> Self {
+        match <ValType as Into<Choice>>::into(cfg.get(CfgKey::DisplayUnits)).as_str() {
+            "ms" => Self::Ms,
+            "us" => Self::Us,
+            "fps_priority" => Self::FpsPriority,
+            _ => Self::Auto,
+        }
+    }
+
+    // 0.25ms shows as "250us" in auto, "0.25ms" in ms & "250us" in us.
+    //     fps_priority only changes frame times, anything else is shown like auto
+    fn duration(self, d: Duration) -> String {
+        let secs = d.as_secs_f64();
+        match self {
+            Self::Ms => format!("{:.2}ms", secs * 1e3),
+            Self::Us => format!("{:.0}us", secs * 1e6),
+            Self::Auto | Self::FpsPriority => {
+                // smallest unit that keeps the number under 1000, so it stays 1-999. 999.5 rounds up, so it moves on
+                let (val, unit) = [(1e9, "ns"), (1e6, "us"), (1e3, "ms")]
+                    .into_iter()
+                    .map(|(scale, unit)| (secs * scale, unit))
+                    .find(|(val, _)| *val < 999.5)
+                    .unwrap_or((secs, "s"));
+                match val {
+                    v if v < 10. => format!("{:.2}{}", v, unit),
+                    v if v < 100. => format!("{:.1}{}", v, unit),
+                    v => format!("{:.0}{}", v, unit),
+                }
+            }
+        }
+    }
+
+    // The graph label, whichever of the two the mode cares about goes first
+    fn frame_time(self, avg: Duration) -> String {
+        // zero frame time would show up as "inf" fps
+        let (time, fps) = match avg.is_zero() {
+            true => ("--".to_string(), "--".to_string()),
+            false => (self.duration(avg), format!("{:.0}", 1. / avg.as_secs_f32())),
+        };
+        match self {
+            Self::FpsPriority => format!("FPS: {} | Frame time: {}", fps, time),
+            _ => format!("Frame time: {} | FPS: {}", time, fps),
+        }
+    }
+}
+
+// Tiny borderless always on top window with just the bot state, target & fps, for single monitor setups
+//     where the main window ends up behind the game. Double clicking it brings the main window back
+struct CompactOverlay {
+    win: Window,
+    state_dot: Frame,
+    target_seg: Frame,
+    fps_seg: Frame,
+    state: Option<pixel_bot::BotState>,
+    has_target: bool,
+    iter_avg: RollingAvg,
+    last_update: Instant,
+    theme: Rc<Theme>,
+}
+
+impl CompactOverlay {
+    const W: i32 = 150;
+    const H: i32 = 24;
+    const SCREEN_MARGIN: i32 = 10;
+
+    // Has to be created before the main window, or fltk would nest it inside of it
+    pub fn new(theme: Rc<Theme>) -> Self {
+        let mut win = Window::new(0, 0, Self::W, Self::H, None);
+        win.set_border(false);
+        win.set_color(theme.bg0_h);
+
+        let mut row = Flex::new(0, 0, Self::W, Self::H, None).row();
+        row.set_margin(4);
+        row.set_pad(6);
+        let mut state_dot = Frame::default();
+        state_dot.set_frame(FrameType::OvalBox);
+        state_dot.set_color(theme.gray);
+        row.set_size(&state_dot, Self::H - 8);
+        let segment = || {
+            let mut seg = Frame::default().with_align(Align::Left | Align::Inside);
+            seg.set_label_font(Font::Courier);
+            seg.set_label_size(12);
+            seg.set_label_color(theme.gray);
+            seg
+        };
+        let (target_seg, fps_seg) = (segment(), segment());
+        row.end();
+        win.end();
+
+        Self {
+            win,
+            state_dot,
+            target_seg,
+            fps_seg,
+            state: None,
+            has_target: false,
+            iter_avg: RollingAvg::new(60),
+            last_update: Instant::now(),
+            theme,
+        }
+    }
+
+    pub fn update(&mut self, msgs: &[pixel_bot::Message], units: DisplayUnits) {
+        const UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+        for msg in msgs {
+            match msg {
+                pixel_bot::Message::BotState(state) => self.state = Some(*state),
+                pixel_bot::Message::IterTime(time) => self.iter_avg.push(*time),
+                pixel_bot::Message::CaptureData(data) => self.has_target = data.aim_coord.is_some(),
+                _ => {}
+            }
+        }
+
+        if !self.win.shown() || self.last_update.elapsed() < UPDATE_INTERVAL {
+            return;
+        }
+        self.last_update = Instant::now();
+        let theme = &self.theme;
+
+        let state_color = match self.state {
+            Some(pixel_bot::BotState::Running) => theme.green,
+            Some(pixel_bot::BotState::Paused) => theme.yellow,
+            Some(pixel_bot::BotState::Stalled) => theme.red,
+            Some(pixel_bot::BotState::Idle) => theme.blue,
+            Some(pixel_bot::BotState::WaitingForSlot(_)) => theme.orange,
+            None => theme.gray,
+        };
+        if self.state_dot.color() != state_color {
+            self.state_dot.set_color(state_color);
+            self.state_dot.redraw();
+        }
+
+        let target_color = if self.has_target { theme.green } else { theme.gray };
+        set_segment(&mut self.target_seg, "target", target_color);
+
+        let (fps_label, fps_color) = fps_label(&self.iter_avg, units, theme);
+        set_segment(&mut self.fps_seg, &fps_label, fps_color);
+    }
+}
+
+// Swaps between the main window & the compact overlay, the config key is what gets saved
+fn set_compact_overlay(
+    overlay_win: &mut Window,
+    main_win: &mut Window,
+    config: &RwLock<Config>,
+    active: bool,
+) {
+    let corner: Choice = {
+        let mut cfg = config.write().unwrap();
+        cfg.set_val(CfgKey::CompactOverlay, ValType::Bool(active))
+            .unwrap();
+        cfg.get(CfgKey::CompactOverlayCorner).into()
+    };
+
+    if !active {
+        overlay_win.hide();
+        main_win.show();
+        return;
+    }
+
+    let (screen_w, screen_h) = app::screen_size();
+    let (screen_w, screen_h) = (screen_w as i32, screen_h as i32);
+    let (w, h) = (CompactOverlay::W, CompactOverlay::H);
+    let margin = CompactOverlay::SCREEN_MARGIN;
+    let (x, y) = match corner.as_str() {
+        "top_left" => (margin, margin),
+        "bottom_right" => (screen_w - w - margin, screen_h - h - margin),
+        "bottom_left" => (margin, screen_h - h - margin),
+        _ => (screen_w - w - margin, margin),
+    };
+    overlay_win.resize(x, y, w, h);
+    overlay_win.show();
+    make_overlay_window(overlay_win);
+    main_win.hide();
+}
+
+// fltk can't keep a window from taking focus, so the styles get set on the raw handle.
+//     The handle only exists once the window has been shown
+fn make_overlay_window(win: &Window) {
+    use windows::Win32::{
+        Foundation::HWND,
+        UI::WindowsAndMessaging::{
+            GetWindowLongPtrW, SetWindowLongPtrW, SetWindowPos, GWL_EXSTYLE, HWND_TOPMOST,
+            SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW,
+            WS_EX_TOPMOST,
+        },
+    };
+    let hwnd = HWND(win.raw_handle() as isize);
+    // toolwindow keeps it off the taskbar & alt tab
+    let overlay_style = (WS_EX_TOPMOST.0 | WS_EX_NOACTIVATE.0 | WS_EX_TOOLWINDOW.0) as isize;
+    unsafe {
+        let ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+        SetWindowLongPtrW(hwnd, GWL_EXSTYLE, ex_style | overlay_style);
+        SetWindowPos(
+            hwnd,
+            HWND_TOPMOST,
+            0,
+            0,
+            0,
+            0,
+            SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+        );
+    }
+}
+
+type RefreshFn = Box<dyn FnMut(&Config)>;
+
+// Per-key closures that re-read a widget's value from the config, registered as the widgets get created
+//     so values changed outside of the gui (calibration, resizes, ...) show up without a restart
+#[derive(Clone)]
+struct ConfigRefresher {
+    config: Arc<RwLock<Config>>,
+    refresh_fns: Rc<RefCell<Vec<(CfgKey, RefreshFn)>>>,
+    refreshing: Rc<Cell<bool>>,
+}
+
+impl ConfigRefresher {
+    pub fn new(config: Arc<RwLock<Config>>) -> Self {
+        Self {
+            config,
+            refresh_fns: Rc::new(RefCell::new(Vec::new())),
+            refreshing: Rc::new(Cell::new(false)),
+        }
+    }
+
+    pub fn register(&self, key: CfgKey, refresh_fn: impl FnMut(&Config) + 'static) {
+        self.refresh_fns
+            .borrow_mut()
+            .push((key, Box::new(refresh_fn)));
+    }
+
+    // Widgets check this before writing to the config, so a refresh never feeds back into it
+    pub fn is_refreshing(&self) -> bool {
+        self.refreshing.get()
+    }
+
+    pub fn refresh(&self, keys: &[CfgKey]) {
+        let cfg = self.config.read().unwrap();
+        self.refreshing.set(true);
+        self.refresh_fns
+            .borrow_mut()
+            .iter_mut()
+            .filter(|(key, _)| keys.contains(key))
+            .for_each(|(_, refresh_fn)| refresh_fn(&cfg));
+        self.refreshing.set(false);
+    }
+}
+
+type DirtyFn = Box<dyn FnMut(bool)>;
+
+// Marks widgets whose value differs from the config as it was last loaded or saved. `None` keys
+//     get told whether anything at all is unsaved (the save button)
+#[derive(Clone)]
+struct DirtyTracker {
+    config: Arc<RwLock<Config>>,
+    baseline: Rc<RefCell<Config>>,
+    indicator_fns: Rc<RefCell<Vec<(Option<CfgKey>, Option<bool>, DirtyFn)>>>, // key, last shown state, fn
+    last_update: Rc<Cell<Instant>>,
+}
+
+impl DirtyTracker {
+    pub fn new(config: Arc<RwLock<Config>>) -> Self {
+        let baseline = config.read().unwrap().clone();
+        Self {
+            config,
+            baseline: Rc::new(RefCell::new(baseline)),
+            indicator_fns: Rc::new(RefCell::new(Vec::new())),
+            last_update: Rc::new(Cell::new(Instant::now())),
+        }
+    }
+
+    pub fn register(&self, key: Option<CfgKey>, indicator_fn: impl FnMut(bool) + 'static) {
+        self.indicator_fns
+            .borrow_mut()
+            .push((key, None, Box::new(indicator_fn)));
+    }
+
+    pub fn mark_saved(&self) {
+        *self.baseline.borrow_mut() = self.config.read().unwrap().clone();
+        self.last_update.set(Instant::now() - Self::UPDATE_INTERVAL); // clearing right away
+    }
+
+    const UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+    // Throttled, called from the idle handler
+    pub fn update(&self) {
+        if self.last_update.get().elapsed() < Self::UPDATE_INTERVAL {
+            return;
+        }
+        self.last_update.set(Instant::now());
+
+        let cfg = self.config.read().unwrap();
+        let baseline = self.baseline.borrow();
+        let any_dirty = cfg.changed_keys(&baseline).next().is_some();
+        for (key, last, indicator_fn) in self.indicator_fns.borrow_mut().iter_mut() {
+            let dirty = match key {
+                Some(key) => cfg.differs_from(&baseline, *key),
+                None => any_dirty,
+            };
+            // only touching widgets whose state actually changed
+            if *last != Some(dirty) {
+                *last = Some(dirty);
+                indicator_fn(dirty);
+            }
+        }
+    }
+}
+
+struct ControlsPanel {
+    grp: Group,
+    refresher: ConfigRefresher,
+    power_but: Button,
+}
+
+impl ControlsPanel {
+    pub fn set_running(&mut self, running: bool) {
+        self.power_but.set_label(power_label(running));
+        self.power_but.redraw();
+    }
+
+    pub fn update(&mut self, msgs: &[pixel_bot::Message]) {
+        let changed: Vec<CfgKey> = msgs
+            .iter()
+            .filter_map(|msg| match msg {
+                pixel_bot::Message::ConfigChanged(keys) => Some(keys.iter().copied()),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        if !changed.is_empty() {
+            self.refresher.refresh(&changed);
+        }
+    }
+}
+
+pub struct Gui {
+    app: App,
+    window: Window,
+    config: Arc<RwLock<Config>>,
+
+    // we don't want multiple keycode buttons searching for input concurrently
+    capture_input_lock: Rc<Cell<bool>>,
+
+    refresher: ConfigRefresher,
+    dirty_tracker: DirtyTracker,
+    commands: pixel_bot::CommandSender,
+    theme: Rc<Theme>, // changing the theme requires a restart
+
+    term_panel: Option<Rc<RefCell<TerminalPanel>>>,
+    overlay_win: Window,
+    compact_overlay: Option<CompactOverlay>, // moved into the idle handler on init
+}
+
+impl Gui {
+    pub fn new(
+        w: i32,
+        h: i32,
+        config: Arc<RwLock<Config>>,
+        commands: pixel_bot::CommandSender,
+        theme: Theme,
+    ) -> Self {
+        let app = App::default();
+
+        app::set_visible_focus(false);
+        app::set_frame_type(FrameType::RFlatBox);
+        app::set_frame_border_radius_max(10);
+        app::add_handler(|ev| matches!(ev, Event::Shortcut) && (app::event_key() == (Key::Escape)));
+
+        // ctrl shortcuts for the commands that don't get a button
+        let shortcut_commands = commands.clone();
+        app::add_handler(move |ev| {
+            if !matches!(ev, Event::Shortcut) || !app::event_state().contains(Shortcut::Ctrl) {
+                return false;
+            }
+            let cmd = match app::event_key() {
+                Key::from_char('k') => pixel_bot::BotCommand::Calibrate,
+                Key::from_char('r') => pixel_bot::BotCommand::ReinitCapture,
+                Key::from_char('d') => pixel_bot::BotCommand::DumpDebug,
+                // runs next to the bot, results show up in the terminal
+                Key::from_char('t') => {
+                    spawn_self_test();
+                    return true;
+                }
+                _ => return false,
+            };
+            shortcut_commands.send(cmd);
+            true
+        });
+
+        load_ui_font(Font::Courier, "JetBrainsMono-Medium.ttf", FONT_MEDIUM_BYTES);
+        load_ui_font(Font::CourierBold, "JetBrainsMono-Bold.ttf", FONT_BOLD_BYTES);
+        let theme = Rc::new(theme);
+        let compact_overlay = CompactOverlay::new(theme.clone());
+        let window = Window::new(w / 2, h / 2, w, h, "pb");
+
+        let capture_input_lock = Rc::new(Cell::new(false));
+        let refresher = ConfigRefresher::new(config.clone());
+        let dirty_tracker = DirtyTracker::new(config.clone());
+
+        Self {
+            window,
+            app,
+            config,
+            capture_input_lock,
+            refresher,
+            dirty_tracker,
+            commands,
+            theme,
+            term_panel: None,
+            overlay_win: compact_overlay.win.clone(),
+            compact_overlay: Some(compact_overlay),
+        }
+    }
+
+    pub fn wait(&mut self, dur_secs: f64) -> bool {
+        app::sleep(dur_secs);
+        self.app.wait()
+    }
+
+    // Minimized windows aren't visible, nobody's looking at the mirror then
+    pub fn mirror_visible(&self) -> bool {
+        self.window.visible()
+    }
+
+    // Called on clean shutdown so the terminal contents can be replayed on the next start
+    pub fn save_session(&self) {
+        if let Some(ref term_panel) = self.term_panel {
+            // pulling in the repeat counts of still suppressed warnings first, so the saved log has them
+            logging::flush_suppressed();
+            term_panel.borrow_mut().update();
+            term_panel.borrow_mut().save_session();
+        }
+    }
+
+    pub fn init(
+        &mut self,
+        screen_dims: (u32, u32),
+        receiver: channel::Receiver<pixel_bot::Message>,
+        power_tx: channel::Sender<pixel_bot::PowerRequest>,
+        cfg_path: &'static str,
+        first_run: bool,
+    ) {
+        let (r, g, b) = self.theme.fg2.to_rgb();
+        app::set_foreground_color(r, g, b);
+
+        let (r, g, b) = self.theme.bg1.to_rgb();
+        app::set_background_color(r, g, b);
+
+        self.window.set_color(Color::BackGround);
+
+        let (win_w, win_h) = (self.window.w(), self.window.h());
+
+        self.window.make_resizable(true);
+        self.window.size_range(800, 700, 3480, 2160);
+
+        const GAP: i32 = 5;
+        const MIDDLE_OFFSET: i32 = 50;
+        const STATUS_H: i32 = 20;
+
+        // everything above the status bar
+        let content_h = win_h - STATUS_H;
+
+        // Sliders, crop widget & keycode buttons (right side)
+        let right_x = (win_w / 2) + MIDDLE_OFFSET;
+        let right_w = ((win_w - (GAP * 2)) / 2) - MIDDLE_OFFSET;
+        let mut controls_panel = self.create_controls_panel(
+            Bounds::new(right_x, GAP, right_w, content_h - (GAP * 2)),
+            screen_dims,
+            power_tx,
+            cfg_path,
+            GAP,
+        );
+
+        // Screen mirror widget, graph, and terminal (left side)
+        //     the flex column keeps the three panels at a third of the window height each when resizing
+        let left_w = (win_w / 2) + MIDDLE_OFFSET;
+        let panel_h = (content_h - (GAP * 4)) / 3;
+        let panel_b = |idx: i32| {
+            Bounds::new(
+                GAP,
+                GAP + ((panel_h + GAP) * idx),
+                left_w - (GAP * 2),
+                panel_h,
+            )
+        };
+
+        let mut left_col = Flex::new(0, 0, left_w, content_h, None).column();
+        left_col.set_margin(GAP);
+        left_col.set_pad(GAP);
+        let mut mirror_panel = MirrorPanel::new(
+            panel_b(0),
+            self.config.clone(),
+            &self.refresher,
+            self.theme.clone(),
+        );
+        let mut graph_panel = GraphPanel::new(panel_b(1), self.config.clone(), self.theme.clone());
+        // a fresh install gets walked through the basics in the graph's spot, the graph comes back after
+        if first_run {
+            self.create_setup_wizard(
+                panel_b(1),
+                cfg_path,
+                mirror_panel.picking.clone(),
+                graph_panel.grp.clone(),
+                left_col.clone(),
+            );
+        }
+        let term_panel = Rc::new(RefCell::new(TerminalPanel::new(panel_b(2), &self.theme)));
+        left_col.end();
+        self.term_panel = Some(term_panel.clone());
+
+        let mut status_bar = StatusBar::new(
+            Bounds::new(0, content_h, win_w, STATUS_H),
+            self.theme.clone(),
+        );
+
+        let mut compact_overlay = self.compact_overlay.take().unwrap();
+        let mut overlay_win = self.overlay_win.clone();
+        let mut main_win = self.window.clone();
+        let overlay_config = self.config.clone();
+        compact_overlay.win.handle(move |_, ev| match ev {
+            Event::Push if app::event_clicks() => {
+                set_compact_overlay(&mut overlay_win, &mut main_win, &overlay_config, false);
+                true
+            }
+            _ => false,
+        });
+
+        // picking a color from the mirror stops at Esc or a click anywhere else
+        let picking = mirror_panel.picking.clone();
+        let pick_frame = mirror_panel.img_frame.clone();
+        self.window.handle(move |_, ev| match ev {
+            Event::Push if picking.get() && !app::event_inside_widget(&pick_frame) => {
+                picking.set(false);
+                draw::set_cursor(Cursor::Default);
+                log!("Color pick cancelled");
+                false // the click still goes wherever it was headed
+            }
+            Event::Shortcut if picking.get() && app::event_key() == Key::Escape => {
+                picking.set(false);
+                draw::set_cursor(Cursor::Default);
+                log!("Color pick cancelled");
+                true
+            }
+            _ => false,
+        });
+
+        let dirty_tracker = self.dirty_tracker.clone();
+        let units_config = self.config.clone();
+        let mut summary_logger = SummaryLogger::default();
+        let commands = self.commands.clone();
+        let mut was_running = None;
+        app::add_idle3(move |_| {
+            term_panel.borrow_mut().update();
+            dirty_tracker.update();
+
+            // started & stopped from other threads, the panels just follow along
+            let running = commands.is_running();
+            if was_running != Some(running) {
+                was_running = Some(running);
+                controls_panel.set_running(running);
+                graph_panel.set_stopped(!running);
+                mirror_panel.set_stopped(!running);
+            }
+
+            let msgs: Vec<_> = receiver.try_iter().collect();
+            let units = DisplayUnits::from_config(&units_config.read().unwrap());
+            graph_panel.update(&msgs, units);
+            controls_panel.update(&msgs);
+            status_bar.update(&msgs, units);
+            compact_overlay.update(&msgs, units);
+            summary_logger.update(&msgs);
 
             // only getting the latest capturedata message
             if let Some(pixel_bot::Message::CaptureData(data)) = msgs
@@ -634,150 +2261,702 @@ impl Gui {
                 .rev()
                 .find(|msg| matches!(msg, pixel_bot::Message::CaptureData(_)))
             {
-                let (frame_w, frame_h) = (img_frame.w() as usize, img_frame.h() as usize);
-                let (old_w, old_h) = (data.img.w, data.img.h);
-                let mut resized_data_img = match data.img.scale_keep_aspect(frame_w, frame_h) {
-                    Some(resized) => resized,
-                    None => data.img,
+                mirror_panel.update(data);
+            }
+            mirror_panel.tick();
+        });
+
+        self.window.end();
+        self.window.show();
+
+        let cfg = self.config.read().unwrap();
+        let start_compact: bool = cfg.get(CfgKey::CompactOverlay).into();
+        drop(cfg);
+        if start_compact {
+            set_compact_overlay(&mut self.overlay_win, &mut self.window, &self.config, true);
+        }
+    }
+
+    // A step per thing the defaults can't know about, pointing at the regular widgets where they're visible
+    //     anyway. Skipping leaves everything not set yet at its default, finishing saves the config
+    fn create_setup_wizard(
+        &self,
+        b: Bounds,
+        cfg_path: &'static str,
+        picking: Rc<Cell<bool>>,
+        mut graph_grp: Group,
+        mut left_col: Flex,
+    ) {
+        const TITLE_H: i32 = 24;
+        const NAV_H: i32 = 30;
+        const GAP: i32 = 5;
+
+        let mut wizard = Group::new(b.x, b.y, b.w, b.h, "");
+        wizard.set_frame(app::frame_type());
+        wizard.set_color(self.theme.bg0_h);
+
+        let mut title = Frame::new(b.x + GAP, b.y + GAP, b.w - (GAP * 2), TITLE_H, "")
+            .with_align(Align::Left | Align::Inside);
+        title.set_label_font(Font::CourierBold);
+        title.set_label_color(self.theme.fg0);
+        let text_h = (b.h - TITLE_H - NAV_H - (GAP * 4)) / 2;
+        let mut text = Frame::new(b.x + GAP, title.y() + TITLE_H, b.w - (GAP * 2), text_h, "")
+            .with_align(Align::Left | Align::Top | Align::Inside | Align::Wrap);
+        text.set_label_font(Font::Courier);
+        text.set_label_size(12);
+        text.set_label_color(self.theme.fg2);
+
+        // whatever a step needs goes between the text & the nav buttons
+        let content_b = Bounds::new(
+            b.x + GAP,
+            text.y() + text_h + GAP,
+            b.w - (GAP * 2),
+            b.h - TITLE_H - NAV_H - text_h - (GAP * 4),
+        );
+        let half_b = |idx: i32| {
+            let w = (content_b.w - GAP) / 2;
+            Bounds::new(content_b.x + (w + GAP) * idx, content_b.y, w, content_b.h)
+        };
+        let step_grp = || Group::new(content_b.x, content_b.y, content_b.w, content_b.h, "");
+        let small_but = |b: Bounds, label: &str| {
+            let mut but = Button::new(b.x, b.y, b.w, b.h, "").with_label(label);
+            but.set_frame(app::frame_type());
+            but.set_color(self.theme.bg1);
+            but.set_label_color(self.theme.fg2);
+            but.set_label_font(Font::Courier);
+            but
+        };
+        let mut accents = self.theme.accents.iter().copied().cycle();
+
+        let monitor_grp = step_grp();
+        self.create_config_slider(
+            Bounds::new(content_b.x, content_b.y, content_b.w, content_b.h.min(40)),
+            CfgKey::MonitorIndex,
+            CfgKey::MonitorIndex.as_string(),
+            accents.next().unwrap(),
+        );
+        monitor_grp.end();
+
+        let crop_grp = step_grp(); // the crop sliders on the right are the whole step
+        crop_grp.end();
+
+        let color_grp = step_grp();
+        let mut pick_but = small_but(half_b(0), "pick from the mirror");
+        pick_but.set_callback(move |_| {
+            picking.set(true);
+            draw::set_cursor(Cursor::Cross);
+            log!("Click the mirror to pick the target color, Esc to cancel");
+        });
+        self.create_color_swatch(half_b(1), CfgKey::TargetColor);
+        color_grp.end();
+
+        let keys_grp = step_grp();
+        self.create_keycode_but(
+            half_b(0),
+            CfgKey::AimKeycode,
+            "Start Aim".to_string(),
+            accents.next().unwrap(),
+        );
+        self.create_keycode_but(
+            half_b(1),
+            CfgKey::AutoclickKeycode,
+            "Autoclick".to_string(),
+            accents.next().unwrap(),
+        );
+        keys_grp.end();
+
+        let checks_grp = step_grp();
+        let mut checks_but = small_but(half_b(0), "run checks");
+        checks_but.set_callback(|_| spawn_self_test());
+        checks_grp.end();
+
+        let nav_y = b.y + b.h - NAV_H - GAP;
+        let nav_w = (b.w - (GAP * 4)) / 3;
+        let nav_b = |idx: i32| Bounds::new(b.x + GAP + (nav_w + GAP) * idx, nav_y, nav_w, NAV_H);
+        let mut skip_but = small_but(nav_b(0), "skip setup");
+        let mut back_but = small_but(nav_b(1), "back");
+        let mut next_but = small_but(nav_b(2), "next");
+        wizard.end();
+
+        let steps = Rc::new(vec![
+            (
+                "Monitor",
+                "Which monitor the game runs on, 0 is the first one.",
+                monitor_grp,
+            ),
+            (
+                "Crop",
+                "Drag the crop sliders on the right until the box around the crosshair covers where targets show up. Smaller crops are faster, the mirror above shows what the bot sees.",
+                crop_grp,
+            ),
+            (
+                "Target color",
+                "With a target on screen, pick its outline color from the mirror. The threshold slider on the right sets how close other colors have to be to count.",
+                color_grp,
+            ),
+            (
+                "Keys",
+                "Click a button, then press the key or mouse button you want for it. The rest of the keys are on the right.",
+                keys_grp,
+            ),
+            (
+                "Checks",
+                "Checks capture, detection & the mouse driver, the results show up in the terminal. The driver needs the bot to be running as admin.",
+                checks_grp,
+            ),
+        ]);
+        let step = Rc::new(Cell::new(0));
+
+        let show_step = {
+            let steps = steps.clone();
+            let mut title = title.clone();
+            let mut text = text.clone();
+            let mut back_but = back_but.clone();
+            let mut next_but = next_but.clone();
+            let mut wizard = wizard.clone();
+            move |idx: usize| {
+                for (step_idx, (_, _, grp)) in steps.iter().enumerate() {
+                    let mut grp = grp.clone();
+                    if step_idx == idx {
+                        grp.show();
+                    } else {
+                        grp.hide();
+                    }
+                }
+                let (name, desc, _) = &steps[idx];
+                title.set_label(&format!("Setup {}/{}: {}", idx + 1, steps.len(), name));
+                text.set_label(desc);
+                if idx == 0 {
+                    back_but.deactivate();
+                } else {
+                    back_but.activate();
+                }
+                let last = idx + 1 == steps.len();
+                next_but.set_label(if last { "finish" } else { "next" });
+                wizard.redraw();
+            }
+        };
+        show_step(0);
+        graph_grp.hide();
+
+        let close = {
+            let mut wizard = wizard.clone();
+            move || {
+                wizard.hide();
+                graph_grp.show();
+                left_col.recalc();
+                left_col.redraw();
+            }
+        };
+
+        let mut skip_close = close.clone();
+        skip_but.set_callback(move |_| {
+            log!("Setup skipped, anything not set yet keeps its default");
+            skip_close();
+        });
+
+        let back_step = step.clone();
+        let mut back_show = show_step.clone();
+        back_but.set_callback(move |_| {
+            back_step.set(back_step.get().saturating_sub(1));
+            back_show(back_step.get());
+        });
+
+        let config = self.config.clone();
+        let dirty_tracker = self.dirty_tracker.clone();
+        let mut next_show = show_step;
+        let mut next_close = close;
+        next_but.set_callback(move |_| {
+            if step.get() + 1 < steps.len() {
+                step.set(step.get() + 1);
+                next_show(step.get());
+            } else {
+                save_config(&config, cfg_path, &dirty_tracker);
+                next_close();
+            }
+        });
+    }
+
+    fn create_controls_panel(
+        &mut self,
+        b: Bounds,
+        screen_dims: (u32, u32),
+        power_tx: channel::Sender<pixel_bot::PowerRequest>,
+        cfg_path: &'static str,
+        gap: i32,
+    ) -> ControlsPanel {
+        let grp = Group::new(b.x, b.y, b.w, b.h, "");
+        let slider_h = (self.window.w() as f32 * 0.05) as i32;
+
+        // crop widget
+        let crop_box_b = self.create_crop_widget(b.x, b.y, screen_dims, slider_h, b.w, gap);
+
+        // slider group
+        let mut cur_slider_b = Bounds::new(b.x, crop_box_b.y + crop_box_b.h + gap, b.w, slider_h);
+        let mut slider_grp_b = cur_slider_b;
+        let mut colors_cycle = self.theme.accents.clone().into_iter().cycle().skip(2); // crop sliders took the first two colors
+
+        CfgKey::iter()
+            .filter(|key| !key.is_legacy())
+            .filter(|key| !matches!(key, CfgKey::CropWPercent | CfgKey::CropHPercent))
+            .filter(|key| *key != CfgKey::AutoclickScanCode) // recorded by the keycode button
+            .filter(|key| matches!(key.default_val(), ValType::Unsigned(_) | ValType::Float(_)))
+            .for_each(|key| {
+                self.create_config_slider(
+                    cur_slider_b,
+                    key,
+                    key.as_string(),
+                    colors_cycle.next().unwrap(),
+                );
+                cur_slider_b.y += cur_slider_b.h + gap;
+            });
+        slider_grp_b.h = cur_slider_b.y - slider_grp_b.y;
+
+        // keycode button group, fills whatever height is left under the sliders
+        let buttons_y = slider_grp_b.y + slider_grp_b.h;
+        let power_but = self.create_cfg_button_group(
+            Bounds::new(b.x, buttons_y, b.w, (b.y + b.h) - buttons_y),
+            3,
+            power_tx,
+            cfg_path,
+            gap,
+        );
+        grp.end();
+
+        ControlsPanel {
+            grp,
+            refresher: self.refresher.clone(),
+            power_but,
+        }
+    }
+
+    // Returns the start/stop button, its label follows the bot's state
+    fn create_cfg_button_group(
+        &self,
+        b: Bounds,
+        row_len: i32,
+        power_tx: channel::Sender<pixel_bot::PowerRequest>,
+        cfg_path: &'static str,
+        gap: i32,
+    ) -> Button {
+        let pretty_name = |key: CfgKey| match key {
+            CfgKey::AimKeycode => "Start Aim".to_string(),
+            CfgKey::ToggleAimKeycode => "Toggle Aim".to_string(),
+            CfgKey::AutoclickKeycode => "Autoclick".to_string(),
+            CfgKey::ToggleAutoclickKeycode => "Cycle Autoclick Mode".to_string(),
+            CfgKey::FakeLmbKeycode => "Fake Lmb".to_string(),
+            CfgKey::CalibrateKeycode => "Calibrate Color".to_string(),
+            CfgKey::ScreenshotKeycode => "Save Capture".to_string(),
+            _ => panic!("Keycode match not exhaustive"),
+        };
+        let mut bg_frame = Frame::new(b.x, b.y, b.w, b.h, "");
+        bg_frame.set_color(self.theme.bg0);
+        bg_frame.set_frame(app::frame_type());
+
+        let b = b.gapify(gap);
+
+        let n_buttons = CfgKey::iter().filter(|k| k.is_keycode()).count() as i32 + 10; // start/stop, save, color swatch, picker, commands & overlay
+
+        let button_w = b.w / row_len;
+        let button_h = b.h / ((button_w * n_buttons) as f32 / b.w as f32).ceil() as i32;
+
+        let mut colors_cycle = self.theme.accents.clone().into_iter().cycle();
+        let mut current_bounds = Bounds::new(b.x, b.y, button_w, button_h);
+        // first, it's the one that matters most when something goes wrong
+        let power_but = self.create_power_but(
+            current_bounds.gapify(gap),
+            power_tx,
+            colors_cycle.next().unwrap(),
+        );
+        current_bounds.x += button_w;
+        if current_bounds.x + button_w > b.x + b.w {
+            current_bounds.x = b.x;
+            current_bounds.y += button_h;
+        }
+        for key in CfgKey::iter().filter(|k| k.is_keycode()) {
+            self.create_keycode_but(
+                current_bounds.gapify(gap),
+                key,
+                pretty_name(key),
+                colors_cycle.next().unwrap(),
+            );
+            current_bounds.x += button_w;
+            if current_bounds.x + button_w > b.x + b.w {
+                current_bounds.x = b.x;
+                current_bounds.y += button_h;
+            }
+        }
+        self.create_save_config_but(
+            current_bounds.gapify(gap),
+            cfg_path,
+            colors_cycle.next().unwrap(),
+        );
+        current_bounds.x += button_w;
+        if current_bounds.x + button_w > b.x + b.w {
+            current_bounds.x = b.x;
+            current_bounds.y += button_h;
+        }
+        self.create_color_swatch(current_bounds.gapify(gap), CfgKey::TargetColor);
+        current_bounds.x += button_w;
+        if current_bounds.x + button_w > b.x + b.w {
+            current_bounds.x = b.x;
+            current_bounds.y += button_h;
+        }
+        self.create_pick_color_but(current_bounds.gapify(gap), colors_cycle.next().unwrap());
+
+        for (label, cmd) in [
+            ("Pause", pixel_bot::BotCommand::Pause),
+            ("Resume", pixel_bot::BotCommand::Resume),
+            ("Screenshot", pixel_bot::BotCommand::Screenshot),
+            ("Calibrate counts", pixel_bot::BotCommand::CalibrateCounts),
+            ("Decisions", pixel_bot::BotCommand::DumpDecisions),
+        ] {
+            current_bounds.x += button_w;
+            if current_bounds.x + button_w > b.x + b.w {
+                current_bounds.x = b.x;
+                current_bounds.y += button_h;
+            }
+            self.create_command_but(
+                current_bounds.gapify(gap),
+                label,
+                cmd,
+                colors_cycle.next().unwrap(),
+            );
+        }
+
+        current_bounds.x += button_w;
+        if current_bounds.x + button_w > b.x + b.w {
+            current_bounds.x = b.x;
+            current_bounds.y += button_h;
+        }
+        self.create_compact_overlay_but(current_bounds.gapify(gap), colors_cycle.next().unwrap());
+        power_but
+    }
+
+    fn create_power_but(
+        &self,
+        b: Bounds,
+        power_tx: channel::Sender<pixel_bot::PowerRequest>,
+        c: Color,
+    ) -> Button {
+        let label = power_label(self.commands.is_running());
+        let ResponsiveButton {
+            b: _,
+            mut button,
+            push_event: button_pushed,
+            release_event: button_released,
+        } = ResponsiveButton::new(b, label.to_string(), Font::CourierBold, c, &self.theme);
+
+        button.set_label_size(12);
+        button.draw(|b| {
+            b.set_label_size(clamp(b.h() / 6, 1, 12));
+        });
+
+        let commands = self.commands.clone();
+        button.handle(move |_, ev| match ev {
+            Event::Push => {
+                app::handle_main(button_pushed).unwrap();
+                true
+            }
+            Event::Released => {
+                app::handle_main(button_released).unwrap();
+                let request = match commands.is_running() {
+                    true => pixel_bot::PowerRequest::Stop,
+                    false => {
+                        log!("Starting the bot");
+                        pixel_bot::PowerRequest::Start
+                    }
                 };
+                let _ = power_tx.send(request);
+                true
+            }
+            _ => false,
+        });
+        button
+    }
 
-                if let (Some(mut aim_coord), Some(mut target_coords)) =
-                    (data.aim_coord, data.target_coords)
-                {
-                    // scaling coords by resize ratio
-                    let ratio = Coord::new(
-                        resized_data_img.w as f32 / old_w as f32,
-                        resized_data_img.h as f32 / old_h as f32,
-                    );
-
-                    aim_coord = Coord::new(
-                        (aim_coord.x as f32 * ratio.x) as usize,
-                        (aim_coord.y as f32 * ratio.y) as usize,
-                    );
-
-                    target_coords.iter_mut().for_each(|coord| {
-                        coord.x = (coord.x as f32 * ratio.x) as usize;
-                        coord.y = (coord.y as f32 * ratio.y) as usize;
-                    });
+    fn create_compact_overlay_but(&self, b: Bounds, c: Color) {
+        let ResponsiveButton {
+            b: _,
+            mut button,
+            push_event: button_pushed,
+            release_event: button_released,
+        } = ResponsiveButton::new(b, "Overlay".to_string(), Font::CourierBold, c, &self.theme);
+
+        button.set_label_size(12);
+        button.draw(|b| {
+            b.set_label_size(clamp(b.h() / 6, 1, 12));
+        });
+
+        let mut overlay_win = self.overlay_win.clone();
+        let mut main_win = self.window.clone();
+        let config = self.config.clone();
+        button.handle(move |_, ev| match ev {
+            Event::Push => {
+                app::handle_main(button_pushed).unwrap();
+                true
+            }
+            Event::Released => {
+                app::handle_main(button_released).unwrap();
+                set_compact_overlay(&mut overlay_win, &mut main_win, &config, true);
+                true
+            }
+            _ => false,
+        });
+    }
+
+    fn create_command_but(&self, b: Bounds, label: &str, cmd: pixel_bot::BotCommand, c: Color) {
+        let ResponsiveButton {
+            b: _,
+            mut button,
+            push_event: button_pushed,
+            release_event: button_released,
+        } = ResponsiveButton::new(b, label.to_string(), Font::CourierBold, c, &self.theme);
+
+        button.set_label_size(12);
+        button.draw(|b| {
+            b.set_label_size(clamp(b.h() / 6, 1, 12));
+        });
+
+        let commands = self.commands.clone();
+        button.handle(move |_, ev| match ev {
+            Event::Push => {
+                app::handle_main(button_pushed).unwrap();
+                true
+            }
+            Event::Released => {
+                app::handle_main(button_released).unwrap();
+                commands.send(cmd);
+                true
+            }
+            _ => false,
+        });
+    }
+
+    fn create_pick_color_but(&self, b: Bounds, c: Color) {
+        let ResponsiveButton {
+            b: _,
+            mut button,
+            push_event: button_pushed,
+            release_event: button_released,
+        } = ResponsiveButton::new(
+            b,
+            "Pick color from image...".to_string(),
+            Font::CourierBold,
+            c,
+            &self.theme,
+        );
+
+        button.set_label_size(12);
+        button.draw(|b| {
+            b.set_label_size(clamp(b.h() / 6, 1, 12));
+        });
+
+        let config = self.config.clone();
+        let refresher = self.refresher.clone();
+        let bg_color = self.theme.bg0;
+        button.handle(move |_, ev| match ev {
+            Event::Push => {
+                app::handle_main(button_pushed).unwrap();
+                true
+            }
+            Event::Released => {
+                app::handle_main(button_released).unwrap();
 
-                    draw_image_overlay(&mut resized_data_img, aim_coord, target_coords);
+                let mut chooser = NativeFileChooser::new(NativeFileChooserType::BrowseFile);
+                chooser.set_filter("*.bmp");
+                chooser.show();
+                let path = chooser.filename();
+                if path.as_os_str().is_empty() {
+                    return true; // cancelled
                 }
-
-                if let Some(resized_bg) = img_frame_img.scale_nearest(frame_w, frame_h) {
-                    img_frame_img = resized_bg;
+                match image::bmp::load_bmp::<Rgba8, _>(&path) {
+                    Ok(img) => {
+                        show_color_picker(img, config.clone(), refresher.clone(), bg_color)
+                    }
+                    Err(e) => log_err!("Error loading {}:\n\t{}", path.display(), e),
                 }
-
-                img_frame_img.fill_color(Palette::BG0.to_internal());
-                img_frame_img.layer_image_over(&resized_data_img);
-
-                draw::draw_rgba(&mut img_frame, img_frame_img.as_slice()).unwrap();
-                img_frame.redraw();
+                true
             }
+            _ => false,
         });
-
-        self.window.end();
-        self.window.show();
-    }
-
-    fn create_term(b: Bounds) -> SimpleTerminal {
-        let mut term = SimpleTerminal::new(b.x, b.y, b.w, b.h, "");
-        term.set_selection_color(Color::ForeGround);
-        term.set_color(Palette::BG0_H);
-        term.set_cursor_color(Color::ForeGround);
-        term.set_cursor_style(fltk::text::Cursor::Simple);
-        term.set_scrollbar_size(-1); // no scrollbar
-        term.set_ansi(true);
-        term.set_frame(app::frame_type());
-        term
     }
 
-    fn create_cfg_button_group(&self, b: Bounds, row_len: i32, cfg_path: &'static str, gap: i32) {
-        let pretty_name = |key: CfgKey| match key {
-            CfgKey::AimKeycode => "Start Aim".to_string(),
-            CfgKey::ToggleAimKeycode => "Toggle Aim".to_string(),
-            CfgKey::AutoclickKeycode => "Autoclick".to_string(),
-            CfgKey::ToggleAutoclickKeycode => "Cycle Autoclick Mode".to_string(),
-            CfgKey::FakeLmbKeycode => "Fake Lmb".to_string(),
-            _ => panic!("Keycode match not exhaustive"),
+    fn create_color_swatch(&self, b: Bounds, cfg_key: CfgKey) {
+        const COLOR_FIELD_H: i32 = 20;
+        // the numeric fields take a row under the swatch
+        let fields_h = (b.h / 4).clamp(1, COLOR_FIELD_H);
+        self.create_color_fields(
+            Bounds::new(b.x, b.y + b.h - fields_h, b.w, fields_h),
+            cfg_key,
+        );
+        let b = Bounds::new(b.x, b.y, b.w, b.h - fields_h);
+        let mut swatch = Frame::new(b.x, b.y, b.w, b.h, "");
+        swatch.set_frame(app::frame_type());
+        swatch.set_label_font(Font::CourierBold);
+        swatch.set_label_size(12);
+
+        let label_color = self.theme.fg0;
+        let set_color = move |swatch: &mut Frame, cfg: &Config| {
+            let colors: Vec<image::Color<u8>> = cfg.get(cfg_key).into();
+            // filled with the highest priority color, every active one is listed in order
+            let fill = Color::from_internal(colors[0]);
+            swatch.set_color(fill);
+            // keeping the label readable on both light & dark colors
+            swatch.set_label_color(Color::contrast(label_color, fill));
+            let listed: Vec<String> = colors
+                .iter()
+                .map(|c| format!("{}, {}, {}", c.r, c.g, c.b))
+                .collect();
+            swatch.set_label(&format!("{}:\n{}", cfg_key.as_string(), listed.join("\n")));
+            swatch.set_tooltip(&format!(
+                "{} active, earlier colors win when several are on screen",
+                listed.len()
+            ));
+            swatch.redraw();
         };
-        let mut bg_frame = Frame::new(b.x, b.y, b.w, b.h, "");
-        bg_frame.set_color(Palette::BG0);
-        bg_frame.set_frame(app::frame_type());
+        set_color(&mut swatch, &self.config.read().unwrap());
+        self.refresher
+            .register(cfg_key, move |cfg| set_color(&mut swatch, cfg));
+    }
 
-        let b = b.gapify(gap);
+    // R, G, B and hex fields for the first color. Enter commits, bad text flashes red and reverts
+    fn create_color_fields(&self, b: Bounds, cfg_key: CfgKey) {
+        let hex_w = b.w * 2 / 5;
+        let channel_w = (b.w - hex_w) / 3;
+        for field in 0..3 {
+            let x = b.x + channel_w * field as i32;
+            self.init_color_field(IntInput::new(x, b.y, channel_w, b.h, ""), field, cfg_key);
+        }
+        let hex_x = b.x + channel_w * 3;
+        let hex_input = Input::new(hex_x, b.y, b.w - channel_w * 3, b.h, "");
+        self.init_color_field(hex_input, 3, cfg_key);
+    }
 
-        let n_buttons = CfgKey::iter().filter(|k| k.is_keycode()).count() as i32;
+    // `field` 0-2 are r, g, b, 3 is the hex field
+    fn init_color_field<I: InputExt + Clone + 'static>(
+        &self,
+        mut input: I,
+        field: usize,
+        cfg_key: CfgKey,
+    ) {
+        input.set_frame(app::frame_type());
+        input.set_color(self.theme.bg0_h);
+        input.set_text_color(Color::ForeGround);
+        input.set_text_font(Font::Courier);
+        input.set_text_size(clamp(input.h() - 6, 1, 12));
+        let init_color = first_listed_color(&self.config.read().unwrap(), cfg_key);
+        input.set_value(&color_field_text(field, init_color));
+        input.set_tooltip(match field {
+            0 => "Red, 0-255, enter to apply",
+            1 => "Green, 0-255, enter to apply",
+            2 => "Blue, 0-255, enter to apply",
+            _ => "Hex, #RRGGBB, enter to apply",
+        });
+        input.set_trigger(CallbackTrigger::EnterKey);
 
-        let button_w = b.w / row_len;
-        let button_h = b.h / ((button_w * n_buttons) as f32 / b.w as f32).ceil() as i32;
+        let mut refresh_input = input.clone();
+        self.refresher.register(cfg_key, move |cfg| {
+            refresh_input.set_value(&color_field_text(field, first_listed_color(cfg, cfg_key)));
+        });
 
-        let mut colors_cycle = Palette::COLORS.into_iter().cycle();
-        let mut current_bounds = Bounds::new(b.x, b.y, button_w, button_h);
-        for key in CfgKey::iter().filter(|k| k.is_keycode()) {
-            self.create_keycode_but(
-                current_bounds.gapify(gap),
-                key,
-                pretty_name(key),
-                colors_cycle.next().unwrap(),
-            );
-            current_bounds.x += button_w;
-            if current_bounds.x + button_w > b.x + b.w {
-                current_bounds.x = b.x;
-                current_bounds.y += button_h;
+        let config = self.config.clone();
+        let refresher = self.refresher.clone();
+        let (bg_color, error_color) = (self.theme.bg0_h, self.theme.red);
+        input.set_callback(move |input| {
+            let current = first_listed_color(&config.read().unwrap(), cfg_key);
+            let text = input.value();
+            let parsed = if field < 3 {
+                // out of range numbers get clamped, only unparseable text is rejected
+                text.trim().parse::<i64>().ok().map(|v| {
+                    let v = v.clamp(0, 255) as u8;
+                    let mut c = current;
+                    match field {
+                        0 => c.r = v,
+                        1 => c.g = v,
+                        _ => c.b = v,
+                    }
+                    c
+                })
+            } else {
+                parse_hex_color(&text).map(|c| image::Color::new(c.r, c.g, c.b, current.a))
+            };
+            match parsed {
+                Some(color) => {
+                    config
+                        .write()
+                        .unwrap()
+                        .set_first_color(cfg_key, color)
+                        .unwrap();
+                    refresher.refresh(&[cfg_key]); // the swatch & the other fields
+                }
+                None => {
+                    input.set_color(error_color);
+                    input.set_value(&color_field_text(field, current));
+                    input.redraw();
+                    let mut flashed = input.clone();
+                    app::add_timeout3(0.4, move |_| {
+                        flashed.set_color(bg_color);
+                        flashed.redraw();
+                    });
+                }
             }
-        }
-        self.create_save_config_but(
-            current_bounds.gapify(gap),
-            cfg_path,
-            colors_cycle.next().unwrap(),
-        );
+        });
     }
 
     fn create_crop_widget(
         &mut self,
         x: i32,
         y: i32,
-        aspect_ratio: f32,
+        screen_dims: (u32, u32),
         slider_h: i32,
         box_w: i32,
         slider_gap: i32,
     ) -> Bounds {
-        let box_h = (box_w as f32 * aspect_ratio) as i32;
-        let crop_box = Rc::new(RefCell::new(CropBox::new(Bounds::new(x, y, box_w, box_h))));
+        let (screen_w, screen_h) = screen_dims;
+        let box_h = (box_w as f32 * (screen_h as f32 / screen_w as f32)) as i32;
+        let crop_box = Rc::new(RefCell::new(CropBox::new(
+            Bounds::new(x, y, box_w, box_h),
+            &self.theme,
+        )));
+        let mut accents = self.theme.accents.iter().copied().cycle();
 
         let slider1_ypos = y + box_h + slider_gap;
         let slider2_ypos = slider1_ypos + slider_h + slider_gap;
         let mut slider1 = self.create_config_slider(
             Bounds::new(x, slider1_ypos, box_w, slider_h),
-            CfgKey::CropW,
-            CfgKey::CropW.as_string(),
-            Palette::COLORS[0],
+            CfgKey::CropWPercent,
+            CfgKey::CropWPercent.as_string(),
+            accents.next().unwrap(),
         );
         let mut slider2 = self.create_config_slider(
             Bounds::new(x, slider2_ypos, box_w, slider_h),
-            CfgKey::CropH,
-            CfgKey::CropH.as_string(),
-            Palette::COLORS[1],
+            CfgKey::CropHPercent,
+            CfgKey::CropHPercent.as_string(),
+            accents.next().unwrap(),
         );
 
         let slider1_crop_box = crop_box.clone();
         slider1.set_callback(move |slider| {
-            slider1_crop_box
-                .borrow_mut()
-                .change_bounds(slider.norm_val(), 0.);
+            let mut crop_box = slider1_crop_box.borrow_mut();
+            crop_box.change_bounds(slider.value() * 2., 0.);
+            crop_box.set_region_dims(Some(crop_region_len(slider, screen_w)), None);
         });
 
         let slider2_crop_box = crop_box.clone();
         slider2.set_callback(move |slider| {
-            slider2_crop_box
-                .borrow_mut()
-                .change_bounds(0., slider.norm_val());
+            let mut crop_box = slider2_crop_box.borrow_mut();
+            crop_box.change_bounds(0., slider.value() * 2.);
+            crop_box.set_region_dims(None, Some(crop_region_len(slider, screen_h)));
         });
 
-        let (init_x_percent, init_y_percent) = (slider1.norm_val(), slider2.norm_val());
-        crop_box
-            .borrow_mut()
-            .change_bounds(init_x_percent, init_y_percent);
+        let mut crop_box_ref = crop_box.borrow_mut();
+        crop_box_ref.change_bounds(slider1.value() * 2., slider2.value() * 2.);
+        crop_box_ref.set_region_dims(
+            Some(crop_region_len(&slider1, screen_w)),
+            Some(crop_region_len(&slider2, screen_h)),
+        );
+        drop(crop_box_ref);
 
         Bounds::new(x, y, box_w, box_h + (slider_h * 2) + (slider_gap * 2))
     }
@@ -788,14 +2967,29 @@ impl Gui {
             mut button,
             push_event: button_pushed,
             release_event: button_released,
-        } = ResponsiveButton::new(b, "Save config to file".to_string(), Font::CourierBold, c);
+        } = ResponsiveButton::new(
+            b,
+            "Save config to file".to_string(),
+            Font::CourierBold,
+            c,
+            &self.theme,
+        );
 
         button.set_label_size(12);
         button.draw(|b| {
             b.set_label_size(clamp(b.h() / 6, 1, 12));
         });
 
+        // dimmed while there's nothing to save
+        let mut indicator_button = button.clone();
+        let (dirty_color, clean_color) = (self.theme.fg1, self.theme.gray);
+        self.dirty_tracker.register(None, move |dirty| {
+            indicator_button.set_label_color(if dirty { dirty_color } else { clean_color });
+            indicator_button.redraw();
+        });
+
         let config = self.config.clone();
+        let dirty_tracker = self.dirty_tracker.clone();
         button.handle(move |_, ev| match ev {
             Event::Push => {
                 app::handle_main(button_pushed).unwrap();
@@ -803,16 +2997,7 @@ impl Gui {
             }
             Event::Released => {
                 app::handle_main(button_released).unwrap();
-                let abs_cfg_path = match std::path::Path::new(cfg_path).canonicalize() {
-                    Ok(abs_path) => abs_path.to_string_lossy().into_owned().split_off(4), // Removing windows extended path prefix
-                    Err(_) => cfg_path.to_string(),
-                };
-                match config.write().unwrap().write_to_file(cfg_path) {
-                    Ok(_) => {
-                        log!("Saved config to {}", abs_cfg_path);
-                    }
-                    Err(e) => log_err!("Error saving config to {}:\n\t{}", abs_cfg_path, e),
-                }
+                save_config(&config, cfg_path, &dirty_tracker);
                 true
             }
             _ => false,
@@ -824,16 +3009,12 @@ impl Gui {
 
         let capture_input = unique_event_id();
 
+        // invalid keycodes are already reported by the startup validation sweep,
+        //     the stored value is shown in the error style so it's obvious which one needs fixing
         let init_keycode: u16 = self.config.read().unwrap().get(cfg_key).into();
-        let init_string = match keycode_to_string(init_keycode) {
-            Ok(string) => string,
-            Err(_) => {
-                log_err!(
-                    "Config entry `{}` is invalid, using default value",
-                    cfg_key.as_string()
-                );
-                keycode_to_string(cfg_key.default_val().into()).unwrap()
-            }
+        let (init_string, init_valid) = match keycode_to_string(init_keycode) {
+            Ok(string) => (string, true),
+            Err(_) => (format!("invalid: {}", init_keycode), false),
         };
 
         let ResponsiveButton {
@@ -841,7 +3022,7 @@ impl Gui {
             mut button,
             push_event: button_pushed,
             release_event: button_released,
-        } = ResponsiveButton::new(b, "".to_string(), Font::Courier, c);
+        } = ResponsiveButton::new(b, "".to_string(), Font::Courier, c, &self.theme);
 
         // Label frames
         const FONT_SIZE: i32 = 12;
@@ -856,11 +3037,40 @@ impl Gui {
         name_label.set_label_font(Font::Courier);
         name_label.set_label_size(FONT_SIZE);
         name_label.set_label_wrap(format!("{}:", label), button.width());
+
+        let mut dirty_label = name_label.clone();
+        let dirty_color = self.theme.yellow;
+        self.dirty_tracker.register(Some(cfg_key), move |dirty| {
+            dirty_label.set_label_color(if dirty { dirty_color } else { Color::ForeGround });
+            dirty_label.redraw_label();
+        });
         val_label.borrow_mut().set_label_font(Font::CourierBold);
         val_label
             .borrow_mut()
             .set_label(&format!("'{}'", init_string));
         val_label.borrow_mut().set_label_size(FONT_SIZE);
+        if !init_valid {
+            val_label.borrow_mut().set_label_color(self.theme.red);
+        }
+
+        let refresh_label = val_label.clone();
+        let error_color = self.theme.red;
+        let mut refresh_button = button.clone();
+        self.refresher.register(cfg_key, move |cfg| {
+            let keycode: u16 = cfg.get(cfg_key).into();
+            let mut label = refresh_label.borrow_mut();
+            match keycode_to_string(keycode) {
+                Ok(string) => {
+                    label.set_label(&format!("'{}'", string));
+                    label.set_label_color(Color::ForeGround);
+                }
+                Err(_) => {
+                    label.set_label(&format!("'invalid: {}'", keycode));
+                    label.set_label_color(error_color);
+                }
+            }
+            refresh_button.redraw();
+        });
 
         let val_label_clone = val_label.clone();
         button.draw(move |b| {
@@ -909,14 +3119,25 @@ impl Gui {
                     match keycode_to_string(keycode) {
                         Ok(keycode_string) => {
                             wait_for_release(keycode, Duration::from_millis(500));
-                            config
+                            let res = config
                                 .write()
                                 .unwrap()
-                                .set_val(cfg_key, ValType::Keycode(keycode))
-                                .unwrap();
-                            val_label
-                                .borrow_mut()
-                                .set_label(&format!("'{}'", keycode_string));
+                                .set_val(cfg_key, ValType::Keycode(keycode));
+                            match res {
+                                Ok(_) => {
+                                    if cfg_key == CfgKey::AutoclickKeycode {
+                                        record_autoclick_scan_code(&config, keycode);
+                                    }
+                                    val_label
+                                        .borrow_mut()
+                                        .set_label(&format!("'{}'", keycode_string));
+                                    val_label.borrow_mut().set_label_color(Color::ForeGround);
+                                }
+                                Err(e) => {
+                                    val_label.borrow_mut().set_label(&last_label);
+                                    log_err!("Can't bind {}: {}", keycode_string, e);
+                                }
+                            }
                             but.redraw();
                         }
                         Err(_) => {
@@ -951,12 +3172,17 @@ impl Gui {
         label: String,
         color: Color,
     ) -> HorFillSlider {
+        // exact values get typed into the box on the right
+        const INPUT_W: i32 = 70;
+        let input_w = (b.w / 5).min(INPUT_W);
+        let input_b = Bounds::new(b.x + b.w - input_w, b.y, input_w, b.h);
+        let b = Bounds::new(b.x, b.y, b.w - input_w, b.h);
         let mut draw_frame = Frame::new(b.x, b.y, b.w, b.h, "");
         let mut slider = HorFillSlider::new(b.x, b.y, b.w, b.h, "");
         draw_frame.set_frame(FrameType::FlatBox);
         draw_frame.set_color(Color::BackGround);
 
-        slider.set_color(Palette::BG0_H);
+        slider.set_color(self.theme.bg0_h);
         slider.set_selection_color(color);
         slider.set_frame(app::frame_type());
 
@@ -968,40 +3194,99 @@ impl Gui {
                 *v.bounds.end() as f64,
                 0,
             ),
-            ValType::Float(ref v) => (
-                (v.val as f64 * 100.).round() / 100.,
-                (*v.bounds.start() as f64 * 100.).ceil() / 100.,
-                (*v.bounds.end() as f64 * 100.).floor() / 100.,
-                2,
-            ),
+            ValType::Float(ref v) => {
+                let precision = slider_precision(cfg_key, v);
+                (
+                    round_slider_val(v.val as f64, precision, (f64::MIN, f64::MAX)),
+                    *v.bounds.start() as f64,
+                    *v.bounds.end() as f64,
+                    precision,
+                )
+            }
             _ => panic!("Creating config slider from unbounded value"),
         };
 
         slider.set_precision(precision);
-        slider.set_bounds(bounds_start, bounds_end); // -1 since bounds are inclusive
+        slider.set_bounds(bounds_start, bounds_end);
         slider.set_value(cfg_val);
 
+        let mut sync_input = match val_type {
+            ValType::Unsigned(_) => self.create_slider_input(
+                IntInput::new(input_b.x, input_b.y, input_b.w, input_b.h, ""),
+                &slider,
+                cfg_key,
+                precision,
+            ),
+            _ => self.create_slider_input(
+                FloatInput::new(input_b.x, input_b.y, input_b.w, input_b.h, ""),
+                &slider,
+                cfg_key,
+                precision,
+            ),
+        };
+
         const LABEL_SIZE_SCALAR: f32 = 0.3;
-        let mut label_frame = Frame::new(b.x, b.y, b.w, b.h, "")
-            .with_label(format!("{}: {}", label, cfg_val).as_str());
+        let mut label_frame = Frame::new(b.x, b.y, b.w, b.h, "").with_label(
+            format!(
+                "{}: {}",
+                label,
+                slider_label_val(cfg_key, cfg_val, precision, (bounds_start, bounds_end))
+            )
+            .as_str(),
+        );
         label_frame.set_label_font(Font::Courier);
 
+        // unsaved values get highlighted until the next save
+        let mut dirty_label = label_frame.clone();
+        let dirty_color = self.theme.yellow;
+        self.dirty_tracker.register(Some(cfg_key), move |dirty| {
+            dirty_label.set_label_color(if dirty { dirty_color } else { Color::ForeGround });
+            dirty_label.redraw_label();
+        });
+
+        // bounds are read from the slider itself, refreshes can change them
         slider.draw(move |slider| {
-            label_frame.set_label(format!("{}: {}", label, slider.value()).as_str());
+            let val_str = slider_label_val(
+                cfg_key,
+                slider.value(),
+                precision,
+                (slider.minimum(), slider.maximum()),
+            );
+            label_frame.set_label(format!("{}: {}", label, val_str).as_str());
             label_frame.redraw_label();
             draw_frame.redraw();
+            // follows drags & refreshes
+            sync_input(slider);
+        });
+
+        let mut refresh_slider = slider.clone();
+        self.refresher.register(cfg_key, move |cfg| {
+            let (val, start, end) = match cfg.get(cfg_key) {
+                ValType::Unsigned(v) => {
+                    (v.val as f64, *v.bounds.start() as f64, *v.bounds.end() as f64)
+                }
+                ValType::Float(v) => {
+                    (v.val as f64, *v.bounds.start() as f64, *v.bounds.end() as f64)
+                }
+                _ => panic!(),
+            };
+            refresh_slider.set_bounds(start, end);
+            refresh_slider.set_value(round_slider_val(val, precision, (start, end)));
+            refresh_slider.do_callback(); // updates the crop box for the crop sliders
+            refresh_slider.redraw();
         });
 
         let config = self.config.clone();
+        let refresher = self.refresher.clone();
         slider.handle(move |slider, ev| match ev {
-            Event::Released => {
+            Event::Released if !refresher.is_refreshing() => {
+                // committing the same rounded value that the label displays
+                let rounded =
+                    round_slider_val(slider.value(), precision, (slider.minimum(), slider.maximum()));
+                slider.set_value(rounded);
                 let val = match val_type {
-                    ValType::Unsigned(_) => {
-                        ValType::Unsigned(Bounded::new(slider.value() as u32, 0..=0))
-                    }
-                    ValType::Float(_) => {
-                        ValType::Float(Bounded::new(slider.value() as f32, 0.0..=0.0))
-                    }
+                    ValType::Unsigned(_) => ValType::Unsigned(Bounded::new(rounded as u32, 0..=0)),
+                    ValType::Float(_) => ValType::Float(Bounded::new(rounded as f32, 0.0..=0.0)),
                     _ => panic!(),
                 };
 
@@ -1012,21 +3297,326 @@ impl Gui {
         });
         slider
     }
+
+    // Shows the slider's value and commits typed ones on enter or focus out, clamped to the slider's bounds.
+    //     Returns what keeps it in sync with the slider, text that's being edited is left alone
+    fn create_slider_input<I: InputExt + Clone + 'static>(
+        &self,
+        mut input: I,
+        slider: &HorFillSlider,
+        cfg_key: CfgKey,
+        precision: i32,
+    ) -> Box<dyn FnMut(&HorFillSlider)> {
+        input.set_frame(app::frame_type());
+        input.set_color(self.theme.bg0_h);
+        input.set_text_color(Color::ForeGround);
+        input.set_text_font(Font::Courier);
+        input.set_text_size(clamp(input.h() / 2, 1, 12));
+        input.set_tooltip("Exact value, enter to apply");
+        input.set_trigger(CallbackTrigger::EnterKey | CallbackTrigger::Release);
+
+        let bounds = |slider: &HorFillSlider| (slider.minimum(), slider.maximum());
+        let text = move |slider: &HorFillSlider| {
+            format_slider_val(slider.value(), precision, bounds(slider))
+        };
+        input.set_value(&text(slider));
+
+        let config = self.config.clone();
+        let refresher = self.refresher.clone();
+        let mut input_slider = slider.clone();
+        input.set_callback(move |input| {
+            if refresher.is_refreshing() {
+                return;
+            }
+            let typed = input.value();
+            let val = match typed.trim().parse::<f64>().ok().filter(|v| v.is_finite()) {
+                Some(val) => round_slider_val(val, precision, bounds(&input_slider)),
+                None => {
+                    log_err!("Invalid {} value '{}'", cfg_key.as_string(), typed);
+                    input.set_value(&text(&input_slider));
+                    return;
+                }
+            };
+            let new_val = match config.read().unwrap().get(cfg_key) {
+                ValType::Unsigned(_) => ValType::Unsigned(Bounded::new(val as u32, 0..=0)),
+                _ => ValType::Float(Bounded::new(val as f32, 0.0..=0.0)),
+            };
+            if let Err(e) = config.write().unwrap().set_val(cfg_key, new_val) {
+                log_err!("Error setting {}:\n\t{}", cfg_key.as_string(), e);
+                input.set_value(&text(&input_slider));
+                return;
+            }
+            input_slider.set_value(val);
+            input_slider.do_callback(); // the crop box follows the crop sliders
+            input_slider.redraw();
+        });
+
+        Box::new(move |slider| {
+            if !input.has_focus() {
+                input.set_value(&text(slider));
+            }
+        })
+    }
+}
+
+fn save_config(config: &RwLock<Config>, cfg_path: &str, dirty_tracker: &DirtyTracker) {
+    let abs_cfg_path = match std::path::Path::new(cfg_path).canonicalize() {
+        Ok(abs_path) => abs_path.to_string_lossy().into_owned().split_off(4), // Removing windows extended path prefix
+        Err(_) => cfg_path.to_string(),
+    };
+    match config.write().unwrap().write_to_file(cfg_path) {
+        Ok(_) => {
+            log!("Saved config to {}", abs_cfg_path);
+            dirty_tracker.mark_saved();
+        }
+        Err(e) => log_err!("Error saving config to {}:\n\t{}", abs_cfg_path, e),
+    }
+}
+
+fn power_label(running: bool) -> &'static str {
+    match running {
+        true => "Stop bot",
+        false => "Start bot",
+    }
+}
+
+fn first_listed_color(cfg: &Config, cfg_key: CfgKey) -> image::Color<u8> {
+    let colors: Vec<image::Color<u8>> = cfg.get(cfg_key).into();
+    colors[0]
+}
+
+fn color_field_text(field: usize, c: image::Color<u8>) -> String {
+    match field {
+        0 => c.r.to_string(),
+        1 => c.g.to_string(),
+        2 => c.b.to_string(),
+        _ => format!("#{:02X}{:02X}{:02X}", c.r, c.g, c.b),
+    }
+}
+
+// "#RRGGBB", the # is optional
+fn parse_hex_color(text: &str) -> Option<image::Color<u8>> {
+    let hex = text.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    Some(image::Color::new(
+        (rgb >> 16) as u8,
+        (rgb >> 8) as u8,
+        rgb as u8,
+        255,
+    ))
+}
+
+// runs next to the bot, results show up in the terminal
+fn spawn_self_test() {
+    std::thread::spawn(|| {
+        log!("Running self test...");
+        match self_test::run() {
+            true => log!("Self test passed"),
+            false => log_err!("Self test failed"),
+        }
+    });
+}
+
+// The physical key only gets stored when autoclick is read by scan code, so the file stays clean otherwise
+fn record_autoclick_scan_code(config: &RwLock<Config>, keycode: u16) {
+    let mut cfg = config.write().unwrap();
+    let source: Choice = cfg.get(CfgKey::AutoclickKeySource).into();
+    if source.as_str() != "scan_code" {
+        return;
+    }
+    let scan_code = keycode_to_scancode(keycode);
+    if scan_code == 0 {
+        log_err!("That key has no scan code, autoclick will use its virtual key");
+    }
+    cfg.set_val(
+        CfgKey::AutoclickScanCode,
+        ValType::Unsigned(Bounded::new(scan_code as u32, 0..=0)),
+    )
+    .unwrap();
+    log!("Recorded scan code {:#x} for autoclick", scan_code);
+}
+
+// Popup showing the image letterboxed like the mirror, clicking a pixel writes its color to target_color.
+//     Closing the window without clicking leaves the config alone
+fn show_color_picker(
+    img: image::Image<Vec<u8>, Rgba8>,
+    config: Arc<RwLock<Config>>,
+    refresher: ConfigRefresher,
+    bg_color: Color,
+) {
+    const WIN_W: i32 = 800;
+    const WIN_H: i32 = 600;
+
+    let mut win = Window::default()
+        .with_size(WIN_W, WIN_H)
+        .with_label("Click the target color");
+    let mut frame = Frame::new(0, 0, WIN_W, WIN_H, "");
+    win.end();
+    win.make_modal(true);
+
+    // large images only get downscaled for display, sampling happens at native resolution
+    let mut display_img = image::zeroed::<Rgba8>(WIN_W as usize, WIN_H as usize);
+    display_img.fill_color(bg_color.to_internal());
+    match img.scale_keep_aspect(WIN_W as usize, WIN_H as usize) {
+        Some(scaled) => display_img.layer_image_over(&scaled),
+        None => display_img.layer_image_over(&img._clone()),
+    }
+    draw::draw_rgba(&mut frame, display_img.as_slice()).unwrap();
+
+    // first click picks the target color, the optional second one a background pixel for the threshold assist
+    let mut popup = win.clone();
+    let mut picked_target = None;
+    frame.handle(move |f, ev| match ev {
+        Event::Push => {
+            let click = ViewPos::new(app::event_x() - f.x(), app::event_y() - f.y());
+            if let Some(pos) = click.unletterbox((f.w() as usize, f.h() as usize), (img.w, img.h)) {
+                let color = img.get_pixel2d(*pos).as_color();
+                match picked_target {
+                    None => {
+                        config
+                            .write()
+                            .unwrap()
+                            .set_first_color(CfgKey::TargetColor, color)
+                            .unwrap();
+                        refresher.refresh(&[CfgKey::TargetColor]);
+                        log!("Picked target color {}, {}, {}", color.r, color.g, color.b);
+                        picked_target = Some(color);
+                        popup.set_label("Click a background pixel next to it for a threshold suggestion (Esc to skip)");
+                    }
+                    Some(target) => {
+                        popup.hide();
+                        suggest_color_thresh(target, color, &config, &refresher);
+                    }
+                }
+            }
+            true
+        }
+        _ => false,
+    });
+    win.show();
+}
+
+fn suggest_color_thresh(
+    target: image::Color<u8>,
+    background: image::Color<u8>,
+    config: &Arc<RwLock<Config>>,
+    refresher: &ConfigRefresher,
+) {
+    let current: Bounded<f32> =
+        <ValType as Into<Bounded<_>>>::into(config.read().unwrap().get(CfgKey::ColorThresh));
+    let thresh = match suggest_thresh(target, target, background, &current.bounds) {
+        Some(thresh) => thresh,
+        None => {
+            log_err!(
+                "The background pixel matches the target color, no threshold can tell them apart"
+            );
+            return;
+        }
+    };
+    log!(
+        "Suggested {}: {:.3} (currently {:.3})",
+        CfgKey::ColorThresh.as_string(),
+        thresh,
+        current.val
+    );
+
+    let msg = format!(
+        "Suggested {}: {:.3}\ncurrently {:.3}",
+        CfgKey::ColorThresh.as_string(),
+        thresh,
+        current.val
+    );
+    if dialog::choice2_default(&msg, "Dismiss", "Apply", "") == Some(1) {
+        let res = config
+            .write()
+            .unwrap()
+            .set_val(CfgKey::ColorThresh, ValType::Float(Bounded::new(thresh, 0.0..=0.0)));
+        match res {
+            Ok(_) => {
+                refresher.refresh(&[CfgKey::ColorThresh]);
+                log!("Applied {} = {:.3}", CfgKey::ColorThresh.as_string(), thresh);
+            }
+            Err(e) => log_err!("Couldn't apply the suggested threshold: {}", e),
+        }
+    }
 }
 
 fn draw_image_overlay(
     img: &mut image::Image<Vec<u8>, Bgra8>,
-    aim_coord: Coord<usize>,
-    coord_cluster: Vec<Coord<usize>>,
+    aim_coord: MirrorPos,
+    aim_point: MirrorPos, // the line follows where the mouse is actually sent, smoothing lags it behind the target
+    predicted_point: Option<MirrorPos>,
+    coord_cluster: Vec<MirrorPos>,
+    options: &OverlayOptions,
+    theme: &Theme,
 ) {
-    let (x, y, w, h) = Coord::bbox_xywh(&coord_cluster[..]);
-    let img_center = Coord::new(img.w / 2, img.h / 2);
-    img.draw_bbox(Coord::new(x, y), w, h, Palette::GREEN.to_internal());
-    img.draw_crosshair(img_center, 10, Palette::YELLOW.to_internal());
-    if img_center.square_dist(aim_coord) > 4 {
-        img.draw_crosshair(aim_coord, 10, Palette::RED.to_internal());
-        img.draw_line(img_center, aim_coord, Palette::AQUA.to_internal());
+    let (aim_coord, aim_point) = (*aim_coord, *aim_point);
+    let (x, y, w, h) = MirrorPos::bbox_xywh(&coord_cluster);
+    let img_center = coord::center_pixel((img.w, img.h));
+    if options.bbox {
+        img.draw_bbox(Coord::new(x, y), w, h, theme.green.to_internal());
+    }
+    if options.center_crosshair {
+        img.draw_crosshair(img_center, 10, theme.yellow.to_internal());
+    }
+    if options.aim_crosshair && img_center.square_dist(aim_coord) > 4 {
+        img.draw_crosshair(aim_coord, 10, theme.red.to_internal());
+    }
+    // the lead, next to the actual target it's ahead of
+    if let Some(predicted) = predicted_point.filter(|p| options.aim_crosshair && **p != aim_coord) {
+        img.draw_crosshair(*predicted, 6, theme.blue.to_internal());
+    }
+    if options.aim_line && img_center.square_dist(aim_point) > 4 {
+        img.draw_line(img_center, aim_point, theme.aqua.to_internal());
+    }
+}
+
+// Buckets the intervals between their min & max and draws each bucket's count as a block character
+fn interval_histogram(intervals: &[Duration], n_buckets: usize) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let (min, max) = match (intervals.iter().min(), intervals.iter().max()) {
+        (Some(&min), Some(&max)) => (min.as_secs_f32(), max.as_secs_f32()),
+        _ => return String::new(),
+    };
+    let range = (max - min).max(f32::EPSILON);
+
+    let mut buckets = vec![0u32; n_buckets];
+    for interval in intervals {
+        let t = (interval.as_secs_f32() - min) / range;
+        buckets[((t * n_buckets as f32) as usize).min(n_buckets - 1)] += 1;
     }
+    let max_count = *buckets.iter().max().unwrap() as f32;
+    buckets
+        .iter()
+        .map(|&count| BLOCKS[((count as f32 / max_count) * (BLOCKS.len() - 1) as f32) as usize])
+        .collect()
+}
+
+// Plain yellow bbox, for a cluster that still needs to pass the outline check
+fn draw_pending_overlay(
+    img: &mut image::Image<Vec<u8>, Bgra8>,
+    coord_cluster: Vec<MirrorPos>,
+    theme: &Theme,
+) {
+    let (x, y, w, h) = MirrorPos::bbox_xywh(&coord_cluster);
+    img.draw_bbox(Coord::new(x, y), w, h, theme.yellow.to_internal());
+}
+
+// Crossed out bbox for clusters the aspect ratio filter threw out
+fn draw_rejected_overlay(
+    img: &mut image::Image<Vec<u8>, Bgra8>,
+    coord_cluster: Vec<MirrorPos>,
+    theme: &Theme,
+) {
+    let (x, y, w, h) = MirrorPos::bbox_xywh(&coord_cluster);
+    let (x2, y2) = (x + w, y + h);
+    img.draw_bbox(Coord::new(x, y), w, h, theme.gray.to_internal());
+    img.draw_line(Coord::new(x, y), Coord::new(x2, y2), theme.red.to_internal());
+    img.draw_line(Coord::new(x2, y), Coord::new(x, y2), theme.red.to_internal());
 }
 
 fn clamp<T>(val: T, min: T, max: T) -> T
@@ -1042,6 +3632,61 @@ where
     }
 }
 
+// Enough decimals that both bounds & the starting value can be set exactly, so releasing the slider never
+//     rewrites a value it's showing. Crop percentages get at least 3, a whole percent of the screen is a big jump
+fn slider_precision(cfg_key: CfgKey, v: &Bounded<f32>) -> i32 {
+    let min_precision = match cfg_key {
+        CfgKey::CropWPercent | CfgKey::CropHPercent => 3,
+        _ => 2,
+    };
+    [*v.bounds.start(), *v.bounds.end(), v.val]
+        .iter()
+        .map(|&x| decimals(x))
+        .fold(min_precision, i32::max)
+}
+
+// Fewest decimals that still give back `val` as an f32, capped since a slider can't be dragged any finer
+fn decimals(val: f32) -> i32 {
+    const MAX_DECIMALS: i32 = 4;
+    (0..MAX_DECIMALS)
+        .find(|&precision| {
+            let scale = 10f64.powi(precision);
+            ((val as f64 * scale).round() / scale) as f32 == val
+        })
+        .unwrap_or(MAX_DECIMALS)
+}
+
+// Rounds a slider value to its displayed precision and clamps it into the slider bounds
+fn round_slider_val(val: f64, precision: i32, bounds: (f64, f64)) -> f64 {
+    let scale = 10f64.powi(precision);
+    clamp((val * scale).round() / scale, bounds.0, bounds.1)
+}
+
+fn format_slider_val(val: f64, precision: i32, bounds: (f64, f64)) -> String {
+    format!(
+        "{:.*}",
+        precision as usize,
+        round_slider_val(val, precision, bounds)
+    )
+}
+
+// Length of the searched region along one axis, in pixels of the primary display
+fn crop_region_len(crop_slider: &HorFillSlider, screen_len: u32) -> i32 {
+    let crop = config::crop_px(crop_slider.value() as f32, screen_len);
+    screen_len as i32 - (crop as i32 * 2)
+}
+
+// Crop values are fractions of the screen, shown as a percentage cut off each side
+fn slider_label_val(cfg_key: CfgKey, val: f64, precision: i32, bounds: (f64, f64)) -> String {
+    match cfg_key {
+        CfgKey::CropWPercent | CfgKey::CropHPercent => {
+            let val = round_slider_val(val, precision, bounds);
+            format!("{:.1}%", val * 100.)
+        }
+        _ => format_slider_val(val, precision, bounds),
+    }
+}
+
 fn wrap_str_inplace(wrap: &mut [u8], line_w: usize) {
     wrap.split_mut(|c| *c == b'\n').for_each(|substr| {
         let mut last_space_idx = None;
@@ -1064,3 +3709,276 @@ fn unique_event_id() -> i32 {
     static EVENT_ID: AtomicI32 = AtomicI32::new(100);
     EVENT_ID.fetch_add(1, Ordering::Relaxed)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn rolling_avg_warm_up() {
+        let mut avg = RollingAvg::new(8);
+        assert_eq!(avg.avg(), None);
+        avg.push(ms(10));
+        assert_eq!(avg.avg(), Some(ms(10)));
+        avg.push(ms(20));
+        avg.push(ms(30));
+        // 3 samples in, the 5 slots that haven't been written yet don't drag it down
+        assert_eq!(avg.avg(), Some(ms(20)));
+    }
+
+    #[test]
+    fn rolling_avg_full_window() {
+        let mut avg = RollingAvg::new(2);
+        [10, 20, 60].into_iter().for_each(|n| avg.push(ms(n)));
+        assert_eq!(avg.avg(), Some(ms(40)));
+        avg.set_size(1);
+        assert_eq!(avg.avg(), Some(ms(60)));
+    }
+
+    #[test]
+    fn frame_time_zero_avg() {
+        let label = DisplayUnits::Ms.frame_time(Duration::ZERO);
+        assert!(!label.contains("inf"), "{}", label);
+        assert_eq!(label, "Frame time: -- | FPS: --");
+    }
+
+    #[test]
+    fn display_units_duration() {
+        let quarter_ms = Duration::from_micros(250);
+        assert_eq!(DisplayUnits::Auto.duration(quarter_ms), "250us");
+        assert_eq!(DisplayUnits::Ms.duration(quarter_ms), "0.25ms");
+        assert_eq!(DisplayUnits::Us.duration(quarter_ms), "250us");
+        for d in [ms(0), quarter_ms, ms(5), ms(50), Duration::from_nanos(640)] {
+            assert_eq!(
+                DisplayUnits::FpsPriority.duration(d),
+                DisplayUnits::Auto.duration(d)
+            );
+        }
+    }
+
+    #[test]
+    fn display_units_auto_rollover() {
+        let auto = |nanos: u64| DisplayUnits::Auto.duration(Duration::from_nanos(nanos));
+        assert_eq!(auto(640), "640ns");
+        assert_eq!(auto(999_499), "999us");
+        // would print as "1000us"
+        assert_eq!(auto(999_500), "1.00ms");
+        assert_eq!(auto(5_000_000), "5.00ms");
+        assert_eq!(auto(50_000_000), "50.0ms");
+        assert_eq!(auto(999_500_000), "1.00s");
+    }
+
+    #[test]
+    fn frame_time_order() {
+        assert_eq!(
+            DisplayUnits::Auto.frame_time(ms(4)),
+            "Frame time: 4.00ms | FPS: 250"
+        );
+        assert_eq!(
+            DisplayUnits::FpsPriority.frame_time(ms(4)),
+            "FPS: 250 | Frame time: 4.00ms"
+        );
+    }
+
+    #[test]
+    fn slider_val_float() {
+        // mid drag values land between steps, the label has to show what the release stores
+        assert_eq!(round_slider_val(0.8300000000000001, 2, (0., 1.)), 0.83);
+        assert_eq!(format_slider_val(0.8300000000000001, 2, (0., 1.)), "0.83");
+        assert_eq!(format_slider_val(1.004, 2, (0., 1.)), "1.00");
+        assert_eq!(format_slider_val(-0.2, 2, (0., 1.)), "0.00");
+    }
+
+    #[test]
+    fn slider_val_unsigned() {
+        assert_eq!(format_slider_val(41.6, 0, (1., 240.)), "42");
+        assert_eq!(format_slider_val(250.2, 0, (1., 240.)), "240");
+        assert_eq!(round_slider_val(0.4, 0, (1., 240.)), 1.);
+    }
+
+    #[test]
+    fn slider_bounds_round_trip() {
+        // what a float slider gets set to & hands back on release has to be the exact f32 from the config,
+        //     at both ends & at the default
+        for key in CfgKey::iter() {
+            let v: Bounded<f32> = match key.default_val() {
+                ValType::Float(v) => v,
+                _ => continue,
+            };
+            let precision = slider_precision(key, &v);
+            let bounds = (*v.bounds.start() as f64, *v.bounds.end() as f64);
+            for val in [*v.bounds.start(), *v.bounds.end(), v.val] {
+                let released = round_slider_val(val as f64, precision, bounds) as f32;
+                assert_eq!(
+                    released.to_bits(),
+                    val.to_bits(),
+                    "{} = {} came back as {} at {} decimals",
+                    key.as_string(),
+                    val,
+                    released,
+                    precision
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn slider_precision_fits_bounds() {
+        let thresh = Bounded::new(0.83, 0.001..=0.999);
+        assert_eq!(slider_precision(CfgKey::ColorThresh, &thresh), 3);
+        let crop = Bounded::new(0.25, 0.0..=0.5);
+        assert_eq!(slider_precision(CfgKey::CropWPercent, &crop), 3);
+        // past 4 decimals the slider can't be dragged any finer anyway
+        let fine = Bounded::new(0.123456, 0.0..=1.0);
+        assert_eq!(slider_precision(CfgKey::SmoothingFactor, &fine), 4);
+    }
+
+    fn timings(capture: u64, convert: u64, process: u64) -> pixel_bot::Timings {
+        pixel_bot::Timings {
+            capture: ms(capture),
+            convert: ms(convert),
+            process: ms(process),
+        }
+    }
+
+    // 1 / 60 fps, shown as 16.67ms
+    const BUDGET_60: Duration = Duration::from_micros(16_667);
+
+    #[test]
+    fn budget_suggestion_detection() {
+        let msg = budget_suggestion(&timings(5, 0, 20), BUDGET_60);
+        assert_eq!(
+            msg,
+            format!(
+                "A frame takes 25.00ms on average, but 60 fps only leaves 16.67ms.\n\t\
+                 Detection is the slowest stage (20.00ms), raise {} or crop the search region down",
+                CfgKey::DetectDownscale.as_string()
+            )
+        );
+    }
+
+    #[test]
+    fn budget_suggestion_convert() {
+        // 15 of the 20ms capture is conversion, more than the grab itself or detection
+        let msg = budget_suggestion(&timings(20, 15, 3), BUDGET_60);
+        assert!(
+            msg.starts_with("A frame takes 23.00ms on average"),
+            "{}",
+            msg
+        );
+        assert!(
+            msg.contains("\n\tConverting captured frames to BGRA8 is the slowest stage (15.00ms)"),
+            "{}",
+            msg
+        );
+    }
+
+    #[test]
+    fn budget_suggestion_capture() {
+        // conversion gets taken out of capture, 18ms of grabbing is still the biggest
+        let msg = budget_suggestion(&timings(20, 2, 5), BUDGET_60);
+        assert!(
+            msg.contains("\n\tCapture is the slowest stage (18.00ms)"),
+            "{}",
+            msg
+        );
+        assert!(msg.ends_with(&format!("lower {} to match", CfgKey::Fps.as_string())));
+    }
+
+    #[test]
+    fn budget_watch_waits_out_grace() {
+        let mut watch = BudgetWatch::new();
+        let start = Instant::now();
+        let over = timings(10, 0, 10);
+        // 20ms against 16.67, only warns once it's been over for the whole grace period
+        assert_eq!(watch.update(&over, BUDGET_60, start), None);
+        assert_eq!(
+            watch.update(&over, BUDGET_60, start + BUDGET_GRACE - ms(1)),
+            None
+        );
+        let msg = watch.update(&over, BUDGET_60, start + BUDGET_GRACE);
+        assert_eq!(msg, Some(budget_suggestion(&over, BUDGET_60)));
+        // once per budget
+        assert_eq!(
+            watch.update(&over, BUDGET_60, start + BUDGET_GRACE * 2),
+            None
+        );
+    }
+
+    #[test]
+    fn budget_watch_exactly_on_budget() {
+        let mut watch = BudgetWatch::new();
+        let start = Instant::now();
+        let budget = ms(20);
+        for secs in 0..10 {
+            let now = start + Duration::from_secs(secs);
+            assert_eq!(watch.update(&timings(12, 0, 8), budget, now), None);
+        }
+    }
+
+    #[test]
+    fn budget_watch_uses_the_mean() {
+        let mut watch = BudgetWatch::new();
+        let start = Instant::now();
+        let budget = ms(20);
+        // a lone slow frame among fast ones keeps the mean under budget
+        for i in 0..BUDGET_WATCH_SAMPLES as u64 - 1 {
+            assert_eq!(watch.update(&timings(5, 0, 5), budget, start + ms(i)), None);
+        }
+        let later = start + BUDGET_GRACE * 2;
+        assert_eq!(watch.update(&timings(100, 0, 100), budget, later), None);
+    }
+
+    #[test]
+    fn budget_watch_dropping_under_restarts_grace() {
+        let mut watch = BudgetWatch::new();
+        let start = Instant::now();
+        let budget = ms(20);
+        assert_eq!(watch.update(&timings(15, 0, 15), budget, start), None);
+        // enough fast frames to pull the mean back under, then over again from 2s in
+        for i in 0..BUDGET_WATCH_SAMPLES as u64 {
+            watch.update(&timings(1, 0, 1), budget, start + ms(i));
+        }
+        let restart = start + Duration::from_secs(2);
+        for i in 0..BUDGET_WATCH_SAMPLES as u64 {
+            watch.update(&timings(15, 0, 15), budget, restart + ms(i));
+        }
+        // would be past the grace counted from the first overrun, not from the second
+        assert_eq!(
+            watch.update(&timings(15, 0, 15), budget, start + BUDGET_GRACE),
+            None
+        );
+        // it went back over somewhere in those last samples
+        let warn_at = restart + ms(BUDGET_WATCH_SAMPLES as u64) + BUDGET_GRACE;
+        assert!(watch.update(&timings(15, 0, 15), budget, warn_at).is_some());
+    }
+
+    #[test]
+    fn budget_watch_new_budget_warns_again() {
+        let mut watch = BudgetWatch::new();
+        let start = Instant::now();
+        let over = timings(10, 0, 10);
+        watch.update(&over, BUDGET_60, start);
+        assert!(watch
+            .update(&over, BUDGET_60, start + BUDGET_GRACE)
+            .is_some());
+
+        // stopping & starting again doesn't repeat the same warning
+        watch.reset();
+        watch.update(&over, BUDGET_60, start + BUDGET_GRACE * 2);
+        assert_eq!(
+            watch.update(&over, BUDGET_60, start + BUDGET_GRACE * 3),
+            None
+        );
+
+        // a different fps gets its own, right away since it's been over for longer than the grace already
+        let budget_75 = Duration::from_micros(13_333);
+        assert!(watch
+            .update(&over, budget_75, start + BUDGET_GRACE * 4)
+            .is_some());
+    }
+}
@@ -9,16 +9,14 @@ pub enum BlendType {
 }
 
 // https://en.wikipedia.org/wiki/Alpha_compositing#Description
+// Rounds to nearest the same way the avx version does, so both paths give identical pixels
 pub fn over(fg: Color<u8>, bg: Color<u8>) -> Color<u8> {
-    let alpha = fg.a as u16 + 1;
-    let inv_alpha = 256 - fg.a as u16;
-
-    Color::new(
-        ((alpha * fg.r as u16 + inv_alpha * bg.r as u16) >> 8) as u8,
-        ((alpha * fg.g as u16 + inv_alpha * bg.g as u16) >> 8) as u8,
-        ((alpha * fg.b as u16 + inv_alpha * bg.b as u16) >> 8) as u8,
-        255,
-    )
+    let alpha = fg.a as u16;
+    let inv_alpha = 255 - alpha;
+    let mix =
+        |fg_v: u8, bg_v: u8| ((alpha * fg_v as u16 + inv_alpha * bg_v as u16 + 127) / 255) as u8;
+
+    Color::new(mix(fg.r, bg.r), mix(fg.g, bg.g), mix(fg.b, bg.b), 255)
 }
 
 pub fn under(fg_px: Color<u8>, bg_px: Color<u8>) -> Color<u8> {
@@ -65,8 +63,9 @@ pub unsafe fn avx_blend_over(pixels_fg: *const u8, pixels_bg: *const u8, dst: *m
     ret_lo = _mm256_srli_epi16(_mm256_mulhi_epu16(ret_lo, _mm256_set1_epi16(0x8081)), 7);
     ret_hi = _mm256_srli_epi16(_mm256_mulhi_epu16(ret_hi, _mm256_set1_epi16(0x8081)), 7);
 
-    // repack and store
+    // repack, the output is opaque like the scalar version's
     let ret = _mm256_packus_epi16(ret_lo, ret_hi);
+    let ret = _mm256_or_si256(ret, _mm256_set1_epi32(0xff000000));
     _mm256_storeu_si256(dst as *mut _, ret);
 }
 
@@ -0,0 +1,178 @@
+use crate::coord::Coord;
+use crate::image::{self, Color, Image, Pixel, Subpixel};
+
+use std::fs::File;
+use std::io::{self, prelude::*, BufReader, BufWriter, ErrorKind};
+use std::ops::Deref;
+use std::path::Path;
+
+// Uncompressed 32-bit BMPs only, enough for dumping frames & checking them against known good images
+const FILE_HEADER_LEN: u32 = 14;
+const INFO_HEADER_LEN: u32 = 40;
+
+impl<T, S> Image<T, S>
+where
+    T: Deref<Target = [u8]>,
+    S: Subpixel<Inner = u8>,
+{
+    pub fn save_bmp<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let data_len = (self.w * self.h * 4) as u32;
+        let data_offset = FILE_HEADER_LEN + INFO_HEADER_LEN;
+
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(b"BM")?;
+        out.write_all(&(data_offset + data_len).to_le_bytes())?;
+        out.write_all(&[0; 4])?; // reserved
+        out.write_all(&data_offset.to_le_bytes())?;
+
+        out.write_all(&INFO_HEADER_LEN.to_le_bytes())?;
+        out.write_all(&(self.w as i32).to_le_bytes())?;
+        out.write_all(&(-(self.h as i32)).to_le_bytes())?; // negative height means top-down rows
+        out.write_all(&1u16.to_le_bytes())?; // planes
+        out.write_all(&32u16.to_le_bytes())?; // bits per pixel
+        out.write_all(&0u32.to_le_bytes())?; // BI_RGB, no compression
+        out.write_all(&data_len.to_le_bytes())?;
+        out.write_all(&[0; 16])?; // resolution & palette info
+
+        for px in self.pixels() {
+            let [r, g, b, a] = px.rgba();
+            out.write_all(&[b, g, r, a])?;
+        }
+        out.flush()
+    }
+}
+
+pub fn load_bmp<S, P>(path: P) -> io::Result<Image<Vec<u8>, S>>
+where
+    S: Subpixel<Inner = u8>,
+    P: AsRef<Path>,
+{
+    let invalid = |msg: &str| io::Error::new(ErrorKind::InvalidData, msg);
+
+    let mut bytes = Vec::new();
+    BufReader::new(File::open(path)?).read_to_end(&mut bytes)?;
+    if bytes.len() < (FILE_HEADER_LEN + INFO_HEADER_LEN) as usize || &bytes[0..2] != b"BM" {
+        return Err(invalid("Not a bmp file"));
+    }
+
+    let u32_at = |idx: usize| u32::from_le_bytes(bytes[idx..idx + 4].try_into().unwrap());
+    let data_offset = u32_at(10) as usize;
+    let w = u32_at(18) as i32;
+    let h = u32_at(22) as i32;
+    let bpp = u16::from_le_bytes(bytes[28..30].try_into().unwrap());
+    if bpp != 32 || u32_at(30) != 0 {
+        return Err(invalid("Only uncompressed 32-bit bmps are supported"));
+    }
+
+    if w <= 0 || h == 0 {
+        return Err(invalid("Image dimensions must be non-zero"));
+    }
+
+    // the header is untrusted, a huge w * h mustn't wrap around into a small valid-looking range
+    let (w, top_down, h) = (w as usize, h < 0, h.unsigned_abs() as usize);
+    let data_end = w
+        .checked_mul(h)
+        .and_then(|n| n.checked_mul(4))
+        .and_then(|len| data_offset.checked_add(len))
+        .ok_or_else(|| invalid("Image dimensions are too large"))?;
+    let data = bytes
+        .get(data_offset..data_end)
+        .ok_or_else(|| invalid("Pixel data is truncated"))?;
+
+    let mut img = image::zeroed::<S>(w, h);
+    for (row_idx, row) in data.chunks_exact(w * 4).enumerate() {
+        let y = if top_down { row_idx } else { h - 1 - row_idx };
+        for (x, bgra) in row.chunks_exact(4).enumerate() {
+            img.set2d(
+                Coord::new(x, y),
+                Color::new(bgra[2], bgra[1], bgra[0], bgra[3]),
+            );
+        }
+    }
+    Ok(img)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Rgba8;
+
+    // A 32-bit header claiming `w` x `h`, followed by `data_len` bytes of pixel data
+    fn write_bmp(name: &str, w: i32, h: i32, data_len: usize) -> std::path::PathBuf {
+        let data_offset = FILE_HEADER_LEN + INFO_HEADER_LEN;
+        let mut bytes = b"BM".to_vec();
+        bytes.extend_from_slice(&(data_offset + data_len as u32).to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&data_offset.to_le_bytes());
+        bytes.extend_from_slice(&INFO_HEADER_LEN.to_le_bytes());
+        bytes.extend_from_slice(&w.to_le_bytes());
+        bytes.extend_from_slice(&h.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&32u16.to_le_bytes());
+        bytes.extend_from_slice(&[0; 24]);
+        bytes.resize(bytes.len() + data_len, 0);
+
+        let path = std::env::temp_dir().join(format!("pixelbot_bmp_{}.bmp", name));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn load_err(name: &str, w: i32, h: i32, data_len: usize) -> ErrorKind {
+        let path = write_bmp(name, w, h, data_len);
+        let res = load_bmp::<Rgba8, _>(&path);
+        std::fs::remove_file(path).unwrap();
+        res.err().expect("bad header was accepted").kind()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut img = image::zeroed::<Rgba8>(3, 2);
+        img.set2d(Coord::new(2, 1), Color::new(10, 20, 30, 40));
+        let path = std::env::temp_dir().join("pixelbot_bmp_round_trip.bmp");
+        img.save_bmp(&path).unwrap();
+        let loaded = load_bmp::<Rgba8, _>(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(img.diff_count(&loaded), 0);
+    }
+
+    #[test]
+    fn bottom_up_rows() {
+        // positive height, the first row in the file is the bottom one
+        let path = write_bmp("bottom_up", 1, 2, 8);
+        let mut bytes = std::fs::read(&path).unwrap();
+        let data_start = bytes.len() - 8;
+        bytes[data_start..data_start + 4].copy_from_slice(&[30, 20, 10, 255]);
+        std::fs::write(&path, bytes).unwrap();
+        let img = load_bmp::<Rgba8, _>(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(
+            img.get_pixel2d(Coord::new(0, 1)).as_color(),
+            Color::new(10, 20, 30, 255)
+        );
+    }
+
+    #[test]
+    fn rejects_zero_dims() {
+        assert_eq!(load_err("zero_w", 0, 4, 0), ErrorKind::InvalidData);
+        assert_eq!(load_err("zero_h", 4, 0, 0), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_negative_width() {
+        assert_eq!(load_err("neg_w", -4, 4, 64), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_huge_dims() {
+        // w * h * 4 overflows on 32-bit usizes & runs past the data on 64-bit ones
+        assert_eq!(
+            load_err("huge", i32::MAX, i32::MIN, 16),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        assert_eq!(load_err("truncated", 4, 4, 60), ErrorKind::InvalidData);
+    }
+}
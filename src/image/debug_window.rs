@@ -0,0 +1,170 @@
+use crossbeam::channel::{self, Receiver, Sender, TrySendError};
+use minifb::{Window, WindowOptions};
+
+use super::{Image, Pixel, Subpixel};
+use std::ops::Deref;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+enum WindowMsg {
+    Frame { buf: Vec<u32>, w: usize, h: usize },
+    Close,
+}
+
+// Debug only, a minifb window living on its own thread that shows whatever image it was last fed.
+//     update() never blocks, if the window is still busy with the last frame it gets replaced
+pub struct DebugWindow {
+    sender: Sender<WindowMsg>,
+    receiver: Receiver<WindowMsg>, // kept to drop stale frames when the channel is full
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DebugWindow {
+    pub fn update<T, S>(&self, img: &Image<T, S>)
+    where
+        T: Deref<Target = [u8]>,
+        S: Subpixel<Inner = u8>,
+    {
+        let msg = WindowMsg::Frame {
+            buf: to_0rgb(img),
+            w: img.w,
+            h: img.h,
+        };
+        if let Err(TrySendError::Full(msg)) = self.sender.try_send(msg) {
+            let _ = self.receiver.try_recv();
+            let _ = self.sender.try_send(msg);
+        }
+    }
+
+    pub fn close(mut self) {
+        self.close_();
+    }
+
+    fn close_(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            // the window might be waiting on a full channel, so make room for the close message
+            let _ = self.receiver.try_recv();
+            let _ = self.sender.try_send(WindowMsg::Close);
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for DebugWindow {
+    fn drop(&mut self) {
+        self.close_();
+    }
+}
+
+impl<T, S> Image<T, S>
+where
+    T: Deref<Target = [u8]>,
+    S: Subpixel<Inner = u8>,
+{
+    // Opens a window showing a copy of the image and returns right away, feed it new frames with update()
+    pub fn show_nonblocking(&self, title: &str) -> Result<DebugWindow, &'static str> {
+        let (sender, receiver) = channel::bounded(1);
+        let (ready_tx, ready_rx) = channel::bounded(1);
+        let (w, h) = (self.w, self.h);
+        let title = title.to_string();
+        let frame_rx = receiver.clone();
+
+        // minifb windows have to be driven from the thread that created them
+        let handle = thread::spawn(move || {
+            let mut window = match Window::new(&title, w, h, WindowOptions::default()) {
+                Ok(window) => {
+                    let _ = ready_tx.send(Ok(()));
+                    window
+                }
+                Err(_) => {
+                    let _ = ready_tx.send(Err("Failed to open debug window"));
+                    return;
+                }
+            };
+            window.limit_update_rate(Some(FRAME_INTERVAL));
+
+            let mut frame = (vec![0; w * h], w, h);
+            while window.is_open() {
+                match frame_rx.try_recv() {
+                    Ok(WindowMsg::Frame { buf, w, h }) => frame = (buf, w, h),
+                    Ok(WindowMsg::Close) => break,
+                    Err(_) => (),
+                }
+                let (buf, w, h) = &frame;
+                if window.update_with_buffer(buf, *w, *h).is_err() {
+                    break;
+                }
+            }
+        });
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => (),
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Err("Debug window thread died before opening"),
+        }
+
+        let window = DebugWindow {
+            sender,
+            receiver,
+            handle: Some(handle),
+        };
+        window.update(self);
+        Ok(window)
+    }
+}
+
+// minifb wants 0RGB packed into a u32
+fn to_0rgb<T, S>(img: &Image<T, S>) -> Vec<u32>
+where
+    T: Deref<Target = [u8]>,
+    S: Subpixel<Inner = u8>,
+{
+    img.pixels()
+        .map(|px| {
+            let [r, g, b, _] = px.rgba();
+            (r as u32) << 16 | (g as u32) << 8 | b as u32
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{self, Color, Rgba8};
+
+    // minifb needs a display server on linux, headless ci boxes don't have one
+    fn has_display() -> bool {
+        cfg!(any(windows, target_os = "macos"))
+            || std::env::var_os("DISPLAY").is_some()
+            || std::env::var_os("WAYLAND_DISPLAY").is_some()
+    }
+
+    #[test]
+    fn open_update_close() {
+        if !has_display() {
+            eprintln!("No display available, skipping the debug window smoke test");
+            return;
+        }
+        let mut img = image::zeroed::<Rgba8>(64, 48);
+        let window = img.show_nonblocking("pixelbot debug window test").unwrap();
+        img.fill_color(Color::new(255, 0, 0, 255));
+        for _ in 0..5 {
+            window.update(&img);
+            thread::sleep(FRAME_INTERVAL);
+        }
+        // joins the window thread, a hang here means the close message never got through
+        window.close();
+    }
+
+    #[test]
+    fn packs_0rgb() {
+        let mut img = image::zeroed::<Rgba8>(2, 1);
+        img.set2d(
+            crate::coord::Coord::new(1, 0),
+            Color::new(0x12, 0x34, 0x56, 0x78),
+        );
+        assert_eq!(to_0rgb(&img), vec![0, 0x0012_3456]);
+    }
+}
@@ -1,13 +1,15 @@
 extern crate line_drawing;
 use crate::coord::Coord;
-use crate::image::blend::{avx_blend_over, avx_blend_under, over, under};
-use crate::image::{self, get_2d_idx, Color, Image, Pixel, PixelMut, Subpixel};
+use crate::image::blend::{avx_blend_over, avx_blend_under};
+use crate::image::{self, get_2d_idx, Color, Gray8, Image, Pixel, PixelMut, Subpixel};
 
 use rustc_hash::FxHashSet;
 use std::assert;
-use std::ops::{Deref, DerefMut, Index};
+use std::ops::{Deref, DerefMut, Index, RangeInclusive};
+use std::thread;
 
-pub use crate::image::blend::BlendType;
+// the scalar blends are public so the avx path can be checked against them
+pub use crate::image::blend::{over, under, BlendType};
 
 impl<T, S> Image<T, S>
 where
@@ -49,6 +51,13 @@ where
         }
 
         let mut out = image::zeroed(new_w, new_h);
+        self.scale_nearest_into(&mut out);
+        Some(out)
+    }
+
+    // Same as scale_nearest, but fills an image that's already the target size so its buffer gets reused
+    pub fn scale_nearest_into(&self, out: &mut Image<Vec<S::Inner>, S>) {
+        let (new_w, new_h) = (out.w, out.h);
         for x in 0..new_w {
             for y in 0..new_h {
                 let src_x =
@@ -62,7 +71,6 @@ where
                 );
             }
         }
-        Some(out)
     }
 
     pub fn scale_keep_aspect(&self, new_w: usize, new_h: usize) -> Option<Image<Vec<S::Inner>, S>> {
@@ -113,6 +121,35 @@ where
         }
     }
 
+    // Midpoint circle, the parts outside the image are skipped
+    pub fn draw_circle(&mut self, center: Coord<usize>, radius: usize, fill: Color<S::Inner>) {
+        let (cx, cy) = (center.x as i32, center.y as i32);
+        let (w, h) = (self.w as i32, self.h as i32);
+        let mut plot = |x: i32, y: i32| {
+            if (0..w).contains(&x) && (0..h).contains(&y) {
+                self.set2d(Coord::new(x as usize, y as usize), fill);
+            }
+        };
+
+        let (mut x, mut y) = (radius as i32, 0);
+        let mut err = 1 - x;
+        while x >= y {
+            // one point per octant
+            for (dx, dy) in [(x, y), (y, x)] {
+                for (sx, sy) in [(1, 1), (-1, 1), (1, -1), (-1, -1)] {
+                    plot(cx + sx * dx, cy + sy * dy);
+                }
+            }
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
     pub fn draw_grid(&mut self, step: u32, fill: Color<S::Inner>) {
         let img_w = self.w;
 
@@ -193,25 +230,25 @@ where
             BlendType::Over => over,
             BlendType::Under => under,
         };
-        if std::is_x86_feature_detected!("avx2") {
+        // the avx version's alpha shuffle assumes 4 subpixels with alpha last
+        if S::N_SUBPX == 4 && std::is_x86_feature_detected!("avx2") {
             let avx_blend_fn = match blend_type {
                 BlendType::Over => avx_blend_over,
                 BlendType::Under => avx_blend_under,
             };
 
             const STEP: usize = 32; // 32 subpixels (8 RGBA pixels) at a time; 8 * S::N_SUBPX
-            let buf_len = self.buf.len();
-
-            // making sure we don't write off the edge
-            let iter_len = STEP * (buf_len / STEP);
-            for idx in (0..iter_len).step_by(STEP) {
-                unsafe {
-                    avx_blend_fn(
-                        self.buf[idx..].as_ptr(),
-                        other_img.buf[idx..].as_ptr(),
-                        self.buf[idx..].as_mut_ptr(), // I think this is okay since the first pointer is never used after the inital load
-                    );
-                }
+
+            // chunks_exact only hands out full 32 byte chunks, so the loads & stores can't go off the edge
+            let iter_len = STEP * (self.buf.len() / STEP);
+            for (chunk, other_chunk) in self
+                .buf
+                .chunks_exact_mut(STEP)
+                .zip(other_img.buf.chunks_exact(STEP))
+            {
+                // one pointer for both reading & writing, the fg is fully loaded before the store
+                let dst = chunk.as_mut_ptr();
+                unsafe { avx_blend_fn(dst, other_chunk.as_ptr(), dst) };
             }
 
             // if there are pixels remaining, write them with the normal blend function
@@ -233,25 +270,189 @@ where
     }
 
     pub fn detect_color(&self, target: Color<S::Inner>, thresh: f32) -> FxHashSet<Coord<usize>> {
+        self.detect_colors(&[target], thresh)
+    }
+
+    // Pixels matching any of `targets`
+    pub fn detect_colors(
+        &self,
+        targets: &[Color<S::Inner>],
+        thresh: f32,
+    ) -> FxHashSet<Coord<usize>> {
         assert!(thresh > 0. && thresh < 1.);
 
-        FxHashSet::from_iter(
-            self.pixels()
-                .map(|px| 1. - color_distance(px.as_color(), target))
+        let n_threads = thread::available_parallelism().map_or(1, |n| n.get());
+        if self.w * self.h < PAR_DETECT_MIN_PIXELS || n_threads == 1 {
+            return FxHashSet::from_iter(matches_in_rows::<S>(
+                &self.buf, self.w, 0, targets, thresh,
+            ));
+        }
+        self.detect_colors_banded(targets, thresh, n_threads)
+    }
+
+    // each thread gets a band of whole rows, the first row of a band offsets its coords
+    fn detect_colors_banded(
+        &self,
+        targets: &[Color<S::Inner>],
+        thresh: f32,
+        n_threads: usize,
+    ) -> FxHashSet<Coord<usize>> {
+        let w = self.w;
+        let band_rows = band_rows(self.h, n_threads);
+        crossbeam::thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .buf
+                .chunks(band_rows * w * S::N_SUBPX)
                 .enumerate()
-                .filter_map(|(idx, dist)| {
-                    if dist > thresh {
-                        Some(get_2d_idx(self.w, idx))
-                    } else {
-                        None
+                .map(|(band_idx, band)| {
+                    scope.spawn(move |_| {
+                        matches_in_rows::<S>(band, w, band_idx * band_rows, targets, thresh)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        })
+        .unwrap()
+    }
+
+    // detect_colors limited to the w x h box at `pos`, the coords are still in the whole image
+    pub fn detect_colors_in(
+        &self,
+        targets: &[Color<S::Inner>],
+        thresh: f32,
+        pos: Coord<usize>,
+        w: usize,
+        h: usize,
+    ) -> FxHashSet<Coord<usize>> {
+        assert!(thresh > 0. && thresh < 1.);
+        assert!(
+            pos.x + w <= self.w && pos.y + h <= self.h,
+            "Search region out of bounds"
+        );
+
+        let cols = pos.x * S::N_SUBPX..(pos.x + w) * S::N_SUBPX;
+        self.rows()
+            .enumerate()
+            .skip(pos.y)
+            .take(h)
+            .flat_map(|(y, row)| {
+                matches_in_rows::<S>(&row[cols.clone()], w, 0, targets, thresh)
+                    .into_iter()
+                    .map(move |c| Coord::new(pos.x + c.x, y))
+            })
+            .collect()
+    }
+}
+
+impl<T, S> Image<T, S>
+where
+    T: Deref<Target = [S::Inner]>,
+    S: Subpixel<Inner = u8>,
+{
+    // Averages each `factor` x `factor` block into one pixel, partial blocks on the right & bottom edges
+    //     are dropped. None for factors that wouldn't shrink anything
+    pub fn downscale_box(&self, factor: usize) -> Option<Image<Vec<u8>, S>> {
+        if factor <= 1 || self.w < factor || self.h < factor {
+            return None;
+        }
+        let (new_w, new_h) = (self.w / factor, self.h / factor);
+        let n = S::N_SUBPX;
+        let block_px = (factor * factor) as u32;
+        let mut out = image::zeroed::<S>(new_w, new_h);
+        for (y, out_row) in out.buf.chunks_exact_mut(new_w * n).enumerate() {
+            for (x, out_px) in out_row.chunks_exact_mut(n).enumerate() {
+                let mut sums = [0u32; 4];
+                for src_y in y * factor..(y + 1) * factor {
+                    let start = (src_y * self.w + x * factor) * n;
+                    for px in self.buf[start..start + factor * n].chunks_exact(n) {
+                        sums.iter_mut()
+                            .zip(px)
+                            .for_each(|(sum, &v)| *sum += v as u32);
                     }
-                }),
-        )
+                }
+                out_px
+                    .iter_mut()
+                    .zip(sums)
+                    .for_each(|(v, sum)| *v = (sum / block_px) as u8);
+            }
+        }
+        Some(out)
+    }
+
+    // (r + 2g + b) / 4 into `out`, which keeps its buffer across frames while the dims stay the same.
+    //     Plain integer math over the whole buffer, so it vectorizes
+    pub fn luma_into(&self, out: &mut Image<Vec<u8>, Gray8>) {
+        if (out.w, out.h) != (self.w, self.h) {
+            *out = image::zeroed(self.w, self.h);
+        }
+        out.buf
+            .iter_mut()
+            .zip(self.buf.chunks_exact(S::N_SUBPX))
+            .for_each(|(luma, px)| {
+                let [r, g, b, _] = Pixel::<S>::rgba(&px);
+                *luma = ((r as u16 + 2 * g as u16 + b as u16) / 4) as u8;
+            });
+    }
+}
+
+// Crops smaller than this are quicker to search on one thread than to spawn threads for
+const PAR_DETECT_MIN_PIXELS: usize = 300_000;
+
+fn band_rows(h: usize, n_threads: usize) -> usize {
+    (h + n_threads - 1) / n_threads
+}
+
+// Matching pixels in `buf`, which holds whole rows starting at `first_row` of a `w` wide image
+fn matches_in_rows<S: Subpixel<Inner = u8>>(
+    buf: &[u8],
+    w: usize,
+    first_row: usize,
+    targets: &[Color<u8>],
+    thresh: f32,
+) -> Vec<Coord<usize>> {
+    buf.chunks_exact(S::N_SUBPX)
+        .enumerate()
+        .filter_map(|(idx, px)| {
+            let color = Pixel::<S>::as_color(&px);
+            if targets
+                .iter()
+                .any(|&target| color_matches(color, target, thresh))
+            {
+                Some(get_2d_idx(w, first_row * w + idx))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+// The match test detect_color uses, `thresh` is a similarity where 1 only matches the exact color
+pub fn color_matches(color: Color<u8>, target: Color<u8>, thresh: f32) -> bool {
+    1. - color_distance(color, target) > thresh
+}
+
+// Threshold halfway between how similar a known target pixel and a known background pixel are to `target`,
+//     None when the background is at least as close to the target color as the target pixel is.
+//     Clamped into `bounds`, two nearly identical pixels can want a threshold past what the config allows
+pub fn suggest_thresh(
+    target: Color<u8>,
+    target_px: Color<u8>,
+    background_px: Color<u8>,
+    bounds: &RangeInclusive<f32>,
+) -> Option<f32> {
+    let target_sim = 1. - color_distance(target_px, target);
+    let background_sim = 1. - color_distance(background_px, target);
+    if target_sim <= background_sim {
+        return None;
     }
+    Some(((target_sim + background_sim) / 2.).clamp(*bounds.start(), *bounds.end()))
 }
 
-// https://www.compuphase.com/cmetric.htm
-fn color_distance(p1: Color<u8>, p2: Color<u8>) -> f32 {
+// https://www.compuphase.com/cmetric.htm, 0 for identical colors up to ~1
+pub fn color_distance(p1: Color<u8>, p2: Color<u8>) -> f32 {
     let rmean = (p1.r as i32 + p2.r as i32) / 2;
     let r = p1.r as i32 - p2.r as i32;
     let g = p1.g as i32 - p2.g as i32;
@@ -259,3 +460,226 @@ fn color_distance(p1: Color<u8>, p2: Color<u8>) -> f32 {
     f32::sqrt(((((512 + rmean) * r * r) >> 8) + 4 * g * g + (((767 - rmean) * b * b) >> 8)) as f32)
         / (255 * 3) as f32
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Bounded, CfgKey, Config, ValType};
+    use crate::image::{Bgra8, Rgba8};
+
+    // deterministic pixels with every alpha showing up, so both blend paths see partial transparency
+    fn noise_image<S: Subpixel<Inner = u8>>(w: usize, h: usize, seed: u32) -> Image<Vec<u8>, S> {
+        let mut state = seed;
+        let buf = (0..w * h * S::N_SUBPX)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 24) as u8
+            })
+            .collect();
+        Image::new(buf, w, h)
+    }
+
+    fn check_blend_matches_scalar<S: Subpixel<Inner = u8>>(w: usize, h: usize) {
+        for blend_type in [BlendType::Over, BlendType::Under] {
+            let scalar_fn = match blend_type {
+                BlendType::Over => over,
+                BlendType::Under => under,
+            };
+            // exactly w * h pixels long, a chunk running past the end would panic on the slice
+            let mut fg = noise_image::<S>(w, h, 1);
+            let bg = noise_image::<S>(w, h, 2);
+            assert_eq!(fg.as_slice().len(), w * h * S::N_SUBPX);
+            let expected: Vec<Color<u8>> = fg
+                .pixels()
+                .zip(bg.pixels())
+                .map(|(fg_px, bg_px)| scalar_fn(fg_px.as_color(), bg_px.as_color()))
+                .collect();
+
+            fg.blend(blend_type, &bg);
+            for (idx, (px, want)) in fg.pixels().zip(expected).enumerate() {
+                assert_eq!(px.as_color(), want, "{}x{} pixel {}", w, h, idx);
+            }
+        }
+    }
+
+    #[test]
+    fn blend_3x3() {
+        // one avx chunk & a 1 pixel tail
+        check_blend_matches_scalar::<Bgra8>(3, 3);
+        check_blend_matches_scalar::<Rgba8>(3, 3);
+    }
+
+    #[test]
+    fn blend_7x5() {
+        // 4 chunks & a 3 pixel tail
+        check_blend_matches_scalar::<Bgra8>(7, 5);
+        check_blend_matches_scalar::<Rgba8>(7, 5);
+    }
+
+    #[test]
+    fn blend_9x1() {
+        // one chunk & a 1 pixel tail in a single row
+        check_blend_matches_scalar::<Bgra8>(9, 1);
+    }
+
+    #[test]
+    fn blend_smaller_than_a_chunk() {
+        // no avx chunk at all, only the tail
+        check_blend_matches_scalar::<Bgra8>(7, 1);
+        check_blend_matches_scalar::<Bgra8>(1, 1);
+    }
+
+    #[test]
+    fn blend_whole_chunks() {
+        check_blend_matches_scalar::<Bgra8>(8, 4);
+    }
+
+    // Past PAR_DETECT_MIN_PIXELS so detect_colors would split it into bands, with a prime height so the
+    //     last band comes up short on any thread count
+    const PAR_W: usize = 701;
+    const PAR_H: usize = 503;
+
+    // targets on the corners, along every edge, around each band boundary & scattered in between
+    fn par_targets(n_threads: usize) -> FxHashSet<Coord<usize>> {
+        let band_rows = band_rows(PAR_H, n_threads);
+        let mut coords = FxHashSet::default();
+        for (x, y) in [
+            (0, 0),
+            (PAR_W - 1, 0),
+            (0, PAR_H - 1),
+            (PAR_W - 1, PAR_H - 1),
+        ] {
+            coords.insert(Coord::new(x, y));
+        }
+        for band in 1..n_threads {
+            let boundary = band * band_rows;
+            if boundary < PAR_H {
+                coords.insert(Coord::new(band % PAR_W, boundary - 1));
+                coords.insert(Coord::new(PAR_W - 1, boundary - 1));
+                coords.insert(Coord::new(0, boundary));
+            }
+        }
+        let mut state = 7u32;
+        for _ in 0..500 {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let idx = (state >> 8) as usize % (PAR_W * PAR_H);
+            coords.insert(get_2d_idx(PAR_W, idx));
+        }
+        coords
+    }
+
+    #[test]
+    fn parallel_detect_matches_serial() {
+        let target = Color::new(255, 0, 0, 255);
+        let other = Color::new(0, 0, 255, 255);
+        let middle = Coord::new(PAR_W / 2, PAR_H / 2);
+
+        // the band split doesn't depend on how many cores this machine has
+        for n_threads in [2, 3, 4, 7, 8, 16] {
+            let mut img = image::zeroed::<Bgra8>(PAR_W, PAR_H);
+            img.fill_color(Color::new(0, 0, 0, 255));
+            let planted = par_targets(n_threads);
+            for &pos in &planted {
+                img.set2d(pos, target);
+            }
+            // a second color only shows up when it's asked for
+            img.set2d(middle, other);
+
+            let serial: FxHashSet<_> =
+                matches_in_rows::<Bgra8>(img.as_slice(), PAR_W, 0, &[target], 0.9)
+                    .into_iter()
+                    .collect();
+            assert_eq!(serial, planted, "{} threads", n_threads);
+            assert_eq!(
+                img.detect_colors_banded(&[target], 0.9, n_threads),
+                serial,
+                "{} threads",
+                n_threads
+            );
+
+            let mut both = planted;
+            both.insert(middle);
+            assert_eq!(
+                img.detect_colors_banded(&[target, other], 0.9, n_threads),
+                both,
+                "{} threads",
+                n_threads
+            );
+            assert_eq!(img.detect_colors(&[target, other], 0.9), both);
+        }
+    }
+
+    #[test]
+    fn matches_in_rows_offsets_by_first_row() {
+        let target = Color::new(255, 0, 0, 255);
+        let mut img = image::zeroed::<Bgra8>(PAR_W, PAR_H);
+        let planted = par_targets(3);
+        for &pos in &planted {
+            img.set2d(pos, target);
+        }
+
+        // a band of rows from the middle of the image gives back coords in the whole image
+        let (first_row, rows) = (100, 37);
+        let band = &img.as_slice()[first_row * PAR_W * 4..(first_row + rows) * PAR_W * 4];
+        let got: FxHashSet<_> = matches_in_rows::<Bgra8>(band, PAR_W, first_row, &[target], 0.9)
+            .into_iter()
+            .collect();
+        let expected: FxHashSet<_> = planted
+            .into_iter()
+            .filter(|c| (first_row..first_row + rows).contains(&c.y))
+            .collect();
+        assert_eq!(got, expected);
+    }
+
+    fn color_thresh(cfg: &Config) -> Bounded<f32> {
+        <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::ColorThresh))
+    }
+
+    #[test]
+    fn suggest_thresh_halfway() {
+        let bounds = color_thresh(&Config::default()).bounds;
+        let target = Color::new(200, 40, 40, 255);
+        let target_px = Color::new(190, 50, 40, 255);
+        let background = Color::new(60, 90, 160, 255);
+
+        let target_sim = 1. - color_distance(target_px, target);
+        let background_sim = 1. - color_distance(background, target);
+        let thresh = suggest_thresh(target, target_px, background, &bounds).unwrap();
+        assert!((thresh - (target_sim + background_sim) / 2.).abs() < 1e-6);
+        // the same match test detect_color uses tells the two pixels apart
+        assert!(color_matches(target_px, target, thresh));
+        assert!(!color_matches(background, target, thresh));
+    }
+
+    #[test]
+    fn suggest_thresh_background_closer() {
+        let bounds = color_thresh(&Config::default()).bounds;
+        let target = Color::new(200, 40, 40, 255);
+        let near = Color::new(190, 50, 40, 255);
+        let far = Color::new(60, 90, 160, 255);
+        assert_eq!(suggest_thresh(target, far, near, &bounds), None);
+        assert_eq!(suggest_thresh(target, near, near, &bounds), None);
+    }
+
+    #[test]
+    fn suggested_thresh_fits_color_thresh() {
+        let mut cfg = Config::default();
+        let bounds = color_thresh(&cfg).bounds;
+        let target = Color::new(200, 40, 40, 255);
+        // (target pixel, background pixel), from one step apart to opposite colors
+        let pairs = [
+            (target, Color::new(201, 40, 40, 255)),
+            (target, Color::new(55, 215, 215, 255)),
+            (Color::new(0, 0, 0, 255), Color::new(255, 255, 255, 255)),
+        ];
+        for (target_px, background) in pairs {
+            let thresh = suggest_thresh(target, target_px, background, &bounds).unwrap();
+            let val = ValType::Float(Bounded::new(thresh, 0.0..=0.0));
+            assert_eq!(cfg.set_val(CfgKey::ColorThresh, val), Ok(()), "{}", thresh);
+            assert_eq!(color_thresh(&cfg).val, thresh);
+        }
+        // one step apart wants more than the config allows
+        let thresh = suggest_thresh(target, target, pairs[0].1, &bounds).unwrap();
+        assert_eq!(thresh, *bounds.end());
+    }
+}
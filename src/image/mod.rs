@@ -7,7 +7,11 @@ use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 
 mod blend;
+pub mod bmp;
+#[cfg(feature = "debug-window")]
+pub mod debug_window;
 pub mod image_ops;
+pub mod png;
 
 pub struct SubpxOrder {
     r: usize,
@@ -17,6 +21,8 @@ pub struct SubpxOrder {
 }
 const RGBA_ORDER: SubpxOrder = SubpxOrder { r: 0, g: 1, b: 2, a: 3 };
 const BGRA_ORDER: SubpxOrder = SubpxOrder { r: 2, g: 1, b: 0, a: 3 };
+// single channel, every color reads back as the luma. set() only keeps the last write (alpha), so fill it directly
+const GRAY_ORDER: SubpxOrder = SubpxOrder { r: 0, g: 0, b: 0, a: 0 };
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub struct Color<T> {
@@ -107,6 +113,7 @@ macro_rules! define_subpx {
 
 define_subpx!(Rgba8, u8, RGBA_ORDER, 4);
 define_subpx!(Bgra8, u8, BGRA_ORDER, 4);
+define_subpx!(Gray8, u8, GRAY_ORDER, 1); // a quarter of the bytes, for the consumers that only need brightness
 
 #[derive(Debug, Clone)]
 pub struct Image<T, S> {
@@ -157,6 +164,36 @@ where
     pub fn as_slice(&self) -> &[S::Inner] {
         &self.buf[..]
     }
+
+    // Number of pixels that differ at all, images with different dims differ everywhere
+    pub fn diff_count<U: Deref<Target = [S::Inner]>>(&self, other: &Image<U, S>) -> usize
+    where
+        S::Inner: PartialEq,
+    {
+        if (self.w, self.h) != (other.w, other.h) {
+            return self.w.max(other.w) * self.h.max(other.h);
+        }
+        self.pixels()
+            .zip(other.pixels())
+            .filter(|(a, b)| a.as_ref() != b.as_ref())
+            .count()
+    }
+
+    // Same dims and no subpixel further apart than `tolerance`
+    pub fn approx_eq<U: Deref<Target = [S::Inner]>>(
+        &self,
+        other: &Image<U, S>,
+        tolerance: S::Inner,
+    ) -> bool
+    where
+        S::Inner: PartialOrd + std::ops::Sub<Output = S::Inner>,
+    {
+        (self.w, self.h) == (other.w, other.h)
+            && self.buf.iter().zip(other.buf.iter()).all(|(&a, &b)| {
+                let diff = if a > b { a - b } else { b - a };
+                diff <= tolerance
+            })
+    }
 }
 
 impl<T, S> Image<T, S>
@@ -0,0 +1,98 @@
+use crate::image::{Image, Pixel, Subpixel};
+
+use std::fs::File;
+use std::io::{self, prelude::*, BufWriter};
+use std::ops::Deref;
+use std::path::Path;
+
+// 8-bit RGBA with stored (uncompressed) deflate blocks, bigger than a real encoder's output
+//     but anything can open it & there's no zlib to pull in
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+const MAX_STORED_LEN: usize = 65535; // a stored block's length is a u16
+
+impl<T, S> Image<T, S>
+where
+    T: Deref<Target = [u8]>,
+    S: Subpixel<Inner = u8>,
+{
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut ihdr = Vec::with_capacity(13);
+        ihdr.extend_from_slice(&(self.w as u32).to_be_bytes());
+        ihdr.extend_from_slice(&(self.h as u32).to_be_bytes());
+        ihdr.extend_from_slice(&[8, 6, 0, 0, 0]); // bit depth, rgba, deflate, no filter, no interlace
+
+        // every scanline starts with its filter type, 0 leaves the bytes as they are
+        let mut raw = Vec::with_capacity(self.h * (self.w * 4 + 1));
+        for (i, px) in self.pixels().enumerate() {
+            if i % self.w == 0 {
+                raw.push(0);
+            }
+            raw.extend_from_slice(&px.rgba());
+        }
+
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(&SIGNATURE)?;
+        write_chunk(&mut out, b"IHDR", &ihdr)?;
+        write_chunk(&mut out, b"IDAT", &zlib_stored(&raw))?;
+        write_chunk(&mut out, b"IEND", &[])?;
+        out.flush()
+    }
+}
+
+fn write_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let crc = crc32(crc32(!0, kind), data);
+    out.write_all(&(!crc).to_be_bytes())
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let n_blocks = data.len() / MAX_STORED_LEN + 1;
+    let mut out = Vec::with_capacity(data.len() + n_blocks * 5 + 6);
+    out.extend_from_slice(&[0x78, 0x01]); // deflate, 32k window, no preset dictionary
+
+    let mut blocks = data.chunks(MAX_STORED_LEN).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]); // still needs a final block when there's nothing to store
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        out.push(blocks.peek().is_none() as u8); // BFINAL on the last one, BTYPE 00
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+// Bitwise instead of a table, a screenshot is a one-off so the speed doesn't matter
+fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the most bytes that can be summed before b could overflow a u32
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
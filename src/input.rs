@@ -1,20 +1,73 @@
+use crate::config::{Bounded, CfgKey, Choice, Config, ValType};
 use crate::coord::Coord;
-use crate::logging::log;
+use crate::logging::{log, log_err, log_err_once};
 use interception::{is_mouse, Device, Filter, Interception, MouseFlags, MouseState, Stroke};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::lazy::SyncLazy;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 use windows::Win32::{
     Foundation::PWSTR,
+    System::LibraryLoader::{GetModuleFileNameW, GetModuleHandleW},
     UI::{
         Input::KeyboardAndMouse::{
             GetAsyncKeyState, GetKeyNameTextW, GetKeyboardState, MapVirtualKeyW, VK_LBUTTON,
             VK_MBUTTON, VK_RBUTTON, VK_XBUTTON1, VK_XBUTTON2,
         },
-        WindowsAndMessaging::MAPVK_VK_TO_VSC_EX,
+        WindowsAndMessaging::{MAPVK_VK_TO_VSC_EX, MAPVK_VSC_TO_VK_EX},
     },
 };
 
 const INTERCEPTION_ERR: &str = "Error initializing interception - is the interception driver installed? (https://github.com/oblitum/Interception)";
+const INTERCEPTION_DLL: &str = "interception.dll";
+const INTERCEPTION_EXPECTED_VERSION: &str = "1.0.1";
+pub const MOUSE_CAPTURE_TIMEOUT_ERR: &str = "Mouse capture timed out";
+// long enough for someone who just launched it to touch the mouse
+const MOUSE_CAPTURE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriverStatus {
+    Ok,
+    Degraded, // driver context was created, but the device or the send path isn't responding
+    Missing,
+    TimedOut, // driver is there but the mouse never sent anything, usually another interception program's filter
+}
+
+// The synthetic button that's currently held down, so it can still be released if we panic or exit mid-click.
+//     Packed as (mouse device << 16) | release state bits, 0 when nothing is held. An atomic instead of a
+//     mutex since the panic hook can't afford to block on (or find poisoned) a lock
+static HELD_BUTTON: AtomicU32 = AtomicU32::new(0);
+
+fn pack_held(mouse_dev: Device, click_up: MouseState) -> u32 {
+    ((mouse_dev as u32) << 16) | click_up.bits() as u32
+}
+
+fn unpack_held(packed: u32) -> Option<(Device, MouseState)> {
+    match packed {
+        0 => None,
+        _ => Some((
+            (packed >> 16) as Device,
+            MouseState::from_bits_truncate(packed as u16),
+        )),
+    }
+}
+
+// Best effort, sends the matching button up for a synthetic press that never got released
+pub fn release_held_button() {
+    if let Some((mouse_dev, click_up)) = unpack_held(HELD_BUTTON.swap(0, Ordering::SeqCst)) {
+        if let Some(interception) = Interception::new() {
+            let mut stroke = Stroke::default();
+            if let Stroke::Mouse { ref mut state, .. } = stroke {
+                *state = click_up;
+            }
+            interception.send(mouse_dev, &[stroke]);
+        }
+    }
+}
 
 trait Empty {
     fn default() -> Self;
@@ -33,79 +86,215 @@ impl Empty for Stroke {
 }
 
 trait CaptureMouse {
-    fn capture_mouse(&mut self) -> i32;
+    fn capture_mouse(&mut self) -> Option<i32>;
+    fn capture_mouse_timeout(&mut self, timeout: Duration) -> Option<i32>;
 }
 impl CaptureMouse for Interception {
-    fn capture_mouse(&mut self) -> i32 {
+    // A plain wait() would block forever if another program's filter swallows the strokes first
+    fn capture_mouse(&mut self) -> Option<i32> {
         log!("Looking for mouse...");
+        let mouse_dev = self.capture_mouse_timeout(MOUSE_CAPTURE_TIMEOUT);
+        match mouse_dev {
+            Some(_) => log!("Found mouse"),
+            None => log_err!(
+                "No mouse input in {}s. If the mouse was moving, another program using interception probably holds a conflicting filter, try closing it.\n\tMice the driver sees, none of which sent anything:\n\t{}",
+                MOUSE_CAPTURE_TIMEOUT.as_secs(),
+                match mouse_hardware_ids(self) {
+                    ids if ids.is_empty() => "<none>".to_string(),
+                    ids => ids.join("\n\t"),
+                }
+            ),
+        }
+        mouse_dev
+    }
+
+    // None when the mouse didn't do anything in time
+    fn capture_mouse_timeout(&mut self, timeout: Duration) -> Option<i32> {
         self.set_filter(is_mouse, Filter::MouseFilter(MouseState::all()));
-        let mouse_dev = self.wait();
+        let mouse_dev = self.wait_with_timeout(timeout);
         self.set_filter(is_mouse, Filter::MouseFilter(MouseState::empty()));
-        log!("Found mouse");
-        mouse_dev
+        (mouse_dev > 0).then(|| mouse_dev)
     }
 }
 
-pub struct InterceptionState {
-    interception: Interception,
+// Every mouse device the driver knows about with its hardware id
+fn mouse_hardware_ids(interception: &Interception) -> Vec<String> {
+    (1..=20)
+        .filter(|&dev| is_mouse(dev))
+        .filter_map(|dev| {
+            let mut buf = [0u8; 512];
+            let len = (interception.get_hardware_id(dev, &mut buf) as usize).min(buf.len());
+            // a list of utf-16 strings, the first one is the most specific
+            let wide: Vec<u16> = buf[..len]
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .take_while(|&c| c != 0)
+                .collect();
+            (!wide.is_empty()).then(|| format!("{}: {}", dev, String::from_utf16_lossy(&wide)))
+        })
+        .collect()
+}
+
+// What the mover & click threads send their input through
+pub trait MouseBackend {
+    fn move_mouse_relative(&self, pos: Coord<i32>);
+    fn click_down(&self);
+    fn click_up(&self);
+    fn set_click_keycode(&mut self, keycode: u16) -> Result<(), &'static str>;
+}
+
+// Where the bot's mouse input ends up, each thread makes its own backend from it
+#[derive(Clone)]
+pub enum MouseSink {
+    Interception(Arc<InterceptionShared>),
+    Recording(Arc<MouseRecording>),
+}
+
+impl MouseSink {
+    pub fn backend(&self) -> Box<dyn MouseBackend> {
+        match self {
+            MouseSink::Interception(shared) => Box::new(InterceptionState::new(shared.clone())),
+            MouseSink::Recording(recording) => Box::new(RecordingMouse {
+                recording: recording.clone(),
+                click_keycode: VK_LBUTTON.0,
+            }),
+        }
+    }
+}
+
+// The one driver context & the mouse it captured, shared by every thread sending input.
+//     The context gets destroyed when the last Arc is dropped
+pub struct InterceptionShared {
+    interception: Mutex<Interception>,
     mouse_dev: Device,
+}
+
+// the context is a driver handle that any thread can send through, the mutex keeps sends from interleaving
+unsafe impl Send for InterceptionShared {}
+unsafe impl Sync for InterceptionShared {}
+
+impl InterceptionShared {
+    // Blocks until the mouse sends something (or gives up after MOUSE_CAPTURE_TIMEOUT), that's how we know which device it is
+    pub fn new() -> Result<Self, &'static str> {
+        let mut interception = Interception::new().ok_or(INTERCEPTION_ERR)?;
+        let mouse_dev = interception
+            .capture_mouse()
+            .ok_or(MOUSE_CAPTURE_TIMEOUT_ERR)?;
+        Ok(Self {
+            interception: Mutex::new(interception),
+            mouse_dev,
+        })
+    }
+
+    // Same as new() but gives up if the mouse doesn't move, for when nobody might be at the desk
+    pub fn with_timeout(timeout: Duration) -> Result<Self, &'static str> {
+        let mut interception = Interception::new().ok_or(INTERCEPTION_ERR)?;
+        let mouse_dev = interception
+            .capture_mouse_timeout(timeout)
+            .ok_or("No mouse input in time")?;
+        Ok(Self {
+            interception: Mutex::new(interception),
+            mouse_dev,
+        })
+    }
+
+    fn send(&self, stroke: Stroke) -> i32 {
+        self.interception
+            .lock()
+            .unwrap()
+            .send(self.mouse_dev, &[stroke])
+    }
+
+    // An outdated interception.dll can talk to a newer driver without erroring while sends do nothing,
+    //     so we send a stroke that doesn't move anything and check that the device actually answers
+    pub fn probe(&self) -> DriverStatus {
+        let mut problems = Vec::new();
+
+        let zero_move = Stroke::Mouse {
+            state: MouseState::MOVE,
+            flags: MouseFlags::MOVE_RELATIVE,
+            rolling: 0,
+            x: 0,
+            y: 0,
+            information: 0,
+        };
+        if self.send(zero_move) != 1 {
+            problems.push("zero-delta test stroke was not sent");
+        }
+
+        let mut hwid_buf = [0u8; 512];
+        if self
+            .interception
+            .lock()
+            .unwrap()
+            .get_hardware_id(self.mouse_dev, &mut hwid_buf)
+            == 0
+        {
+            problems.push("mouse device did not report a hardware id");
+        }
+
+        if problems.is_empty() {
+            return DriverStatus::Ok;
+        }
+
+        log_err!(
+            "Interception driver looks degraded: {}\n\tLoaded dll: {}\n\tExpected interception {}, make sure {} matches the installed driver version",
+            problems.join(", "),
+            interception_dll_path().unwrap_or_else(|| "<not loaded>".to_string()),
+            INTERCEPTION_EXPECTED_VERSION,
+            INTERCEPTION_DLL
+        );
+        DriverStatus::Degraded
+    }
+}
+
+// A thread's view of the shared context, only the button it clicks with is its own
+pub struct InterceptionState {
+    shared: Arc<InterceptionShared>,
     click_down: MouseState,
     click_up: MouseState,
 }
 
 impl InterceptionState {
-    pub fn new(mouse_dev: Device) -> Result<Self, &'static str> {
-        let interception = Interception::new().ok_or(INTERCEPTION_ERR)?;
-
-        Ok(InterceptionState {
-            interception,
-            mouse_dev,
+    pub fn new(shared: Arc<InterceptionShared>) -> Self {
+        Self {
+            shared,
             click_down: MouseState::LEFT_BUTTON_DOWN,
             click_up: MouseState::LEFT_BUTTON_UP,
-        })
+        }
     }
+}
 
-    pub fn click_down(&self) {
+impl MouseBackend for InterceptionState {
+    fn click_down(&self) {
         let mut stroke = Stroke::default();
         if let Stroke::Mouse { ref mut state, .. } = stroke {
             *state = self.click_down;
         }
-        self.interception.send(self.mouse_dev, &[stroke]);
+        self.shared.send(stroke);
+        HELD_BUTTON.store(pack_held(self.shared.mouse_dev, self.click_up), Ordering::SeqCst);
     }
 
-    pub fn click_up(&self) {
+    fn click_up(&self) {
         let mut stroke = Stroke::default();
         if let Stroke::Mouse { ref mut state, .. } = stroke {
             *state = self.click_up;
         }
-        self.interception.send(self.mouse_dev, &[stroke]);
+        self.shared.send(stroke);
+        HELD_BUTTON.store(0, Ordering::SeqCst);
     }
 
-    pub fn set_click_keycode(&mut self, keycode: u16) -> Result<(), &'static str> {
-        let (click_down, click_up) = match keycode.into() {
-            VK_LBUTTON => (MouseState::LEFT_BUTTON_DOWN, MouseState::LEFT_BUTTON_UP),
-            VK_RBUTTON => (MouseState::RIGHT_BUTTON_DOWN, MouseState::RIGHT_BUTTON_UP),
-            VK_MBUTTON => (MouseState::MIDDLE_BUTTON_DOWN, MouseState::MIDDLE_BUTTON_UP),
-            VK_XBUTTON1 => (MouseState::BUTTON_4_DOWN, MouseState::BUTTON_4_UP),
-            VK_XBUTTON2 => (MouseState::BUTTON_5_DOWN, MouseState::BUTTON_5_UP),
-            _ => return Err("Invalid click keycode"),
-        };
+    fn set_click_keycode(&mut self, keycode: u16) -> Result<(), &'static str> {
+        let (click_down, click_up) = click_states(keycode)?;
+        // the old button would otherwise stay held with nothing left that knows how to release it
+        if unpack_held(HELD_BUTTON.load(Ordering::SeqCst)).is_some() && click_up != self.click_up {
+            self.click_up();
+        }
         self.click_down = click_down;
         self.click_up = click_up;
         Ok(())
     }
 
-    pub fn move_mouse_over_time(&self, dur: Duration, n_chunks: u32, pos: Coord<i32>) {
-        let sleep_dur = dur / n_chunks;
-        let chunked_x = pos.x / n_chunks as i32;
-        let chunked_y = pos.y / n_chunks as i32;
-
-        for _ in 0..n_chunks {
-            self.move_mouse_relative(Coord::new(chunked_x, chunked_y));
-            spin_sleep::sleep(sleep_dur);
-        }
-    }
-
     fn move_mouse_relative(&self, pos: Coord<i32>) {
         let stroke = Stroke::Mouse {
             state: MouseState::MOVE,
@@ -115,12 +304,246 @@ impl InterceptionState {
             y: pos.y,
             information: 0,
         };
-        self.interception.send(self.mouse_dev, &[stroke]);
+        self.shared.send(stroke);
+    }
+}
+
+// (down, up) driver states for a mouse button's keycode
+fn click_states(keycode: u16) -> Result<(MouseState, MouseState), &'static str> {
+    match keycode.into() {
+        VK_LBUTTON => Ok((MouseState::LEFT_BUTTON_DOWN, MouseState::LEFT_BUTTON_UP)),
+        VK_RBUTTON => Ok((MouseState::RIGHT_BUTTON_DOWN, MouseState::RIGHT_BUTTON_UP)),
+        VK_MBUTTON => Ok((MouseState::MIDDLE_BUTTON_DOWN, MouseState::MIDDLE_BUTTON_UP)),
+        VK_XBUTTON1 => Ok((MouseState::BUTTON_4_DOWN, MouseState::BUTTON_4_UP)),
+        VK_XBUTTON2 => Ok((MouseState::BUTTON_5_DOWN, MouseState::BUTTON_5_UP)),
+        _ => Err("Invalid click keycode"),
+    }
+}
+
+// Timestamped log of everything the bot would have done with the mouse, for checking its behavior
+//     without the driver (or a game) in the loop. Shared by every thread's RecordingMouse
+pub struct MouseRecording {
+    path: PathBuf,
+    start: Instant,
+    out: Mutex<BufWriter<File>>,
+    held: AtomicU32, // same as HELD_BUTTON, but only for this recording's backends
+}
+
+impl MouseRecording {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "# ms, event, dx, dy, keycode")?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            start: Instant::now(),
+            out: Mutex::new(out),
+            held: AtomicU32::new(0),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn record(&self, event: &str, delta: Option<Coord<i32>>, keycode: Option<u16>) {
+        let (dx, dy) = match delta {
+            Some(d) => (d.x.to_string(), d.y.to_string()),
+            None => (String::new(), String::new()),
+        };
+        let keycode = keycode.map_or(String::new(), |k| k.to_string());
+        let ms = self.start.elapsed().as_secs_f64() * 1000.;
+
+        let mut out = self.out.lock().unwrap();
+        let written = writeln!(out, "{:.3}, {}, {}, {}, {}", ms, event, dx, dy, keycode);
+        // clicks are rare enough to flush right away, so a crash mid-session still leaves them on disk
+        let flushed = match event {
+            "move" => Ok(()),
+            _ => out.flush(),
+        };
+        if let Err(e) = written.and(flushed) {
+            log_err!("Error writing mouse recording to {}:\n\t{}", self.path.display(), e);
+        }
+    }
+}
+
+const RECORDING_DEV: Device = 0; // there's no real mouse behind a recording
+
+// Tracks the held button the same way InterceptionState does, so a recording shows the same releases
+//     the driver would've gotten. Nothing real is held, it stays out of HELD_BUTTON
+struct RecordingMouse {
+    recording: Arc<MouseRecording>,
+    click_keycode: u16,
+}
+
+impl MouseBackend for RecordingMouse {
+    fn move_mouse_relative(&self, pos: Coord<i32>) {
+        self.recording.record("move", Some(pos), None);
+    }
+
+    fn click_down(&self) {
+        self.recording.record("down", None, Some(self.click_keycode));
+        let (_, click_up) = click_states(self.click_keycode).unwrap();
+        self.recording.held.store(pack_held(RECORDING_DEV, click_up), Ordering::SeqCst);
+    }
+
+    fn click_up(&self) {
+        self.recording.record("up", None, Some(self.click_keycode));
+        self.recording.held.store(0, Ordering::SeqCst);
+    }
+
+    fn set_click_keycode(&mut self, keycode: u16) -> Result<(), &'static str> {
+        click_states(keycode)?;
+        let held = unpack_held(self.recording.held.load(Ordering::SeqCst)).is_some();
+        if held && keycode != self.click_keycode {
+            self.click_up();
+        }
+        self.click_keycode = keycode;
+        Ok(())
     }
 }
 
 pub fn key_pressed(key_code: u16) -> bool {
-    unsafe { GetAsyncKeyState(key_code as i32) < 0 }
+    let down = unsafe { GetAsyncKeyState(key_code as i32) < 0 };
+    if down {
+        note_key_activity();
+    }
+    down
+}
+
+// When a bound key was last seen held, in ms since KEY_CLOCK_START. Every key check goes through
+//     key_pressed or KeyToggle::pressed, so bumping it in there covers all of them
+static KEY_CLOCK_START: SyncLazy<Instant> = SyncLazy::new(Instant::now);
+static LAST_KEY_ACTIVITY: AtomicU64 = AtomicU64::new(0);
+
+fn note_key_activity() {
+    let ms = KEY_CLOCK_START.elapsed().as_millis() as u64;
+    LAST_KEY_ACTIVITY.fetch_max(ms, Ordering::Relaxed);
+}
+
+pub fn last_key_activity() -> Instant {
+    *KEY_CLOCK_START + Duration::from_millis(LAST_KEY_ACTIVITY.load(Ordering::Relaxed))
+}
+
+// Goes idle once no key has been pressed for `timeout`, and back on the next press. The clock is passed
+//     in, so it doesn't care where the times come from
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IdleTracker {
+    idle: bool,
+}
+
+impl IdleTracker {
+    // Some(new state) when it changes, a zero timeout never idles
+    pub fn update(
+        &mut self,
+        last_activity: Instant,
+        now: Instant,
+        timeout: Duration,
+    ) -> Option<bool> {
+        let idle = !timeout.is_zero() && now.saturating_duration_since(last_activity) >= timeout;
+        if idle == self.idle {
+            return None;
+        }
+        self.idle = idle;
+        Some(idle)
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+}
+
+// Where a key's held state gets read from. The scan code path still asks for a virtual key, just whichever
+//     one the physical key maps to under the current layout. It follows the key through layout remaps,
+//     a game that blocks virtual key queries blocks it all the same
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySource {
+    VirtualKey,
+    ScanCode(u16),
+}
+
+impl KeySource {
+    // Falls back to the virtual key until a scan code has been recorded by rebinding in the gui
+    pub fn autoclick(cfg: &Config) -> Self {
+        let source: Choice = cfg.get(CfgKey::AutoclickKeySource).into();
+        let scan_code: u32 =
+            <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::AutoclickScanCode)).val;
+        match source.as_str() {
+            "scan_code" if scan_code != 0 => KeySource::ScanCode(scan_code as u16),
+            _ => KeySource::VirtualKey,
+        }
+    }
+
+    pub fn is_down(&self, key_code: u16) -> bool {
+        let scan_to_vk =
+            |scan_code: u16| unsafe { MapVirtualKeyW(scan_code as u32, MAPVK_VSC_TO_VK_EX) as u16 };
+        self.is_down_with(key_code, scan_to_vk, key_pressed)
+    }
+
+    // The layout lookup & the virtual key query get passed in, so it can run against a scripted keyboard
+    fn is_down_with(
+        &self,
+        key_code: u16,
+        scan_to_vk: impl Fn(u16) -> u16,
+        vk_down: impl Fn(u16) -> bool,
+    ) -> bool {
+        match *self {
+            KeySource::VirtualKey => vk_down(key_code),
+            KeySource::ScanCode(scan_code) => match scan_to_vk(scan_code) {
+                0 => false, // no key there in this layout
+                mapped => vk_down(mapped),
+            },
+        }
+    }
+}
+
+// 0 for mouse buttons & keys without a scan code
+pub fn keycode_to_scancode(key_code: u16) -> u16 {
+    if is_mouse_button(key_code) {
+        return 0;
+    }
+    unsafe { MapVirtualKeyW(key_code as u32, MAPVK_VK_TO_VSC_EX) as u16 }
+}
+
+// Edge & hold state of one toggle-style key check. Every check keeps its own, two of them bound to the same
+//     key would otherwise take turns seeing the press
+#[derive(Debug, Default, Clone, Copy)]
+pub struct KeyToggle {
+    down_since: Option<Instant>,
+    fired: bool, // already fired for the current press
+}
+
+impl KeyToggle {
+    // Reports each press of the key exactly once, no matter how long it's held
+    pub fn edge_pressed(&mut self, key_code: u16) -> bool {
+        self.pressed(key_code, Duration::ZERO)
+    }
+
+    // Same as edge_pressed, but the key needs to be held for `hold` before it counts
+    pub fn pressed(&mut self, key_code: u16, hold: Duration) -> bool {
+        let key_state = unsafe { GetAsyncKeyState(key_code as i32) };
+        if key_state < 0 || key_state & 1 != 0 {
+            note_key_activity();
+        }
+        self.step(key_state < 0, key_state & 1 != 0, Instant::now(), hold)
+    }
+
+    // Fires once per press. With a zero hold that's on the rising edge, a tap that started and ended
+    //     between two polls only shows up in the "pressed since last call" bit so that counts too.
+    //     Otherwise it fires as soon as the key has been held for `hold`, quick taps never fire
+    fn step(&mut self, is_down: bool, tapped: bool, now: Instant, hold: Duration) -> bool {
+        let was_down = self.down_since.is_some();
+        if !is_down {
+            *self = Self::default();
+            return hold.is_zero() && !was_down && tapped;
+        }
+
+        let down_since = *self.down_since.get_or_insert(now);
+        if !self.fired && now.duration_since(down_since) >= hold {
+            self.fired = true;
+            return true;
+        }
+        false
+    }
 }
 
 pub fn wait_for_release(key_code: u16, timeout: Duration) {
@@ -177,6 +600,446 @@ pub fn keycode_to_string(key_code: u16) -> Result<String, &'static str> {
     }
 }
 
-pub fn find_mouse_dev() -> Result<i32, &'static str> {
-    Ok(Interception::new().ok_or(INTERCEPTION_ERR)?.capture_mouse())
+fn interception_dll_path() -> Option<String> {
+    const BUF_SIZE: usize = 260; // MAX_PATH
+    let mut name_buf = INTERCEPTION_DLL.encode_utf16().collect::<Vec<_>>();
+    name_buf.push(0); // null termination
+    let mut path_buf = [0u16; BUF_SIZE];
+    unsafe {
+        let module = GetModuleHandleW(PWSTR(name_buf.as_mut_ptr()));
+        if module.is_invalid() {
+            return None;
+        }
+        let len = GetModuleFileNameW(module, PWSTR(path_buf.as_mut_ptr()), BUF_SIZE as u32);
+        if len == 0 {
+            None
+        } else {
+            Some(String::from_utf16_lossy(&path_buf[..len as usize]))
+        }
+    }
+}
+
+pub fn is_valid_keycode(key_code: u16) -> bool {
+    keycode_to_string(key_code).is_ok()
+}
+
+// Invalid keycodes get replaced with the key's default for runtime use only,
+//     the stored value is left alone so it can still be fixed from the gui or the file
+pub fn validated_keycode(cfg: &Config, key: CfgKey) -> u16 {
+    let key_code: u16 = cfg.get(key).into();
+    let autoclick_conflict = key == CfgKey::FakeLmbKeycode
+        && key_code == <ValType as Into<u16>>::into(cfg.get(CfgKey::AutoclickKeycode));
+    if is_valid_keycode(key_code) && !autoclick_conflict {
+        key_code
+    } else {
+        key.default_val().into()
+    }
+}
+
+pub fn is_mouse_button(key_code: u16) -> bool {
+    [VK_LBUTTON, VK_RBUTTON, VK_MBUTTON, VK_XBUTTON1, VK_XBUTTON2].contains(&key_code.into())
+}
+
+pub fn validate_keycodes(cfg: &Config) {
+    CfgKey::iter()
+        .filter(|key| key.is_keycode())
+        .for_each(|key| {
+            let key_code: u16 = cfg.get(key).into();
+            if !is_valid_keycode(key_code) {
+                log_err_once!(for key;
+                    "Config entry `{} = {}` isn't a valid keycode, using the default ({}) until it's changed",
+                    key.as_string(),
+                    key_code,
+                    <ValType as Into<u16>>::into(key.default_val())
+                );
+            }
+        });
+
+    let source: Choice = cfg.get(CfgKey::AutoclickKeySource).into();
+    if source.as_str() == "scan_code" && KeySource::autoclick(cfg) == KeySource::VirtualKey {
+        log_err_once!(
+            "{} is scan_code but no scan code has been recorded yet, rebind autoclick in the gui.\n\tUsing the virtual key until then",
+            CfgKey::AutoclickKeySource.as_string()
+        );
+    }
+
+    // the fake lmb button gets synthesized by the click thread, so anything else bound to it sees our own clicks
+    let fake_lmb: u16 = cfg.get(CfgKey::FakeLmbKeycode).into();
+    if !is_mouse_button(fake_lmb) {
+        log_err_once!(
+            "{} is bound to a keyboard key, clicks can only be sent as mouse buttons",
+            CfgKey::FakeLmbKeycode.as_string()
+        );
+    }
+    for key in [CfgKey::AutoclickKeycode, CfgKey::AimKeycode] {
+        let key_code: u16 = cfg.get(key).into();
+        if key_code != fake_lmb {
+            continue;
+        }
+        match key {
+            CfgKey::AutoclickKeycode => log_err_once!(
+                "{0} is the same button as {1}, the fake clicks would keep retriggering the autoclicker.\n\tUsing the default {1} until one of them is changed",
+                key.as_string(),
+                CfgKey::FakeLmbKeycode.as_string()
+            ),
+            _ => log_err_once!(
+                "{} is the same button as {}, autoclicking will also trigger aiming",
+                key.as_string(),
+                CfgKey::FakeLmbKeycode.as_string()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (is_down, tapped) for each poll, 1ms apart. Returns which polls fired
+    fn run_script(toggle: &mut KeyToggle, script: &[(bool, bool)], hold: Duration) -> Vec<usize> {
+        let start = Instant::now();
+        let mut fired = Vec::new();
+        for (i, &(is_down, tapped)) in script.iter().enumerate() {
+            if toggle.step(
+                is_down,
+                tapped,
+                start + Duration::from_millis(i as u64),
+                hold,
+            ) {
+                fired.push(i);
+            }
+        }
+        fired
+    }
+
+    const UP: (bool, bool) = (false, false);
+    const PRESS: (bool, bool) = (true, true);
+    const HELD: (bool, bool) = (true, false);
+    const TAPPED: (bool, bool) = (false, true); // pressed & released between two polls
+
+    #[test]
+    fn edge_fires_once_per_press() {
+        let script = [UP, PRESS, HELD, HELD, UP, PRESS, UP];
+        assert_eq!(
+            run_script(&mut KeyToggle::default(), &script, Duration::ZERO),
+            vec![1, 5]
+        );
+    }
+
+    #[test]
+    fn edge_catches_tap_between_polls() {
+        let script = [UP, TAPPED, UP, TAPPED];
+        assert_eq!(
+            run_script(&mut KeyToggle::default(), &script, Duration::ZERO),
+            vec![1, 3]
+        );
+    }
+
+    #[test]
+    fn edge_release_doesnt_fire_again() {
+        // the tapped bit is still set on the poll that sees the release
+        let script = [PRESS, (false, true), UP];
+        assert_eq!(
+            run_script(&mut KeyToggle::default(), &script, Duration::ZERO),
+            vec![0]
+        );
+    }
+
+    #[test]
+    fn edge_separate_callers_same_key() {
+        let script = [UP, PRESS, HELD, UP];
+        let (mut aim_toggle, mut clickmode_toggle) = (KeyToggle::default(), KeyToggle::default());
+        let start = Instant::now();
+        for (i, &(is_down, tapped)) in script.iter().enumerate() {
+            let now = start + Duration::from_millis(i as u64);
+            let aim_fired = aim_toggle.step(is_down, tapped, now, Duration::ZERO);
+            let clickmode_fired = clickmode_toggle.step(is_down, tapped, now, Duration::ZERO);
+            assert_eq!(aim_fired, i == 1);
+            assert_eq!(clickmode_fired, i == 1);
+        }
+    }
+
+    const HOLD: Duration = Duration::from_millis(5);
+
+    #[test]
+    fn hold_short_tap_doesnt_fire() {
+        // released 3ms after the press, and a tap between polls is never held at all
+        let script = [UP, PRESS, HELD, HELD, UP, TAPPED, UP];
+        assert!(run_script(&mut KeyToggle::default(), &script, HOLD).is_empty());
+    }
+
+    #[test]
+    fn hold_fires_once() {
+        let mut script = vec![UP, PRESS];
+        script.extend([HELD; 10]);
+        script.push(UP);
+        // pressed on poll 1, held long enough on poll 6 & never again while still down
+        assert_eq!(
+            run_script(&mut KeyToggle::default(), &script, HOLD),
+            vec![6]
+        );
+    }
+
+    #[test]
+    fn hold_fires_again_after_release() {
+        let mut script = vec![PRESS];
+        script.extend([HELD; 5]);
+        script.push(UP);
+        script.push(PRESS);
+        script.extend([HELD; 5]);
+        assert_eq!(
+            run_script(&mut KeyToggle::default(), &script, HOLD),
+            vec![5, 12]
+        );
+    }
+
+    // The two keys QWERTY & AZERTY swap, scan code -> vk. 0 for anything else, like MapVirtualKeyW
+    fn layout(azerty: bool) -> impl Fn(u16) -> u16 {
+        move |scan_code| match (scan_code, azerty) {
+            (0x10, false) | (0x1e, true) => 0x51, // Q
+            (0x10, true) | (0x1e, false) => 0x41, // A
+            _ => 0,
+        }
+    }
+
+    // (scan code, down) strokes in order, returns whether the bound key read as down after each one
+    fn run_strokes(
+        source: KeySource,
+        key_code: u16,
+        azerty: bool,
+        strokes: &[(u16, bool)],
+    ) -> Vec<bool> {
+        let scan_to_vk = layout(azerty);
+        // the virtual keys held so far, what GetAsyncKeyState would answer from
+        let mut held = std::collections::HashSet::new();
+        strokes
+            .iter()
+            .map(|&(scan_code, down)| {
+                match down {
+                    true => held.insert(scan_to_vk(scan_code)),
+                    false => held.remove(&scan_to_vk(scan_code)),
+                };
+                source.is_down_with(key_code, &scan_to_vk, |vk| held.contains(&vk))
+            })
+            .collect()
+    }
+
+    // Q bound on a QWERTY layout, so its scan code got recorded as 0x10
+    const KEY_Q: u16 = 0x51;
+    const STROKES: [(u16, bool); 4] = [(0x10, true), (0x10, false), (0x1e, true), (0x1e, false)];
+
+    #[test]
+    fn key_sources_agree_without_remap() {
+        for source in [KeySource::VirtualKey, KeySource::ScanCode(0x10)] {
+            assert_eq!(
+                run_strokes(source, KEY_Q, false, &STROKES),
+                vec![true, false, false, false]
+            );
+        }
+    }
+
+    #[test]
+    fn scan_code_follows_physical_key_through_remap() {
+        // after switching to AZERTY, the key labeled Q is where A used to be
+        assert_eq!(
+            run_strokes(KeySource::VirtualKey, KEY_Q, true, &STROKES),
+            vec![false, false, true, false]
+        );
+        assert_eq!(
+            run_strokes(KeySource::ScanCode(0x10), KEY_Q, true, &STROKES),
+            vec![true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn unmapped_scan_code_never_down() {
+        let strokes = [(0x7f, true), (0x10, true)];
+        assert_eq!(
+            run_strokes(KeySource::ScanCode(0x7f), KEY_Q, false, &strokes),
+            vec![false, false]
+        );
+    }
+
+    fn temp_recording(name: &str) -> (MouseSink, PathBuf) {
+        let path = std::env::temp_dir().join(format!(
+            "pixelbot_mouse_{}_{}.csv",
+            name,
+            std::process::id()
+        ));
+        let recording = MouseRecording::create(&path).unwrap();
+        (MouseSink::Recording(Arc::new(recording)), path)
+    }
+
+    // "event, dx, dy, keycode" for each recorded line, without the timestamps
+    fn recorded_events(path: &Path) -> Vec<String> {
+        let text = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        text.lines()
+            .skip(1) // header
+            .map(|line| line.split_once(", ").unwrap().1.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn backends_share_one_recording() {
+        // the mover & click threads each make a backend from the same sink
+        let (sink, path) = temp_recording("shared");
+        let mover = sink.backend();
+        let mut clicker = sink.backend();
+        clicker.set_click_keycode(VK_RBUTTON.0).unwrap();
+
+        mover.move_mouse_relative(Coord::new(3, -2));
+        clicker.click_down();
+        mover.move_mouse_relative(Coord::new(-1, 0));
+        clicker.click_up();
+        // the recording is flushed once the last backend lets go of it
+        drop((sink, mover, clicker));
+
+        assert_eq!(
+            recorded_events(&path),
+            vec!["move, 3, -2, ", "down, , , 2", "move, -1, 0, ", "up, , , 2"]
+        );
+    }
+
+    #[test]
+    fn click_keycode_is_per_backend() {
+        let (sink, path) = temp_recording("per_backend");
+        let mut first = sink.backend();
+        let second = sink.backend();
+        first.set_click_keycode(VK_XBUTTON1.0).unwrap();
+
+        first.click_down();
+        second.click_down();
+        drop((sink, first, second));
+
+        assert_eq!(recorded_events(&path), vec!["down, , , 5", "down, , , 1"]);
+    }
+
+    #[test]
+    fn invalid_click_keycode_keeps_old_one() {
+        let (sink, path) = temp_recording("invalid_key");
+        let mut backend = sink.backend();
+        backend.set_click_keycode(VK_MBUTTON.0).unwrap();
+        // 'A' isn't a mouse button
+        assert!(backend.set_click_keycode(0x41).is_err());
+
+        backend.click_up();
+        drop((sink, backend));
+
+        assert_eq!(recorded_events(&path), vec!["up, , , 4"]);
+    }
+
+    const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn idle_after_timeout_and_back_on_activity() {
+        let start = Instant::now();
+        let secs = |s: u64| start + Duration::from_secs(s);
+        let mut idle = IdleTracker::default();
+
+        assert_eq!(idle.update(start, secs(59), IDLE_TIMEOUT), None);
+        assert_eq!(idle.update(start, secs(60), IDLE_TIMEOUT), Some(true));
+        assert!(idle.is_idle());
+        // only the change gets reported
+        assert_eq!(idle.update(start, secs(300), IDLE_TIMEOUT), None);
+        // a key press at 301
+        assert_eq!(idle.update(secs(301), secs(301), IDLE_TIMEOUT), Some(false));
+        assert!(!idle.is_idle());
+        assert_eq!(idle.update(secs(301), secs(360), IDLE_TIMEOUT), None);
+        assert_eq!(idle.update(secs(301), secs(361), IDLE_TIMEOUT), Some(true));
+    }
+
+    #[test]
+    fn idle_zero_timeout_never_idles() {
+        let start = Instant::now();
+        let mut idle = IdleTracker::default();
+        let day = Duration::from_secs(24 * 60 * 60);
+        assert_eq!(idle.update(start, start + day, Duration::ZERO), None);
+        assert!(!idle.is_idle());
+    }
+
+    #[test]
+    fn idle_turning_timeout_off_wakes_up() {
+        let start = Instant::now();
+        let later = start + Duration::from_secs(120);
+        let mut idle = IdleTracker::default();
+        assert_eq!(idle.update(start, later, IDLE_TIMEOUT), Some(true));
+        // set to 0 in the config while idle
+        assert_eq!(idle.update(start, later, Duration::ZERO), Some(false));
+    }
+
+    #[test]
+    fn idle_activity_after_now() {
+        // last_activity is read from another thread & can be a hair newer than `now`
+        let now = Instant::now();
+        let mut idle = IdleTracker::default();
+        let activity = now + Duration::from_millis(5);
+        assert_eq!(idle.update(activity, now, IDLE_TIMEOUT), None);
+        assert!(!idle.is_idle());
+    }
+
+    const BUTTONS: [u16; 5] = [
+        VK_LBUTTON.0,
+        VK_RBUTTON.0,
+        VK_MBUTTON.0,
+        VK_XBUTTON1.0,
+        VK_XBUTTON2.0,
+    ];
+
+    #[test]
+    fn held_button_round_trip() {
+        assert_eq!(unpack_held(0), None);
+        for keycode in BUTTONS {
+            let (_, click_up) = click_states(keycode).unwrap();
+            for mouse_dev in [RECORDING_DEV, 1, 11, 20] {
+                let packed = pack_held(mouse_dev, click_up);
+                assert_ne!(packed, 0, "a held button has to look held");
+                assert_eq!(unpack_held(packed), Some((mouse_dev, click_up)));
+            }
+        }
+    }
+
+    fn held(sink: &MouseSink) -> Option<(Device, MouseState)> {
+        match sink {
+            MouseSink::Recording(recording) => unpack_held(recording.held.load(Ordering::SeqCst)),
+            MouseSink::Interception(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn recording_click_clears_held() {
+        let (sink, path) = temp_recording("held_clears");
+        let mut mouse = sink.backend();
+        for keycode in BUTTONS {
+            mouse.set_click_keycode(keycode).unwrap();
+            mouse.click_down();
+            let (_, click_up) = click_states(keycode).unwrap();
+            assert_eq!(held(&sink), Some((RECORDING_DEV, click_up)));
+            mouse.click_up();
+            assert_eq!(held(&sink), None);
+        }
+        drop((sink, mouse));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn switching_button_while_held_releases_it() {
+        let (sink, path) = temp_recording("held_switch");
+        let mut mouse = sink.backend();
+        mouse.click_down();
+        // the same button again isn't a switch
+        mouse.set_click_keycode(VK_LBUTTON.0).unwrap();
+        mouse.set_click_keycode(VK_RBUTTON.0).unwrap();
+        assert_eq!(held(&sink), None);
+        // nothing held, nothing to release
+        mouse.set_click_keycode(VK_MBUTTON.0).unwrap();
+        mouse.click_down();
+        mouse.click_up();
+        drop((sink, mouse));
+
+        assert_eq!(
+            recorded_events(&path),
+            vec!["down, , , 1", "up, , , 1", "down, , , 4", "up, , , 4"]
+        );
+    }
 }
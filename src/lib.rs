@@ -0,0 +1,9 @@
+#![allow(dead_code)]
+#![feature(once_cell)]
+
+// Platform independent parts of the bot (config parsing, images & the detection/aim math), split out so they
+//     can be reused by other tools. Capture, input & the gui are windows only and live in the binary
+pub mod aim;
+pub mod config;
+pub mod coord;
+pub mod image;
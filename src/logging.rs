@@ -1,3 +1,6 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::lazy::SyncLazy;
 use std::sync::Mutex;
 
@@ -21,6 +24,69 @@ pub fn log_err__(string: String) {
     buf.push('\n');
 }
 
+// file, line & a subkey for call sites that log about several things (eg. a loop over keys)
+type OnceSite = (&'static str, u32, u64);
+
+struct OnceState {
+    msg_hash: u64,
+    first_line: String, // for the suppressed note, the hash alone can't say what got repeated
+    suppressed: u32,
+}
+
+// Validation warnings get re-checked on every reload, only the first of a run of identical ones gets through
+static ONCE_SITES: SyncLazy<Mutex<HashMap<OnceSite, OnceState>>> =
+    SyncLazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn hash_of<T: Hash + ?Sized>(val: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    val.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn suppressed_note(state: &OnceState) -> String {
+    format!(
+        "(suppressed \u{d7}{}) {}",
+        state.suppressed, state.first_line
+    )
+}
+
+// Logs unless this site's last message was the same one, a different message resets the suppression
+pub fn log_err_once__(site: OnceSite, string: String) {
+    let new_state = OnceState {
+        msg_hash: hash_of(&string),
+        first_line: string.lines().next().unwrap_or_default().to_string(),
+        suppressed: 0,
+    };
+    let mut sites = ONCE_SITES.lock().unwrap();
+    match sites.get_mut(&site) {
+        Some(state) if state.msg_hash == new_state.msg_hash => {
+            state.suppressed += 1;
+            return;
+        }
+        Some(state) => {
+            let old = std::mem::replace(state, new_state);
+            if old.suppressed > 0 {
+                log__(suppressed_note(&old));
+            }
+        }
+        None => {
+            sites.insert(site, new_state);
+        }
+    }
+    drop(sites);
+    log_err__(string);
+}
+
+// Reports repeats that never got followed by a different message, called on shutdown
+pub fn flush_suppressed() {
+    for state in ONCE_SITES.lock().unwrap().values_mut() {
+        if state.suppressed > 0 {
+            log__(suppressed_note(state));
+            state.suppressed = 0;
+        }
+    }
+}
+
 pub fn drain_log() -> String {
     std::mem::take(&mut LOG_BUF.lock().unwrap())
 }
@@ -35,5 +101,95 @@ macro_rules! log_err {
         $crate::logging::log_err__(format!("{}", format_args!($( $arg ),*) ))
     };
 }
+// `log_err_once!(for subkey; ...)` keeps a separate suppression per subkey at the same call site
+macro_rules! log_err_once {
+    (for $subkey: expr; $( $arg: expr ),*) => {
+        $crate::logging::log_err_once__(
+            (file!(), line!(), $crate::logging::hash_of(&$subkey)),
+            format!("{}", format_args!($( $arg ),*)),
+        )
+    };
+    ($( $arg: expr ),*) => {
+        $crate::logging::log_err_once__((file!(), line!(), 0), format!("{}", format_args!($( $arg ),*)))
+    };
+}
 pub(crate) use log;
 pub(crate) use log_err;
+pub(crate) use log_err_once;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the log buffer is shared by the whole process, these tests take turns draining it
+    static LOG_TEST_LOCK: Mutex<()> = Mutex::new(());
+
+    // drained log lines containing `marker`, without the error coloring
+    fn drained_lines(marker: &str) -> Vec<String> {
+        drain_log()
+            .lines()
+            .filter(|line| line.contains(marker))
+            .map(|line| line.replace(FAKE_ANSI, ""))
+            .collect()
+    }
+
+    #[test]
+    fn once_suppresses_repeats_at_same_site() {
+        let _lock = LOG_TEST_LOCK.lock().unwrap();
+        let site = ("logging test", 1, 0);
+        for _ in 0..3 {
+            log_err_once__(site, "repeat_a".into());
+        }
+        assert_eq!(drained_lines("repeat_a"), vec!["repeat_a"]);
+
+        // a different message gets through & reports how many of the last one were hidden
+        log_err_once__(site, "repeat_b".into());
+        assert_eq!(
+            drained_lines("repeat_"),
+            vec!["(suppressed \u{d7}2) repeat_a", "repeat_b"]
+        );
+
+        // and the earlier message counts as new again
+        log_err_once__(site, "repeat_a".into());
+        assert_eq!(drained_lines("repeat_"), vec!["repeat_a"]);
+    }
+
+    #[test]
+    fn once_sites_are_separate() {
+        let _lock = LOG_TEST_LOCK.lock().unwrap();
+        // same file & line, different subkeys, eg. one per config key
+        log_err_once__(("logging test", 2, 1), "per_key".into());
+        log_err_once__(("logging test", 2, 2), "per_key".into());
+        log_err_once__(("logging test", 3, 0), "per_key".into());
+        log_err_once__(("logging test", 2, 1), "per_key".into());
+        assert_eq!(drained_lines("per_key"), vec!["per_key"; 3]);
+    }
+
+    #[test]
+    fn flush_reports_pending_repeats_once() {
+        let _lock = LOG_TEST_LOCK.lock().unwrap();
+        let site = ("logging test", 4, 0);
+        log_err_once__(site, "flushed first line\n\tdetails".into());
+        log_err_once__(site, "flushed first line\n\tdetails".into());
+        drain_log();
+
+        // only the first line goes in the note
+        flush_suppressed();
+        assert_eq!(
+            drained_lines("flushed"),
+            vec!["(suppressed \u{d7}1) flushed first line"]
+        );
+        flush_suppressed();
+        assert!(drained_lines("flushed").is_empty());
+    }
+
+    #[test]
+    fn once_macro_keys_on_call_site() {
+        let _lock = LOG_TEST_LOCK.lock().unwrap();
+        for _ in 0..2 {
+            log_err_once!("macro_site {}", 1);
+        }
+        log_err_once!("macro_site {}", 1);
+        assert_eq!(drained_lines("macro_site"), vec!["macro_site 1"; 2]);
+    }
+}
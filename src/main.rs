@@ -1,27 +1,44 @@
 #![allow(dead_code)]
 #![feature(once_cell)]
 
+mod bot_config;
 mod capture;
-mod config;
-mod coord;
+mod decision_log;
+mod events;
+mod frame_ring;
 mod gui;
-mod image;
 mod input;
 mod logging;
+mod paths;
 mod pixel_bot;
+mod self_test;
+mod sound;
+mod theme;
 
-mod svg_drawing;
+// imported at the crate root so the binary's modules keep using crate:: paths
+use pixelbot::{aim, config, coord, image};
 
 use config::{Bounded, CfgKey, Config, ParseError, ValType};
 use crossbeam::channel;
 use gui::Gui;
-use logging::log_err;
-use pixel_bot::PixelBot;
+use input::{validate_keycodes, MouseRecording};
+use logging::{log, log_err};
+use pixel_bot::{PixelBot, PowerRequest};
+use theme::Theme;
 use std::io::{self, ErrorKind};
 use std::panic;
-use std::sync::{Arc, RwLock};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
-const CFG_PATH: &str = "config.cfg";
+// Called from its own thread, looking for the mouse blocks until it's found or times out
+fn start_bot(pixel_bot: &Mutex<PixelBot>, gui_sender: channel::Sender<pixel_bot::Message>) {
+    if let Err(msg) = pixel_bot.lock().unwrap().start(gui_sender) {
+        log_err!("{}", msg); // Interception driver not installed or mouse capture timed out
+    }
+}
 
 // Kills the entire process if one thread panics, shows panicinfo in messagebox
 fn set_panic_hook() {
@@ -32,6 +49,8 @@ fn set_panic_hook() {
     let orig_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
         orig_hook(panic_info);
+        // a redirected click held down when we died would stay held in the game
+        input::release_held_button();
 
         let mut caption_buf = "Fatal Error\0".encode_utf16().collect::<Vec<_>>();
         let mut text_buf = panic_info
@@ -49,6 +68,11 @@ fn set_panic_hook() {
     }));
 }
 
+// Dims of the output the capture thread will use, the primary display if it can't be found
+fn display_dims(monitor_idx: u32) -> (u32, u32) {
+    capture::output_dims(monitor_idx).unwrap_or_else(primary_display_dims)
+}
+
 fn primary_display_dims() -> (u32, u32) {
     use windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_CXSCREEN, SM_CYSCREEN};
     unsafe {
@@ -59,10 +83,109 @@ fn primary_display_dims() -> (u32, u32) {
     }
 }
 
+// `--record-mouse [path]` writes what would've been sent to the mouse to a file instead of sending it
+fn record_mouse_arg() -> Option<PathBuf> {
+    let mut args = std::env::args().skip(1).skip_while(|arg| arg != "--record-mouse");
+    args.next()?;
+    Some(match args.next() {
+        Some(path) if !path.starts_with("--") => PathBuf::from(path),
+        _ => paths::resolve_data_path("mouse-recording.csv"),
+    })
+}
+
+// Picks up edits made to the config file while running. Only keys whose line changed since the last look get
+//     applied, so unsaved gui changes to other keys survive & the gui's own saves don't change anything
+fn spawn_config_watcher(
+    config: Arc<RwLock<Config>>,
+    cfg_path: &'static str,
+    gui_sender: channel::Sender<pixel_bot::Message>,
+) {
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+    let modified = move || std::fs::metadata(cfg_path).and_then(|m| m.modified()).ok();
+    let mut last_modified = modified();
+    let mut last_file =
+        Config::from_file(cfg_path).unwrap_or_else(|_| config.read().unwrap().clone());
+
+    thread::Builder::new()
+        .name("config watcher".to_string())
+        .spawn(move || loop {
+            thread::sleep(POLL_INTERVAL);
+            let now_modified = modified();
+            if now_modified == last_modified {
+                continue;
+            }
+            last_modified = now_modified;
+
+            // a half edited file keeps the running config as is, deleted lines don't reset anything either
+            let (file_cfg, missing) = match Config::from_file(cfg_path) {
+                Ok(cfg) => (cfg, Vec::new()),
+                Err(err) => match err.downcast::<ParseError>() {
+                    Ok(e) => match *e {
+                        ParseError::NotExhaustive(cfg, missing) => (cfg, missing),
+                        e => {
+                            log_err!("Ignoring the edited config file:\n\t{}", e);
+                            continue;
+                        }
+                    },
+                    Err(e) => {
+                        log_err!("Couldn't read the edited config file:\n\t{}", e);
+                        continue;
+                    }
+                },
+            };
+
+            let mut cfg = config.write().unwrap();
+            let changed: Vec<CfgKey> = file_cfg
+                .changed_keys(&last_file)
+                .filter(|key| !missing.contains(key))
+                .filter(|&key| file_cfg.differs_from(&cfg, key))
+                .collect();
+            let mut applied = Vec::new();
+            for key in changed {
+                // runtime bounds (eg. the monitor index) still apply to edited values
+                match cfg.set_val(key, file_cfg.get(key)) {
+                    Ok(_) => {
+                        log!(
+                            "Config file changed: {} = {}",
+                            key.as_string(),
+                            cfg.get(key)
+                        );
+                        applied.push(key);
+                    }
+                    Err(e) => log_err!("Not applying edited {}: {}", key.as_string(), e),
+                }
+            }
+            drop(cfg);
+            last_file = file_cfg;
+
+            if !applied.is_empty() {
+                let _ = gui_sender.send(pixel_bot::Message::ConfigChanged(applied));
+            }
+        })
+        .unwrap();
+}
+
 fn main() {
+    // for external config editors, every key with its type, bounds & default as json
+    if std::env::args().skip(1).any(|arg| arg == "--dump-schema") {
+        println!("{}", Config::schema_json());
+        return;
+    }
+    // for scripted installs, nonzero when any stage failed
+    if std::env::args().skip(1).any(|arg| arg == "--self-test") {
+        let passed = self_test::run();
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
     set_panic_hook();
 
-    let config = Arc::new(RwLock::new(match Config::from_file(CFG_PATH) {
+    // the gui holds on to it for the save button
+    let cfg_path: &'static str =
+        Box::leak(paths::init_config_path().to_string_lossy().into_owned().into_boxed_str());
+
+    // no config file yet, the gui walks through the setup wizard
+    let mut first_run = false;
+    let config = Arc::new(RwLock::new(match Config::from_file(cfg_path) {
         Ok(cfg) => cfg,
         Err(err) => {
             let default_cfg = Config::default();
@@ -70,8 +193,9 @@ fn main() {
 
             if let Some(e) = err.downcast_ref::<io::Error>() {
                 if e.kind() == ErrorKind::NotFound {
-                    log_err!("\tConfig file not found, saving default to {}", CFG_PATH);
-                    default_cfg.write_to_file(CFG_PATH).unwrap();
+                    log_err!("\tConfig file not found, saving default to {}", cfg_path);
+                    default_cfg.write_to_file(cfg_path).unwrap();
+                    first_run = true;
                 }
                 default_cfg
             } else if let Ok(e) = err.downcast::<ParseError>() {
@@ -89,35 +213,130 @@ fn main() {
         }
     }));
 
-    // Setting crop_w and crop_h bounds relative to screen size
-    let (screen_w, screen_h) = primary_display_dims();
-    let crop_w = ValType::Unsigned(Bounded::new(0, 0..=(screen_w / 2) - 1));
-    let crop_h = ValType::Unsigned(Bounded::new(0, 0..=(screen_h / 2) - 1));
     let mut cfg = config.write().unwrap();
-    cfg.set_bounds(CfgKey::CropW, crop_w).unwrap();
-    cfg.set_bounds(CfgKey::CropH, crop_h).unwrap();
+
+    // the monitor slider only goes as far as the connected outputs, keeping a saved index for an unplugged one
+    let monitor_idx: u32 = <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::MonitorIndex)).val;
+    let last_output = (capture::output_count().max(1) - 1) as u32;
+    cfg.set_bounds(
+        CfgKey::MonitorIndex,
+        ValType::Unsigned(Bounded::new(0, 0..=last_output.max(monitor_idx))),
+    )
+    .unwrap();
+    cfg.take_dirty_key(CfgKey::MonitorIndex); // only the bounds changed, nothing to save
+
+    // Old configs stored the crop in pixels, converting them against this screen & saving the percentages
+    let (screen_w, screen_h) = display_dims(monitor_idx);
+    // even an uncropped screen has nothing further from the crosshair than its corners
+    let half_diagonal = ((screen_w as f32).hypot(screen_h as f32) / 2.) as u32;
+    let fov_radius: u32 = <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::FovRadius)).val;
+    cfg.set_bounds(
+        CfgKey::FovRadius,
+        ValType::Unsigned(Bounded::new(0, 0..=half_diagonal.max(fov_radius))),
+    )
+    .unwrap();
+    cfg.take_dirty_key(CfgKey::FovRadius);
+    let migrated = cfg.migrate_legacy_crop((screen_w, screen_h));
+    for key in &migrated {
+        log!("Migrated pixel crop to {} = {}", key.as_string(), cfg.get(*key));
+    }
+    let migrated_y = cfg.migrate_legacy_y_multiplier();
+    if let Some(val) = migrated_y {
+        let crop_h = screen_h - 2 * cfg.crop_px((screen_w, screen_h)).1;
+        let old_bias = coord::frame_center((1, crop_h as usize)).y * (1. - val);
+        log!(
+            "Migrated y_multiplier = {0} to y_offset_multiplier = {0}, it now scales the vertical offset from the crosshair instead of the target's position in the crop.\n\t\
+            The old key also aimed {1:.0}px above every target on this crop, that upward bias is gone",
+            val,
+            old_bias
+        );
+    }
+    if !migrated.is_empty() || migrated_y.is_some() {
+        if let Err(e) = cfg.write_to_file(cfg_path) {
+            log_err!("Failed to save migrated config: {}", e);
+        }
+    }
     cfg.is_dirty = false;
+    validate_keycodes(&cfg);
     drop(cfg);
 
     let (gui_sender, gui_receiver) = channel::unbounded();
-    let pixel_bot = std::sync::Mutex::new(PixelBot::new(config.clone()));
+    spawn_config_watcher(config.clone(), cfg_path, gui_sender.clone());
+    let mut bot = PixelBot::new(config.clone());
+    if let Some(path) = record_mouse_arg() {
+        match MouseRecording::create(&path) {
+            Ok(recording) => bot.record_mouse(recording),
+            Err(e) => log_err!("Couldn't create mouse recording {}:\n\t{}", path.display(), e),
+        }
+    }
+    let pixel_bot = Mutex::new(bot);
+    let preview_wanted = pixel_bot.lock().unwrap().preview_flag();
+    let commands = pixel_bot.lock().unwrap().command_sender();
+    let (power_tx, power_rx) = channel::unbounded();
 
-    crossbeam::scope(|s| {
-        // calling start in a thread to avoid blocking while looking for mouse
-        s.spawn(|_| {
-            if let Err(msg) = pixel_bot.lock().unwrap().start(gui_sender) {
-                log_err!("{}", msg); // Interception driver not installed error
-            }
-        });
+    let clean_shutdown = crossbeam::scope(|s| {
+        let start_sender = gui_sender.clone();
+        s.spawn(|_| start_bot(&pixel_bot, start_sender));
 
-        let mut gui = Gui::new(1000, 1000, config.clone());
-        gui.init(screen_h as f32 / screen_w as f32, gui_receiver, CFG_PATH);
+        let theme = Theme::from_file(cfg_path).unwrap_or_else(|e| {
+            log_err!("{}\n\tUsing the default theme", e);
+            Theme::default()
+        });
+        let mut gui = Gui::new(1000, 1000, config.clone(), commands, theme);
+        gui.init(
+            (screen_w, screen_h),
+            gui_receiver,
+            power_tx,
+            cfg_path,
+            first_run,
+        );
+        // every reload restarts the bot threads' loops, so a burst of config writes only gets one
+        const RELOAD_INTERVAL: Duration = Duration::from_millis(50);
+        let mut last_reload: Option<Instant> = None;
         while gui.wait(0.01) {
-            if config.read().unwrap().is_dirty {
-                pixel_bot.lock().unwrap().reload().unwrap();
+            preview_wanted.store(gui.mirror_visible(), Ordering::Relaxed);
+            for request in power_rx.try_iter() {
+                match request {
+                    PowerRequest::Start => {
+                        let start_sender = gui_sender.clone();
+                        s.spawn(|_| start_bot(&pixel_bot, start_sender));
+                    }
+                    // the lock is held for as long as start() looks for the mouse
+                    PowerRequest::Stop => match pixel_bot.try_lock() {
+                        Ok(mut bot) => match bot.stop() {
+                            Ok(detached) if detached.is_empty() => log!("Stopped the bot"),
+                            Ok(detached) => log_err!(
+                                "Stopped the bot, {} thread(s) had to be detached",
+                                detached.len()
+                            ),
+                            Err(e) => log_err!("{}", e),
+                        },
+                        Err(_) => log_err!("The bot is still starting, stop it again in a moment"),
+                    },
+                }
+            }
+            let reload_due = last_reload.map_or(true, |t| t.elapsed() >= RELOAD_INTERVAL);
+            if reload_due && config.read().unwrap().is_dirty {
+                last_reload = Some(Instant::now());
+                validate_keycodes(&config.read().unwrap());
+                // not started when the driver is missing, the threads pick up the config once they do
+                let _ = pixel_bot.lock().unwrap().reload();
                 config.write().unwrap().is_dirty = false;
             }
         }
+        gui.save_session();
+
+        // stopping the bot threads before the gui (and the channel receiver it owns) goes away,
+        //     so shutdown doesn't depend on drop order. Erroring just means it never started
+        let stuck_threads = pixel_bot.lock().unwrap().stop().unwrap_or_default();
+        drop(gui);
+        stuck_threads.is_empty()
     })
     .unwrap();
+
+    // nonzero so wrapper scripts can tell a thread had to be abandoned on the way out
+    if !clean_shutdown {
+        drop(pixel_bot); // exit() skips destructors, this one releases any held mouse button
+        std::process::exit(2);
+    }
 }
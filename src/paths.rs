@@ -0,0 +1,175 @@
+use crate::logging::log;
+
+use std::env;
+use std::lazy::SyncOnceCell;
+use std::path::{Path, PathBuf};
+
+pub const CFG_NAME: &str = "config.cfg";
+const APP_DIR_NAME: &str = "pixelbot";
+
+// Directory of the config that got picked, everything else we read or write lives next to it
+static DATA_DIR: SyncOnceCell<PathBuf> = SyncOnceCell::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CfgSource {
+    Flag,       // --config <path>
+    ExeDir,     // config.cfg next to the exe, for portable installs
+    AppData,    // %APPDATA%\pixelbot\config.cfg
+    WorkingDir, // no %APPDATA%, shouldn't happen on windows
+}
+
+// --config flag > config next to the exe > %APPDATA%\pixelbot\config.cfg (created on first save)
+pub fn resolve_config_path(
+    mut args: impl Iterator<Item = String>,
+    exe_dir: Option<&Path>,
+    appdata_dir: Option<&Path>,
+) -> (PathBuf, CfgSource) {
+    while let Some(arg) = args.next() {
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return (PathBuf::from(path), CfgSource::Flag);
+        }
+        if arg == "--config" {
+            if let Some(path) = args.next() {
+                return (PathBuf::from(path), CfgSource::Flag);
+            }
+        }
+    }
+
+    if let Some(exe_cfg) = exe_dir.map(|dir| dir.join(CFG_NAME)) {
+        if exe_cfg.is_file() {
+            return (exe_cfg, CfgSource::ExeDir);
+        }
+    }
+
+    match appdata_dir {
+        Some(dir) => (dir.join(APP_DIR_NAME).join(CFG_NAME), CfgSource::AppData),
+        None => (PathBuf::from(CFG_NAME), CfgSource::WorkingDir),
+    }
+}
+
+// Resolves the config path from the real args & environment, only meant to be called once at startup
+pub fn init_config_path() -> PathBuf {
+    let exe_dir = exe_dir();
+    let appdata_dir = env::var_os("APPDATA").map(PathBuf::from);
+    let (path, source) =
+        resolve_config_path(env::args().skip(1), exe_dir.as_deref(), appdata_dir.as_deref());
+
+    let data_dir = path.parent().map_or_else(PathBuf::new, Path::to_path_buf);
+    let _ = DATA_DIR.set(data_dir);
+    log!("Using config at {} ({:?})", path.display(), source);
+    path
+}
+
+// Files we own (session log, exports) go next to the config. Files that ship with the exe (fonts)
+//     are found next to the exe when they aren't in the data dir
+pub fn resolve_data_path(name: &str) -> PathBuf {
+    let data_path = match DATA_DIR.get() {
+        Some(dir) => dir.join(name),
+        None => return PathBuf::from(name),
+    };
+    if data_path.exists() {
+        return data_path;
+    }
+    match exe_dir().map(|dir| dir.join(name)) {
+        Some(exe_path) if exe_path.exists() => exe_path,
+        _ => data_path,
+    }
+}
+
+fn exe_dir() -> Option<PathBuf> {
+    env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    // fresh dir for each test, with a config.cfg in it when `with_cfg`
+    fn temp_dir(name: &str, with_cfg: bool) -> PathBuf {
+        let dir = env::temp_dir().join(format!("pixelbot_paths_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        if with_cfg {
+            std::fs::write(dir.join(CFG_NAME), "").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn flag_wins() {
+        let exe_dir = temp_dir("flag_wins", true);
+        let appdata = Path::new("appdata");
+        for flag in [
+            &["--config=other.cfg"][..],
+            &["--config", "other.cfg"],
+            &["-v", "--config", "other.cfg"],
+        ] {
+            assert_eq!(
+                resolve_config_path(args(flag), Some(&exe_dir), Some(appdata)),
+                (PathBuf::from("other.cfg"), CfgSource::Flag),
+                "{:?}",
+                flag
+            );
+        }
+        // no path after it, same as not passing it
+        assert_eq!(
+            resolve_config_path(args(&["--config"]), Some(&exe_dir), Some(appdata)),
+            (exe_dir.join(CFG_NAME), CfgSource::ExeDir)
+        );
+        std::fs::remove_dir_all(&exe_dir).unwrap();
+    }
+
+    #[test]
+    fn exe_dir_before_appdata() {
+        let exe_dir = temp_dir("exe_dir_before_appdata", true);
+        let appdata = temp_dir("exe_dir_before_appdata_appdata", false);
+        assert_eq!(
+            resolve_config_path(args(&[]), Some(&exe_dir), Some(&appdata)),
+            (exe_dir.join(CFG_NAME), CfgSource::ExeDir)
+        );
+        std::fs::remove_dir_all(&exe_dir).unwrap();
+        std::fs::remove_dir_all(&appdata).unwrap();
+    }
+
+    #[test]
+    fn appdata_without_exe_dir_cfg() {
+        // only an existing file next to the exe counts, appdata's gets created on the first save
+        let exe_dir = temp_dir("appdata_fallback", false);
+        let appdata = temp_dir("appdata_fallback_appdata", false);
+        let expected = (
+            appdata.join(APP_DIR_NAME).join(CFG_NAME),
+            CfgSource::AppData,
+        );
+        assert_eq!(
+            resolve_config_path(args(&[]), Some(&exe_dir), Some(&appdata)),
+            expected
+        );
+        assert_eq!(
+            resolve_config_path(args(&[]), None, Some(&appdata)),
+            expected
+        );
+        std::fs::remove_dir_all(&exe_dir).unwrap();
+        std::fs::remove_dir_all(&appdata).unwrap();
+    }
+
+    #[test]
+    fn working_dir_without_appdata() {
+        let exe_dir = temp_dir("working_dir", false);
+        for exe_dir in [Some(exe_dir.as_path()), None] {
+            assert_eq!(
+                resolve_config_path(args(&[]), exe_dir, None),
+                (PathBuf::from(CFG_NAME), CfgSource::WorkingDir)
+            );
+        }
+        std::fs::remove_dir_all(&exe_dir).unwrap();
+    }
+}
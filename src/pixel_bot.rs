@@ -1,26 +1,137 @@
-use crate::capture::{CaptureError, DXGICapturer};
-use crate::config::{Bounded, CfgKey, Config, ValType};
-use crate::coord::Coord;
-use crate::image::{Bgra8, Color, Image};
-use crate::input::{find_mouse_dev, key_pressed, wait_for_release, InterceptionState};
-use crate::logging::{log, log_err};
-
-use crossbeam::channel::{self, Receiver, Sender};
+use crate::aim::{
+    center_luma, compute_aim, is_target_switch, measure_shift, upscale_aim_result, AimResult,
+    AimSmoother, AimStats, LeadPredictor, MotionMap, RoiHint, SwitchRamp, SWITCH_JUMP_DIVISOR,
+};
+use crate::bot_config::{AimConfig, CaptureConfig, ClickConfig, MoverConfig};
+use crate::capture::{CaptureError, DXGICapturer, OutputInfo};
+use crate::config::{self, Bounded, CfgKey, Config, ValType};
+use crate::coord::{center_pixel, Coord, CropPos};
+use crate::decision_log::{Decision, DecisionLog};
+use crate::events::{spawn_event_thread, BotEvent, EventSender};
+use crate::frame_ring::{FrameRecord, FrameRing};
+use crate::image::{self, image_ops::color_distance, Bgra8, Color, Gray8, Image, Pixel};
+use crate::input::{
+    key_pressed, last_key_activity, release_held_button, DriverStatus, IdleTracker,
+    InterceptionShared, KeyToggle, MouseRecording, MouseSink, MOUSE_CAPTURE_TIMEOUT_ERR,
+};
+use crate::logging::{log, log_err, log_err_once};
+use crate::paths::resolve_data_path;
+
+use crossbeam::channel::{self, Receiver, RecvTimeoutError, Sender, TrySendError};
 use rand::{self, Rng};
-use rustc_hash::FxHashSet;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
 use std::time::Instant;
 
 pub struct CapData {
-    pub img: Image<Vec<u8>, Bgra8>,
-    pub target_coords: Option<Vec<Coord<usize>>>,
-    pub aim_coord: Option<Coord<usize>>,
+    pub img: Image<Vec<u8>, Bgra8>, // shrunk to fit the mirror pixel budget, can be smaller than crop_dims
+    pub crop_dims: (usize, usize),  // the frame detection ran on, every coord here is in this space
+    pub target_coords: Option<Vec<CropPos>>,
+    pub rejected_coords: Vec<Vec<CropPos>>, // clusters that failed the aspect ratio filter
+    pub other_targets: Vec<Vec<CropPos>>, // bbox corners of acceptable clusters that weren't picked
+    pub aim_coord: Option<CropPos>,
+    pub aim_point: Option<CropPos>, // where this frame's (smoothed) move actually heads, None without a target
+    pub predicted_point: Option<CropPos>, // aim_coord plus the lead, None with prediction off or no velocity yet
+    pub wash_rejected: bool,
+    pub effective_divisor: f32,
+    pub color_readout: Option<ColorReadout>, // None when no cluster was selected
+    pub confirmed: bool, // false while the selected cluster's outline check hasn't passed
+    pub motion_score: Option<f32>, // None with the motion gate off
+}
+
+// How far the selected cluster's actual color is from the configured one, for threshold tuning
+#[derive(Debug, Clone, Copy)]
+pub struct ColorReadout {
+    pub target: Color<u8>,
+    pub measured: Color<u8>,
+    pub distance: f32,
+    pub max_distance: f32, // anything further than this doesn't get detected
+}
+// Per stage timings, capture and processing run concurrently so they don't add up to the frame time
+#[derive(Debug, Clone, Copy)]
+pub struct Timings {
+    pub capture: Duration,
+    pub convert: Duration, // part of `capture` spent converting to BGRA8, zero when the output is already BGRA8
+    pub process: Duration,
+}
+
+// Pixel format of the duplicated output
+#[derive(Debug, Clone, Copy)]
+pub struct CaptureFormat {
+    pub name: &'static str,
+    pub needs_conversion: bool,
+}
+
+// Detection totals over the last second, light enough to leave on for overnight sessions
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub frames: u32,
+    pub frames_with_target: u32,
+    pub clusters_rejected_area: u32,
+    pub clusters_rejected_aspect: u32,
+    pub clusters_rejected_static: u32,
+    pub clusters_rejected_fov: u32, // the selected cluster, too far from the crosshair to aim at
+    pub roi_hits: u32,              // frames where searching around the last target was enough
+    pub capture_reloads: u32,
 }
+
+impl Summary {
+    // New filters in compute_aim should count into AimStats & get their own bucket here.
+    //     `has_target` is after every filter in the aim thread too, the fov one & the wash grace included
+    pub fn record(&mut self, stats: &AimStats, has_target: bool, outside_fov: bool) {
+        self.frames += 1;
+        self.frames_with_target += has_target as u32;
+        self.clusters_rejected_area += stats.clusters_rejected_area;
+        self.clusters_rejected_aspect += stats.clusters_rejected_aspect;
+        self.clusters_rejected_static += stats.clusters_rejected_static;
+        self.clusters_rejected_fov += outside_fov as u32;
+        self.roi_hits += stats.roi_hit as u32;
+    }
+
+    pub fn merge(&mut self, other: &Summary) {
+        self.frames += other.frames;
+        self.frames_with_target += other.frames_with_target;
+        self.clusters_rejected_area += other.clusters_rejected_area;
+        self.clusters_rejected_aspect += other.clusters_rejected_aspect;
+        self.clusters_rejected_static += other.clusters_rejected_static;
+        self.clusters_rejected_fov += other.clusters_rejected_fov;
+        self.roi_hits += other.roi_hits;
+        self.capture_reloads += other.capture_reloads;
+    }
+}
+
 pub enum Message {
     IterTime(Duration),
+    Timings(Timings),
     CaptureData(CapData),
+    DriverStatus(DriverStatus),
+    ConfigChanged(Vec<CfgKey>), // values changed outside of the gui widgets
+    ClickStats(Vec<Duration>),  // most recent autoclick intervals, oldest first
+    ClickMode(ClickMode),
+    BotState(BotState),
+    CaptureFormat(CaptureFormat),
+    Summary(Summary), // sent once a second by the aim thread
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClickMode {
+    Regular,          // Good ole bread and butter, the classic
+    Auto,             // Repeatedly clicks mmb when holding autoclick key
+    Redirected(bool), // mmb clicks mirror autoclick key clicks, stores whether pressed
+    Trigger,          // Auto, but only while a target is under the crosshair
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BotState {
+    Running,
+    Paused,  // aim toggled off
+    Stalled, // aiming, but no frames are coming in (a static screen doesn't produce any either)
+    Idle,    // no bound key pressed in a while, mouse output is off until the next press
+    // other programs hold every duplication slot, stores the seconds spent retrying so far
+    WaitingForSlot(u32),
 }
 
 enum ThreadMsg {
@@ -28,66 +139,368 @@ enum ThreadMsg {
     Reload,
 }
 
+// The gui's start/stop button, handled by whoever owns the PixelBot since starting blocks on the mouse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerRequest {
+    Start,
+    Stop,
+}
+
+// One-off actions from the gui, these don't belong in the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BotCommand {
+    Pause,
+    Resume,
+    Screenshot,    // saves the current crop as a png, same as the screenshot key
+    Calibrate,     // same as the calibrate key
+    ReinitCapture, // recreates the output duplication
+    DumpDebug,     // logs the current aim params & stats
+    DumpDecisions, // logs the last few hundred aim decisions, one line each
+    CalibrateCounts, // measures pixels_per_count by turning the view a known number of counts
+}
+
+// Routes each command to the thread that handles it. Cheap to clone, sending while the bot isn't
+//     running just logs and drops the command
+#[derive(Clone)]
+pub struct CommandSender {
+    aim_tx: Sender<BotCommand>,
+    capture_tx: Sender<BotCommand>,
+    running: Arc<AtomicBool>,
+}
+
+impl CommandSender {
+    pub fn send(&self, cmd: BotCommand) {
+        if !self.running.load(Ordering::Relaxed) {
+            log!("Bot isn't running, ignoring {:?}", cmd);
+            return;
+        }
+        let tx = match cmd {
+            BotCommand::ReinitCapture => &self.capture_tx,
+            _ => &self.aim_tx,
+        };
+        let _ = tx.send(cmd);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+}
+
+// Threads get this long in total to notice Stop, anything still running after it gets detached
+const STOP_TIMEOUT: Duration = Duration::from_millis(500);
+// Longest a thread waits on anything before checking for Stop again
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// JoinHandle that can be waited on with a deadline. The done sender lives inside the thread's closure,
+//     so the channel disconnects once the thread exits (or panics)
+pub struct BotThread {
+    name: &'static str,
+    handle: JoinHandle<()>,
+    done_rx: Receiver<()>,
+}
+
+impl BotThread {
+    // Joins the thread if it exits before `deadline`, otherwise leaves it detached and returns false
+    fn join_until(self, deadline: Instant) -> bool {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match self.done_rx.recv_timeout(timeout) {
+            Err(RecvTimeoutError::Disconnected) => {
+                // re-raising a panic here would crash whatever is shutting the bot down, the exit included
+                if let Err(e) = self.handle.join() {
+                    log_err!("{} thread panicked: {}", self.name, panic_message(&*e));
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+// panic!() payloads are a &str or a String, anything else has nothing to show
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(msg) => msg,
+        None => payload
+            .downcast_ref::<String>()
+            .map_or("unknown", |msg| msg),
+    }
+}
+
+pub fn spawn_bot_thread(name: &'static str, f: impl FnOnce() + Send + 'static) -> BotThread {
+    let (done_tx, done_rx) = channel::bounded::<()>(0);
+    let handle = thread::Builder::new()
+        .name(name.to_string())
+        .spawn(move || {
+            let _done = done_tx;
+            f();
+        })
+        .unwrap();
+    BotThread {
+        name,
+        handle,
+        done_rx,
+    }
+}
+
+// Other programs (recorders, overlays, remote desktop) can hold every duplication slot the system has.
+//     One of them closing frees a slot up, so creating the duplication gets retried with a backoff
+//     for a while instead of killing the capture thread right away
+struct SlotWait<'a> {
+    thread_rx: &'a Receiver<ThreadMsg>,
+    gui_sender: &'a Sender<Message>,
+    aim_enabled: &'a AtomicBool,
+}
+
+impl SlotWait<'_> {
+    const MIN_BACKOFF: Duration = Duration::from_millis(250);
+    const MAX_BACKOFF: Duration = Duration::from_secs(4);
+
+    // Ok(None) means the thread got stopped or the budget ran out, either way the caller should exit.
+    //     Errors other than NoFreeSlot are passed on untouched
+    fn retry<T>(
+        &self,
+        budget: Duration,
+        mut attempt: impl FnMut() -> Result<T, CaptureError>,
+    ) -> Result<Option<T>, CaptureError> {
+        let start = Instant::now();
+        let mut backoff = Self::MIN_BACKOFF;
+        let mut waited = false;
+        loop {
+            match attempt() {
+                Ok(val) => {
+                    if waited {
+                        log!(
+                            "Got a duplication slot after {:.1}s",
+                            start.elapsed().as_secs_f32()
+                        );
+                        let state = match self.aim_enabled.load(Ordering::Relaxed) {
+                            true => BotState::Running,
+                            false => BotState::Paused,
+                        };
+                        let _ = self.gui_sender.try_send(Message::BotState(state));
+                    }
+                    return Ok(Some(val));
+                }
+                Err(CaptureError::NoFreeSlot) => {}
+                Err(e) => return Err(e),
+            }
+
+            if !waited {
+                waited = true;
+                log!(
+                    "Every duplication slot is in use by other programs, retrying for {}s...",
+                    budget.as_secs()
+                );
+            }
+            if start.elapsed() >= budget {
+                log_err!(
+                    "No duplication slot freed up in {}s, close other recording or overlay programs and restart the bot",
+                    budget.as_secs()
+                );
+                return Ok(None);
+            }
+
+            // sleeping in short slices so Stop still gets through & the gui countdown keeps ticking
+            let wake = Instant::now() + backoff.min(budget.saturating_sub(start.elapsed()));
+            while Instant::now() < wake {
+                let secs = start.elapsed().as_secs() as u32;
+                let _ = self
+                    .gui_sender
+                    .try_send(Message::BotState(BotState::WaitingForSlot(secs)));
+                let slice = wake
+                    .saturating_duration_since(Instant::now())
+                    .min(Duration::from_secs(1));
+                match self.thread_rx.recv_timeout(slice) {
+                    Ok(ThreadMsg::Stop) | Err(RecvTimeoutError::Disconnected) => return Ok(None),
+                    // the config gets re-read once there's a duplication again
+                    Ok(ThreadMsg::Reload) | Err(RecvTimeoutError::Timeout) => {}
+                }
+            }
+            backoff = (backoff * 2).min(Self::MAX_BACKOFF);
+        }
+    }
+}
+
+// Cropped frame handed from the capture thread to the aim thread
+struct Frame {
+    img: Image<Vec<u8>, Bgra8>,
+    capture_time: Duration,
+    convert_time: Duration,
+}
+
 pub struct PixelBot {
     config: Arc<RwLock<Config>>,
-    handles: Vec<JoinHandle<()>>,
-    aim_thread_sender: Option<Sender<ThreadMsg>>,
-    click_thread_sender: Option<Sender<ThreadMsg>>,
-    mouse_dev: Option<i32>,
+    handles: Vec<BotThread>,
+    thread_senders: Vec<Sender<ThreadMsg>>,
+    mouse_sink: Option<MouseSink>,
+    mouse_recording: Option<Arc<MouseRecording>>, // replaces the driver when set
+    preview_wanted: Arc<AtomicBool>, // the gui is showing frames, so they're worth capturing without aiming
+    commands: CommandSender,
+    aim_cmd_rx: Receiver<BotCommand>,
+    capture_cmd_rx: Receiver<BotCommand>,
 }
 
 impl Drop for PixelBot {
     fn drop(&mut self) {
         let _ = self.stop();
+        release_held_button();
     }
 }
 
 impl PixelBot {
     pub fn new(config: Arc<RwLock<Config>>) -> Self {
+        let (aim_tx, aim_cmd_rx) = channel::unbounded();
+        let (capture_tx, capture_cmd_rx) = channel::unbounded();
         Self {
             config,
             handles: Vec::new(),
-            aim_thread_sender: None,
-            click_thread_sender: None,
-            mouse_dev: None,
+            thread_senders: Vec::new(),
+            mouse_sink: None,
+            mouse_recording: None,
+            preview_wanted: Arc::new(AtomicBool::new(true)),
+            commands: CommandSender {
+                aim_tx,
+                capture_tx,
+                running: Arc::new(AtomicBool::new(false)),
+            },
+            aim_cmd_rx,
+            capture_cmd_rx,
         }
     }
 
+    // Created up front so the gui can hold on to it across restarts
+    pub fn command_sender(&self) -> CommandSender {
+        self.commands.clone()
+    }
+
+    // Shared instead of a setter so the gui loop never waits on the bot lock (start() holds it while looking for the mouse)
+    pub fn preview_flag(&self) -> Arc<AtomicBool> {
+        self.preview_wanted.clone()
+    }
+
+    // Has to be set before start(), the mouse never gets touched while recording
+    pub fn record_mouse(&mut self, recording: MouseRecording) {
+        self.mouse_recording = Some(Arc::new(recording));
+    }
+
     pub fn start(&mut self, gui_sender: Sender<Message>) -> Result<(), &'static str> {
         if !self.handles.is_empty() {
             return Err("Already started");
         }
 
+        self.mouse_sink = match self.mouse_recording {
+            Some(ref recording) => {
+                log!("Recording mouse input to {} instead of sending it", recording.path().display());
+                Some(MouseSink::Recording(recording.clone()))
+            }
+            None => {
+                let shared = match InterceptionShared::new() {
+                    Ok(shared) => Arc::new(shared),
+                    Err(e) => {
+                        let status = match e {
+                            MOUSE_CAPTURE_TIMEOUT_ERR => DriverStatus::TimedOut,
+                            _ => DriverStatus::Missing,
+                        };
+                        let _ = gui_sender.try_send(Message::DriverStatus(status));
+                        return Err(e);
+                    }
+                };
+                let _ = gui_sender.try_send(Message::DriverStatus(shared.probe()));
+                Some(MouseSink::Interception(shared))
+            }
+        };
+
+        // pushed first so it's joined last, it exits once both bot threads drop their senders
+        let (event_sender, event_handles) = spawn_event_thread(self.config.clone());
+        self.handles.extend(event_handles);
+
+        let (capture_sender, capture_receiver) = channel::unbounded();
         let (aim_sender, aim_receiver) = channel::unbounded();
+        let (mover_sender, mover_receiver) = channel::unbounded();
         let (click_sender, click_receiver) = channel::unbounded();
-        self.aim_thread_sender = Some(aim_sender);
-        self.click_thread_sender = Some(click_sender);
-        self.mouse_dev = Some(find_mouse_dev()?);
+        self.thread_senders = vec![capture_sender, aim_sender, mover_sender, click_sender];
+
+        // holds at most one frame, the capture thread replaces it if the aim thread falls behind
+        let (frame_tx, frame_rx) = channel::bounded(1);
+        let aim_enabled = Arc::new(AtomicBool::new(true));
+        // same idea for mouse movement, the mover thread paces the strokes so the aim thread never sleeps
+        let (move_tx, move_rx) = channel::bounded(1);
+        // bumped by the capture thread, collected into the aim thread's per second summary
+        let capture_reloads = Arc::new(AtomicU32::new(0));
+        // set by the click thread around its clicks, the mover thread holds off while it's active
+        let stabilizer = Arc::new(ClickStabilizer::new());
+        // set by the aim thread every frame, what the click thread's trigger mode fires on
+        let target_under_crosshair = Arc::new(AtomicBool::new(false));
 
+        self.handles.push(self.spawn_capture_thread(
+            gui_sender.clone(),
+            capture_receiver,
+            self.capture_cmd_rx.clone(),
+            frame_tx,
+            frame_rx.clone(),
+            aim_enabled.clone(),
+            self.preview_wanted.clone(),
+            capture_reloads.clone(),
+        ));
+        self.handles.push(self.spawn_aim_thread(
+            gui_sender.clone(),
+            aim_receiver,
+            self.aim_cmd_rx.clone(),
+            frame_rx,
+            move_tx,
+            move_rx.clone(),
+            aim_enabled,
+            event_sender.clone(),
+            capture_reloads,
+            target_under_crosshair.clone(),
+        ));
         self.handles
-            .push(self.spawn_aim_thread(gui_sender, aim_receiver));
-        self.handles.push(self.spawn_click_thread(click_receiver));
+            .push(self.spawn_mover_thread(mover_receiver, move_rx, stabilizer.clone()));
+        self.handles.push(self.spawn_click_thread(
+            gui_sender,
+            click_receiver,
+            event_sender,
+            stabilizer,
+            target_under_crosshair,
+        ));
+        self.commands.running.store(true, Ordering::Relaxed);
         Ok(())
     }
 
-    pub fn stop(&mut self) -> Result<(), &'static str> {
+    // Returns the names of the threads that didn't exit in time and had to be detached
+    pub fn stop(&mut self) -> Result<Vec<&'static str>, &'static str> {
         if self.handles.is_empty() {
             return Err("Already stopped");
         }
 
-        std::mem::take(&mut self.aim_thread_sender)
-            .unwrap()
-            .send(ThreadMsg::Stop)
-            .unwrap();
-        std::mem::take(&mut self.click_thread_sender)
-            .unwrap()
-            .send(ThreadMsg::Stop)
-            .unwrap();
+        // a thread that already exited has dropped its receiver, there's nothing left to stop
+        std::mem::take(&mut self.thread_senders)
+            .into_iter()
+            .for_each(|sender| {
+                let _ = sender.send(ThreadMsg::Stop);
+            });
 
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        let mut detached = Vec::new();
         while let Some(handle) = self.handles.pop() {
-            handle.join().unwrap();
+            let name = handle.name;
+            if !handle.join_until(deadline) {
+                log_err!(
+                    "{} thread didn't exit within {}ms, detaching it",
+                    name,
+                    STOP_TIMEOUT.as_millis()
+                );
+                detached.push(name);
+            }
         }
-        Ok(())
+
+        // anything still queued would otherwise fire on the next start
+        self.commands.running.store(false, Ordering::Relaxed);
+        self.aim_cmd_rx.try_iter().for_each(drop);
+        self.capture_cmd_rx.try_iter().for_each(drop);
+        self.mouse_sink = None; // the driver context goes once the threads holding it are gone too
+        release_held_button(); // a redirected click could've been down when the click thread stopped
+        Ok(detached)
     }
 
     pub fn reload(&mut self) -> Result<(), &'static str> {
@@ -95,59 +508,84 @@ impl PixelBot {
             return Err("Not Started");
         }
 
-        self.aim_thread_sender
-            .as_ref()
-            .unwrap()
-            .send(ThreadMsg::Reload)
-            .unwrap();
-        self.click_thread_sender
-            .as_ref()
-            .unwrap()
-            .send(ThreadMsg::Reload)
-            .unwrap();
+        self.thread_senders
+            .iter()
+            .for_each(|sender| {
+                let _ = sender.send(ThreadMsg::Reload);
+            });
 
         Ok(())
     }
 
-    fn spawn_aim_thread(
+    fn spawn_capture_thread(
         &self,
         gui_sender: Sender<Message>,
         thread_rx: Receiver<ThreadMsg>,
-    ) -> JoinHandle<()> {
+        cmd_rx: Receiver<BotCommand>,
+        frame_tx: Sender<Frame>,
+        frame_rx: Receiver<Frame>,
+        aim_enabled: Arc<AtomicBool>,
+        preview_wanted: Arc<AtomicBool>,
+        capture_reloads: Arc<AtomicU32>,
+    ) -> BotThread {
         let config = self.config.clone();
-        let mouse_dev = self.mouse_dev.unwrap();
 
-        thread::spawn(move || {
-            let mut enabled = true;
-            let mut capturer = DXGICapturer::new().unwrap();
-            let (screen_w, screen_h) = capturer.dims();
-            let interception = InterceptionState::new(mouse_dev).unwrap();
+        spawn_bot_thread("capture", move || {
+            // slow captures keep the duplication from going stale while nothing needs frames
+            const KEEPALIVE_INTERVAL: Duration = Duration::from_millis(500);
+            let mut keepalive = false;
+            let mut last_capture = Instant::now();
+
+            let mut capture_cfg = CaptureConfig::from_config(&config.read().unwrap());
+            let mut monitor_idx = capture_cfg.monitor_idx;
+            let slot_wait = SlotWait {
+                thread_rx: &thread_rx,
+                gui_sender: &gui_sender,
+                aim_enabled: &aim_enabled,
+            };
+
+            let mut capturer = match slot_wait
+                .retry(capture_cfg.retry_budget, || DXGICapturer::new(monitor_idx))
+                .unwrap()
+            {
+                Some(capturer) => capturer,
+                None => return,
+            };
+            let (mut screen_w, mut screen_h) = capturer.dims();
             log!(
-                "Starting aim thread on primary display\nScreen size: {}x{}",
-                screen_w,
-                screen_h
+                "Starting capture thread on monitor {}",
+                capturer.monitor_idx()
             );
+            log_output_info(&gui_sender, capturer.output_info());
 
-            let mut last_iter = Instant::now();
+            let mut last_capture_timeout = None;
             'outer: loop {
-                let cfg = config.read().unwrap();
-                let fps: u32 = <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::Fps)).val;
-                let crop_w: u32 = <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::CropW)).val;
-                let crop_h: u32 = <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::CropH)).val;
-                let color_thresh: f32 =
-                    <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::ColorThresh)).val;
-                let aim_divisor: f32 =
-                    <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::AimDivisor)).val;
-                let y_multiplier: f32 =
-                    <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::YMultiplier)).val;
-                let aim_dur: u32 =
-                    <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::AimDurationMicros)).val;
-                let aim_steps: u32 =
-                    <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::AimSteps)).val;
-                let aim_key: u16 = cfg.get(CfgKey::AimKeycode).into();
-                let toggle_key: u16 = cfg.get(CfgKey::ToggleAimKeycode).into();
-                let target_color = <ValType as Into<Color<u8>>>::into(cfg.get(CfgKey::TargetColor));
-                drop(cfg);
+                capture_cfg = CaptureConfig::from_config(&config.read().unwrap());
+                let capture_timeout = capture_cfg.timeout_ms;
+
+                // a different monitor needs a whole new duplication, the crop percentages carry over as is
+                if capture_cfg.monitor_idx != monitor_idx {
+                    monitor_idx = capture_cfg.monitor_idx;
+                    drop(capturer);
+                    capturer = match slot_wait
+                        .retry(capture_cfg.retry_budget, || DXGICapturer::new(monitor_idx))
+                        .unwrap()
+                    {
+                        Some(capturer) => capturer,
+                        None => break 'outer,
+                    };
+                    (screen_w, screen_h) = capturer.dims();
+                    log!("Switched capture to monitor {}", capturer.monitor_idx());
+                    log_output_info(&gui_sender, capturer.output_info());
+                }
+
+                if last_capture_timeout != Some(capture_timeout) {
+                    match capture_timeout {
+                        0 => log!("Capture mode: polling"),
+                        ms => log!("Capture mode: blocking ({}ms timeout)", ms),
+                    }
+                    last_capture_timeout = Some(capture_timeout);
+                }
 
                 loop {
                     if let Ok(msg) = thread_rx.try_recv() {
@@ -157,99 +595,625 @@ impl PixelBot {
                         }
                     }
 
-                    if key_pressed(toggle_key) {
-                        enabled = !enabled;
-                        log!("Aim {}.", if enabled { "enabled" } else { "disabled" });
-                        wait_for_release(toggle_key, Duration::from_millis(500));
+                    if let Ok(BotCommand::ReinitCapture) = cmd_rx.try_recv() {
+                        log!("Reinitializing capture...");
+                        if slot_wait
+                            .retry(capture_cfg.retry_budget, || capturer.reload())
+                            .unwrap()
+                            .is_none()
+                        {
+                            break 'outer;
+                        }
+                        capture_reloads.fetch_add(1, Ordering::Relaxed);
+                        log_output_info(&gui_sender, capturer.output_info());
                     }
 
-                    if !enabled {
+                    // nothing needs full rate frames while aiming is off and the mirror isn't shown
+                    let idle = !aim_enabled.load(Ordering::Relaxed)
+                        && !preview_wanted.load(Ordering::Relaxed);
+                    if idle != keepalive {
+                        keepalive = idle;
+                        match keepalive {
+                            true => log!("Nothing needs frames, dropping to keep-alive captures"),
+                            false => log!("Back to full rate captures"),
+                        }
+                    }
+                    if keepalive && last_capture.elapsed() < KEEPALIVE_INTERVAL {
                         thread::sleep(Duration::from_millis(1));
                         continue;
                     }
+                    last_capture = Instant::now();
+
+                    // resolved against what we actually capture, which isn't always the primary display
+                    let crop_w = config::crop_px(capture_cfg.crop_percent.0, screen_w);
+                    let crop_h = config::crop_px(capture_cfg.crop_percent.1, screen_h);
 
-                    // Grab DXGI buffer
-                    let buffer = match capturer.capture_frame(0) {
-                        Ok(Some(buffer)) => buffer,
+                    // Grab DXGI buffer, only the crop gets copied off the gpu unless there's nothing to cut off.
+                    //     Either way the frame is a copy, so it can outlive the next capture
+                    let capture_start = Instant::now();
+                    let captured = match (crop_w, crop_h) {
+                        (0, 0) => capturer
+                            .capture_frame(capture_timeout)
+                            .map(|buffer| buffer.map(|buffer| buffer.crop_to_center(0, 0))),
+                        _ => capturer.capture_frame_region(
+                            capture_timeout,
+                            crop_w,
+                            crop_h,
+                            screen_w - (crop_w * 2),
+                            screen_h - (crop_h * 2),
+                        ),
+                    };
+                    let img = match captured {
+                        Ok(Some(img)) => img,
                         Ok(None) => {
-                            spin_sleep::sleep(Duration::from_secs_f32(1. / fps as f32));
+                            // a blocking capture already waited inside AcquireNextFrame
+                            if capture_timeout == 0 {
+                                spin_sleep::sleep(capture_cfg.poll_sleep().min(STOP_POLL_INTERVAL));
+                            }
                             continue;
                         }
                         Err(e) => match e {
                             CaptureError::AccessLost => {
                                 log!("Capture access lost, reloading...");
-                                capturer.reload().unwrap();
+                                if slot_wait
+                                    .retry(capture_cfg.retry_budget, || capturer.reload())
+                                    .unwrap()
+                                    .is_none()
+                                {
+                                    break 'outer;
+                                }
+                                capture_reloads.fetch_add(1, Ordering::Relaxed);
+                                log_output_info(&gui_sender, capturer.output_info());
+
+                                // fullscreen games switching display modes also cause access lost
+                                let (new_w, new_h) = capturer.dims();
+                                if (new_w, new_h) != (screen_w, screen_h) {
+                                    log!(
+                                        "Screen size changed: {}x{} -> {}x{}",
+                                        screen_w,
+                                        screen_h,
+                                        new_w,
+                                        new_h
+                                    );
+                                    apply_screen_resize(
+                                        &config,
+                                        (screen_w, screen_h),
+                                        (new_w, new_h),
+                                    );
+                                    (screen_w, screen_h) = (new_w, new_h);
+                                    let _ = gui_sender.try_send(Message::ConfigChanged(vec![
+                                        CfgKey::CropWPercent,
+                                        CfgKey::CropHPercent,
+                                    ]));
+                                    break; // re-reading the updated crop values
+                                }
                                 continue;
                             }
                             CaptureError::WinErr(e) => {
                                 panic!("err {:#x}: {}", e.code().0, e.message())
                             }
+                            // only DuplicateOutput returns this, never a frame capture
+                            CaptureError::NoFreeSlot => unreachable!(),
                         },
                     };
 
-                    // Crop image
-                    let cropped = buffer.crop_to_center(crop_w as usize, crop_h as usize);
+                    let frame = Frame {
+                        img,
+                        capture_time: capture_start.elapsed(),
+                        convert_time: Duration::ZERO, // DuplicateOutput always hands us BGRA8
+                    };
+
+                    // the processing thread only ever wants the newest frame, a stale one gets replaced
+                    if let Err(TrySendError::Full(frame)) = frame_tx.try_send(frame) {
+                        let _ = frame_rx.try_recv();
+                        let _ = frame_tx.try_send(frame);
+                    }
+                }
+            }
+        })
+    }
+
+    fn spawn_mover_thread(
+        &self,
+        thread_rx: Receiver<ThreadMsg>,
+        move_rx: Receiver<Coord<i32>>,
+        stabilizer: Arc<ClickStabilizer>,
+    ) -> BotThread {
+        let config = self.config.clone();
+        let mouse_sink = self.mouse_sink.clone().unwrap();
+
+        spawn_bot_thread("mover", move || {
+            let mouse = mouse_sink.backend();
+            log!("Starting mover thread");
+
+            'outer: loop {
+                let mover_cfg = MoverConfig::from_config(&config.read().unwrap());
+                let (aim_steps, step_dur) = (mover_cfg.aim_steps, mover_cfg.step_dur());
+
+                // (requested delta, steps already applied)
+                let mut current: Option<(Coord<i32>, u32)> = None;
+                let mut next_step = Instant::now();
+                loop {
+                    if let Ok(msg) = thread_rx.try_recv() {
+                        match msg {
+                            ThreadMsg::Reload => break,
+                            ThreadMsg::Stop => break 'outer,
+                        }
+                    }
+
+                    // a newer request supersedes whatever is left of the current one
+                    let request = match current {
+                        Some(_) => move_rx.try_recv().ok(),
+                        None => move_rx.recv_timeout(Duration::from_millis(1)).ok(),
+                    };
+                    if let Some(delta) = request {
+                        current = Some((delta, 0));
+                        next_step = Instant::now();
+                    }
+
+                    if let Some((delta, applied)) = current {
+                        spin_sleep::sleep(next_step.saturating_duration_since(Instant::now()));
+                        // checked right before the stroke, a click could have started since the request came in.
+                        //     the rest of the correction is stale by the time the window is over, so it's dropped
+                        if stabilizer.is_frozen(Instant::now()) {
+                            current = None;
+                            continue;
+                        }
+                        mouse.move_mouse_relative(mouse_step(delta, aim_steps, applied));
+                        current = (applied + 1 < aim_steps).then(|| (delta, applied + 1));
+                        next_step += step_dur;
+                    }
+                }
+            }
+        })
+    }
+
+    fn spawn_aim_thread(
+        &self,
+        gui_sender: Sender<Message>,
+        thread_rx: Receiver<ThreadMsg>,
+        cmd_rx: Receiver<BotCommand>,
+        frame_rx: Receiver<Frame>,
+        move_tx: Sender<Coord<i32>>,
+        move_rx: Receiver<Coord<i32>>,
+        aim_enabled: Arc<AtomicBool>,
+        events: EventSender,
+        capture_reloads: Arc<AtomicU32>,
+        target_under_crosshair: Arc<AtomicBool>,
+    ) -> BotThread {
+        let config = self.config.clone();
+
+        spawn_bot_thread("aim", move || {
+            log!("Starting aim thread");
+
+            const WASH_GRACE_PERIOD: Duration = Duration::from_millis(250);
+            // a single frame's jump would be over before the click thread ever sees it
+            const LUMA_TRIGGER_HOLD: Duration = Duration::from_millis(100);
+            const LUMA_TRIGGER_RADIUS: usize = 2;
+            let mut last_target: (Option<Vec<CropPos>>, Instant, Option<CropPos>) =
+                (None, Instant::now(), None);
+
+            const STALL_TIMEOUT: Duration = Duration::from_secs(1);
+            let mut last_frame = Instant::now();
+            let mut stalled = false;
+            let _ = gui_sender.try_send(Message::BotState(BotState::Running));
+
+            // commands that need a frame wait here until the next one comes in
+            let (mut calibrate_requested, mut screenshot_requested) = (false, false);
+            let mut count_calibration_requested = false;
+            let mut count_calibration: Option<(Image<Vec<u8>, Bgra8>, Instant)> = None; // frame before the turn
+            let mut last_stats = None;
+            let mut frame_ring = FrameRing::new();
+            let mut decision_log = DecisionLog::new();
+            let mut motion_map = MotionMap::new(1);
+            let mut roi_hint = RoiHint::default();
+            // reused every frame
+            let mut luma = image::zeroed::<Gray8>(0, 0);
+            // last center brightness and when the luma trigger last fired
+            let (mut prev_center_luma, mut luma_fired_at): (Option<f32>, Option<Instant>) =
+                (None, None);
+
+            const SUMMARY_INTERVAL: Duration = Duration::from_secs(1);
+            let mut summary = Summary::default();
+            let mut last_summary = Instant::now();
+
+            let mut idle_tracker = IdleTracker::default();
+            let mut had_target = false;
+            let mut prev_aim_coord = None;
+            let mut last_iter = Instant::now();
+            // kept across reloads, a fresh one would fire again for a key that's still held
+            let mut toggle_press = KeyToggle::default();
+            let mut calibrate_press = KeyToggle::default();
+            let mut screenshot_press = KeyToggle::default();
+            'outer: loop {
+                let aim_cfg = AimConfig::from_config(&config.read().unwrap());
+                motion_map.set_frames(aim_cfg.motion_gate_frames);
+                let idle_timeout = aim_cfg.idle_timeout();
+                let (motion_gated, needs_luma) = (aim_cfg.motion_gated(), aim_cfg.needs_luma());
+                let mut switch_ramp = SwitchRamp::new(aim_cfg.switch_ramp.0, aim_cfg.switch_ramp.1);
+                let mut smoother = AimSmoother::new(aim_cfg.smoothing_factor);
+                let mut predictor = LeadPredictor::new(aim_cfg.prediction_factor);
+                if !aim_cfg.record_frames {
+                    frame_ring.clear();
+                }
+                // the color, threshold or downscale could've changed, the last target's box means nothing now
+                roi_hint.reset();
+                prev_center_luma = None;
+                let small_aim_params = aim_cfg.small_aim_params();
+
+                loop {
+                    if let Ok(msg) = thread_rx.try_recv() {
+                        match msg {
+                            ThreadMsg::Reload => break,
+                            ThreadMsg::Stop => break 'outer,
+                        }
+                    }
+
+                    let mut set_enabled = None;
+                    let mut suffix = String::new();
+                    if toggle_press.pressed(aim_cfg.toggle_key, aim_cfg.toggle_hold) {
+                        set_enabled = Some(!aim_enabled.load(Ordering::Relaxed));
+                        suffix = held_suffix(aim_cfg.toggle_hold);
+                    }
+                    for cmd in cmd_rx.try_iter() {
+                        match cmd {
+                            BotCommand::Pause => set_enabled = Some(false),
+                            BotCommand::Resume => set_enabled = Some(true),
+                            BotCommand::Calibrate => calibrate_requested = true,
+                            BotCommand::Screenshot => screenshot_requested = true,
+                            BotCommand::CalibrateCounts => count_calibration_requested = true,
+                            BotCommand::DumpDebug => {
+                                log!(
+                                    "Aim config: {:#?}\nLast frame stats: {:#?}",
+                                    aim_cfg,
+                                    last_stats
+                                );
+                                if aim_cfg.record_frames {
+                                    if let Some(dir) = frame_ring.dump() {
+                                        decision_log.write_to(&dir);
+                                    }
+                                }
+                            }
+                            BotCommand::DumpDecisions => decision_log.dump(),
+                            BotCommand::ReinitCapture => {} // routed to the capture thread
+                        }
+                    }
+
+                    if let Some(enabled) = set_enabled {
+                        aim_enabled.store(enabled, Ordering::Relaxed);
+                        log!(
+                            "Aim {}{}.",
+                            if enabled { "enabled" } else { "disabled" },
+                            suffix
+                        );
+                        let _ = gui_sender.try_send(Message::BotState(if enabled {
+                            BotState::Running
+                        } else {
+                            BotState::Paused
+                        }));
+                        last_frame = Instant::now();
+                        stalled = false;
+                        events.send(if enabled {
+                            BotEvent::AimOn
+                        } else {
+                            BotEvent::AimOff
+                        });
+                    }
 
-                    // to send to gui for visualizations
-                    let mut target_coords: Option<Vec<Coord<usize>>> = None; // Vec of detected pixel coords
-                    let mut aim_coord: Option<Coord<usize>> = None; // Average of all the detected pixel coords
+                    if let Some(idle) =
+                        idle_tracker.update(last_key_activity(), Instant::now(), idle_timeout)
+                    {
+                        let state = match (idle, aim_enabled.load(Ordering::Relaxed)) {
+                            (true, _) => {
+                                log!(
+                                    "No keys pressed for {} minutes, idling out mouse output until the next press.",
+                                    aim_cfg.idle_minutes
+                                );
+                                BotState::Idle
+                            }
+                            (false, enabled) => {
+                                log!("Key pressed, resuming mouse output.");
+                                match enabled {
+                                    true => BotState::Running,
+                                    false => BotState::Paused,
+                                }
+                            }
+                        };
+                        let _ = gui_sender.try_send(Message::BotState(state));
+                    }
 
-                    // min area for coordinate clusters
-                    let min_area = (cropped.w / 20) * (cropped.h / 20);
+                    // sent even without frames coming in, a stall should show up as a run of empty summaries
+                    if last_summary.elapsed() >= SUMMARY_INTERVAL {
+                        summary.capture_reloads = capture_reloads.swap(0, Ordering::Relaxed);
+                        let _ = gui_sender.try_send(Message::Summary(std::mem::take(&mut summary)));
+                        last_summary = Instant::now();
+                    }
 
-                    // Search through image and find avg position of the target color
-                    let mut found_coods = cropped.detect_color(target_color, color_thresh);
-                    let mut relative_coord = match loop {
-                        match take_any_cluster(&mut found_coods, 2, (cropped.w, cropped.h)) {
-                            Some(cluster) => {
-                                let (_, _, w, h) = Coord::bbox_xywh(&cluster[..]);
-                                if w * h > min_area {
-                                    break Some(cluster);
+                    // short timeout so the toggle key & thread messages keep getting polled
+                    let Frame {
+                        img: cropped,
+                        capture_time,
+                        convert_time,
+                    } = match frame_rx.recv_timeout(Duration::from_millis(1)) {
+                            Ok(frame) => frame,
+                            Err(_) => {
+                                if !stalled
+                                    && aim_enabled.load(Ordering::Relaxed)
+                                    && last_frame.elapsed() > STALL_TIMEOUT
+                                {
+                                    stalled = true;
+                                    // without new frames there's no telling where the target is anymore
+                                    target_under_crosshair.store(false, Ordering::Relaxed);
+                                    let _ = gui_sender.try_send(Message::BotState(BotState::Stalled));
                                 }
+                                continue;
+                            }
+                        };
+                    last_frame = Instant::now();
+                    if stalled {
+                        stalled = false;
+                        let _ = gui_sender.try_send(Message::BotState(BotState::Running));
+                    }
+
+                    // Taking the target color from whatever is under the crosshair
+                    if calibrate_press.edge_pressed(aim_cfg.calibrate_key)
+                        || std::mem::take(&mut calibrate_requested)
+                    {
+                        let center = center_pixel((cropped.w, cropped.h));
+                        let color = cropped.get_pixel2d(center).as_color();
+                        config
+                            .write()
+                            .unwrap()
+                            .set_first_color(CfgKey::TargetColor, color)
+                            .unwrap();
+                        log!("Calibrated target color to {}, {}, {}", color.r, color.g, color.b);
+                        let _ = gui_sender.try_send(Message::ConfigChanged(vec![CfgKey::TargetColor]));
+                    }
+
+                    if screenshot_press.edge_pressed(aim_cfg.screenshot_key) || std::mem::take(&mut screenshot_requested)
+                    {
+                        save_screenshot(&cropped);
+                    }
+
+                    // turns the view by a known count, then compares the scene once the turn has landed
+                    if std::mem::take(&mut count_calibration_requested) && count_calibration.is_none() {
+                        log!("Calibrating mouse counts, keep the game focused & the view still...");
+                        send_move(&move_tx, &move_rx, Coord::new(CALIBRATION_COUNTS, 0));
+                        count_calibration = Some((cropped._clone(), Instant::now()));
+                    }
+                    if let Some((_, turned_at)) = count_calibration {
+                        if turned_at.elapsed() >= CALIBRATION_SETTLE {
+                            let (before, _) = count_calibration.take().unwrap();
+                            send_move(&move_tx, &move_rx, Coord::new(-CALIBRATION_COUNTS, 0));
+                            if apply_count_calibration(&config, &before, &cropped) {
+                                let _ = gui_sender
+                                    .try_send(Message::ConfigChanged(vec![CfgKey::PixelsPerCount]));
                             }
-                            None => break None,
                         }
-                    } {
-                        Some(cluster) => {
-                            let count = cluster.len();
-
-                            // Getting avg position of detected points
-                            let mut coord_sum = Coord::new(0, 0);
-                            cluster.iter().for_each(|&coord| coord_sum += coord);
-                            let coords_avg = Coord::new(
-                                coord_sum.x / count,
-                                ((coord_sum.y / count) as f32 * y_multiplier) as usize,
-                            );
-
-                            target_coords = Some(cluster);
-                            aim_coord = Some(coords_avg);
-
-                            // making coord relative to center
-                            Coord::new(
-                                coords_avg.x as i32 - (cropped.w / 2) as i32,
-                                coords_avg.y as i32 - (cropped.h / 2) as i32,
+                    }
+
+                    let process_start = Instant::now();
+                    let full_dims = (cropped.w, cropped.h);
+                    let small_dims = (
+                        (cropped.w / aim_cfg.downscale as usize).max(1),
+                        (cropped.h / aim_cfg.downscale as usize).max(1),
+                    );
+                    // the gui keeps getting the full res crop, only detection sees the small one
+                    // the motion map follows whichever frame detection runs on
+                    let aim_result = match cropped.scale_nearest(small_dims.0, small_dims.1) {
+                        Some(small) => {
+                            if needs_luma {
+                                small.luma_into(&mut luma);
+                            }
+                            if motion_gated {
+                                motion_map.update(&luma);
+                            }
+                            let motion = motion_gated.then(|| &motion_map);
+                            let roi = aim_cfg.roi_search.then(|| &mut roi_hint);
+                            let mut result = compute_aim(&small, &small_aim_params, motion, roi);
+                            upscale_aim_result(&mut result, small_dims, full_dims);
+                            result
+                        }
+                        None => {
+                            if needs_luma {
+                                cropped.luma_into(&mut luma);
+                            }
+                            if motion_gated {
+                                motion_map.update(&luma);
+                            }
+                            compute_aim(
+                                &cropped,
+                                &aim_cfg.aim_params,
+                                motion_gated.then(|| &motion_map),
+                                aim_cfg.roi_search.then(|| &mut roi_hint),
                             )
                         }
-                        // Found target pixels, but no clusters greater than min_area
-                        None => Coord::new(0, 0),
                     };
+                    let AimResult {
+                        mut relative_coord,
+                        cluster: mut target_coords,
+                        rejected: mut rejected_coords,
+                        others: other_targets,
+                        mut aim_coord,
+                        wash_rejected,
+                        effective_divisor,
+                        cluster_mean,
+                        confirmed,
+                        candidates,
+                        motion_score,
+                        stats,
+                        matched_color,
+                    } = aim_result;
+                    last_stats = Some(stats);
+                    let color_readout =
+                        cluster_mean
+                            .zip(matched_color)
+                            .map(|(measured, target)| ColorReadout {
+                                target,
+                                measured,
+                                distance: color_distance(measured, target),
+                                max_distance: 1. - aim_cfg.aim_params.color_thresh,
+                            });
+
+                    let area = target_coords.as_ref().map_or(0, |c| c.len() as u32);
+                    let unconfirmed = target_coords.is_some() && !confirmed;
+
+                    // keeping the last real target around for a moment while a wash is rejected
+                    let mut wash_grace = false;
+                    if wash_rejected {
+                        if last_target.1.elapsed() < WASH_GRACE_PERIOD {
+                            wash_grace = true;
+                            target_coords = last_target.0.clone();
+                            aim_coord = last_target.2;
+                        }
+                    } else {
+                        last_target = (target_coords.clone(), Instant::now(), aim_coord);
+                    }
+
+                    // too far from the crosshair counts as no target, the mirror shows it with the rejected ones
+                    let center = center_pixel((cropped.w, cropped.h));
+                    let fov_radius = aim_cfg.fov_radius;
+                    let outside_fov = fov_radius != 0
+                        && aim_coord.map_or(false, |c| {
+                            c.square_dist(center) > (fov_radius * fov_radius) as i32
+                        });
+                    if outside_fov {
+                        rejected_coords.extend(target_coords.take());
+                        aim_coord = None;
+                        relative_coord = Coord::new(0, 0);
+                    }
+                    summary.record(&stats, aim_coord.is_some(), outside_fov);
+                    let on_target = if aim_cfg.trigger_luma_delta > 0 {
+                        // something bright (or dark) crossing the crosshair, no color match needed
+                        let center_now = center_luma(&luma, LUMA_TRIGGER_RADIUS);
+                        if prev_center_luma.map_or(false, |prev| {
+                            (center_now - prev).abs() > aim_cfg.trigger_luma_delta as f32
+                        }) {
+                            luma_fired_at = Some(Instant::now());
+                        }
+                        prev_center_luma = Some(center_now);
+                        luma_fired_at.map_or(false, |t| t.elapsed() < LUMA_TRIGGER_HOLD)
+                    } else {
+                        aim_coord.is_some()
+                            && target_coords.as_ref().map_or(false, |coords| {
+                                let (x, y, w, h) = CropPos::bbox_xywh(coords);
+                                (x..=x + w).contains(&center.x) && (y..=y + h).contains(&center.y)
+                            })
+                    };
+                    target_under_crosshair.store(on_target, Ordering::Relaxed);
+
+                    // a new target would otherwise get the full correction in a single frame
+                    let switched = is_target_switch(
+                        prev_aim_coord,
+                        aim_coord,
+                        cropped.w / SWITCH_JUMP_DIVISOR,
+                    );
+                    if switched {
+                        switch_ramp.trigger();
+                    }
+                    prev_aim_coord = aim_coord;
 
-                    // scaling for sensitivity
-                    relative_coord.x = (relative_coord.x as f32 / aim_divisor) as i32;
-                    relative_coord.y = (relative_coord.y as f32 / aim_divisor) as i32;
+                    // the time since the last frame, not a fixed interval, so dropped frames don't skew the lead
+                    let lead = predictor.update(aim_coord, switched, last_iter.elapsed());
+                    let predicted_point = aim_coord.zip(lead).map(|(c, lead)| {
+                        let to_px = |v: f32, max: usize| v.round().clamp(0., max as f32 - 1.) as usize;
+                        CropPos::new(
+                            to_px(c.x as f32 + lead.x, cropped.w),
+                            to_px(c.y as f32 + lead.y, cropped.h),
+                        )
+                    });
+                    if let Some(lead) = lead {
+                        relative_coord.x += (lead.x / effective_divisor).round() as i32;
+                        relative_coord.y += (lead.y / effective_divisor).round() as i32;
+                    }
+                    let gain = switch_ramp.next_gain();
+                    relative_coord.x = (relative_coord.x as f32 * gain) as i32;
+                    relative_coord.y = (relative_coord.y as f32 * gain) as i32;
+
+                    // the average starts over with each target & key press, it'd drag the new moves toward old ones
+                    if aim_coord.is_none() || !key_pressed(aim_cfg.aim_key) {
+                        smoother.reset();
+                    } else {
+                        relative_coord = smoother.next(relative_coord);
+                    }
+                    let aim_point = aim_coord.map(|_| {
+                        let to_px = |center: usize, counts: i32, max: usize| {
+                            (center as f32 + counts as f32 * effective_divisor)
+                                .round()
+                                .clamp(0., max as f32 - 1.) as usize
+                        };
+                        CropPos::new(
+                            to_px(center.x, relative_coord.x, cropped.w),
+                            to_px(center.y, relative_coord.y, cropped.h),
+                        )
+                    });
+
+                    if aim_coord.is_some() != had_target {
+                        had_target = aim_coord.is_some();
+                        events.send(if had_target {
+                            BotEvent::TargetAcquired
+                        } else {
+                            BotEvent::TargetLost
+                        });
+                    }
+
+                    // frames still come in for the mirror while aiming is toggled off
+                    let moved = aim_enabled.load(Ordering::Relaxed)
+                        && !idle_tracker.is_idle()
+                        && key_pressed(aim_cfg.aim_key)
+                        && count_calibration.is_none();
+                    if moved {
+                        send_move(&move_tx, &move_rx, relative_coord);
+                    }
+                    decision_log.push(Decision {
+                        at: Instant::now(),
+                        area,
+                        aim_coord,
+                        candidates,
+                        stats,
+                        relative_coord,
+                        gain,
+                        wash_rejected,
+                        wash_grace,
+                        unconfirmed,
+                        outside_fov,
+                        moved,
+                    });
+                    let process_time = process_start.elapsed();
 
-                    if key_pressed(aim_key) {
-                        interception.move_mouse_over_time(
-                            Duration::from_micros(aim_dur as u64),
-                            aim_steps,
-                            relative_coord,
+                    if aim_cfg.record_frames {
+                        frame_ring.push(
+                            &cropped,
+                            FrameRecord {
+                                aim_coord,
+                                relative_coord,
+                                confirmed,
+                                wash_rejected,
+                                candidates,
+                            },
                         );
                     }
 
+                    let crop_dims = (cropped.w, cropped.h);
                     let _ = gui_sender.try_send(Message::CaptureData(CapData {
-                        img: cropped,
+                        img: fit_pixel_budget(cropped, aim_cfg.mirror_budget),
+                        crop_dims,
                         target_coords,
+                        rejected_coords,
+                        other_targets,
                         aim_coord,
+                        aim_point,
+                        predicted_point,
+                        wash_rejected,
+                        effective_divisor,
+                        color_readout,
+                        confirmed: confirmed || aim_coord.is_some(), // the wash grace period reuses a confirmed target
+                        motion_score,
+                    }));
+                    let _ = gui_sender.try_send(Message::Timings(Timings {
+                        capture: capture_time,
+                        convert: convert_time,
+                        process: process_time,
                     }));
                     let _ = gui_sender.try_send(Message::IterTime(last_iter.elapsed()));
                     last_iter = Instant::now();
@@ -258,106 +1222,131 @@ impl PixelBot {
         })
     }
 
-    fn spawn_click_thread(&self, thread_rx: Receiver<ThreadMsg>) -> JoinHandle<()> {
+    fn spawn_click_thread(
+        &self,
+        gui_sender: Sender<Message>,
+        thread_rx: Receiver<ThreadMsg>,
+        events: EventSender,
+        stabilizer: Arc<ClickStabilizer>,
+        target_under_crosshair: Arc<AtomicBool>,
+    ) -> BotThread {
         let config = self.config.clone();
-        let mouse_dev = self.mouse_dev.unwrap();
-
-        thread::spawn(move || {
-            #[derive(Debug)]
-            enum ClickMode {
-                Regular,          // Good ole bread and butter, the classic
-                Auto,             // Repeatedly clicks mmb when holding autoclick key
-                Redirected(bool), // mmb clicks mirror autoclick key clicks, stores whether pressed
-            }
+        let mouse_sink = self.mouse_sink.clone().unwrap();
+
+        spawn_bot_thread("click", move || {
             let mut click_mode = ClickMode::Regular;
-            let mut interception = InterceptionState::new(mouse_dev).unwrap();
+            let _ = gui_sender.try_send(Message::ClickMode(click_mode));
+            let mut mouse = mouse_sink.backend();
             let mut rng = rand::thread_rng();
+            let mut click_intervals: VecDeque<Duration> = VecDeque::with_capacity(CLICK_STATS_LEN);
             log!("Clickmode: {:?}\nStarting click thread", click_mode);
+            let mut toggle_press = KeyToggle::default();
 
             'outer: loop {
-                let cfg = config.read().unwrap();
-                let autoclick_key: u16 = cfg.get(CfgKey::AutoclickKeycode).into();
-                let toggle_autoclick_key: u16 = cfg.get(CfgKey::ToggleAutoclickKeycode).into();
-                let fake_lmb_key: u16 = cfg.get(CfgKey::FakeLmbKeycode).into();
-
-                let mut max_sleep: u32 =
-                    <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::MaxAutoclickSleepMs)).val;
-                let mut min_sleep: u32 =
-                    <ValType as Into<Bounded<_>>>::into(cfg.get(CfgKey::MinAutoclickSleepMs)).val;
-                drop(cfg);
-
-                if interception.set_click_keycode(fake_lmb_key).is_err() {
-                    log_err!(
+                let click_cfg = ClickConfig::from_config(&config.read().unwrap());
+                let mut cutoff = AutoclickCutoff::new(click_cfg.autoclick_max);
+                let (min_sleep, max_sleep) = click_cfg.sleep_range;
+
+                // checked again on every reload, only worth saying once per distinct problem
+                if mouse.set_click_keycode(click_cfg.fake_lmb_key).is_err() {
+                    log_err_once!(
                         "Invalid value for {}, using default",
                         CfgKey::FakeLmbKeycode.as_string()
                     );
                 }
 
-                if max_sleep < min_sleep {
-                    log_err!(
-                        "{} shouldn't be less than {}\n Using swapped values",
-                        CfgKey::MaxAutoclickSleepMs.as_string(),
-                        CfgKey::MinAutoclickSleepMs.as_string()
-                    );
-                    std::mem::swap(&mut max_sleep, &mut min_sleep);
-                }
-
                 loop {
-                    thread::sleep(Duration::from_millis(1));
-
-                    if let Ok(msg) = thread_rx.try_recv() {
-                        match msg {
-                            ThreadMsg::Reload => break,
-                            ThreadMsg::Stop => break 'outer,
+                    // a reload or stop wakes this right away, the timeout is just how often the keys get polled
+                    let msg = match click_poll_interval(click_mode) {
+                        ClickPoll::Spin(interval) => {
+                            spin_sleep::sleep(interval);
+                            thread_rx.try_recv().map_err(|e| match e.is_disconnected() {
+                                true => RecvTimeoutError::Disconnected,
+                                false => RecvTimeoutError::Timeout,
+                            })
                         }
+                        ClickPoll::Wait(timeout) => thread_rx.recv_timeout(timeout),
+                    };
+                    match msg {
+                        Ok(ThreadMsg::Reload) => break,
+                        Ok(ThreadMsg::Stop) | Err(RecvTimeoutError::Disconnected) => break 'outer,
+                        Err(RecvTimeoutError::Timeout) => {}
                     }
 
                     // Cycling to the next clickmode when the toggle key is pressed
-                    if key_pressed(toggle_autoclick_key) {
-                        click_mode = match click_mode {
-                            ClickMode::Regular => ClickMode::Auto,
-                            ClickMode::Auto => ClickMode::Redirected(false),
-                            ClickMode::Redirected(is_pressed) => {
-                                // if the clickmode was cycled while redirectedclick was pressed down, we reset it.
-                                if is_pressed {
-                                    interception.click_up()
-                                }
-                                ClickMode::Regular
-                            }
-                        };
-                        log!("Toggled clickmode to {:?}.", click_mode);
-                        wait_for_release(toggle_autoclick_key, Duration::from_millis(500));
+                    if toggle_press.pressed(click_cfg.toggle_autoclick_key, click_cfg.toggle_hold) {
+                        // if the clickmode was cycled while redirectedclick was pressed down, we reset it.
+                        if click_mode == ClickMode::Redirected(true) {
+                            mouse.click_up()
+                        }
+                        click_mode = next_click_mode(click_mode);
+                        log!(
+                            "Toggled clickmode to {:?}{}.",
+                            click_mode,
+                            held_suffix(click_cfg.toggle_hold)
+                        );
+                        let _ = gui_sender.try_send(Message::ClickMode(click_mode));
+                        events.send(BotEvent::ClickmodeChanged);
                     }
 
                     match click_mode {
                         ClickMode::Regular => {}
-                        ClickMode::Auto => {
-                            if key_pressed(autoclick_key) {
-                                let (sleep1, sleep2) = if (min_sleep..max_sleep).is_empty() {
-                                    (max_sleep.into(), max_sleep.into())
-                                } else {
-                                    (
-                                        rng.gen_range(min_sleep..max_sleep).into(),
-                                        rng.gen_range(min_sleep..max_sleep).into(),
-                                    )
-                                };
-
-                                interception.click_down();
+                        ClickMode::Auto | ClickMode::Trigger => {
+                            let held = click_cfg.autoclick_source.is_down(click_cfg.autoclick_key);
+                            let armed = click_mode == ClickMode::Auto
+                                || target_under_crosshair.load(Ordering::Relaxed);
+                            let was_cut = cutoff.is_cut();
+                            if cutoff.step(held, Instant::now()) && armed {
+                                let sleep1 = sample_click_sleep(
+                                    &mut rng,
+                                    min_sleep,
+                                    max_sleep,
+                                    click_cfg.normal_timing,
+                                );
+                                let sleep2 = sample_click_sleep(
+                                    &mut rng,
+                                    min_sleep,
+                                    max_sleep,
+                                    click_cfg.normal_timing,
+                                );
+
+                                // frozen from before the press until after the release
+                                stabilizer.settle_before_click(click_cfg.stabilize);
+                                mouse.click_down();
                                 spin_sleep::sleep(Duration::from_millis(sleep1));
-                                interception.click_up();
+                                mouse.click_up();
+                                stabilizer.settle_after_click(click_cfg.stabilize, Instant::now());
                                 spin_sleep::sleep(Duration::from_millis(sleep2));
+
+                                if click_intervals.len() == CLICK_STATS_LEN {
+                                    click_intervals.pop_front();
+                                }
+                                click_intervals.push_back(Duration::from_millis(sleep1 + sleep2));
+                                let _ = gui_sender.try_send(Message::ClickStats(
+                                    click_intervals.iter().copied().collect(),
+                                ));
+                            } else if cutoff.is_cut() && !was_cut {
+                                log_err!(
+                                    "Autoclicked for {}s straight, stopping until the key is released",
+                                    click_cfg.autoclick_max.as_secs()
+                                );
                             }
                         }
                         ClickMode::Redirected(ref mut was_pressed) => {
-                            if key_pressed(autoclick_key) {
-                                if !*was_pressed {
-                                    interception.click_down();
-                                    *was_pressed = true;
+                            let key_down =
+                                click_cfg.autoclick_source.is_down(click_cfg.autoclick_key);
+                            match redirect_edge(*was_pressed, key_down) {
+                                Some(ButtonEdge::Press) => {
+                                    // the shot goes off on the press, holding the button keeps aiming
+                                    stabilizer.settle_before_click(click_cfg.stabilize);
+                                    mouse.click_down();
+                                    stabilizer
+                                        .settle_after_click(click_cfg.stabilize, Instant::now());
                                 }
-                            } else if *was_pressed {
-                                interception.click_up();
-                                *was_pressed = false;
+                                Some(ButtonEdge::Release) => mouse.click_up(),
+                                None => {}
                             }
+                            *was_pressed = key_down;
                         }
                     }
                 }
@@ -366,62 +1355,893 @@ impl PixelBot {
     }
 }
 
-fn coord_neighbors(c: Coord<usize>, range: u32) -> Vec<Coord<usize>> {
-    (1..range as usize + 1)
-        .flat_map(|offset| {
-            let (xinc, yinc) = (c.x + offset, c.y + offset);
-            let (xdec, ydec) = (
-                (c.x as isize - offset as isize) as usize,
-                (c.y as isize - offset as isize) as usize,
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClickPoll {
+    Spin(Duration), // sleeping the os scheduler's way can overshoot by a whole timer tick
+    Wait(Duration),
+}
+
+// How long the click loop waits on its thread messages before checking the keys again. A held redirected
+//     click polls the fastest since the release has to follow the key closely, an idle one waits like
+//     autoclicking does for the press. Without any clicking to do only the toggle key needs catching
+fn click_poll_interval(click_mode: ClickMode) -> ClickPoll {
+    match click_mode {
+        ClickMode::Redirected(true) => ClickPoll::Spin(Duration::from_micros(250)),
+        ClickMode::Redirected(false) | ClickMode::Auto | ClickMode::Trigger => {
+            ClickPoll::Wait(Duration::from_millis(1))
+        }
+        ClickMode::Regular => ClickPoll::Wait(Duration::from_millis(10)),
+    }
+}
+
+// The toggle key's cycle, a redirected click still held has to be released by the caller first
+fn next_click_mode(click_mode: ClickMode) -> ClickMode {
+    match click_mode {
+        ClickMode::Regular => ClickMode::Auto,
+        ClickMode::Auto => ClickMode::Redirected(false),
+        ClickMode::Redirected(_) => ClickMode::Trigger,
+        // trigger clicks are released before the loop comes back around, nothing can be held here
+        ClickMode::Trigger => ClickMode::Regular,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ButtonEdge {
+    Press,
+    Release,
+}
+
+// What the redirected button does this poll to follow the autoclick key
+fn redirect_edge(was_pressed: bool, key_down: bool) -> Option<ButtonEdge> {
+    match (was_pressed, key_down) {
+        (false, true) => Some(ButtonEdge::Press),
+        (true, false) => Some(ButtonEdge::Release),
+        _ => None,
+    }
+}
+
+// Deadline the mover thread holds off until, as micros since `start` so it fits in an atomic.
+//     Only the click thread writes it
+pub struct ClickStabilizer {
+    start: Instant,
+    frozen_until: AtomicU64,
+}
+
+impl ClickStabilizer {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            frozen_until: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_frozen(&self, now: Instant) -> bool {
+        let micros = now.saturating_duration_since(self.start).as_micros() as u64;
+        micros < self.frozen_until.load(Ordering::Relaxed)
+    }
+
+    // Freezes movement until settle_after_click, the sleep lets a stroke that's already in flight land
+    //     before the button goes down. Nothing in between the two can bail out of the click
+    pub fn settle_before_click(&self, window: Duration) {
+        if window.is_zero() {
+            return;
+        }
+        self.frozen_until.store(u64::MAX, Ordering::Relaxed);
+        spin_sleep::sleep(window);
+    }
+
+    // Keeps it frozen for `window` more, counted from `sent`, when the last click stroke went out
+    pub fn settle_after_click(&self, window: Duration, sent: Instant) {
+        if window.is_zero() {
+            return;
+        }
+        let until = sent + window;
+        self.frozen_until.store(
+            until.saturating_duration_since(self.start).as_micros() as u64,
+            Ordering::Relaxed,
+        );
+    }
+}
+
+// Stops autoclicking after it's been held for `max` straight, so a stuck key doesn't click forever.
+//     Takes the time as an argument instead of reading the clock itself
+#[derive(Debug, Clone)]
+pub struct AutoclickCutoff {
+    max: Duration, // zero is unlimited
+    held_since: Option<Instant>,
+    cut: bool,
+}
+
+impl AutoclickCutoff {
+    pub fn new(max: Duration) -> Self {
+        Self {
+            max,
+            held_since: None,
+            cut: false,
+        }
+    }
+
+    // Whether to click this tick
+    pub fn step(&mut self, held: bool, now: Instant) -> bool {
+        if !held {
+            self.held_since = None;
+            self.cut = false;
+            return false;
+        }
+        let since = *self.held_since.get_or_insert(now);
+        if !self.max.is_zero() && now.duration_since(since) >= self.max {
+            self.cut = true;
+        }
+        !self.cut
+    }
+
+    pub fn is_cut(&self) -> bool {
+        self.cut
+    }
+}
+
+fn log_output_info(gui_sender: &Sender<Message>, info: OutputInfo) {
+    log!(
+        "Capture output: {}x{} @ {:.2}hz, format {} ({})",
+        info.w,
+        info.h,
+        info.refresh_hz,
+        info.format_name(),
+        info.format.0
+    );
+    // every capturer re-init (reloads, lost access) finds the same format again
+    if info.needs_conversion() {
+        log_err_once!(
+            "Capture format {} isn't BGRA8, every frame needs an extra conversion pass before detection",
+            info.format_name()
+        );
+    }
+    let _ = gui_sender.try_send(Message::CaptureFormat(CaptureFormat {
+        name: info.format_name(),
+        needs_conversion: info.needs_conversion(),
+    }));
+}
+
+// Only the newest delta matters, a pending one gets replaced
+fn send_move(move_tx: &Sender<Coord<i32>>, move_rx: &Receiver<Coord<i32>>, coord: Coord<i32>) {
+    if let Err(TrySendError::Full(coord)) = move_tx.try_send(coord) {
+        let _ = move_rx.try_recv();
+        let _ = move_tx.try_send(coord);
+    }
+}
+
+// Huge crops would otherwise sit in the channel & get copied around the gui at full size,
+//     the smallest whole box factor that fits is used so the mirror stays sharp
+fn fit_pixel_budget(img: Image<Vec<u8>, Bgra8>, budget: usize) -> Image<Vec<u8>, Bgra8> {
+    let pixels = img.w * img.h;
+    if pixels <= budget {
+        return img;
+    }
+    let factor = ((pixels as f64 / budget as f64).sqrt().ceil() as usize).max(2);
+    img.downscale_box(factor).unwrap_or(img)
+}
+
+const CALIBRATION_COUNTS: i32 = 100;
+const CALIBRATION_SETTLE: Duration = Duration::from_millis(250); // long enough for the mover to finish the turn
+
+// Stores pixels_per_count from the scene shift between the frames before & after the calibration turn,
+//     returns whether the config changed
+fn apply_count_calibration(
+    config: &RwLock<Config>,
+    before: &Image<Vec<u8>, Bgra8>,
+    after: &Image<Vec<u8>, Bgra8>,
+) -> bool {
+    let shift = match measure_shift(before, after, after.w / 3) {
+        Some(shift) if shift != 0 => shift,
+        Some(_) => {
+            log_err!("Calibration turn didn't move the view, is the game focused & the mouse driver working?");
+            return false;
+        }
+        None => {
+            log_err!(
+                "Couldn't measure how far the view turned (flat scenery, motion blur or too big of a turn),\n\t\
+                look at something detailed, hold still & run it again"
             );
-            [
-                Coord::new(xdec, ydec),
-                Coord::new(c.x, ydec),
-                Coord::new(xinc, ydec),
-                Coord::new(c.x, yinc),
-                Coord::new(xinc, c.y),
-                Coord::new(xdec, c.y),
-                Coord::new(xdec, yinc),
-                Coord::new(xinc, yinc),
-            ]
-        })
-        .collect()
-}
-
-fn take_any_cluster(
-    coords: &mut FxHashSet<Coord<usize>>,
-    radius: u32,
-    dims: (usize, usize),
-) -> Option<Vec<Coord<usize>>> {
-    if coords.is_empty() {
-        return None;
-    }
-
-    // starting cluster with the closest coord to the middle of the plane
-    let ref_coord = Coord { x: dims.0 / 2, y: dims.1 / 2 };
-    let mut init_coord = ref_coord;
-    let mut closest_dist = i32::MAX;
-    for coord in coords.iter() {
-        let dist = coord.square_dist(ref_coord);
-        if dist < closest_dist {
-            init_coord = *coord;
-            closest_dist = dist;
+            return false;
+        }
+    };
+
+    let pixels_per_count = shift.abs() as f32 / CALIBRATION_COUNTS as f32;
+    let res = config.write().unwrap().set_val(
+        CfgKey::PixelsPerCount,
+        ValType::Float(Bounded::new(pixels_per_count, 0.0..=0.0)),
+    );
+    match res {
+        Ok(_) => {
+            log!(
+                "Measured {}px for {} counts, {} = {:.3}",
+                shift.abs(),
+                CALIBRATION_COUNTS,
+                CfgKey::PixelsPerCount.as_string(),
+                pixels_per_count
+            );
+            true
+        }
+        Err(e) => {
+            log_err!("Measured {:.3} px per count: {}", pixels_per_count, e);
+            false
         }
     }
-    coords.remove(&init_coord);
+}
+
+// Encoding & writing happen on their own thread so the aim loop only pays for copying the crop
+fn save_screenshot(img: &Image<Vec<u8>, Bgra8>) {
+    let img = img._clone();
+    let path = resolve_data_path(&format!("capture_{}.png", utc_timestamp()));
+    thread::spawn(move || match img.save_png(&path) {
+        Ok(_) => log!("Saved screenshot to {}", path.display()),
+        Err(e) => log_err!("Error saving screenshot to {}:\n\t{}", path.display(), e),
+    });
+}
+
+// YYYYMMDD_HHMMSS in utc, std has no timezones & this only needs to sort & not collide
+fn utc_timestamp() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (days, rem) = ((secs / 86400) as i64, secs % 86400);
+
+    // days since the epoch to a civil date, from howard hinnant's date algorithms
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{:04}{:02}{:02}_{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+// Tacked onto the toggle key log lines when a hold is needed, so a quick tap doing nothing isn't a mystery
+fn held_suffix(hold: Duration) -> String {
+    if hold.is_zero() {
+        String::new()
+    } else {
+        format!(" (held {}ms)", hold.as_millis())
+    }
+}
+
+const CLICK_STATS_LEN: usize = 100;
 
-    let mut out = vec![init_coord];
-    for i in 0.. {
-        match out.get(i) {
-            Some(coord) => {
-                for neighbor in coord_neighbors(*coord, radius) {
-                    if coords.take(&neighbor).is_some() {
-                        out.push(neighbor);
+// Sleep in ms between min & max, either flat or a gaussian centered between them with a std-dev of (max-min)/4
+fn sample_click_sleep(rng: &mut impl Rng, min: u32, max: u32, normal: bool) -> u64 {
+    if (min..max).is_empty() {
+        return max.into();
+    }
+    if !normal {
+        return rng.gen_range(min..max).into();
+    }
+
+    // Box-Muller transform, u1 is kept out of 0 since ln(0) is -inf
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen();
+    let std_normal = (-2. * u1.ln()).sqrt() * (2. * std::f64::consts::PI * u2).cos();
+
+    let center = (min + max) as f64 / 2.;
+    let std_dev = (max - min) as f64 / 4.;
+    (center + std_normal * std_dev)
+        .round()
+        .clamp(min as f64, max as f64) as u64
+}
+
+// Delta for step `i` of `n`, the rounding error is spread out so the steps always sum to `total`
+fn mouse_step(total: Coord<i32>, n: u32, i: u32) -> Coord<i32> {
+    let n = n.max(1) as i32;
+    let i = i as i32;
+    Coord::new(
+        total.x * (i + 1) / n - total.x * i / n,
+        total.y * (i + 1) / n - total.y * i / n,
+    )
+}
+
+// The crop percentages already follow the new screen size, keeping the same crop in pixels means rewriting them
+fn apply_screen_resize(config: &RwLock<Config>, old_dims: (u32, u32), new_dims: (u32, u32)) {
+    let mut cfg = config.write().unwrap();
+    let preserve_percent: bool = cfg.get(CfgKey::PreserveCropPercent).into();
+    if preserve_percent {
+        return;
+    }
+    for (key, old_len, new_len) in [
+        (CfgKey::CropWPercent, old_dims.0, new_dims.0),
+        (CfgKey::CropHPercent, old_dims.1, new_dims.1),
+    ] {
+        let percent: f32 = <ValType as Into<Bounded<_>>>::into(cfg.get(key)).val;
+        let new_percent = config::rescale_crop_percent(percent, old_len, new_len);
+        cfg.set_val(key, ValType::Float(Bounded::new(new_percent, 0.0..=0.0)))
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn click_sleep_normal_mean_and_clamp() {
+        let mut rng = StdRng::seed_from_u64(955);
+        let (min, max) = (20, 60);
+        let samples: Vec<u64> = (0..10_000)
+            .map(|_| sample_click_sleep(&mut rng, min, max, true))
+            .collect();
+        assert!(samples
+            .iter()
+            .all(|&s| (min as u64..=max as u64).contains(&s)));
+        let mean = samples.iter().sum::<u64>() as f64 / samples.len() as f64;
+        assert!((mean - 40.).abs() < 0.5, "mean {}", mean);
+        // 2 std devs out, ~5% of the samples get clamped onto each bound
+        assert!(samples.iter().any(|&s| s == min as u64));
+        assert!(samples.iter().any(|&s| s == max as u64));
+    }
+
+    #[test]
+    fn click_sleep_uniform_range() {
+        let mut rng = StdRng::seed_from_u64(955);
+        let samples: Vec<u64> = (0..10_000)
+            .map(|_| sample_click_sleep(&mut rng, 20, 60, false))
+            .collect();
+        assert!(samples.iter().all(|&s| (20..60).contains(&s)));
+        let mean = samples.iter().sum::<u64>() as f64 / samples.len() as f64;
+        assert!((mean - 39.5).abs() < 0.5, "mean {}", mean);
+    }
+
+    #[test]
+    fn click_sleep_empty_range() {
+        let mut rng = StdRng::seed_from_u64(955);
+        assert_eq!(sample_click_sleep(&mut rng, 30, 30, true), 30);
+        assert_eq!(sample_click_sleep(&mut rng, 30, 30, false), 30);
+    }
+
+    // (held, ms since the start) for each tick, returns whether each tick clicked
+    fn run_cutoff(cutoff: &mut AutoclickCutoff, ticks: &[(bool, u64)]) -> Vec<bool> {
+        let start = Instant::now();
+        ticks
+            .iter()
+            .map(|&(held, ms)| cutoff.step(held, start + Duration::from_millis(ms)))
+            .collect()
+    }
+
+    #[test]
+    fn cutoff_after_max_held() {
+        let mut cutoff = AutoclickCutoff::new(Duration::from_secs(2));
+        let clicked = run_cutoff(
+            &mut cutoff,
+            &[(true, 0), (true, 1999), (true, 2000), (true, 5000)],
+        );
+        assert_eq!(clicked, vec![true, true, false, false]);
+        assert!(cutoff.is_cut());
+    }
+
+    #[test]
+    fn cutoff_resets_on_release() {
+        let mut cutoff = AutoclickCutoff::new(Duration::from_secs(2));
+        let clicked = run_cutoff(
+            &mut cutoff,
+            &[
+                (true, 0),
+                (true, 2500),
+                (false, 2600),
+                (true, 2700),
+                (true, 4600),
+            ],
+        );
+        // the hold starting at 2700 gets its own 2s
+        assert_eq!(clicked, vec![true, false, false, true, true]);
+        assert!(!cutoff.is_cut());
+    }
+
+    #[test]
+    fn cutoff_zero_is_unlimited() {
+        let mut cutoff = AutoclickCutoff::new(Duration::ZERO);
+        let clicked = run_cutoff(&mut cutoff, &[(true, 0), (true, 60_000), (true, 3_600_000)]);
+        assert_eq!(clicked, vec![true; 3]);
+        assert!(!cutoff.is_cut());
+    }
+
+    // (ms, event) of each recorded stroke
+    fn recorded_strokes(path: &std::path::Path) -> Vec<(f64, String)> {
+        let text = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        text.lines()
+            .skip(1) // header
+            .map(|line| {
+                let mut fields = line.split(", ");
+                let ms = fields.next().unwrap().parse().unwrap();
+                (ms, fields.next().unwrap().to_string())
+            })
+            .collect()
+    }
+
+    // Timing dependent, a loaded machine can stall the mover past the slack. The freeze itself is
+    //     covered by stabilizer_frozen_until_after_click, run with --ignored to check it end to end
+    #[test]
+    #[ignore]
+    fn stabilizer_no_moves_around_clicks() {
+        const WINDOW: Duration = Duration::from_millis(10);
+        const HOLD: Duration = Duration::from_millis(15);
+        // a move that passed the check right before the freeze can still get recorded a little later
+        const SLACK_MS: f64 = 2.;
+
+        let path =
+            std::env::temp_dir().join(format!("pixelbot_stabilize_{}.csv", std::process::id()));
+        let sink = MouseSink::Recording(Arc::new(MouseRecording::create(&path).unwrap()));
+        let stabilizer = Arc::new(ClickStabilizer::new());
+        let done = Arc::new(AtomicBool::new(false));
+
+        // stands in for the mover thread, checking right before every stroke like it does
+        let mover = {
+            let (sink, stabilizer, done) = (sink.clone(), stabilizer.clone(), done.clone());
+            thread::spawn(move || {
+                let mouse = sink.backend();
+                while !done.load(Ordering::Relaxed) {
+                    if !stabilizer.is_frozen(Instant::now()) {
+                        mouse.move_mouse_relative(Coord::new(1, 0));
+                    }
+                    thread::sleep(Duration::from_micros(200));
+                }
+            })
+        };
+
+        // the autoclick sequence
+        let clicker = sink.backend();
+        thread::sleep(Duration::from_millis(20));
+        for _ in 0..3 {
+            stabilizer.settle_before_click(WINDOW);
+            clicker.click_down();
+            thread::sleep(HOLD);
+            clicker.click_up();
+            stabilizer.settle_after_click(WINDOW, Instant::now());
+            thread::sleep(Duration::from_millis(30));
+        }
+        done.store(true, Ordering::Relaxed);
+        mover.join().unwrap();
+        drop((sink, clicker));
+
+        let strokes = recorded_strokes(&path);
+        let downs: Vec<f64> = strokes
+            .iter()
+            .filter(|(_, e)| e == "down")
+            .map(|s| s.0)
+            .collect();
+        let ups: Vec<f64> = strokes
+            .iter()
+            .filter(|(_, e)| e == "up")
+            .map(|s| s.0)
+            .collect();
+        let moves: Vec<f64> = strokes
+            .iter()
+            .filter(|(_, e)| e == "move")
+            .map(|s| s.0)
+            .collect();
+        assert_eq!((downs.len(), ups.len()), (3, 3));
+
+        let window_ms = WINDOW.as_secs_f64() * 1000.;
+        for (down, up) in downs.into_iter().zip(ups) {
+            let frozen = down - window_ms + SLACK_MS..up + window_ms;
+            let inside: Vec<_> = moves.iter().filter(|ms| frozen.contains(ms)).collect();
+            assert!(
+                inside.is_empty(),
+                "moves at {:?} inside {:?}",
+                inside,
+                frozen
+            );
+            // and the mover did get going again after each click
+            assert!(moves
+                .iter()
+                .any(|&ms| ms >= frozen.end && ms < frozen.end + 20.));
+        }
+    }
+
+    #[test]
+    fn stabilizer_zero_window_never_freezes() {
+        let stabilizer = ClickStabilizer::new();
+        stabilizer.settle_before_click(Duration::ZERO);
+        assert!(!stabilizer.is_frozen(Instant::now()));
+        stabilizer.settle_after_click(Duration::ZERO, Instant::now());
+        assert!(!stabilizer.is_frozen(Instant::now()));
+    }
+
+    #[test]
+    fn stabilizer_frozen_until_after_click() {
+        let stabilizer = ClickStabilizer::new();
+        let ms = |ms: u64| stabilizer.start + Duration::from_millis(ms);
+        assert!(!stabilizer.is_frozen(ms(0)));
+
+        stabilizer.settle_before_click(Duration::from_millis(1));
+        // however long the button stays down
+        assert!(stabilizer.is_frozen(ms(60_000)));
+        // released at 100ms
+        stabilizer.settle_after_click(Duration::from_millis(5), ms(100));
+        assert!(stabilizer.is_frozen(ms(100)));
+        assert!(stabilizer.is_frozen(ms(105) - Duration::from_micros(1)));
+        assert!(!stabilizer.is_frozen(ms(105)));
+        assert!(!stabilizer.is_frozen(ms(60_000)));
+
+        // the next click freezes it again
+        stabilizer.settle_before_click(Duration::from_millis(1));
+        assert!(stabilizer.is_frozen(ms(105)));
+        stabilizer.settle_after_click(Duration::from_millis(5), ms(200));
+        assert!(stabilizer.is_frozen(ms(204)));
+        assert!(!stabilizer.is_frozen(ms(205)));
+    }
+
+    #[test]
+    fn click_mode_cycle() {
+        let mut mode = ClickMode::Regular;
+        let mut seen = vec![mode];
+        for _ in 0..4 {
+            mode = next_click_mode(mode);
+            seen.push(mode);
+        }
+        assert_eq!(
+            seen,
+            vec![
+                ClickMode::Regular,
+                ClickMode::Auto,
+                ClickMode::Redirected(false),
+                ClickMode::Trigger,
+                ClickMode::Regular
+            ]
+        );
+        // cycled away from while held, the caller releases it
+        assert_eq!(
+            next_click_mode(ClickMode::Redirected(true)),
+            ClickMode::Trigger
+        );
+    }
+
+    #[test]
+    fn click_poll_only_spins_while_held() {
+        let waits = |mode| matches!(click_poll_interval(mode), ClickPoll::Wait(_));
+        assert!(waits(ClickMode::Regular));
+        assert!(waits(ClickMode::Auto));
+        assert!(waits(ClickMode::Trigger));
+        assert!(waits(ClickMode::Redirected(false)));
+        assert!(!waits(ClickMode::Redirected(true)));
+        assert_eq!(
+            click_poll_interval(ClickMode::Redirected(false)),
+            click_poll_interval(ClickMode::Auto)
+        );
+    }
+
+    #[test]
+    fn redirect_follows_key() {
+        let keys = [false, true, true, true, false, false, true, false];
+        let mut was_pressed = false;
+        let edges: Vec<_> = keys
+            .iter()
+            .map(|&key_down| {
+                let edge = redirect_edge(was_pressed, key_down);
+                was_pressed = key_down;
+                edge
+            })
+            .collect();
+        use ButtonEdge::*;
+        assert_eq!(
+            edges,
+            vec![
+                None,
+                Some(Press),
+                None,
+                None,
+                Some(Release),
+                None,
+                Some(Press),
+                Some(Release)
+            ]
+        );
+    }
+
+    // The click loop's wait & redirect step against a key flipped at a random point, press to recorded
+    //     click. Timing dependent, run with --ignored --nocapture to get the numbers
+    #[test]
+    #[ignore]
+    fn redirected_press_latency() {
+        const TRIALS: usize = 200;
+
+        let path =
+            std::env::temp_dir().join(format!("pixelbot_redirect_{}.csv", std::process::id()));
+        let sink = MouseSink::Recording(Arc::new(MouseRecording::create(&path).unwrap()));
+        // the recording's timestamps count from its creation, close enough to this
+        let start = Instant::now();
+        let key_down = Arc::new(AtomicBool::new(false));
+        let edges = Arc::new(AtomicU32::new(0)); // each flip waits for the last one to be followed
+        let (stop_tx, thread_rx) = channel::unbounded::<ThreadMsg>();
+
+        let clicker = {
+            let (sink, key_down, edges) = (sink.clone(), key_down.clone(), edges.clone());
+            thread::spawn(move || {
+                let mouse = sink.backend();
+                let mut click_mode = ClickMode::Redirected(false);
+                loop {
+                    let msg = match click_poll_interval(click_mode) {
+                        ClickPoll::Spin(interval) => {
+                            spin_sleep::sleep(interval);
+                            thread_rx.try_recv().ok()
+                        }
+                        ClickPoll::Wait(timeout) => thread_rx.recv_timeout(timeout).ok(),
+                    };
+                    if msg.is_some() {
+                        break;
                     }
+                    if let ClickMode::Redirected(ref mut was_pressed) = click_mode {
+                        let down = key_down.load(Ordering::Relaxed);
+                        match redirect_edge(*was_pressed, down) {
+                            Some(ButtonEdge::Press) => mouse.click_down(),
+                            Some(ButtonEdge::Release) => mouse.click_up(),
+                            None => continue,
+                        }
+                        edges.fetch_add(1, Ordering::Relaxed);
+                        *was_pressed = down;
+                    }
+                }
+            })
+        };
+
+        let mut flips = Vec::with_capacity(TRIALS * 2);
+        let mut rng = rand::thread_rng();
+        for _ in 0..TRIALS {
+            for down in [true, false] {
+                thread::sleep(Duration::from_micros(rng.gen_range(3_000..8_000)));
+                flips.push(start.elapsed().as_secs_f64() * 1000.);
+                key_down.store(down, Ordering::Relaxed);
+                while edges.load(Ordering::Relaxed) < flips.len() as u32 {
+                    thread::yield_now();
                 }
             }
-            None => break,
+        }
+        stop_tx.send(ThreadMsg::Stop).unwrap();
+        clicker.join().unwrap();
+        drop(sink);
+
+        let strokes = recorded_strokes(&path);
+        assert_eq!(strokes.len(), flips.len());
+        let mut press: Vec<f64> = Vec::new();
+        let mut release: Vec<f64> = Vec::new();
+        for ((ms, event), flip) in strokes.into_iter().zip(flips) {
+            match event.as_str() {
+                "down" => press.push(ms - flip),
+                _ => release.push(ms - flip),
+            }
+        }
+        for (name, mut latencies) in [("press", press), ("release", release)] {
+            latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let pct = |p: usize| latencies[(latencies.len() - 1) * p / 100];
+            println!(
+                "{}: median {:.3}ms, p99 {:.3}ms, max {:.3}ms",
+                name,
+                pct(50),
+                pct(99),
+                pct(100)
+            );
+        }
+    }
+
+    fn stoppable_bot() -> PixelBot {
+        PixelBot::new(Arc::new(RwLock::new(Config::default())))
+    }
+
+    // a thread that only exits once it gets Stop, then takes `exit_time` to wind down
+    fn add_thread(bot: &mut PixelBot, name: &'static str, exit_time: Duration) {
+        let (tx, rx) = channel::unbounded();
+        bot.thread_senders.push(tx);
+        bot.handles.push(spawn_bot_thread(name, move || {
+            let _ = rx.recv();
+            thread::sleep(exit_time);
+        }));
+    }
+
+    #[test]
+    fn stop_detaches_slow_thread() {
+        let mut bot = stoppable_bot();
+        add_thread(&mut bot, "quick", Duration::ZERO);
+        add_thread(&mut bot, "slow", STOP_TIMEOUT * 4);
+        add_thread(&mut bot, "quick", Duration::from_millis(10));
+
+        let start = Instant::now();
+        let detached = bot.stop().unwrap();
+        let elapsed = start.elapsed();
+        assert_eq!(detached, vec!["slow"]);
+        // one deadline for every thread, not one each. the slack is for a loaded machine
+        assert!(elapsed >= STOP_TIMEOUT, "{:?}", elapsed);
+        assert!(
+            elapsed < STOP_TIMEOUT + Duration::from_millis(250),
+            "{:?}",
+            elapsed
+        );
+        assert_eq!(bot.stop(), Err("Already stopped"));
+    }
+
+    #[test]
+    fn stop_survives_panicked_thread() {
+        let mut bot = stoppable_bot();
+        let (tx, rx) = channel::unbounded::<ThreadMsg>();
+        bot.thread_senders.push(tx);
+        bot.handles.push(spawn_bot_thread("panicky", move || {
+            let _ = rx.recv();
+            panic!("mock thread failure");
+        }));
+        add_thread(&mut bot, "quick", Duration::ZERO);
+
+        // the thread exited, so it's joined & not reported as detached
+        assert_eq!(bot.stop(), Ok(vec![]));
+    }
+
+    #[test]
+    fn panic_message_payloads() {
+        let msg = std::panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(&*msg), "static");
+        let msg = std::panic::catch_unwind(|| panic!("formatted {}", 1)).unwrap_err();
+        assert_eq!(panic_message(&*msg), "formatted 1");
+        let msg = std::panic::catch_unwind(|| std::panic::panic_any(1)).unwrap_err();
+        assert_eq!(panic_message(&*msg), "unknown");
+    }
+
+    #[test]
+    fn stop_after_receivers_dropped() {
+        let mut bot = stoppable_bot();
+        let (gui_sender, gui_rx) = channel::bounded::<Message>(1);
+        // the gui went away first, a thread still sending to it keeps going until it gets Stop
+        drop(gui_rx);
+        let (tx, rx) = channel::unbounded();
+        bot.thread_senders.push(tx);
+        bot.handles.push(spawn_bot_thread("sender", move || {
+            while rx.try_recv().is_err() {
+                let _ = gui_sender.try_send(Message::ClickMode(ClickMode::Regular));
+                thread::sleep(Duration::from_millis(1));
+            }
+        }));
+        // and a thread that already exited has dropped its ThreadMsg receiver
+        let (tx, rx) = channel::unbounded::<ThreadMsg>();
+        bot.thread_senders.push(tx);
+        bot.handles
+            .push(spawn_bot_thread("exited", move || drop(rx)));
+        thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(bot.reload(), Ok(()));
+        assert_eq!(bot.stop(), Ok(vec![]));
+        drop(bot); // stops again on drop, already stopped is fine
+    }
+
+    #[test]
+    fn summary_record_counts_final_target() {
+        let stats = AimStats {
+            clusters_rejected_area: 2,
+            clusters_rejected_aspect: 1,
+            clusters_rejected_static: 3,
+            roi_hit: true,
+            ..Default::default()
+        };
+        let mut summary = Summary::default();
+        summary.record(&stats, true, false);
+        // found, but thrown out for being outside the fov
+        summary.record(&AimStats::default(), false, true);
+        assert_eq!(
+            summary,
+            Summary {
+                frames: 2,
+                frames_with_target: 1,
+                clusters_rejected_area: 2,
+                clusters_rejected_aspect: 1,
+                clusters_rejected_static: 3,
+                clusters_rejected_fov: 1,
+                roi_hits: 1,
+                capture_reloads: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn summary_merge_adds_every_bucket() {
+        // every field set on both sides, a bucket merge forgets about stays at the first one's value
+        let a = Summary {
+            frames: 1,
+            frames_with_target: 2,
+            clusters_rejected_area: 3,
+            clusters_rejected_aspect: 4,
+            clusters_rejected_static: 5,
+            clusters_rejected_fov: 6,
+            roi_hits: 7,
+            capture_reloads: 8,
+        };
+        let b = Summary {
+            frames: 10,
+            frames_with_target: 20,
+            clusters_rejected_area: 30,
+            clusters_rejected_aspect: 40,
+            clusters_rejected_static: 50,
+            clusters_rejected_fov: 60,
+            roi_hits: 70,
+            capture_reloads: 80,
+        };
+        let mut merged = a;
+        merged.merge(&b);
+        assert_eq!(
+            merged,
+            Summary {
+                frames: 11,
+                frames_with_target: 22,
+                clusters_rejected_area: 33,
+                clusters_rejected_aspect: 44,
+                clusters_rejected_static: 55,
+                clusters_rejected_fov: 66,
+                roi_hits: 77,
+                capture_reloads: 88,
+            }
+        );
+    }
+
+    #[test]
+    fn fit_pixel_budget_shrinks_oversized() {
+        // (crop, budget), 4k with the crop near zero down to sizes that don't divide evenly
+        for ((w, h), budget) in [
+            ((3840, 2160), 2_000_000),
+            ((3840, 2160), 100_000),
+            ((3001, 1999), 1_000_000),
+            ((2000, 1000), 1_999_999),
+        ] {
+            let img = fit_pixel_budget(image::zeroed::<Bgra8>(w, h), budget);
+            assert!(
+                img.w * img.h <= budget,
+                "{}x{} over {}",
+                img.w,
+                img.h,
+                budget
+            );
+            // one factor less wouldn't have fit
+            let factor = w / img.w;
+            assert_eq!((img.w, img.h), (w / factor, h / factor));
+            assert!((w / (factor - 1)) * (h / (factor - 1)) > budget);
+        }
+        // already fits, sent as is
+        let img = fit_pixel_budget(image::zeroed::<Bgra8>(1920, 1080), 1920 * 1080);
+        assert_eq!((img.w, img.h), (1920, 1080));
+    }
+
+    #[test]
+    fn fit_pixel_budget_keeps_overlay_on_target() {
+        let red = Color::new(255, 0, 0, 255);
+        let crop_dims = (3001, 1999);
+        let mut crop = image::zeroed::<Bgra8>(crop_dims.0, crop_dims.1);
+        crop.fill_color(Color::new(0, 0, 0, 255));
+        // 3x3 blocks of the shrunk image, where the factor is 3
+        let blob = (1500..1509, 900..909);
+        for y in blob.1.clone() {
+            for x in blob.0.clone() {
+                crop.set2d(Coord::new(x, y), red);
+            }
+        }
+
+        let img = fit_pixel_budget(crop, 1_000_000);
+        assert_eq!((img.w, img.h), (1000, 666));
+        // the gui maps the crop space coords onto whatever size it got, like CapData.crop_dims says to
+        let to_shrunk = |x, y| *CropPos::new(x, y).to_mirror(crop_dims, (img.w, img.h));
+        assert_eq!(img.get_pixel2d(to_shrunk(1504, 904)).as_color(), red);
+        // the column & row the box filter drops only nudge it, nothing lands more than a pixel off
+        for (x, y) in [(0, 0), (1500, 900), (1508, 908), (3000, 1998)] {
+            let pos = to_shrunk(x, y);
+            assert!(
+                pos.x.abs_diff(x / 3) <= 1 && pos.y.abs_diff(y / 3) <= 1,
+                "{:?}",
+                (x, y)
+            );
+            assert!(pos.x < img.w && pos.y < img.h);
         }
     }
-    Some(out)
 }
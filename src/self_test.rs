@@ -0,0 +1,160 @@
+use crate::aim::{compute_aim, AimParams};
+use crate::bot_config::AimConfig;
+use crate::capture::DXGICapturer;
+use crate::config::Config;
+use crate::coord::Coord;
+use crate::image::{self, Bgra8, Color};
+use crate::input::{key_pressed, keycode_to_string, DriverStatus, InterceptionShared};
+use crate::logging::{log, log_err};
+use crate::paths::resolve_data_path;
+
+use std::panic;
+use std::time::{Duration, Instant};
+
+const USER_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Stage = fn() -> Result<String, String>;
+
+// Runs every stage even if earlier ones fail, prints a PASS/FAIL line per stage & saves them next to
+//     the config. Returns whether everything passed
+pub fn run() -> bool {
+    let stages: [(&str, Stage); 4] = [
+        ("capture", test_capture),
+        ("detection", test_detection),
+        ("input driver", test_input_driver),
+        ("key polling", test_key_polling),
+    ];
+
+    let mut report = String::new();
+    let mut all_passed = true;
+    for (name, stage) in stages {
+        // only catches anything from the --self-test command line, the gui's panic hook exits first
+        let outcome = panic::catch_unwind(stage).unwrap_or_else(|_| Err("panicked".to_string()));
+        let line = match outcome {
+            Ok(ref details) => format!("[PASS] {}: {}", name, details),
+            Err(ref details) => format!("[FAIL] {}: {}", name, details),
+        };
+        println!("{}", line);
+        match outcome {
+            Ok(_) => log!("{}", line),
+            Err(_) => log_err!("{}", line),
+        }
+        all_passed &= outcome.is_ok();
+        report.push_str(&line);
+        report.push('\n');
+    }
+
+    let report_path = resolve_data_path("self-test.txt");
+    if let Err(e) = std::fs::write(&report_path, report) {
+        log_err!("Error saving self test results to {}:\n\t{}", report_path.display(), e);
+    }
+    all_passed
+}
+
+fn test_capture() -> Result<String, String> {
+    const FRAME_TIMEOUT: Duration = Duration::from_secs(2);
+
+    let mut capturer = DXGICapturer::new(0).map_err(|e| format!("{:?}", e))?;
+    let info = capturer.output_info();
+    let desc = format!("{}x{} {} @ {:.0}hz", info.w, info.h, info.format_name(), info.refresh_hz);
+
+    // the duplication only hands out frames when something on screen changes
+    let start = Instant::now();
+    while start.elapsed() < FRAME_TIMEOUT {
+        match capturer.capture_frame(100) {
+            Ok(Some(frame)) => {
+                return Ok(format!(
+                    "{}, got a {}x{} frame after {}ms",
+                    desc,
+                    frame.w,
+                    frame.h,
+                    start.elapsed().as_millis()
+                ))
+            }
+            Ok(None) => continue,
+            Err(e) => return Err(format!("{}, capturing failed: {:?}", desc, e)),
+        }
+    }
+    Ok(format!(
+        "{}, no new frames in {}s (normal on a still screen)",
+        desc,
+        FRAME_TIMEOUT.as_secs()
+    ))
+}
+
+// A target colored blob on a flat background, detection has to land inside it
+fn test_detection() -> Result<String, String> {
+    const RUNS: u32 = 20;
+    let (w, h) = (400, 300);
+    let (blob_x, blob_y, blob_w, blob_h) = (250, 80, 30, 60);
+
+    // the default colors & threshold, everything that could reject a plain rectangle is switched off below
+    let aim_cfg = AimConfig::from_config(&Config::default());
+    let target_color = aim_cfg.aim_params.target_colors[0];
+    let mut frame = image::zeroed::<Bgra8>(w, h);
+    frame.fill_color(Color::new(40, 40, 40, 255));
+    for y in blob_y..blob_y + blob_h {
+        for x in blob_x..blob_x + blob_w {
+            frame.set2d(Coord::new(x, y), target_color);
+        }
+    }
+
+    let params = AimParams {
+        aim_divisor: 1.,
+        y_multiplier: 1.,
+        wash_reject_fraction: 1.,
+        adaptive_ref_area: 1,
+        adaptive_scale_range: (1., 1.),
+        aspect_range: (0., 100.),
+        outline_samples: 16,
+        outline_confirm_fraction: 0.,
+        motion_thresh: 0.,
+        prefer_largest: false,
+        ..aim_cfg.aim_params
+    };
+
+    let start = Instant::now();
+    let mut result = compute_aim(&frame, &params, None, None);
+    for _ in 1..RUNS {
+        result = compute_aim(&frame, &params, None, None);
+    }
+    let avg = start.elapsed() / RUNS;
+
+    let aim = result.aim_coord.ok_or("the injected target wasn't found")?;
+    let inside_x = (blob_x..blob_x + blob_w).contains(&aim.x);
+    let inside_y = (blob_y..blob_y + blob_h).contains(&aim.y);
+    if !inside_x || !inside_y {
+        return Err(format!("aimed at {}, {}, outside the injected target", aim.x, aim.y));
+    }
+    Ok(format!(
+        "found the injected target, {:.2}ms per {}x{} frame",
+        avg.as_secs_f32() * 1000.,
+        w,
+        h
+    ))
+}
+
+fn test_input_driver() -> Result<String, String> {
+    println!("Move the mouse within {}s...", USER_TIMEOUT.as_secs());
+    log!("Move the mouse within {}s...", USER_TIMEOUT.as_secs());
+    let shared = InterceptionShared::with_timeout(USER_TIMEOUT)?;
+    match shared.probe() {
+        DriverStatus::Ok => Ok("mouse found, zero-delta stroke sent".to_string()),
+        status => Err(format!("driver is {:?}, details are in the log", status)),
+    }
+}
+
+fn test_key_polling() -> Result<String, String> {
+    println!("Press any key within {}s...", USER_TIMEOUT.as_secs());
+    log!("Press any key within {}s...", USER_TIMEOUT.as_secs());
+    let start = Instant::now();
+    while start.elapsed() < USER_TIMEOUT {
+        // mouse buttons count too, anything a keycode button could bind
+        if let Some(key_code) = (1..=254).find(|&k| key_pressed(k)) {
+            let name = keycode_to_string(key_code).unwrap_or_else(|_| "?".to_string());
+            return Ok(format!("saw {} ({})", name, key_code));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    Err(format!("no key press seen in {}s", USER_TIMEOUT.as_secs()))
+}
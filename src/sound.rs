@@ -0,0 +1,54 @@
+use crate::config::{CfgKey, Config};
+use crate::events::BotEvent;
+use crate::pixel_bot::{spawn_bot_thread, BotThread};
+
+use crossbeam::channel::Receiver;
+use rustc_hash::FxHashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use windows::Win32::System::Diagnostics::Debug::Beep;
+
+// The per event toggle & (frequency hz, duration ms) of its beep, None for events that stay silent
+fn event_tone(event: BotEvent) -> Option<(CfgKey, u32, u32)> {
+    match event {
+        BotEvent::AimOn => Some((CfgKey::SoundAimToggle, 880, 40)),
+        BotEvent::AimOff => Some((CfgKey::SoundAimToggle, 440, 40)),
+        BotEvent::ClickmodeChanged => Some((CfgKey::SoundClickmode, 660, 30)),
+        BotEvent::TargetAcquired => Some((CfgKey::SoundTargetAcquired, 1320, 15)), // short, it fires a lot
+        BotEvent::TargetLost => None,
+    }
+}
+
+// Beep blocks until the tone is done, so it gets its own thread instead of holding up the bot threads.
+//     Exits once every EventSender has been dropped
+pub fn spawn_sound_thread(config: Arc<RwLock<Config>>, receiver: Receiver<BotEvent>) -> BotThread {
+    const MIN_REPEAT_INTERVAL: Duration = Duration::from_millis(250);
+
+    spawn_bot_thread("sound", move || {
+        let mut last_played: FxHashMap<BotEvent, Instant> = FxHashMap::default();
+
+        for event in receiver.iter() {
+            let (enable_key, freq, duration_ms) = match event_tone(event) {
+                Some(tone) => tone,
+                None => continue,
+            };
+            let cfg = config.read().unwrap();
+            let enabled: bool = cfg.get(CfgKey::SoundEnabled).into();
+            let event_enabled: bool = cfg.get(enable_key).into();
+            drop(cfg);
+            if !enabled || !event_enabled {
+                continue;
+            }
+
+            // a target flickering in & out would otherwise turn into one long buzz
+            if let Some(last) = last_played.get(&event) {
+                if last.elapsed() < MIN_REPEAT_INTERVAL {
+                    continue;
+                }
+            }
+            last_played.insert(event, Instant::now());
+
+            unsafe { Beep(freq, duration_ms) };
+        }
+    })
+}
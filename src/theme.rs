@@ -0,0 +1,251 @@
+use fltk::enums::Color;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::Path;
+
+pub const THEME_SECTION: &str = "[theme]";
+
+// Gui colors, defaults to gruvbox dark. Any of them can be overridden from the [theme] section of the config
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub bg0_h: Color,
+    pub bg0: Color,
+    pub bg1: Color,
+    pub gray: Color,
+    pub fg0: Color,
+    pub fg1: Color,
+    pub fg2: Color,
+
+    pub red: Color,
+    pub green: Color,
+    pub yellow: Color,
+    pub blue: Color,
+    pub purple: Color,
+    pub aqua: Color,
+    pub orange: Color,
+
+    pub accents: Vec<Color>, // cycled through for sliders & buttons, any length > 0
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let mut theme = Self {
+            bg0_h: Color::from_hex(0x1d2021),
+            bg0: Color::from_hex(0x282828),
+            bg1: Color::from_hex(0x3c3836),
+            gray: Color::from_hex(0x928374),
+            fg0: Color::from_hex(0xfbf1c7),
+            fg1: Color::from_hex(0xebdbb2),
+            fg2: Color::from_hex(0xd5c4a1),
+
+            red: Color::from_hex(0xfb4934),
+            green: Color::from_hex(0xb8bb26),
+            yellow: Color::from_hex(0xfabd2f),
+            blue: Color::from_hex(0x83a598),
+            purple: Color::from_hex(0xd3869b),
+            aqua: Color::from_hex(0x8ec07c),
+            orange: Color::from_hex(0xfe8019),
+
+            accents: Vec::new(),
+        };
+        theme.accents = theme.named_accents();
+        theme
+    }
+}
+
+impl Theme {
+    fn named_accents(&self) -> Vec<Color> {
+        vec![
+            self.red,
+            self.green,
+            self.yellow,
+            self.blue,
+            self.purple,
+            self.aqua,
+            self.orange,
+        ]
+    }
+
+    // A missing file or section just means the default theme
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        match File::open(Path::new(path)) {
+            Ok(file) => Self::parse(
+                BufReader::new(file)
+                    .lines()
+                    .map(|line| line.map_err(|e| e.to_string())),
+            ),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    fn parse(lines: impl Iterator<Item = Result<String, String>>) -> Result<Self, String> {
+        let mut theme = Self::default();
+        let mut accents = None;
+        let mut in_section = false;
+
+        for (line_num, line) in lines.enumerate() {
+            let line_num = line_num + 1;
+            let line = line?;
+            // hex colors start with a '#' too, so inline comments need a space after it
+            let line = match line.find("# ") {
+                Some(idx) => &line[..idx],
+                None => &line,
+            }
+            .trim();
+
+            if line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') {
+                in_section = line == THEME_SECTION;
+                continue;
+            }
+            if !in_section || line.is_empty() {
+                continue;
+            }
+
+            let (key, val) = line
+                .split_once('=')
+                .ok_or(format!("Theme: no delimiter on line {}", line_num))?;
+            let (key, val) = (key.trim(), val.trim());
+            let err = |e: &str| format!("Theme: {} on line {}", e, line_num);
+
+            if key == "accents" {
+                let colors = val
+                    .split(',')
+                    .map(parse_hex)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(err)?;
+                accents = Some(colors);
+                continue;
+            }
+
+            let color = parse_hex(val).map_err(err)?;
+            match key {
+                "bg0_h" => theme.bg0_h = color,
+                "bg0" => theme.bg0 = color,
+                "bg1" => theme.bg1 = color,
+                "gray" => theme.gray = color,
+                "fg0" => theme.fg0 = color,
+                "fg1" => theme.fg1 = color,
+                "fg" | "fg2" => theme.fg2 = color, // fg2 is the default text color
+                "red" => theme.red = color,
+                "green" => theme.green = color,
+                "yellow" => theme.yellow = color,
+                "blue" => theme.blue = color,
+                "purple" => theme.purple = color,
+                "aqua" => theme.aqua = color,
+                "orange" => theme.orange = color,
+                _ => return Err(err("unknown key")),
+            }
+        }
+
+        // without an explicit accent list the accents follow the (possibly overridden) named colors
+        theme.accents = accents.unwrap_or_else(|| theme.named_accents());
+        Ok(theme)
+    }
+}
+
+// "#rrggbb" or "rrggbb"
+fn parse_hex(s: &str) -> Result<Color, &'static str> {
+    let s = s.trim();
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 {
+        return Err("hex colors need 6 digits");
+    }
+    // from_str_radix takes a leading sign too
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err("invalid hex color");
+    }
+    u32::from_str_radix(hex, 16)
+        .map(Color::from_hex)
+        .map_err(|_| "invalid hex color")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(lines: Vec<&str>) -> Result<Theme, String> {
+        Theme::parse(lines.into_iter().map(|line| Ok(line.to_string())))
+    }
+
+    #[test]
+    fn bad_hex() {
+        for (val, err) in [
+            ("#12345", "hex colors need 6 digits"),
+            ("1234567", "hex colors need 6 digits"),
+            ("#12345g", "invalid hex color"),
+            ("+12345", "invalid hex color"),
+        ] {
+            let line = format!("red = {}", val);
+            assert_eq!(
+                parse(vec![THEME_SECTION, &line]).unwrap_err(),
+                format!("Theme: {} on line 2", err)
+            );
+        }
+        assert_eq!(
+            parse(vec![THEME_SECTION, "accents = #000001, #00002"]).unwrap_err(),
+            "Theme: hex colors need 6 digits on line 2"
+        );
+    }
+
+    #[test]
+    fn accents_only_keeps_named_defaults() {
+        let theme = parse(vec![THEME_SECTION, "accents = #000001, 000002"]).unwrap();
+        let default = Theme::default();
+        assert_eq!(
+            theme.accents,
+            vec![Color::from_hex(0x000001), Color::from_hex(0x000002)]
+        );
+        assert_eq!(theme.named_accents(), default.named_accents());
+        assert_eq!(theme.fg2, default.fg2);
+    }
+
+    #[test]
+    fn partial_override() {
+        let theme = parse(vec![THEME_SECTION, "red = #010203", "fg = #040506"]).unwrap();
+        let default = Theme::default();
+        assert_eq!(theme.red, Color::from_hex(0x010203));
+        assert_eq!(theme.fg2, Color::from_hex(0x040506));
+        assert_eq!(theme.green, default.green);
+        assert_eq!(theme.bg0, default.bg0);
+        // no accent list, so they follow the overridden red
+        assert_eq!(theme.accents[0], Color::from_hex(0x010203));
+        assert_eq!(theme.accents[1..], default.accents[1..]);
+    }
+
+    #[test]
+    fn unknown_key_line_number() {
+        assert_eq!(
+            parse(vec!["fov_radius = 0", THEME_SECTION, "", "bogus = #000000"]).unwrap_err(),
+            "Theme: unknown key on line 4"
+        );
+        assert_eq!(
+            parse(vec![THEME_SECTION, "red #000000"]).unwrap_err(),
+            "Theme: no delimiter on line 2"
+        );
+    }
+
+    #[test]
+    fn comments_and_other_sections() {
+        let mut lines = vec![
+            "[profile] # not the theme",
+            "red = not a color",
+            "[theme] # gui colors",
+            "# red = #ffffff",
+            "#red = #ffffff",
+            "red = #010203 # a bit darker",
+        ];
+        assert_eq!(parse(lines.clone()).unwrap().red, Color::from_hex(0x010203));
+
+        // without the space it's part of the value
+        lines.push("blue = #040506#brighter");
+        assert_eq!(
+            parse(lines).unwrap_err(),
+            "Theme: hex colors need 6 digits on line 7"
+        );
+    }
+}
@@ -0,0 +1,59 @@
+// The config file format through the public api, the way other tools using the library would see it
+use pixelbot::config::{Bounded, CfgKey, Config, ParseError, ValType};
+
+use std::path::PathBuf;
+
+fn temp_cfg_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("pixelbot_cfg_{}_{}.cfg", name, std::process::id()))
+}
+
+#[test]
+fn write_then_read_back() {
+    let path = temp_cfg_path("round_trip");
+    let path_str = path.to_str().unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    let mut cfg = Config::default();
+    cfg.set_val(CfgKey::Fps, ValType::Unsigned(Bounded::new(60, 1..=240)))
+        .unwrap();
+    cfg.set_val(
+        CfgKey::EventCommand,
+        ValType::Text("notify --app pixelbot".into()),
+    )
+    .unwrap();
+    cfg.write_to_file(path_str).unwrap();
+
+    let loaded = Config::from_file(path_str);
+    std::fs::remove_file(&path).unwrap();
+    let loaded = loaded.unwrap();
+    for key in CfgKey::iter().filter(|k| !k.is_legacy()) {
+        assert_eq!(loaded.get(key), cfg.get(key), "{} changed", key.as_string());
+    }
+}
+
+#[test]
+fn parse_key_val_lines() {
+    assert_eq!(
+        Config::parse_key_val("fps = 90 # comment").unwrap(),
+        Some((CfgKey::Fps, ValType::Unsigned(Bounded::new(90, 1..=240))))
+    );
+    assert_eq!(Config::parse_key_val("  # just a comment").unwrap(), None);
+    assert_eq!(Config::parse_key_val("").unwrap(), None);
+
+    assert!(matches!(
+        Config::parse_key_val("fps 90"),
+        Err(ParseError::Parse(..))
+    ));
+    assert!(matches!(
+        Config::parse_key_val("not_a_key = 1"),
+        Err(ParseError::InvalidKey(_))
+    ));
+    assert!(matches!(
+        Config::parse_key_val("fps = 1000"),
+        Err(ParseError::OutOfBounds(_))
+    ));
+    assert!(matches!(
+        Config::parse_key_val("target_color = 1, 2"),
+        Err(ParseError::Parse(..))
+    ));
+}
@@ -0,0 +1,110 @@
+// Drawing & blending output checked against known good bmps in tests/golden.
+//     PIXELBOT_REGEN_GOLDENS=1 cargo test --test golden rewrites them from the current output
+use pixelbot::coord::Coord;
+use pixelbot::image::image_ops::{self, BlendType};
+use pixelbot::image::{self, bmp, Color, Image, Pixel, Rgba8};
+
+use std::path::PathBuf;
+
+const REGEN_VAR: &str = "PIXELBOT_REGEN_GOLDENS";
+const W: usize = 100;
+const H: usize = 80;
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.bmp", name))
+}
+
+fn check_golden(name: &str, img: &Image<Vec<u8>, Rgba8>) {
+    let path = golden_path(name);
+    if std::env::var_os(REGEN_VAR).is_some() {
+        img.save_bmp(&path).unwrap();
+        return;
+    }
+
+    let golden = bmp::load_bmp::<Rgba8, _>(&path).unwrap_or_else(|e| {
+        panic!(
+            "Couldn't load {}: {}, set {} to create it",
+            path.display(),
+            e,
+            REGEN_VAR
+        )
+    });
+    assert_eq!(
+        img.diff_count(&golden),
+        0,
+        "{} doesn't match {}",
+        name,
+        path.display()
+    );
+}
+
+fn background() -> Image<Vec<u8>, Rgba8> {
+    let mut img = image::zeroed::<Rgba8>(W, H);
+    img.fill_color(Color::new(0, 0, 0, 255));
+    img
+}
+
+#[test]
+fn grid() {
+    let mut img = background();
+    img.draw_grid(30, Color::new(255, 255, 255, 255));
+    check_golden("grid", &img);
+}
+
+#[test]
+fn bbox_at_border() {
+    // the box's right & bottom edges land on the last column & row
+    let mut img = background();
+    img.draw_bbox(Coord::new(0, 0), W - 1, H - 1, Color::new(255, 0, 0, 255));
+    check_golden("bbox_at_border", &img);
+}
+
+#[test]
+fn crosshair_at_corners() {
+    // the arms get clipped at the edges instead of wrapping to the other side
+    let mut img = background();
+    img.draw_crosshair(Coord::new(0, 0), 10, Color::new(0, 255, 0, 255));
+    img.draw_crosshair(Coord::new(W - 1, H - 1), 10, Color::new(0, 255, 0, 255));
+    check_golden("crosshair_at_corners", &img);
+}
+
+// red with alpha ramping up to the right, over blue getting brighter towards the bottom
+fn gradient_pair(w: usize, h: usize) -> (Image<Vec<u8>, Rgba8>, Image<Vec<u8>, Rgba8>) {
+    let mut fg = image::zeroed::<Rgba8>(w, h);
+    let mut bg = image::zeroed::<Rgba8>(w, h);
+    for y in 0..h {
+        for x in 0..w {
+            let pos = Coord::new(x, y);
+            fg.set2d(pos, Color::new(255, 40, 0, (x * 255 / (w - 1)) as u8));
+            bg.set2d(pos, Color::new(0, 80, (y * 255 / (h - 1)) as u8, 255));
+        }
+    }
+    (fg, bg)
+}
+
+#[test]
+fn blended_gradient() {
+    let (mut fg, bg) = gradient_pair(W, H);
+    fg.blend(BlendType::Over, &bg);
+    check_golden("blended_gradient", &fg);
+}
+
+#[test]
+fn blend_matches_scalar() {
+    // odd dims so the last few pixels go through the scalar tail after the avx chunks
+    let (w, h) = (101, 79);
+    let (mut fg, bg) = gradient_pair(w, h);
+    let expected: Vec<_> = fg
+        .pixels()
+        .zip(bg.pixels())
+        .map(|(fg_px, bg_px)| image_ops::over(fg_px.as_color(), bg_px.as_color()))
+        .collect();
+
+    fg.blend(BlendType::Over, &bg);
+    for (idx, (px, want)) in fg.pixels().zip(expected).enumerate() {
+        assert_eq!(px.as_color(), want, "pixel {} differs", idx);
+    }
+}